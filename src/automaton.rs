@@ -0,0 +1,1365 @@
+//! Compilation of content models into automata.
+//!
+//! The particle tree of a complex type is compiled once the schema is
+//! resolved into its position automaton: each element declaration and
+//! wildcard of the model, its leaves, is given a position, and the
+//! automaton records which positions can start the content, which can
+//! follow each other and which can end it.
+//!
+//! Particles repeated a bounded number of times, or at least twice, and
+//! `xs:all` groups are not unrolled but counted: a counter has a slot for
+//! each particle it counts, the repeated particle or each particle of the
+//! group, which holds the number of occurrences of its term, so that
+//! large `maxOccurs` values and large groups cost nothing. A transition
+//! keeps the values of the counters around both of its ends, may start
+//! another occurrence of a slot of one of them, and leaves the other
+//! counters around its source, whose slots must have reached their
+//! minimums. As the same children can repeat a particle or leave it, a
+//! match tracks every set of counter values they can lead to.
+//!
+//! Moving through the automaton is deterministic: when several positions
+//! accept a child element, element declarations take precedence over
+//! wildcards, then the first position in the model is taken. This is
+//! exact for models that satisfy the Unique Particle Attribution
+//! constraint, where at most one particle can match each child.
+//!
+//! An `xs:all` group that makes up the whole content model is matched
+//! apart, by counting the occurrences of each of its particles.
+//!
+//! The compiled models are also what the schema is checked with: two
+//! particles that can match the same child after the same children
+//! break the Unique Particle Attribution constraint, except, in XSD 1.1,
+//! an element declaration and a wildcard, and element declarations of
+//! the same name must have the same type (Element Declarations
+//! Consistent).
+//!
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    components::{
+        ComplexType, Compositor, Datatype, Element, MaxOccurs, NamespaceConstraint, Particle, Term,
+        Wildcard,
+    },
+    error::SchemaError,
+    graph::SchemaGraph,
+    loader::{definition_error, SchemaDocument},
+    parser::{XmlSchema, XmlSchemaNode},
+    qname::QName,
+    schema::XsdVersion,
+    validator::Validator,
+};
+
+/// The number of sets of counter values reached while looking for the
+/// children after which two particles compete, past which the search is
+/// abandoned.
+const SEARCH_LIMIT: usize = 1 << 16;
+
+/// The compiled content model of a complex type.
+#[derive(Debug, Clone)]
+pub(crate) enum ContentModel {
+    /// A position automaton
+    Positions(PositionAutomaton),
+    /// An `xs:all` group making up the whole content model
+    All(AllModel),
+}
+
+/// The position automaton of a content model.
+#[derive(Debug, Clone)]
+pub(crate) struct PositionAutomaton {
+    positions: Vec<Position>,
+    /// The positions that can start the content
+    first: Vec<usize>,
+    /// The transitions from each position
+    follow: Vec<Vec<Edge>>,
+    /// Whether each position can end the content
+    last: Vec<bool>,
+    /// Whether the content can be empty
+    nullable: bool,
+}
+
+/// A position of a [`PositionAutomaton`].
+#[derive(Debug, Clone)]
+struct Position {
+    /// The index of the term of the position among the leaves of the
+    /// model, as returned by [`leaves`]
+    leaf: usize,
+    /// The slots of the counters around the position, outermost first,
+    /// each the index of a counter value
+    slots: Vec<Slot>,
+    /// The counter values when the position is entered from outside the
+    /// counters around it: one occurrence of the slots it is in
+    entry: Vec<u32>,
+}
+
+/// A slot of a counter: the bounds of the occurrences of the term of a
+/// particle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Slot {
+    /// The minimum, zero if the term can match no element
+    min: u32,
+    max: MaxOccurs,
+}
+
+/// A transition of a [`PositionAutomaton`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Edge {
+    target: usize,
+    /// The number of counter values the transition keeps, those of the
+    /// counters around both positions; the slots of the others must have
+    /// reached their minimums
+    kept: usize,
+    /// The counter value of the slot whose term occurs once more, which
+    /// must be below its maximum
+    increment: Option<usize>,
+}
+
+/// An `xs:all` group making up a whole content model.
+#[derive(Debug, Clone)]
+pub(crate) struct AllModel {
+    /// The leaf index and bounds of each particle of the group
+    particles: Vec<(usize, u32, MaxOccurs)>,
+    /// Whether the group itself is optional
+    optional: bool,
+}
+
+impl ContentModel {
+    /// Compiles the content model `particle`.
+    pub(crate) fn compile(particle: &Particle) -> ContentModel {
+        if let Some(all) = AllModel::compile(particle) {
+            return ContentModel::All(all);
+        }
+        let mut builder = Builder::default();
+        let fragment = builder.particle(particle, 0);
+        let mut last = vec![false; builder.positions.len()];
+        for position in &fragment.last {
+            last[*position] = true;
+        }
+        ContentModel::Positions(PositionAutomaton {
+            positions: builder.positions,
+            first: fragment.first,
+            follow: builder.follow,
+            last,
+            nullable: fragment.nullable,
+        })
+    }
+
+    /// Returns a match of the model at the start of the content.
+    pub(crate) fn start(&self) -> ContentRun<'_> {
+        let state = match self {
+            ContentModel::Positions(_) => RunState::Start,
+            ContentModel::All(all) => RunState::All(vec![0; all.particles.len()]),
+        };
+        ContentRun { model: self, state }
+    }
+}
+
+impl AllModel {
+    /// Returns the model of `particle` if it is an `xs:all` group of
+    /// element declarations and wildcards, occurring at most once.
+    fn compile(particle: &Particle) -> Option<AllModel> {
+        let group = match &particle.term {
+            Term::ModelGroup(group) if group.compositor == Compositor::All => group,
+            _ => return None,
+        };
+        if particle.max_occurs > MaxOccurs::Bounded(1) {
+            return None;
+        }
+        let mut particles = Vec::new();
+        for (leaf, child) in group.particles.iter().enumerate() {
+            match &child.term {
+                Term::Element(_) | Term::Wildcard(_) => {
+                    particles.push((leaf, child.min_occurs, child.max_occurs))
+                }
+                _ => return None,
+            }
+        }
+        Some(AllModel {
+            particles,
+            optional: particle.min_occurs == 0 || particle.max_occurs == MaxOccurs::Bounded(0),
+        })
+    }
+}
+
+/// The element declarations and wildcards of the content model
+/// `particle`, in the order they appear: the leaves the positions of its
+/// compiled model refer to.
+pub(crate) fn leaves(particle: &Particle) -> Vec<&Term> {
+    let mut leaves = Vec::new();
+    collect_leaves(&particle.term, &mut leaves);
+    leaves
+}
+
+fn collect_leaves<'p>(term: &'p Term, leaves: &mut Vec<&'p Term>) {
+    match term {
+        Term::Element(_) | Term::Wildcard(_) => leaves.push(term),
+        Term::ModelGroup(group) => group
+            .particles
+            .iter()
+            .for_each(|particle| collect_leaves(&particle.term, leaves)),
+        Term::GroupReference(_) => {}
+    }
+}
+
+/// Returns the number of leaves of `term`.
+fn leaf_count(term: &Term) -> usize {
+    match term {
+        Term::Element(_) | Term::Wildcard(_) => 1,
+        Term::ModelGroup(group) => group
+            .particles
+            .iter()
+            .map(|particle| leaf_count(&particle.term))
+            .sum(),
+        Term::GroupReference(_) => 0,
+    }
+}
+
+/// Returns `true` if `term` can match no element.
+fn is_emptiable(term: &Term) -> bool {
+    let emptiable = |particle: &Particle| {
+        particle.min_occurs == 0
+            || particle.max_occurs == MaxOccurs::Bounded(0)
+            || is_emptiable(&particle.term)
+    };
+    match term {
+        Term::Element(_) | Term::Wildcard(_) => false,
+        Term::ModelGroup(group) if group.compositor == Compositor::Choice => {
+            group.particles.iter().any(emptiable)
+        }
+        Term::ModelGroup(group) => group.particles.iter().all(emptiable),
+        Term::GroupReference(_) => true,
+    }
+}
+
+/// The positions of a part of a content model, in the Glushkov
+/// construction.
+struct Fragment {
+    /// Whether the part can match no element
+    nullable: bool,
+    /// The positions that can start the part
+    first: Vec<usize>,
+    /// The positions that can end the part
+    last: Vec<usize>,
+}
+
+impl Fragment {
+    fn empty() -> Fragment {
+        Fragment {
+            nullable: true,
+            first: Vec::new(),
+            last: Vec::new(),
+        }
+    }
+
+    fn optional(mut self) -> Fragment {
+        self.nullable = true;
+        self
+    }
+}
+
+/// Builds the positions and transitions of a [`PositionAutomaton`].
+#[derive(Default)]
+struct Builder {
+    positions: Vec<Position>,
+    follow: Vec<Vec<Edge>>,
+    /// The counters around the part being built, outermost first: their
+    /// slots, and the slot the part is in
+    counters: Vec<(Vec<Slot>, usize)>,
+}
+
+impl Builder {
+    /// Returns the fragment of `particle`, whose leaves start at index
+    /// `leaf`.
+    fn particle(&mut self, particle: &Particle, leaf: usize) -> Fragment {
+        let (min, max) = (particle.min_occurs, particle.max_occurs);
+        if max == MaxOccurs::Bounded(0) {
+            return Fragment::empty();
+        }
+        if min > 1 || max > MaxOccurs::Bounded(1) && max != MaxOccurs::Unbounded {
+            return self.counter(&[(particle, leaf)]);
+        }
+        let fragment = self.term(&particle.term, leaf);
+        let fragment = match max {
+            MaxOccurs::Unbounded => self.repeat(fragment),
+            MaxOccurs::Bounded(_) => fragment,
+        };
+        match min {
+            0 => fragment.optional(),
+            _ => fragment,
+        }
+    }
+
+    /// Returns the fragment of one occurrence of `term`, whose leaves
+    /// start at index `leaf`.
+    fn term(&mut self, term: &Term, leaf: usize) -> Fragment {
+        let group = match term {
+            Term::Element(_) | Term::Wildcard(_) => return self.position(leaf),
+            Term::ModelGroup(group) => group,
+            Term::GroupReference(_) => return Fragment::empty(),
+        };
+        let mut starts = Vec::new();
+        let mut next = leaf;
+        for particle in &group.particles {
+            starts.push(next);
+            next += leaf_count(&particle.term);
+        }
+        let particles: Vec<(&Particle, usize)> = group.particles.iter().zip(starts).collect();
+        match group.compositor {
+            Compositor::Sequence => {
+                let parts = particles
+                    .iter()
+                    .map(|(particle, leaf)| self.particle(particle, *leaf))
+                    .collect();
+                self.sequence(parts)
+            }
+            Compositor::Choice => {
+                let parts = particles
+                    .iter()
+                    .map(|(particle, leaf)| self.particle(particle, *leaf))
+                    .collect();
+                self.choice(parts)
+            }
+            Compositor::All => self.counter(&particles),
+        }
+    }
+
+    /// Returns the fragment of the position of an element declaration or
+    /// wildcard, the leaf `leaf`.
+    fn position(&mut self, leaf: usize) -> Fragment {
+        let position = self.positions.len();
+        let mut slots = Vec::new();
+        let mut entry = Vec::new();
+        for (counter, slot) in &self.counters {
+            slots.extend(counter);
+            entry.extend((0..counter.len()).map(|index| (index == *slot) as u32));
+        }
+        self.positions.push(Position { leaf, slots, entry });
+        self.follow.push(Vec::new());
+        Fragment {
+            nullable: false,
+            first: vec![position],
+            last: vec![position],
+        }
+    }
+
+    /// Returns the fragment of a counter with a slot for each of
+    /// `particles`, given with the index of their first leaf: the
+    /// occurrences of their terms in any order, as many as each particle
+    /// allows. A single particle is a repeated particle, several the
+    /// particles of an `xs:all` group.
+    fn counter(&mut self, particles: &[(&Particle, usize)]) -> Fragment {
+        let counter: Vec<Slot> = particles
+            .iter()
+            .map(|(particle, _)| Slot {
+                min: match is_emptiable(&particle.term) {
+                    true => 0,
+                    false => particle.min_occurs,
+                },
+                max: particle.max_occurs,
+            })
+            .collect();
+        let mut terms = Vec::new();
+        for (slot, (particle, leaf)) in particles.iter().enumerate() {
+            if particle.max_occurs == MaxOccurs::Bounded(0) {
+                continue;
+            }
+            self.counters.push((counter.clone(), slot));
+            terms.push((slot, self.term(&particle.term, *leaf)));
+            self.counters.pop();
+        }
+        // Any occurrence can be followed by one of another slot, or of
+        // the same slot if it allows several
+        let kept = self.values() + counter.len();
+        for (from, source) in &terms {
+            for (to, target) in &terms {
+                if from == to && counter[*to].max <= MaxOccurs::Bounded(1) {
+                    continue;
+                }
+                let increment = Some(self.values() + to);
+                for position in &source.last {
+                    for next in &target.first {
+                        self.edge(*position, *next, kept, increment);
+                    }
+                }
+            }
+        }
+        let mut fragment = Fragment {
+            nullable: counter.iter().all(|slot| slot.min == 0),
+            first: Vec::new(),
+            last: Vec::new(),
+        };
+        for (_, term) in terms {
+            fragment.first.extend(term.first);
+            fragment.last.extend(term.last);
+        }
+        fragment
+    }
+
+    fn sequence(&mut self, parts: Vec<Fragment>) -> Fragment {
+        let kept = self.values();
+        let mut result = Fragment::empty();
+        for part in parts {
+            for from in &result.last {
+                for to in &part.first {
+                    self.edge(*from, *to, kept, None);
+                }
+            }
+            if result.nullable {
+                result.first.extend(&part.first);
+            }
+            result.last = match part.nullable {
+                true => result.last.into_iter().chain(part.last).collect(),
+                false => part.last,
+            };
+            result.nullable &= part.nullable;
+        }
+        result
+    }
+
+    fn choice(&mut self, parts: Vec<Fragment>) -> Fragment {
+        let mut result = Fragment {
+            nullable: false,
+            first: Vec::new(),
+            last: Vec::new(),
+        };
+        for part in parts {
+            result.nullable |= part.nullable;
+            result.first.extend(part.first);
+            result.last.extend(part.last);
+        }
+        result
+    }
+
+    /// Returns `fragment` repeated one or more times.
+    fn repeat(&mut self, fragment: Fragment) -> Fragment {
+        let kept = self.values();
+        for from in &fragment.last {
+            for to in &fragment.first {
+                self.edge(*from, *to, kept, None);
+            }
+        }
+        fragment
+    }
+
+    /// Returns the number of values of the counters around the part being
+    /// built.
+    fn values(&self) -> usize {
+        self.counters.iter().map(|(counter, _)| counter.len()).sum()
+    }
+
+    fn edge(&mut self, from: usize, target: usize, kept: usize, increment: Option<usize>) {
+        let edge = Edge {
+            target,
+            kept,
+            increment,
+        };
+        if !self.follow[from].contains(&edge) {
+            self.follow[from].push(edge);
+        }
+    }
+}
+
+/// A match in progress of the child elements of an element against a
+/// compiled content model.
+pub(crate) struct ContentRun<'m> {
+    model: &'m ContentModel,
+    state: RunState,
+}
+
+enum RunState {
+    /// No child has been matched yet
+    Start,
+    /// The last child matched the position, with one of the sets of
+    /// counter values
+    At(usize, Vec<Vec<u32>>),
+    /// The numbers of occurrences of the particles of an `xs:all` group
+    All(Vec<u32>),
+}
+
+impl ContentRun<'_> {
+    /// Matches the next child element, and returns the index of the leaf
+    /// of the model it matches, or `None` if the model does not allow it
+    /// here.
+    ///
+    /// # Arguments
+    ///
+    /// * `leaves` - The leaves of the model, as returned by [`leaves`].
+    /// * `matches` - Returns `true` if the child is accepted by an
+    ///   element declaration or wildcard.
+    ///
+    pub(crate) fn step(
+        &mut self,
+        leaves: &[&Term],
+        matches: &dyn Fn(&Term) -> bool,
+    ) -> Option<usize> {
+        let is_wildcard = |leaf: usize| matches!(leaves.get(leaf), Some(Term::Wildcard(_)));
+        let accepts = |leaf: usize| leaves.get(leaf).map_or(false, |term| matches(term));
+        match (self.model, &mut self.state) {
+            (ContentModel::All(all), RunState::All(counts)) => {
+                let index = (0..all.particles.len())
+                    .filter(|index| {
+                        let (leaf, _, max) = all.particles[*index];
+                        MaxOccurs::Bounded(counts[*index]) < max && accepts(leaf)
+                    })
+                    .min_by_key(|index| is_wildcard(all.particles[*index].0))?;
+                counts[index] += 1;
+                Some(all.particles[index].0)
+            }
+            (ContentModel::Positions(automaton), state) => {
+                let transitions = automaton.transitions(state);
+                let target = transitions
+                    .iter()
+                    .map(|(target, _)| *target)
+                    .filter(|target| accepts(automaton.positions[*target].leaf))
+                    .min_by_key(|target| {
+                        (is_wildcard(automaton.positions[*target].leaf), *target)
+                    })?;
+                let mut values: Vec<Vec<u32>> = transitions
+                    .into_iter()
+                    .filter(|(to, _)| *to == target)
+                    .map(|(_, values)| values)
+                    .collect();
+                values.sort_unstable();
+                values.dedup();
+                *state = RunState::At(target, values);
+                Some(automaton.positions[target].leaf)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if the children matched so far make up a complete
+    /// content.
+    pub(crate) fn is_complete(&self) -> bool {
+        match (self.model, &self.state) {
+            (ContentModel::All(all), RunState::All(counts)) => {
+                let complete = all
+                    .particles
+                    .iter()
+                    .zip(counts)
+                    .all(|((_, min, _), count)| count >= min);
+                complete || all.optional && counts.iter().all(|count| *count == 0)
+            }
+            (ContentModel::Positions(automaton), RunState::Start) => automaton.nullable,
+            (ContentModel::Positions(automaton), RunState::At(position, values)) => {
+                automaton.last[*position]
+                    && values
+                        .iter()
+                        .any(|values| automaton.can_leave(*position, values, 0))
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the indices of the leaves of the model that can match the
+    /// next child element, in the order of the model.
+    pub(crate) fn expected(&self) -> Vec<usize> {
+        let mut expected: Vec<usize> = match (self.model, &self.state) {
+            (ContentModel::All(all), RunState::All(counts)) => all
+                .particles
+                .iter()
+                .zip(counts)
+                .filter(|((_, _, max), count)| MaxOccurs::Bounded(**count) < *max)
+                .map(|((leaf, _, _), _)| *leaf)
+                .collect(),
+            (ContentModel::Positions(automaton), state) => automaton
+                .transitions(state)
+                .iter()
+                .map(|(target, _)| automaton.positions[*target].leaf)
+                .collect(),
+            _ => Vec::new(),
+        };
+        expected.sort_unstable();
+        expected.dedup();
+        expected
+    }
+}
+
+impl PositionAutomaton {
+    /// Returns the transitions that can be taken from `state`, as their
+    /// targets with the counter values they lead to.
+    fn transitions(&self, state: &RunState) -> Vec<(usize, Vec<u32>)> {
+        match state {
+            RunState::Start => self
+                .first
+                .iter()
+                .map(|target| (*target, self.positions[*target].entry.clone()))
+                .collect(),
+            RunState::At(position, values) => values
+                .iter()
+                .flat_map(|values| {
+                    self.follow[*position].iter().filter_map(move |edge| {
+                        self.take(*position, values, edge)
+                            .map(|values| (edge.target, values))
+                    })
+                })
+                .collect(),
+            RunState::All(_) => Vec::new(),
+        }
+    }
+
+    /// Returns the counter values `edge` leads to from `position` with
+    /// `values`, or `None` if it cannot be taken.
+    fn take(&self, position: usize, values: &[u32], edge: &Edge) -> Option<Vec<u32>> {
+        if !self.can_leave(position, values, edge.kept) {
+            return None;
+        }
+        let mut next = values[..edge.kept].to_vec();
+        next.extend(&self.positions[edge.target].entry[edge.kept..]);
+        if let Some(index) = edge.increment {
+            let slot = self.positions[position].slots[index];
+            if MaxOccurs::Bounded(values[index]) >= slot.max {
+                return None;
+            }
+            next[index] = slot.saturate(values[index] + 1);
+        }
+        Some(next)
+    }
+
+    /// Returns `true` if the slots of `position` past the first `kept`
+    /// reach their minimums with `values`.
+    fn can_leave(&self, position: usize, values: &[u32], kept: usize) -> bool {
+        values[kept..]
+            .iter()
+            .zip(&self.positions[position].slots[kept..])
+            .all(|(value, slot)| *value >= slot.min)
+    }
+}
+
+impl Slot {
+    /// Returns `count`, capped at the minimum of a slot without maximum,
+    /// as larger counts are equivalent.
+    fn saturate(&self, count: u32) -> u32 {
+        match self.max {
+            MaxOccurs::Unbounded => count.min(self.min.max(1)),
+            MaxOccurs::Bounded(_) => count,
+        }
+    }
+}
+
+/// Two particles of a content model that can both match the last child
+/// of a sequence of children, in violation of the Unique Particle
+/// Attribution constraint.
+pub(crate) struct Competition {
+    /// The leaf index of the particle taking precedence
+    pub(crate) first: usize,
+    /// The leaf index of the other particle
+    pub(crate) second: usize,
+    /// The leaf indices of the particles matching the children before
+    /// the ambiguous one
+    pub(crate) prefix: Vec<usize>,
+}
+
+/// The outcome of a search for the children leading to some counter
+/// values.
+enum Search {
+    /// The leaf indices of the particles matching the children
+    Found(Vec<usize>),
+    /// No children lead to such values
+    Unreachable,
+    /// The search reached [`SEARCH_LIMIT`] sets of values
+    Abandoned,
+}
+
+impl ContentModel {
+    /// Returns two particles that compete for the same child after some
+    /// sequence of children, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `competes` - Returns `true` if the particles of two leaves,
+    ///   given by their indices, can match the same element.
+    ///
+    pub(crate) fn competition(
+        &self,
+        competes: &dyn Fn(usize, usize) -> bool,
+    ) -> Option<Competition> {
+        let automaton = match self {
+            ContentModel::Positions(automaton) => automaton,
+            ContentModel::All(all) => {
+                let leaves: Vec<usize> = all.particles.iter().map(|(leaf, _, _)| *leaf).collect();
+                return pair(&leaves, competes).map(|(first, second)| Competition {
+                    first,
+                    second,
+                    prefix: Vec::new(),
+                });
+            }
+        };
+        let leaves: Vec<usize> = automaton
+            .first
+            .iter()
+            .map(|position| automaton.positions[*position].leaf)
+            .collect();
+        if let Some((first, second)) = pair(&leaves, competes) {
+            return Some(Competition {
+                first,
+                second,
+                prefix: Vec::new(),
+            });
+        }
+        let prefixes = automaton.prefixes();
+        for (position, prefix) in prefixes.iter().enumerate() {
+            let prefix = match prefix {
+                Some(prefix) => prefix,
+                None => continue,
+            };
+            let leaf = |edge: &Edge| automaton.positions[edge.target].leaf;
+            let edges = &automaton.follow[position];
+            let mut pairs = Vec::new();
+            for (index, edge) in edges.iter().enumerate() {
+                for other in &edges[index + 1..] {
+                    let (first, second) =
+                        (leaf(edge).min(leaf(other)), leaf(edge).max(leaf(other)));
+                    if first != second
+                        && !automaton.exclusive(position, edge, other)
+                        && competes(first, second)
+                    {
+                        pairs.push((first, second, edge, other));
+                    }
+                }
+            }
+            pairs.sort_by_key(|(first, second, _, _)| (*first, *second));
+            for (first, second, edge, other) in pairs {
+                let prefix = match automaton.search(position, edge, other) {
+                    Search::Found(prefix) => prefix,
+                    Search::Unreachable => continue,
+                    Search::Abandoned => prefix.clone(),
+                };
+                return Some(Competition {
+                    first,
+                    second,
+                    prefix,
+                });
+            }
+        }
+        None
+    }
+}
+
+impl PositionAutomaton {
+    /// Returns, for each position, the leaves of a shortest sequence of
+    /// children ending at the position, counters aside, or `None` if it
+    /// cannot be reached.
+    fn prefixes(&self) -> Vec<Option<Vec<usize>>> {
+        let mut prefixes: Vec<Option<Vec<usize>>> = vec![None; self.positions.len()];
+        let mut queue = VecDeque::new();
+        for position in &self.first {
+            if prefixes[*position].is_none() {
+                prefixes[*position] = Some(vec![self.positions[*position].leaf]);
+                queue.push_back(*position);
+            }
+        }
+        while let Some(position) = queue.pop_front() {
+            let prefix = prefixes[position].clone().unwrap_or_default();
+            for edge in &self.follow[position] {
+                if prefixes[edge.target].is_none() {
+                    let mut next = prefix.clone();
+                    next.push(self.positions[edge.target].leaf);
+                    prefixes[edge.target] = Some(next);
+                    queue.push_back(edge.target);
+                }
+            }
+        }
+        prefixes
+    }
+
+    /// Returns `true` if `first` and `second` can never both be taken
+    /// from `position`: one of them starts another occurrence of a slot
+    /// whose minimum is its maximum, which the other leaves.
+    fn exclusive(&self, position: usize, first: &Edge, second: &Edge) -> bool {
+        let slots = &self.positions[position].slots;
+        let excludes = |edge: &Edge, other: &Edge| match edge.increment {
+            Some(index) => {
+                index >= other.kept && MaxOccurs::Bounded(slots[index].min) >= slots[index].max
+            }
+            None => false,
+        };
+        excludes(first, second) || excludes(second, first)
+    }
+
+    /// Looks for a shortest sequence of children ending at `position`
+    /// with counter values from which both `first` and `second` can be
+    /// taken.
+    fn search(&self, position: usize, first: &Edge, second: &Edge) -> Search {
+        // Each position and counter values reached, with the index of the
+        // ones they were reached from
+        let mut reached: Vec<(Option<usize>, usize, Vec<u32>)> = Vec::new();
+        let mut seen = HashSet::new();
+        for target in &self.first {
+            let values = self.positions[*target].entry.clone();
+            if seen.insert((*target, values.clone())) {
+                reached.push((None, *target, values));
+            }
+        }
+        let mut index = 0;
+        while index < reached.len() {
+            let (current, values) = (reached[index].1, reached[index].2.clone());
+            if current == position
+                && self.take(current, &values, first).is_some()
+                && self.take(current, &values, second).is_some()
+            {
+                let mut prefix = Vec::new();
+                let mut at = Some(index);
+                while let Some(node) = at {
+                    prefix.push(self.positions[reached[node].1].leaf);
+                    at = reached[node].0;
+                }
+                prefix.reverse();
+                return Search::Found(prefix);
+            }
+            for edge in &self.follow[current] {
+                if let Some(next) = self.take(current, &values, edge) {
+                    if seen.len() == SEARCH_LIMIT {
+                        return Search::Abandoned;
+                    }
+                    if seen.insert((edge.target, next.clone())) {
+                        reached.push((Some(index), edge.target, next));
+                    }
+                }
+            }
+            index += 1;
+        }
+        Search::Unreachable
+    }
+}
+
+/// Returns the first two distinct leaves of `leaves` whose particles
+/// compete, in the order of the model.
+fn pair(leaves: &[usize], competes: &dyn Fn(usize, usize) -> bool) -> Option<(usize, usize)> {
+    let mut leaves = leaves.to_vec();
+    leaves.sort_unstable();
+    leaves.dedup();
+    leaves.iter().enumerate().find_map(|(index, first)| {
+        leaves[index + 1..]
+            .iter()
+            .find(|second| competes(*first, **second))
+            .map(|second| (*first, *second))
+    })
+}
+
+/// Compiles the content models of the complex types of `nodes`, global
+/// and anonymous, once the schema is resolved.
+pub(crate) fn compile_content_models(nodes: &mut [XmlSchemaNode]) {
+    for node in nodes {
+        match node {
+            XmlSchemaNode::ComplexType(complex_type) => compile_complex_type(complex_type),
+            XmlSchemaNode::Element(element) => compile_element(element),
+            XmlSchemaNode::Group(group) => group
+                .model_group
+                .particles
+                .iter_mut()
+                .for_each(compile_particle),
+            _ => {}
+        }
+    }
+}
+
+fn compile_complex_type(complex_type: &mut ComplexType) {
+    if let Some(particle) = &mut complex_type.particle {
+        complex_type.content_model = Some(ContentModel::compile(particle));
+        compile_particle(particle);
+    }
+}
+
+fn compile_element(element: &mut Element) {
+    if let Datatype::AnonymousComplexType(complex_type) = &mut element.datatype {
+        compile_complex_type(complex_type);
+    }
+}
+
+fn compile_particle(particle: &mut Particle) {
+    match &mut particle.term {
+        Term::Element(element) => compile_element(element),
+        Term::ModelGroup(group) => group.particles.iter_mut().for_each(compile_particle),
+        Term::Wildcard(_) | Term::GroupReference(_) => {}
+    }
+}
+
+/// Checks the content models of the complex types of `schema`, global and
+/// anonymous, against the Unique Particle Attribution and Element
+/// Declarations Consistent constraints.
+///
+/// # Arguments
+///
+/// * `schema` - The schema, once resolved and its content models
+///   compiled.
+/// * `documents` - The schema documents the schema is assembled from.
+///
+pub(crate) fn check_content_models(
+    schema: &XmlSchema,
+    documents: &[SchemaDocument],
+) -> Result<(), SchemaError> {
+    let checker = ModelChecker {
+        schema,
+        documents,
+        validator: Validator::new(schema, ""),
+        elements: schema
+            .nodes
+            .iter()
+            .filter_map(|node| match node {
+                XmlSchemaNode::Element(element) => Some((&element.name, element)),
+                _ => None,
+            })
+            .collect(),
+    };
+    for node in &schema.nodes {
+        match node {
+            XmlSchemaNode::ComplexType(complex_type) => {
+                let description = format!("type '{}'", complex_type.name);
+                checker.complex_type(complex_type, &description)?
+            }
+            XmlSchemaNode::Element(element) => checker.element(element)?,
+            XmlSchemaNode::Group(group) => {
+                for particle in &group.model_group.particles {
+                    checker.particle(particle)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Checks the content models of a schema.
+struct ModelChecker<'s> {
+    schema: &'s XmlSchema,
+    documents: &'s [SchemaDocument],
+    /// Matches elements against the particles of the models
+    validator: Validator<'s>,
+    /// The global element declarations, by name
+    elements: HashMap<&'s QName, &'s Element>,
+}
+
+impl<'s> ModelChecker<'s> {
+    /// Checks the content model of `complex_type`, then the anonymous
+    /// types of its element declarations.
+    ///
+    /// # Arguments
+    ///
+    /// * `complex_type` - The complex type.
+    /// * `description` - How the type is named in error messages.
+    ///
+    fn complex_type(
+        &self,
+        complex_type: &'s ComplexType,
+        description: &str,
+    ) -> Result<(), SchemaError> {
+        let (particle, model) = match (&complex_type.particle, &complex_type.content_model) {
+            (Some(particle), Some(model)) => (particle, model),
+            _ => return Ok(()),
+        };
+        let leaves = leaves(particle);
+        self.consistent(complex_type, &leaves, description)?;
+        let competes = |first: usize, second: usize| {
+            self.overlap(leaves[first], leaves[second], particle)
+                .is_some()
+        };
+        if let Some(competition) = model.competition(&competes) {
+            let (first, second) = (leaves[competition.first], leaves[competition.second]);
+            let mut sequence: Vec<String> = competition
+                .prefix
+                .iter()
+                .filter_map(|leaf| self.sample(leaves[*leaf], particle))
+                .map(|name| display_name(&name))
+                .collect();
+            sequence.extend(
+                self.overlap(first, second, particle)
+                    .map(|name| display_name(&name)),
+            );
+            return Err(definition_error(
+                self.documents,
+                complex_type,
+                "cos-nonambig",
+                &format!(
+                    "{} and {} (or elements from their substitution group) violate \
+                     \"Unique Particle Attribution\" in {}: the last element of the \
+                     sequence '{}' can be matched by either particle",
+                    leaf_name(first).unwrap_or_default(),
+                    leaf_name(second).unwrap_or_default(),
+                    description,
+                    sequence.join(", ")
+                ),
+            ));
+        }
+        self.particle(particle)
+    }
+
+    /// Checks the anonymous type of `element`, if it has one.
+    fn element(&self, element: &'s Element) -> Result<(), SchemaError> {
+        match &element.datatype {
+            Datatype::AnonymousComplexType(complex_type) => {
+                let description = format!("the anonymous type of element '{}'", element.name);
+                self.complex_type(complex_type, &description)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks the anonymous types of the element declarations of
+    /// `particle`.
+    fn particle(&self, particle: &'s Particle) -> Result<(), SchemaError> {
+        match &particle.term {
+            Term::Element(element) => self.element(element),
+            Term::ModelGroup(group) => group
+                .particles
+                .iter()
+                .try_for_each(|particle| self.particle(particle)),
+            Term::Wildcard(_) | Term::GroupReference(_) => Ok(()),
+        }
+    }
+
+    /// Checks that the element declarations of a content model, and the
+    /// members of the substitution groups they head, have the same type
+    /// when they have the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `complex_type` - The type of the content model.
+    /// * `leaves` - The leaves of the content model.
+    /// * `description` - How the type is named in error messages.
+    ///
+    fn consistent(
+        &self,
+        complex_type: &ComplexType,
+        leaves: &[&'s Term],
+        description: &str,
+    ) -> Result<(), SchemaError> {
+        let mut declarations: Vec<(&QName, &Datatype)> = Vec::new();
+        for leaf in leaves {
+            let element = match leaf {
+                Term::Element(element) => element,
+                _ => continue,
+            };
+            let declaration = match element.reference_id {
+                Some(reference) => SchemaGraph::new(self.schema).element(reference),
+                None => element,
+            };
+            declarations.push((&declaration.name, &declaration.datatype));
+            let mut members: Vec<&Element> = self.elements.values().copied().collect();
+            members.sort_by_key(|member| &member.name);
+            for member in members {
+                if member.name != element.name
+                    && self.validator.substitution(element, &member.name).is_some()
+                {
+                    declarations.push((&member.name, &member.datatype));
+                }
+            }
+        }
+        for (index, (name, datatype)) in declarations.iter().enumerate() {
+            let conflict = declarations[..index]
+                .iter()
+                .find(|(other, other_type)| other == name && !same_type(datatype, other_type));
+            if let Some((_, other_type)) = conflict {
+                return Err(definition_error(
+                    self.documents,
+                    complex_type,
+                    "cos-element-consistent",
+                    &format!(
+                        "Error for {}. Multiple elements with name '{}', with different \
+                         types ({} and {}), appear in the model group",
+                        description,
+                        name,
+                        type_name(other_type),
+                        type_name(datatype)
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the name of an element that both `first` and `second`
+    /// match, if they compete for the same elements.
+    ///
+    /// In XSD 1.1, an element declaration takes precedence over a
+    /// wildcard, and they do not compete.
+    fn overlap(&self, first: &Term, second: &Term, model: &Particle) -> Option<QName> {
+        let element_and_wildcard = matches!(
+            (first, second),
+            (Term::Element(_), Term::Wildcard(_)) | (Term::Wildcard(_), Term::Element(_))
+        );
+        if element_and_wildcard && self.schema.version == XsdVersion::V1_1 {
+            return None;
+        }
+        let mut candidates = self.candidates(first);
+        candidates.extend(self.candidates(second));
+        candidates.into_iter().find(|name| {
+            self.validator.accepts_child(first, name, model)
+                && self.validator.accepts_child(second, name, model)
+        })
+    }
+
+    /// Returns the name of an element that `term` matches, if any.
+    fn sample(&self, term: &Term, model: &Particle) -> Option<QName> {
+        self.candidates(term)
+            .into_iter()
+            .find(|name| self.validator.accepts_child(term, name, model))
+    }
+
+    /// Returns the names of elements that `term` may match: the name of
+    /// an element declaration and of every global element, which may be
+    /// in its substitution group, or names in the namespaces of a
+    /// wildcard.
+    fn candidates(&self, term: &Term) -> Vec<QName> {
+        let mut names = Vec::new();
+        match term {
+            Term::Element(element) => names.push(element.name.clone()),
+            Term::Wildcard(wildcard) => names.extend(sample_names(wildcard, self.schema)),
+            _ => {}
+        }
+        let mut globals: Vec<&QName> = self.elements.keys().copied().collect();
+        globals.sort();
+        names.extend(globals.into_iter().cloned());
+        names
+    }
+}
+
+/// Returns names in the namespaces a wildcard may allow.
+fn sample_names(wildcard: &Wildcard, schema: &XmlSchema) -> Vec<QName> {
+    let mut namespaces: Vec<Option<&str>> = Vec::new();
+    if let NamespaceConstraint::Enumeration(enumerated) = &wildcard.namespace {
+        namespaces.extend(enumerated.iter().map(Option::as_deref));
+    }
+    namespaces.push(schema.target_namespace.as_deref());
+    namespaces.push(None);
+    namespaces.push(Some("urn:example"));
+    namespaces
+        .into_iter()
+        .map(|namespace| QName::new(namespace, "any"))
+        .collect()
+}
+
+/// Returns `true` if `first` and `second` are the same type definition:
+/// the same named type, or the same anonymous type.
+fn same_type(first: &Datatype, second: &Datatype) -> bool {
+    match (first, second) {
+        (
+            Datatype::SimpleType(first) | Datatype::ComplexType(first),
+            Datatype::SimpleType(second) | Datatype::ComplexType(second),
+        ) => first == second,
+        (Datatype::AnonymousSimpleType(first), Datatype::AnonymousSimpleType(second)) => {
+            (first.document, first.position) == (second.document, second.position)
+        }
+        (Datatype::AnonymousComplexType(first), Datatype::AnonymousComplexType(second)) => {
+            (first.document, first.position) == (second.document, second.position)
+        }
+        _ => false,
+    }
+}
+
+/// Returns how the type `datatype` is named in error messages.
+fn type_name(datatype: &Datatype) -> String {
+    match datatype {
+        Datatype::SimpleType(name) | Datatype::ComplexType(name) => format!("'{}'", name),
+        Datatype::AnonymousSimpleType(_) | Datatype::AnonymousComplexType(_) => {
+            "an anonymous type".to_string()
+        }
+    }
+}
+
+/// Returns how the element declaration or wildcard `term` is named in
+/// error messages: the name of the element, or the namespace constraint
+/// of the wildcard.
+pub(crate) fn leaf_name(term: &Term) -> Option<String> {
+    match term {
+        Term::Element(element) => Some(display_name(&element.name)),
+        Term::Wildcard(wildcard) => Some(format!("WC[{}]", wildcard.namespace)),
+        _ => None,
+    }
+}
+
+/// Returns how the name of an element is written in error messages: its
+/// local name, after its quoted namespace name if it has one.
+fn display_name(name: &QName) -> String {
+    match name.namespace() {
+        Some(namespace) => format!("\"{}\":{}", namespace, name.local()),
+        None => name.local().to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+    use crate::{components::ModelGroup, schema::Schema};
+
+    /// Returns particles of the element declarations `names`, each
+    /// occurring once.
+    fn elements(names: &[&str]) -> Vec<Particle> {
+        let declarations: String = names
+            .iter()
+            .map(|name| format!(r#"<xs:element name="{}"/>"#, name))
+            .collect();
+        let input = format!(
+            r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+                <xs:complexType name="t"><xs:sequence>{}</xs:sequence></xs:complexType>
+            </xs:schema>"#,
+            declarations
+        );
+        let schema = Schema::from_str(&input).unwrap();
+        let particle = schema.complex_type("t").unwrap().particle.clone().unwrap();
+        match particle.term {
+            Term::ModelGroup(group) => group.particles,
+            _ => unreachable!("the content is a sequence"),
+        }
+    }
+
+    fn group(
+        compositor: Compositor,
+        particles: Vec<Particle>,
+        min: u32,
+        max: MaxOccurs,
+    ) -> Particle {
+        Particle {
+            min_occurs: min,
+            max_occurs: max,
+            term: Term::ModelGroup(ModelGroup {
+                compositor,
+                particles,
+            }),
+        }
+    }
+
+    /// Returns `true` if the children called `children` make up a
+    /// complete content of the model `particle`.
+    fn accepts(particle: &Particle, children: &[&str]) -> bool {
+        let model = ContentModel::compile(particle);
+        let leaves = leaves(particle);
+        let mut run = model.start();
+        for child in children {
+            let matches = |term: &Term| match term {
+                Term::Element(element) => element.name.local() == *child,
+                _ => false,
+            };
+            if run.step(&leaves, &matches).is_none() {
+                return false;
+            }
+        }
+        run.is_complete()
+    }
+
+    fn positions(particle: &Particle) -> usize {
+        match ContentModel::compile(particle) {
+            ContentModel::Positions(automaton) => automaton.positions.len(),
+            ContentModel::All(_) => 0,
+        }
+    }
+
+    #[test]
+    fn bounds_of_a_repeated_group() {
+        let sequence = group(
+            Compositor::Sequence,
+            elements(&["a", "b"]),
+            3000,
+            MaxOccurs::Bounded(3000),
+        );
+        assert_eq!(positions(&sequence), 2);
+        let children = |count: usize| ["a", "b"].repeat(count);
+        assert!(accepts(&sequence, &children(3000)));
+        assert!(!accepts(&sequence, &children(2999)));
+        assert!(!accepts(&sequence, &children(3001)));
+        let optional = group(
+            Compositor::Sequence,
+            vec![sequence],
+            0,
+            MaxOccurs::Bounded(1),
+        );
+        assert!(accepts(&optional, &[]));
+        assert!(!accepts(&optional, &children(1)));
+    }
+
+    #[test]
+    fn nested_repeated_groups() {
+        let mut particle = group(
+            Compositor::Sequence,
+            elements(&["a", "b"]),
+            1,
+            MaxOccurs::Bounded(60),
+        );
+        for _ in 0..3 {
+            particle = group(
+                Compositor::Sequence,
+                vec![particle],
+                1,
+                MaxOccurs::Bounded(60),
+            );
+        }
+        assert_eq!(positions(&particle), 2);
+        assert!(accepts(&particle, &["a", "b", "a", "b"]));
+        assert!(!accepts(&particle, &["a", "b", "a"]));
+        let exact = group(
+            Compositor::Choice,
+            vec![group(
+                Compositor::Sequence,
+                elements(&["a"]),
+                60,
+                MaxOccurs::Bounded(60),
+            )],
+            2,
+            MaxOccurs::Bounded(2),
+        );
+        assert!(accepts(&exact, &["a"; 120]));
+        assert!(!accepts(&exact, &["a"; 119]));
+        assert!(!accepts(&exact, &["a"; 121]));
+    }
+
+    #[test]
+    fn nested_all_group() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        let mut particles = elements(&names);
+        particles[7].min_occurs = 0;
+        particles[0].max_occurs = MaxOccurs::Bounded(2);
+        let all = group(Compositor::All, particles, 1, MaxOccurs::Bounded(1));
+        let model = group(
+            Compositor::Sequence,
+            vec![all, elements(&["z"]).remove(0)],
+            1,
+            MaxOccurs::Bounded(1),
+        );
+        assert_eq!(positions(&model), 9);
+        assert!(accepts(
+            &model,
+            &["g", "f", "e", "a", "d", "c", "b", "a", "h", "z"]
+        ));
+        assert!(accepts(&model, &["a", "b", "c", "d", "e", "f", "g", "z"]));
+        assert!(!accepts(&model, &["a", "b", "c", "d", "e", "f", "z"]));
+        assert!(!accepts(
+            &model,
+            &["a", "b", "c", "d", "e", "f", "g", "b", "z"]
+        ));
+        assert!(!accepts(
+            &model,
+            &["a", "a", "a", "b", "c", "d", "e", "f", "g", "z"]
+        ));
+    }
+
+    #[test]
+    fn competition_after_the_minimum_of_a_counter() {
+        let competition = |max: u32| {
+            let mut particles = elements(&["a", "b"]);
+            particles[1].min_occurs = 0;
+            let mut last = elements(&["a"]).remove(0);
+            last.min_occurs = 0;
+            let model = group(
+                Compositor::Sequence,
+                vec![
+                    group(Compositor::Sequence, particles, 2, MaxOccurs::Bounded(max)),
+                    last,
+                ],
+                1,
+                MaxOccurs::Bounded(1),
+            );
+            let leaves = leaves(&model);
+            let competes = |first: usize, second: usize| match (leaves[first], leaves[second]) {
+                (Term::Element(first), Term::Element(second)) => first.name == second.name,
+                _ => false,
+            };
+            ContentModel::compile(&model)
+                .competition(&competes)
+                .map(|competition| (competition.first, competition.second, competition.prefix))
+        };
+        assert_eq!(competition(3), Some((0, 2, vec![0, 0])));
+        assert!(competition(2).is_none());
+    }
+}
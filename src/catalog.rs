@@ -0,0 +1,455 @@
+//! OASIS XML Catalogs, mapping the locations and namespaces of schema
+//! documents to local copies.
+//!
+//! A catalog is read from an XML document in the OASIS catalog namespace.
+//! Its `system`, `rewriteSystem`, `systemSuffix` and `delegateSystem`
+//! entries map system identifiers, and its `uri`, `rewriteURI`,
+//! `uriSuffix` and `delegateURI` entries map URIs, following the
+//! resolution rules of the XML Catalogs 1.1 specification; `nextCatalog`
+//! entries chain further catalogs. Relative URIs in a catalog are
+//! resolved against its own location, or the `xml:base` in scope.
+//!
+
+use std::{fs, path::Path, str::FromStr};
+
+use crate::{
+    document::XmlElement,
+    error::SchemaError,
+    resolver::{resolve_uri, FileResolver, SchemaResolver, SchemaSource},
+};
+
+/// The namespace of OASIS XML Catalog documents.
+pub const CATALOG_NAMESPACE: &str = "urn:oasis:names:tc:entity:xmlns:xml:catalog";
+
+/// A loaded OASIS XML Catalog.
+///
+/// Catalogs chained with `nextCatalog` or delegated to with
+/// `delegateSystem` and `delegateURI` are read from the filesystem when
+/// the catalog is loaded. Catalogs that cannot be read are ignored, as
+/// the specification requires.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::Catalog;
+///
+/// let input = r#"<catalog xmlns="urn:oasis:names:tc:entity:xmlns:xml:catalog">
+///     <uri name="urn:example:order" uri="file:///schemas/order.xsd"/>
+///     <rewriteSystem systemIdStartString="http://example.com/schemas/"
+///                    rewritePrefix="file:///mirror/"/>
+/// </catalog>"#;
+/// match Catalog::from_str(input) {
+///     Ok(catalog) => {
+///         assert_eq!(
+///             catalog.resolve_uri("urn:example:order").as_deref(),
+///             Some("file:///schemas/order.xsd")
+///         );
+///         assert_eq!(
+///             catalog.resolve_system("http://example.com/schemas/common/types.xsd").as_deref(),
+///             Some("file:///mirror/common/types.xsd")
+///         );
+///         assert_eq!(catalog.resolve_system("http://example.org/other.xsd"), None);
+///     }
+///     Err(error) => eprintln!("Error loading catalog: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    entries: Vec<Entry>,
+}
+
+/// The kinds of identifiers a catalog maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Identifier {
+    /// System identifiers, mapped by the `system*` entries
+    System,
+    /// URIs, mapped by the `uri*` entries
+    Uri,
+}
+
+/// An entry of a catalog, with its URIs made absolute.
+#[derive(Debug, Clone)]
+enum Entry {
+    /// `system` or `uri`: maps one identifier
+    Exact {
+        kind: Identifier,
+        identifier: String,
+        uri: String,
+    },
+    /// `rewriteSystem` or `rewriteURI`: replaces a prefix of identifiers
+    Rewrite {
+        kind: Identifier,
+        prefix: String,
+        replacement: String,
+    },
+    /// `systemSuffix` or `uriSuffix`: maps the identifiers ending with a
+    /// suffix
+    Suffix {
+        kind: Identifier,
+        suffix: String,
+        uri: String,
+    },
+    /// `delegateSystem` or `delegateURI`: resolves the identifiers
+    /// starting with a prefix in other catalogs only
+    Delegate {
+        kind: Identifier,
+        prefix: String,
+        catalog: Catalog,
+    },
+    /// `nextCatalog`: a catalog tried when this one has no match
+    Next(Catalog),
+}
+
+impl Catalog {
+    /// Loads the catalog stored at `path`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the catalog file to load.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use xmlschema::{Catalog, CatalogResolver, FileResolver, Schema};
+    ///
+    /// let catalog = Catalog::from_file("fpml/catalog.xml").unwrap();
+    /// let resolver = CatalogResolver::new(catalog, FileResolver::default());
+    /// match Schema::from_location("http://www.fpml.org/FpML-5/confirmation/fpml-main-5-12.xsd", &resolver) {
+    ///     Ok(schema) => {
+    ///         // Do something with the loaded schema
+    ///     }
+    ///     Err(error) => eprintln!("Error loading schema: {}", error),
+    /// }
+    /// ```
+    ///
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Catalog, SchemaError> {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path).map_err(|err| SchemaError::Io {
+            message: format!("Cannot read {}: {}", path.display(), err),
+        })?;
+        let location = path.to_string_lossy();
+        Catalog::parse(&input, Some(&location), &mut vec![location.to_string()])
+    }
+
+    /// Returns the URI the system identifier `system_id` is mapped to, if
+    /// any.
+    pub fn resolve_system(&self, system_id: &str) -> Option<String> {
+        self.lookup(Identifier::System, system_id)
+    }
+
+    /// Returns the URI the URI `uri`, such as a namespace name, is mapped
+    /// to, if any.
+    pub fn resolve_uri(&self, uri: &str) -> Option<String> {
+        self.lookup(Identifier::Uri, uri)
+    }
+
+    /// Reads the catalog document `input`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text of the catalog document.
+    /// * `location` - The location of the document, if known.
+    /// * `loading` - The locations of the catalogs being loaded, to
+    ///   ignore circular references between catalogs.
+    ///
+    fn parse(
+        input: &str,
+        location: Option<&str>,
+        loading: &mut Vec<String>,
+    ) -> Result<Catalog, SchemaError> {
+        let root = XmlElement::parse(input)?;
+        if root.qname.namespace() != Some(CATALOG_NAMESPACE) || root.qname.local() != "catalog" {
+            return Err(SchemaError::Io {
+                message: format!(
+                    "{} is not an OASIS XML catalog",
+                    location.unwrap_or("The document")
+                ),
+            });
+        }
+        let mut catalog = Catalog::default();
+        catalog.read_entries(
+            &root,
+            with_base(&root, location.map(str::to_string)),
+            loading,
+        );
+        Ok(catalog)
+    }
+
+    /// Adds the entries among the children of `node` to the catalog.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `catalog` or `group` element.
+    /// * `base` - The base URI in scope on the element.
+    /// * `loading` - The locations of the catalogs being loaded.
+    ///
+    fn read_entries(&mut self, node: &XmlElement, base: Option<String>, loading: &mut Vec<String>) {
+        for child in node.child_elements() {
+            if child.qname.namespace() != Some(CATALOG_NAMESPACE) {
+                continue;
+            }
+            let base = with_base(child, base.clone());
+            let uri = |name: &str| {
+                child
+                    .attribute(name)
+                    .map(|value| resolve_uri(base.as_deref(), value.trim()))
+            };
+            // The kind of identifier each entry maps, with the attributes
+            // holding the identifier and the URI it maps to
+            let (kind, key, value) = match child.qname.local() {
+                "group" => {
+                    self.read_entries(child, base.clone(), loading);
+                    continue;
+                }
+                "nextCatalog" => {
+                    if let Some(catalog) = uri("catalog").and_then(|uri| load(&uri, loading)) {
+                        self.entries.push(Entry::Next(catalog));
+                    }
+                    continue;
+                }
+                "system" => (Identifier::System, "systemId", "uri"),
+                "rewriteSystem" => (Identifier::System, "systemIdStartString", "rewritePrefix"),
+                "systemSuffix" => (Identifier::System, "systemIdSuffix", "uri"),
+                "delegateSystem" => (Identifier::System, "systemIdStartString", "catalog"),
+                "uri" => (Identifier::Uri, "name", "uri"),
+                "rewriteURI" => (Identifier::Uri, "uriStartString", "rewritePrefix"),
+                "uriSuffix" => (Identifier::Uri, "uriSuffix", "uri"),
+                "delegateURI" => (Identifier::Uri, "uriStartString", "catalog"),
+                // Public identifiers do not apply to schema documents
+                _ => continue,
+            };
+            let (identifier, uri) = match (child.attribute(key), uri(value)) {
+                (Some(identifier), Some(uri)) => (identifier.trim().to_string(), uri),
+                _ => continue,
+            };
+            let entry = match child.qname.local() {
+                "system" | "uri" => Entry::Exact {
+                    kind,
+                    identifier,
+                    uri,
+                },
+                "rewriteSystem" | "rewriteURI" => Entry::Rewrite {
+                    kind,
+                    prefix: identifier,
+                    replacement: uri,
+                },
+                "systemSuffix" | "uriSuffix" => Entry::Suffix {
+                    kind,
+                    suffix: identifier,
+                    uri,
+                },
+                _ => match load(&uri, loading) {
+                    Some(catalog) => Entry::Delegate {
+                        kind,
+                        prefix: identifier,
+                        catalog,
+                    },
+                    None => continue,
+                },
+            };
+            self.entries.push(entry);
+        }
+    }
+
+    /// Returns the URI the identifier `identifier` of the kind `kind` is
+    /// mapped to, if any.
+    ///
+    /// The entries matching the identifier exactly win, then the rewrite
+    /// entry with the longest matching prefix, then the suffix entry with
+    /// the longest matching suffix. If delegate entries match, the
+    /// identifier is only looked up in their catalogs, longest prefix
+    /// first; otherwise the next catalogs are tried in order.
+    fn lookup(&self, kind: Identifier, identifier: &str) -> Option<String> {
+        let exact = self.entries.iter().find_map(|entry| match entry {
+            Entry::Exact {
+                kind: entry_kind,
+                identifier: entry_identifier,
+                uri,
+            } if *entry_kind == kind && entry_identifier == identifier => Some(uri.clone()),
+            _ => None,
+        });
+        if exact.is_some() {
+            return exact;
+        }
+        let rewrite = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Rewrite {
+                    kind: entry_kind,
+                    prefix,
+                    replacement,
+                } if *entry_kind == kind && identifier.starts_with(prefix.as_str()) => Some((
+                    prefix.len(),
+                    format!("{}{}", replacement, &identifier[prefix.len()..]),
+                )),
+                _ => None,
+            })
+            .max_by_key(|(length, _)| *length);
+        if let Some((_, uri)) = rewrite {
+            return Some(uri);
+        }
+        let suffix = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Suffix {
+                    kind: entry_kind,
+                    suffix,
+                    uri,
+                } if *entry_kind == kind && identifier.ends_with(suffix.as_str()) => {
+                    Some((suffix.len(), uri.clone()))
+                }
+                _ => None,
+            })
+            .max_by_key(|(length, _)| *length);
+        if let Some((_, uri)) = suffix {
+            return Some(uri);
+        }
+        let mut delegates: Vec<(usize, &Catalog)> = self
+            .entries
+            .iter()
+            .filter_map(|entry| match entry {
+                Entry::Delegate {
+                    kind: entry_kind,
+                    prefix,
+                    catalog,
+                } if *entry_kind == kind && identifier.starts_with(prefix.as_str()) => {
+                    Some((prefix.len(), catalog))
+                }
+                _ => None,
+            })
+            .collect();
+        if !delegates.is_empty() {
+            delegates.sort_by_key(|(length, _)| std::cmp::Reverse(*length));
+            return delegates
+                .into_iter()
+                .find_map(|(_, catalog)| catalog.lookup(kind, identifier));
+        }
+        self.entries.iter().find_map(|entry| match entry {
+            Entry::Next(catalog) => catalog.lookup(kind, identifier),
+            _ => None,
+        })
+    }
+}
+
+impl FromStr for Catalog {
+    type Err = SchemaError;
+
+    /// Loads a catalog from the text of a catalog document.
+    ///
+    /// Relative URIs in the catalog are resolved against the current
+    /// directory, unless an `xml:base` attribute is in scope.
+    fn from_str(input: &str) -> Result<Catalog, SchemaError> {
+        Catalog::parse(input, None, &mut Vec::new())
+    }
+}
+
+/// Loads the catalog at `uri`, a file path or `file:` URI, or returns
+/// `None` if it cannot be read or is already being loaded.
+fn load(uri: &str, loading: &mut Vec<String>) -> Option<Catalog> {
+    if loading.iter().any(|location| location == uri) {
+        return None;
+    }
+    let source = FileResolver::default()
+        .resolve(None, Some(uri), None)
+        .ok()?;
+    let input = std::str::from_utf8(source.content()).ok()?;
+    loading.push(uri.to_string());
+    let catalog = Catalog::parse(input, Some(uri), loading).ok();
+    loading.pop();
+    catalog
+}
+
+/// Returns the base URI in scope on `node`: its `xml:base` attribute
+/// resolved against the base URI `base` of its parent, or `base`.
+fn with_base(node: &XmlElement, base: Option<String>) -> Option<String> {
+    match node.attribute("xml:base") {
+        Some(value) => Some(resolve_uri(base.as_deref(), value.trim())),
+        None => base,
+    }
+}
+
+/// A resolver mapping the locations and namespaces of schema documents
+/// with an OASIS XML [`Catalog`], and reading them with another
+/// resolver.
+///
+/// The location of a reference, resolved against the base URI, is looked
+/// up as a URI, then as a system identifier; the namespace of an
+/// `xs:import` is looked up as a URI. The document is then read by the
+/// inner resolver from the location it is mapped to, or from its own
+/// location if the catalog has no match, so that unmapped `http:`
+/// locations are still refused by a [`FileResolver`].
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{Catalog, CatalogResolver, MemoryResolver, Schema};
+///
+/// let catalog = Catalog::from_str(
+///     r#"<catalog xmlns="urn:oasis:names:tc:entity:xmlns:xml:catalog">
+///         <rewriteURI uriStartString="http://example.com/schemas/" rewritePrefix="mirror/"/>
+///     </catalog>"#,
+/// )
+/// .unwrap();
+/// let mut mirror = MemoryResolver::new();
+/// mirror.insert(
+///     "mirror/note.xsd",
+///     r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///         <xs:element name="note" type="xs:string"/>
+///     </xs:schema>"#,
+/// );
+/// let resolver = CatalogResolver::new(catalog, mirror);
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:include schemaLocation="http://example.com/schemas/note.xsd"/>
+/// </xs:schema>"#;
+/// match Schema::from_str_with_resolver(input, None, &resolver) {
+///     Ok(schema) => assert!(schema.element("note").is_some()),
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct CatalogResolver<R = FileResolver> {
+    catalog: Catalog,
+    resolver: R,
+}
+
+impl<R: SchemaResolver> CatalogResolver<R> {
+    /// Creates a resolver mapping locations with `catalog` and reading
+    /// documents with `resolver`.
+    ///
+    /// # Arguments
+    ///
+    /// * `catalog` - The catalog mapping locations and namespaces.
+    /// * `resolver` - The resolver reading the documents.
+    ///
+    pub fn new(catalog: Catalog, resolver: R) -> Self {
+        Self { catalog, resolver }
+    }
+}
+
+impl<R: SchemaResolver> SchemaResolver for CatalogResolver<R> {
+    fn resolve(
+        &self,
+        base_uri: Option<&str>,
+        location: Option<&str>,
+        namespace: Option<&str>,
+    ) -> std::io::Result<SchemaSource> {
+        let mapped = location
+            .map(|location| resolve_uri(base_uri, location))
+            .and_then(|uri| {
+                self.catalog
+                    .resolve_uri(&uri)
+                    .or_else(|| self.catalog.resolve_system(&uri))
+            })
+            .or_else(|| namespace.and_then(|namespace| self.catalog.resolve_uri(namespace)));
+        match mapped {
+            Some(uri) => self.resolver.resolve(None, Some(&uri), None),
+            None => self.resolver.resolve(base_uri, location, namespace),
+        }
+    }
+}
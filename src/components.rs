@@ -0,0 +1,1647 @@
+//! The components of an XML Schema.
+//!
+//! These are the read-only views over the declarations and definitions
+//! found in a schema document, as returned by the accessors of
+//! [`Schema`](crate::Schema).
+//!
+
+use std::{
+    collections::HashMap,
+    fmt::{Display, Error, Formatter},
+    str::FromStr,
+};
+
+use crate::{
+    automaton::ContentModel,
+    datatypes::SimpleDatatype,
+    facets::Facet,
+    graph::{AttributeId, ElementId, TypeId},
+    qname::{QName, XSD_NAMESPACE},
+    xpath::XPath,
+    xpath2::Expression,
+};
+
+/// An `xs:element` declaration.
+///
+/// A global element can name other global elements as the heads of its
+/// substitution groups, and then appear wherever they are allowed,
+/// unless they block it. Its type must be derived from theirs, and it
+/// takes on the type of its first head if it declares none. Abstract
+/// elements cannot appear in instances, only the members of their
+/// substitution groups.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::Schema;
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:element name="shape" type="xs:string" abstract="true"/>
+///     <xs:element name="circle" substitutionGroup="shape"/>
+///     <xs:element name="drawing">
+///         <xs:complexType>
+///             <xs:sequence>
+///                 <xs:element ref="shape" maxOccurs="unbounded"/>
+///             </xs:sequence>
+///         </xs:complexType>
+///     </xs:element>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let circle = schema.element("circle").unwrap();
+///         assert_eq!(circle.substitution_group()[0].local(), "shape");
+///         assert!(schema.validate("<drawing><circle>r=1</circle></drawing>").is_ok());
+///         assert!(schema.validate("<drawing><shape>r=1</shape></drawing>").is_err());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Element {
+    /// The name of the element, or of the referenced element for
+    /// element references
+    pub(crate) name: QName,
+    /// The global element referenced by the declaration, if any
+    pub(crate) reference: Option<QName>,
+    /// The handle of the referenced element, once the schema is built
+    pub(crate) reference_id: Option<ElementId>,
+    /// The datatype of the element
+    pub(crate) datatype: Datatype,
+    /// The handle of the type of the element if it is a named type, once
+    /// the schema is built
+    pub(crate) type_id: Option<TypeId>,
+    /// The maximum number of times the element can occur
+    pub(crate) max_occurs: MaxOccurs,
+    /// The minimum number of times the element can occur
+    pub(crate) min_occurs: u32,
+    /// The value of the `default` attribute, if any
+    pub(crate) default_value: Option<String>,
+    /// The value of the `fixed` attribute, if any
+    pub(crate) fixed_value: Option<String>,
+    /// Whether the element can be nil
+    pub(crate) nillable: bool,
+    /// Whether the element is abstract
+    pub(crate) is_abstract: bool,
+    /// The heads of the substitution groups of the element
+    pub(crate) substitution_group: Vec<QName>,
+    /// The handles of the heads, once the schema is built
+    pub(crate) head_ids: Vec<ElementId>,
+    /// Whether the element declares no type of its own, and takes on the
+    /// type of the head of its substitution group
+    pub(crate) type_from_head: bool,
+    /// The substitutions and derivations blocked for the element
+    pub(crate) block_set: DerivationSet,
+    /// The derivations of the element's type that cannot be substituted
+    /// for it
+    pub(crate) final_set: DerivationSet,
+    /// The `xs:unique`, `xs:key` and `xs:keyref` constraints of the
+    /// element
+    pub(crate) identity_constraints: Vec<IdentityConstraint>,
+    /// The index of the schema document the definition is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the declaration in the schema document
+    pub(crate) position: usize,
+    /// The path of the declaration in the schema document
+    pub(crate) path: String,
+}
+
+impl Element {
+    /// Returns the expanded name of the element.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns the name of the global element referenced with `ref`, if
+    /// any.
+    pub fn reference(&self) -> Option<&QName> {
+        self.reference.as_ref()
+    }
+
+    /// Returns the handle of the global element referenced with `ref`, if
+    /// any, in the [`SchemaGraph`](crate::SchemaGraph) of the schema.
+    pub fn reference_id(&self) -> Option<ElementId> {
+        self.reference_id
+    }
+
+    /// Returns the datatype of the element.
+    pub fn datatype(&self) -> &Datatype {
+        &self.datatype
+    }
+
+    /// Returns the handle of the type of the element, in the
+    /// [`SchemaGraph`](crate::SchemaGraph) of the schema, if it is a
+    /// named type.
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.type_id
+    }
+
+    /// Returns the maximum number of times the element can occur.
+    pub fn max_occurs(&self) -> MaxOccurs {
+        self.max_occurs
+    }
+
+    /// Returns the minimum number of times the element can occur.
+    pub fn min_occurs(&self) -> u32 {
+        self.min_occurs
+    }
+
+    /// Returns the value of the `default` attribute, if any.
+    pub fn default_value(&self) -> Option<&str> {
+        self.default_value.as_deref()
+    }
+
+    /// Returns the value of the `fixed` attribute, if any.
+    pub fn fixed_value(&self) -> Option<&str> {
+        self.fixed_value.as_deref()
+    }
+
+    /// Returns `true` if instances of the element can be nil.
+    pub fn is_nillable(&self) -> bool {
+        self.nillable
+    }
+
+    /// Returns `true` if the element is abstract and must be substituted
+    /// in instances.
+    pub fn is_abstract(&self) -> bool {
+        self.is_abstract
+    }
+
+    /// Returns the names of the heads of the substitution groups the
+    /// element belongs to.
+    pub fn substitution_group(&self) -> &[QName] {
+        &self.substitution_group
+    }
+
+    /// Returns the substitutions and derivations blocked for the element,
+    /// from its `block` attribute or the `blockDefault` of the schema.
+    pub fn block_set(&self) -> DerivationSet {
+        self.block_set
+    }
+
+    /// Returns the derivations of the type of the element excluded from
+    /// its substitution group, from its `final` attribute or the
+    /// `finalDefault` of the schema.
+    pub fn final_set(&self) -> DerivationSet {
+        self.final_set
+    }
+
+    /// Returns the `xs:unique`, `xs:key` and `xs:keyref` constraints
+    /// declared on the element.
+    pub fn identity_constraints(&self) -> &[IdentityConstraint] {
+        &self.identity_constraints
+    }
+}
+
+/// An identity constraint of an element declaration: an `xs:unique`,
+/// `xs:key` or `xs:keyref` definition.
+///
+/// The selector picks out nodes among the descendants of the element,
+/// and the fields the values that identify each of them. The values of
+/// a unique or key constraint must differ between the selected nodes,
+/// every node selected by a key must have them, and the values of a
+/// keyref must be those of a node of the key it refers to.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{IdentityConstraintCategory, Schema};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:element name="library">
+///         <xs:complexType>
+///             <xs:sequence>
+///                 <xs:element name="book" maxOccurs="unbounded">
+///                     <xs:complexType>
+///                         <xs:attribute name="isbn" type="xs:string"/>
+///                     </xs:complexType>
+///                 </xs:element>
+///                 <xs:element name="loan" minOccurs="0" maxOccurs="unbounded">
+///                     <xs:complexType>
+///                         <xs:attribute name="book" type="xs:string"/>
+///                     </xs:complexType>
+///                 </xs:element>
+///             </xs:sequence>
+///         </xs:complexType>
+///         <xs:key name="isbn">
+///             <xs:selector xpath="book"/>
+///             <xs:field xpath="@isbn"/>
+///         </xs:key>
+///         <xs:keyref name="loaned" refer="isbn">
+///             <xs:selector xpath="loan"/>
+///             <xs:field xpath="@book"/>
+///         </xs:keyref>
+///     </xs:element>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let library = schema.element("library").unwrap();
+///         let key = &library.identity_constraints()[0];
+///         assert_eq!(key.category(), IdentityConstraintCategory::Key);
+///         assert_eq!(key.selector(), "book");
+///         assert!(schema
+///             .validate(r#"<library><book isbn="1"/><loan book="1"/></library>"#)
+///             .is_ok());
+///         let duplicate = schema.validate(r#"<library><book isbn="1"/><book isbn="1"/></library>"#);
+///         assert_eq!(duplicate.unwrap_err().code(), Some("cvc-identity-constraint.4.2.2"));
+///         let dangling = schema.validate(r#"<library><book isbn="1"/><loan book="2"/></library>"#);
+///         assert_eq!(dangling.unwrap_err().code(), Some("cvc-identity-constraint.4.3"));
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct IdentityConstraint {
+    pub(crate) name: QName,
+    pub(crate) category: IdentityConstraintCategory,
+    pub(crate) selector: XPath,
+    pub(crate) fields: Vec<XPath>,
+    /// The key or unique constraint a keyref refers to
+    pub(crate) refer: Option<QName>,
+    /// The index of the schema document the definition is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the definition in the schema document
+    pub(crate) position: usize,
+    /// The path of the definition in the schema document
+    pub(crate) path: String,
+}
+
+impl IdentityConstraint {
+    /// Returns the expanded name of the constraint.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns whether the constraint is a unique, key or keyref
+    /// constraint.
+    pub fn category(&self) -> IdentityConstraintCategory {
+        self.category
+    }
+
+    /// Returns the XPath expression of the selector, as written.
+    pub fn selector(&self) -> &str {
+        &self.selector.source
+    }
+
+    /// Returns an iterator over the XPath expressions of the fields, as
+    /// written.
+    pub fn fields(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(|field| field.source.as_str())
+    }
+
+    /// Returns the name of the key or unique constraint a keyref refers
+    /// to, or `None` for other constraints.
+    pub fn refer(&self) -> Option<&QName> {
+        self.refer.as_ref()
+    }
+}
+
+/// The kind of an [`IdentityConstraint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IdentityConstraintCategory {
+    /// `xs:unique`: the selected nodes that have values for every field
+    /// have distinct values
+    Unique,
+    /// `xs:key`: every selected node has values for every field, and
+    /// they are distinct
+    Key,
+    /// `xs:keyref`: the values of the selected nodes are those of a node
+    /// of the referenced key or unique constraint
+    KeyRef,
+}
+
+impl Display for IdentityConstraintCategory {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            IdentityConstraintCategory::Unique => write!(f, "unique"),
+            IdentityConstraintCategory::Key => write!(f, "key"),
+            IdentityConstraintCategory::KeyRef => write!(f, "keyref"),
+        }
+    }
+}
+
+/// The maximum number of times a particle can occur.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::MaxOccurs;
+///
+/// assert_eq!("unbounded".parse(), Ok(MaxOccurs::Unbounded));
+/// assert_eq!("3".parse(), Ok(MaxOccurs::Bounded(3)));
+/// assert!(MaxOccurs::Unbounded > MaxOccurs::Bounded(u32::MAX));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MaxOccurs {
+    /// At most the given number of times
+    Bounded(u32),
+    /// Any number of times
+    Unbounded,
+}
+
+impl Display for MaxOccurs {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            MaxOccurs::Bounded(max) => write!(f, "{}", max),
+            MaxOccurs::Unbounded => write!(f, "unbounded"),
+        }
+    }
+}
+
+impl FromStr for MaxOccurs {
+    type Err = String;
+
+    /// Parses a `maxOccurs` value: a non-negative integer or `unbounded`.
+    fn from_str(value: &str) -> Result<MaxOccurs, String> {
+        match value {
+            "unbounded" => Ok(MaxOccurs::Unbounded),
+            _ => parse_occurs(value).map(MaxOccurs::Bounded),
+        }
+    }
+}
+
+/// Parses an occurrence bound in the lexical space of
+/// `xs:nonNegativeInteger`.
+pub(crate) fn parse_occurs(value: &str) -> Result<u32, String> {
+    let digits = value.strip_prefix('+').unwrap_or(value);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err("not a non-negative integer".to_string());
+    }
+    digits
+        .parse()
+        .map_err(|_| "occurrence bound too large".to_string())
+}
+
+/// A kind of derivation or substitution that can be blocked or
+/// prohibited with the `block` and `final` attributes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Derivation {
+    /// Derivation by extension
+    Extension,
+    /// Derivation by restriction
+    Restriction,
+    /// Substitution group membership
+    Substitution,
+    /// Derivation by list
+    List,
+    /// Derivation by union
+    Union,
+}
+
+impl Derivation {
+    const ALL: [Derivation; 5] = [
+        Derivation::Extension,
+        Derivation::Restriction,
+        Derivation::Substitution,
+        Derivation::List,
+        Derivation::Union,
+    ];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl Display for Derivation {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Derivation::Extension => write!(f, "extension"),
+            Derivation::Restriction => write!(f, "restriction"),
+            Derivation::Substitution => write!(f, "substitution"),
+            Derivation::List => write!(f, "list"),
+            Derivation::Union => write!(f, "union"),
+        }
+    }
+}
+
+/// A set of [`Derivation`]s, as given by a `block`, `final`,
+/// `blockDefault` or `finalDefault` attribute.
+///
+/// `#all` is parsed as every kind of derivation; the set is narrowed to
+/// the kinds that apply when it is attached to a component.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{Derivation, DerivationSet};
+///
+/// let set: DerivationSet = "extension substitution".parse().unwrap();
+/// assert!(set.contains(Derivation::Extension));
+/// assert!(!set.contains(Derivation::Restriction));
+/// assert!("#all".parse::<DerivationSet>().unwrap().contains(Derivation::Union));
+/// ```
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct DerivationSet {
+    bits: u8,
+}
+
+impl DerivationSet {
+    /// Creates a set of the given derivations.
+    ///
+    /// # Arguments
+    ///
+    /// * `derivations` - The kinds of derivation in the set.
+    ///
+    pub fn new(derivations: &[Derivation]) -> Self {
+        Self {
+            bits: derivations
+                .iter()
+                .fold(0, |bits, derivation| bits | derivation.bit()),
+        }
+    }
+
+    /// Returns `true` if `derivation` is in the set.
+    pub fn contains(&self, derivation: Derivation) -> bool {
+        self.bits & derivation.bit() != 0
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bits == 0
+    }
+
+    /// Returns an iterator over the derivations in the set.
+    pub fn iter(&self) -> impl Iterator<Item = Derivation> + '_ {
+        Derivation::ALL
+            .into_iter()
+            .filter(move |derivation| self.contains(*derivation))
+    }
+
+    /// Returns the derivations in both this set and `other`.
+    pub(crate) fn intersection(self, other: DerivationSet) -> DerivationSet {
+        Self {
+            bits: self.bits & other.bits,
+        }
+    }
+
+    /// Returns the derivations in this set or `other`.
+    pub(crate) fn union(self, other: DerivationSet) -> DerivationSet {
+        Self {
+            bits: self.bits | other.bits,
+        }
+    }
+}
+
+impl FromStr for DerivationSet {
+    type Err = String;
+
+    /// Parses `#all` or a whitespace-separated list of derivation names.
+    fn from_str(value: &str) -> Result<DerivationSet, String> {
+        if value.trim() == "#all" {
+            return Ok(DerivationSet::new(&Derivation::ALL));
+        }
+        let mut set = DerivationSet::default();
+        for token in value.split_whitespace() {
+            let derivation = Derivation::ALL
+                .into_iter()
+                .find(|derivation| derivation.to_string() == token)
+                .ok_or_else(|| format!("unknown derivation '{}'", token))?;
+            set.bits |= derivation.bit();
+        }
+        Ok(set)
+    }
+}
+
+/// The type of an element or attribute declaration.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Datatype {
+    /// A reference to a simple type
+    SimpleType(QName),
+    /// A reference to a complex type
+    ComplexType(QName),
+    /// An anonymous simple type defined inside the declaration
+    AnonymousSimpleType(Box<SimpleType>),
+    /// An anonymous complex type defined inside the declaration
+    AnonymousComplexType(Box<ComplexType>),
+}
+
+/// An `xs:attribute` declaration.
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub(crate) name: QName,
+    pub(crate) reference: Option<QName>,
+    /// The handle of the referenced attribute, once the schema is built,
+    /// unless it is one of the attributes of the XML namespace known
+    /// without an import
+    pub(crate) reference_id: Option<AttributeId>,
+    pub(crate) datatype: Datatype,
+    /// The handle of the type of the attribute if it is a named type,
+    /// once the schema is built
+    pub(crate) type_id: Option<TypeId>,
+    pub(crate) default_value: Option<String>,
+    pub(crate) fixed_value: Option<String>,
+    pub(crate) use_option: UseOption,
+    /// The index of the schema document the definition is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the declaration in the schema document
+    pub(crate) position: usize,
+    /// The path of the declaration in the schema document
+    pub(crate) path: String,
+}
+
+impl Attribute {
+    /// Returns the expanded name of the attribute.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns the name of the global attribute referenced with `ref`, if
+    /// any.
+    pub fn reference(&self) -> Option<&QName> {
+        self.reference.as_ref()
+    }
+
+    /// Returns the handle of the global attribute referenced with `ref`,
+    /// if any, in the [`SchemaGraph`](crate::SchemaGraph) of the schema.
+    pub fn reference_id(&self) -> Option<AttributeId> {
+        self.reference_id
+    }
+
+    /// Returns the name the attribute is known by in a complex type: the
+    /// referenced name for references, its own name otherwise.
+    pub(crate) fn key(&self) -> QName {
+        self.reference.clone().unwrap_or_else(|| self.name.clone())
+    }
+
+    /// Returns the datatype of the attribute.
+    pub fn datatype(&self) -> &Datatype {
+        &self.datatype
+    }
+
+    /// Returns the handle of the type of the attribute, in the
+    /// [`SchemaGraph`](crate::SchemaGraph) of the schema, if it is a
+    /// named type.
+    pub fn type_id(&self) -> Option<TypeId> {
+        self.type_id
+    }
+
+    /// Returns the value of the `default` attribute, if any.
+    pub fn default_value(&self) -> Option<&str> {
+        self.default_value.as_deref()
+    }
+
+    /// Returns the value of the `fixed` attribute, if any.
+    pub fn fixed_value(&self) -> Option<&str> {
+        self.fixed_value.as_deref()
+    }
+
+    /// Returns whether the attribute is required, optional or prohibited.
+    pub fn use_option(&self) -> &UseOption {
+        &self.use_option
+    }
+}
+
+/// An `xs:attributeGroup` definition: a named set of attribute
+/// declarations that complex types include by reference.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::Schema;
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:attributeGroup name="dimensions">
+///         <xs:attribute name="width" type="xs:positiveInteger"/>
+///         <xs:attribute name="height" type="xs:positiveInteger"/>
+///     </xs:attributeGroup>
+///     <xs:element name="image">
+///         <xs:complexType>
+///             <xs:attributeGroup ref="dimensions"/>
+///         </xs:complexType>
+///     </xs:element>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let dimensions = schema.attribute_group("dimensions").unwrap();
+///         assert_eq!(dimensions.attributes().count(), 2);
+///         assert!(schema.validate(r#"<image width="640" height="480"/>"#).is_ok());
+///         assert!(schema.validate(r#"<image width="wide"/>"#).is_err());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct AttributeGroup {
+    pub(crate) name: QName,
+    /// The attributes declared by the group, then, once the schema is
+    /// resolved, those of the attribute groups it references
+    pub(crate) attributes: HashMap<QName, Attribute>,
+    /// The names of the attribute groups referenced by the group
+    pub(crate) attribute_groups: Vec<QName>,
+    pub(crate) attribute_wildcard: Option<Wildcard>,
+    /// The index of the schema document the definition is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the definition in the schema document
+    pub(crate) position: usize,
+    /// The path of the definition in the schema document
+    pub(crate) path: String,
+}
+
+impl AttributeGroup {
+    /// Returns the expanded name of the attribute group.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns the attribute declared by the group called `name`, if
+    /// any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name for attributes in no namespace.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        let name: QName = name.parse().ok()?;
+        self.attributes.get(&name)
+    }
+
+    /// Returns an iterator over the attributes declared by the group,
+    /// including those of the attribute groups it references.
+    pub fn attributes(&self) -> impl Iterator<Item = &Attribute> {
+        self.attributes.values()
+    }
+
+    /// Returns the `xs:anyAttribute` wildcard of the group, if any.
+    pub fn attribute_wildcard(&self) -> Option<&Wildcard> {
+        self.attribute_wildcard.as_ref()
+    }
+}
+
+/// The `use` of an attribute declaration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UseOption {
+    /// The attribute must be present
+    Required,
+    /// The attribute may be omitted
+    Optional,
+    /// The attribute must not appear
+    Prohibited,
+}
+
+/// An `xs:simpleType` definition.
+#[derive(Debug, Clone)]
+pub struct SimpleType {
+    pub(crate) name: QName,
+    pub(crate) base_type: Option<QName>,
+    /// The handle of the base type, once the schema is built
+    pub(crate) base_id: Option<TypeId>,
+    /// The anonymous simple type nested in the restriction, if the type
+    /// restricts one rather than a named type
+    pub(crate) anonymous_base: Option<Box<SimpleType>>,
+    pub(crate) datatype: SimpleDatatype,
+    pub(crate) variety: Variety,
+    /// The resolved item type of a list, or member types of a union
+    pub(crate) members: Vec<SimpleType>,
+    /// The facets declared by the restriction, then, once the schema is
+    /// resolved, merged with those of the base type
+    pub(crate) facets: Vec<Facet>,
+    /// The names of the facets that are fixed
+    pub(crate) fixed_facets: Vec<&'static str>,
+    /// The index of the schema document the definition is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the definition in the schema document
+    pub(crate) position: usize,
+    /// The path of the definition in the schema document
+    pub(crate) path: String,
+}
+
+impl SimpleType {
+    /// Returns the expanded name of the simple type.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns the name of the base type, if the type restricts another
+    /// simple type of the schema.
+    pub fn base_type(&self) -> Option<&QName> {
+        self.base_type.as_ref()
+    }
+
+    /// Returns the built-in datatype the simple type is based on, which
+    /// is `xs:anySimpleType` for list and union types.
+    pub fn datatype(&self) -> &SimpleDatatype {
+        &self.datatype
+    }
+
+    /// Returns whether the values of the simple type are atomic, lists
+    /// or unions, and the item or member types of lists and unions.
+    pub fn variety(&self) -> &Variety {
+        &self.variety
+    }
+
+    /// Returns the facets of the simple type: those it declares, and
+    /// those it inherits from its base types and does not override.
+    pub fn facets(&self) -> &[Facet] {
+        &self.facets
+    }
+
+    /// Returns the simple type of the built-in datatype `datatype`, with
+    /// no facets.
+    pub(crate) fn builtin(datatype: SimpleDatatype) -> Self {
+        Self {
+            name: QName::new(Some(XSD_NAMESPACE), datatype.name()),
+            base_type: None,
+            base_id: None,
+            anonymous_base: None,
+            datatype,
+            variety: Variety::Atomic,
+            members: Vec::new(),
+            facets: Vec::new(),
+            fixed_facets: Vec::new(),
+            document: 0,
+            position: 0,
+            path: String::new(),
+        }
+    }
+}
+
+/// The variety of a simple type.
+///
+/// Restrictions of a list or union type are lists or unions themselves,
+/// with the item or member types of their base type.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{Schema, Variety};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:simpleType name="sizes">
+///         <xs:list itemType="xs:positiveInteger"/>
+///     </xs:simpleType>
+///     <xs:element name="sizes" type="sizes"/>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let sizes = schema.simple_types().next().unwrap();
+///         assert!(matches!(sizes.variety(), Variety::List(_)));
+///         assert!(schema.validate("<sizes>8 10 12</sizes>").is_ok());
+///         assert!(schema.validate("<sizes>8 -10</sizes>").is_err());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Variety {
+    /// Values are single values of the built-in datatype
+    Atomic,
+    /// Values are whitespace-separated lists of values of the item type
+    List(Datatype),
+    /// Values are values of any of the member types, tried in order
+    Union(Vec<Datatype>),
+}
+
+/// An `xs:complexType` definition.
+///
+/// Once the schema is loaded, a type derived from another complex type
+/// carries the attributes and content model it inherits: an extension
+/// appends its particles to those of its base type, and a restriction is
+/// checked to only narrow down what its base type allows.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{Derivation, Schema};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:complexType name="name">
+///         <xs:sequence>
+///             <xs:element name="first" type="xs:string"/>
+///         </xs:sequence>
+///         <xs:attribute name="lang" type="xs:language"/>
+///     </xs:complexType>
+///     <xs:complexType name="fullName">
+///         <xs:complexContent>
+///             <xs:extension base="name">
+///                 <xs:sequence>
+///                     <xs:element name="last" type="xs:string"/>
+///                 </xs:sequence>
+///             </xs:extension>
+///         </xs:complexContent>
+///     </xs:complexType>
+///     <xs:element name="person" type="fullName"/>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let full_name = schema.complex_type("fullName").unwrap();
+///         assert_eq!(full_name.derivation(), Some(Derivation::Extension));
+///         assert!(full_name.attribute("lang").is_some());
+///         let person = r#"<person lang="en"><first>Ada</first><last>Lovelace</last></person>"#;
+///         assert!(schema.validate(person).is_ok());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct ComplexType {
+    pub(crate) name: QName,
+    pub(crate) base_type: Option<QName>,
+    /// The handle of the base type, once the schema is built
+    pub(crate) base_id: Option<TypeId>,
+    /// How the type is derived from its base type, if it has one
+    pub(crate) derivation: Option<Derivation>,
+    /// The attributes declared by the type, then, once the schema is
+    /// resolved, merged with those of the base type
+    pub(crate) attributes: HashMap<QName, Attribute>,
+    /// The names of the attribute groups referenced by the type
+    pub(crate) attribute_groups: Vec<QName>,
+    pub(crate) content: ComplexContent,
+    pub(crate) mixed_content: Option<String>,
+    /// The content model declared by the type, then, once the schema is
+    /// resolved, extended with that of the base type
+    pub(crate) particle: Option<Particle>,
+    /// The content model compiled from the particle once the schema is
+    /// resolved
+    pub(crate) content_model: Option<ContentModel>,
+    /// The type of the content of a type with simple content: the
+    /// facets declared by a restriction, then, once the schema is
+    /// resolved, the simple type they restrict
+    pub(crate) simple_content: Option<Box<SimpleType>>,
+    pub(crate) attribute_wildcard: Option<Wildcard>,
+    /// The assertions declared by the type, then, once the schema is
+    /// resolved, preceded by those of the base type
+    pub(crate) assertions: Vec<Assertion>,
+    /// Whether the type is abstract, and can only be used in instances
+    /// through the types derived from it
+    pub(crate) is_abstract: bool,
+    /// The derivations that cannot be substituted for the type in
+    /// instances
+    pub(crate) block_set: DerivationSet,
+    /// The derivations that cannot be derived from the type
+    pub(crate) final_set: DerivationSet,
+    /// The index of the schema document the definition is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the definition in the schema document
+    pub(crate) position: usize,
+    /// The path of the definition in the schema document
+    pub(crate) path: String,
+}
+
+impl ComplexType {
+    /// Returns the expanded name of the complex type.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns the name of the base type, if the type is derived.
+    pub fn base_type(&self) -> Option<&QName> {
+        self.base_type.as_ref()
+    }
+
+    /// Returns whether the type extends or restricts its base type, if
+    /// it is derived with `xs:simpleContent` or `xs:complexContent`.
+    pub fn derivation(&self) -> Option<Derivation> {
+        self.derivation
+    }
+
+    /// Returns the attribute declared by the type called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name for attributes in no namespace.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        let name: QName = name.parse().ok()?;
+        self.attributes.get(&name)
+    }
+
+    /// Returns an iterator over the attributes declared by the type.
+    pub fn attributes(&self) -> impl Iterator<Item = &Attribute> {
+        self.attributes.values()
+    }
+
+    /// Returns the kind of content of the type.
+    pub fn content(&self) -> &ComplexContent {
+        &self.content
+    }
+
+    /// Returns the value of the `mixed` attribute, if any.
+    pub fn mixed_content(&self) -> Option<&str> {
+        self.mixed_content.as_deref()
+    }
+
+    /// Returns `true` if character data can appear between the child
+    /// elements of the type.
+    pub(crate) fn is_mixed(&self) -> bool {
+        matches!(
+            self.mixed_content.as_deref().map(str::trim),
+            Some("true" | "1")
+        )
+    }
+
+    /// Returns the content model of the type: the particle its child
+    /// elements must match, if the type has element content.
+    ///
+    /// The content model of an extension is a sequence of the content
+    /// model of its base type followed by the particle it declares.
+    pub fn particle(&self) -> Option<&Particle> {
+        self.particle.as_ref()
+    }
+
+    /// Returns the simple type the content of the type must be a value
+    /// of, if the type has simple content.
+    pub fn simple_content(&self) -> Option<&SimpleType> {
+        self.simple_content.as_deref()
+    }
+
+    /// Returns the `xs:anyAttribute` wildcard of the type, if any.
+    pub fn attribute_wildcard(&self) -> Option<&Wildcard> {
+        self.attribute_wildcard.as_ref()
+    }
+
+    /// Returns the XSD 1.1 assertions every element of the type must
+    /// satisfy, those of its base types first.
+    pub fn assertions(&self) -> &[Assertion] {
+        &self.assertions
+    }
+
+    /// Returns `true` if the type is abstract: elements of the type must
+    /// select one of its derived types with `xsi:type`.
+    pub fn is_abstract(&self) -> bool {
+        self.is_abstract
+    }
+
+    /// Returns the derivations of the type that cannot be used in its
+    /// place in instances, from its `block` attribute or the
+    /// `blockDefault` of the schema.
+    pub fn block_set(&self) -> DerivationSet {
+        self.block_set
+    }
+
+    /// Returns the derivations by which no type can be derived from the
+    /// type, from its `final` attribute or the `finalDefault` of the
+    /// schema.
+    pub fn final_set(&self) -> DerivationSet {
+        self.final_set
+    }
+}
+
+/// An XSD 1.1 assertion: an `xs:assert` of a complex type, or an
+/// `xs:assertion` facet of a simple type.
+///
+/// The test of an assertion is an XPath 2.0 expression, and an element
+/// or value satisfies the assertion if the effective boolean value of
+/// the expression is true. The test of an `xs:assert` is evaluated with
+/// the element as the context node, the root of a tree of its own, and
+/// `$value` bound to the value of its simple content, if any; that of an
+/// `xs:assertion` facet has no context node, and `$value` bound to the
+/// value being checked.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{Schema, XsdVersion};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:complexType name="range">
+///         <xs:attribute name="min" type="xs:integer"/>
+///         <xs:attribute name="max" type="xs:integer"/>
+///         <xs:assert test="@min le @max"/>
+///     </xs:complexType>
+///     <xs:element name="range" type="range"/>
+/// </xs:schema>"#;
+/// match Schema::from_str_with_version(input, XsdVersion::V1_1) {
+///     Ok(schema) => {
+///         let range = schema.complex_type("range").unwrap();
+///         assert_eq!(range.assertions()[0].test(), "@min le @max");
+///         assert!(schema.validate(r#"<range min="2" max="10"/>"#).is_ok());
+///         let error = schema.validate(r#"<range min="20" max="10"/>"#).unwrap_err();
+///         assert_eq!(error.code(), Some("cvc-assertion"));
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+/// Integers and decimals are computed and compared exactly, whatever
+/// their number of digits:
+///
+/// ```
+/// use xmlschema::{Schema, XsdVersion};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:simpleType name="tenth">
+///         <xs:restriction base="xs:decimal">
+///             <xs:assertion test="$value + 0.2 = 0.3"/>
+///         </xs:restriction>
+///     </xs:simpleType>
+///     <xs:element name="tenth" type="tenth"/>
+///     <xs:element name="checks">
+///         <xs:complexType>
+///             <xs:assert test="not(12345678901234567.1 = 12345678901234567.2)"/>
+///             <xs:assert test="99999999999999999999 gt 9223372036854775806"/>
+///         </xs:complexType>
+///     </xs:element>
+/// </xs:schema>"#;
+/// match Schema::from_str_with_version(input, XsdVersion::V1_1) {
+///     Ok(schema) => {
+///         assert!(schema.validate("<tenth>0.1</tenth>").is_ok());
+///         assert!(schema.validate("<tenth>0.10000000000000001</tenth>").is_err());
+///         assert!(schema.validate("<checks/>").is_ok());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Assertion {
+    pub(crate) test: Expression,
+    /// The index of the schema document the assertion is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the assertion in the schema document
+    pub(crate) position: usize,
+    /// The path of the assertion in the schema document
+    pub(crate) path: String,
+}
+
+impl Assertion {
+    /// Returns the XPath expression of the test, as written.
+    pub fn test(&self) -> &str {
+        &self.test.source
+    }
+}
+
+/// A particle of a content model: a term with the number of times it
+/// can occur.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{Compositor, MaxOccurs, Schema, Term};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:complexType name="list">
+///         <xs:sequence>
+///             <xs:element name="item" type="xs:string" maxOccurs="unbounded"/>
+///         </xs:sequence>
+///     </xs:complexType>
+/// </xs:schema>"#;
+/// let schema = Schema::from_str(input).unwrap();
+/// let particle = schema.complex_type("list").unwrap().particle().unwrap();
+/// match particle.term() {
+///     Term::ModelGroup(group) => {
+///         assert_eq!(group.compositor(), Compositor::Sequence);
+///         assert_eq!(group.particles()[0].max_occurs(), MaxOccurs::Unbounded);
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Particle {
+    pub(crate) min_occurs: u32,
+    pub(crate) max_occurs: MaxOccurs,
+    pub(crate) term: Term,
+}
+
+impl Particle {
+    /// Returns the minimum number of times the term must occur.
+    pub fn min_occurs(&self) -> u32 {
+        self.min_occurs
+    }
+
+    /// Returns the maximum number of times the term can occur.
+    pub fn max_occurs(&self) -> MaxOccurs {
+        self.max_occurs
+    }
+
+    /// Returns the term of the particle.
+    pub fn term(&self) -> &Term {
+        &self.term
+    }
+}
+
+/// The term of a [`Particle`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Term {
+    /// A local element declaration, or a reference to a global one
+    Element(Box<Element>),
+    /// A nested model group
+    ModelGroup(ModelGroup),
+    /// An `xs:any` wildcard
+    Wildcard(Wildcard),
+    /// A reference to a named model group, replaced by its model group
+    /// once the schema is resolved
+    GroupReference(QName),
+}
+
+/// An `xs:sequence`, `xs:choice` or `xs:all` model group.
+#[derive(Debug, Clone)]
+pub struct ModelGroup {
+    pub(crate) compositor: Compositor,
+    pub(crate) particles: Vec<Particle>,
+}
+
+impl ModelGroup {
+    /// Returns how the particles of the group are combined.
+    pub fn compositor(&self) -> Compositor {
+        self.compositor
+    }
+
+    /// Returns the particles of the group, in document order.
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+}
+
+/// An `xs:group` definition: a named model group that content models
+/// include by reference.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{Compositor, Schema};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:group name="address">
+///         <xs:sequence>
+///             <xs:element name="street" type="xs:string"/>
+///             <xs:element name="city" type="xs:string"/>
+///         </xs:sequence>
+///     </xs:group>
+///     <xs:element name="shipTo">
+///         <xs:complexType>
+///             <xs:group ref="address"/>
+///         </xs:complexType>
+///     </xs:element>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let address = schema.group("address").unwrap();
+///         assert_eq!(address.model_group().compositor(), Compositor::Sequence);
+///         let valid = "<shipTo><street>1 Main St</street><city>Springfield</city></shipTo>";
+///         assert!(schema.validate(valid).is_ok());
+///         assert!(schema.validate("<shipTo><city>Springfield</city></shipTo>").is_err());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub(crate) name: QName,
+    pub(crate) model_group: ModelGroup,
+    /// The index of the schema document the definition is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the definition in the schema document
+    pub(crate) position: usize,
+    /// The path of the definition in the schema document
+    pub(crate) path: String,
+}
+
+impl Group {
+    /// Returns the expanded name of the group.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns the model group of the definition, in which references
+    /// to other groups are replaced by their model group once the schema
+    /// is resolved.
+    pub fn model_group(&self) -> &ModelGroup {
+        &self.model_group
+    }
+}
+
+/// An `xs:notation` declaration.
+///
+/// Notations name the formats of unparsed data, and are the values of
+/// the `xs:NOTATION` simple types of the schema.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::Schema;
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:notation name="png" public="image/png" system="viewer.exe"/>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let png = schema.notation("png").unwrap();
+///         assert_eq!(png.public(), Some("image/png"));
+///         assert_eq!(png.system(), Some("viewer.exe"));
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Notation {
+    pub(crate) name: QName,
+    /// The value of the `public` attribute, if any
+    pub(crate) public: Option<String>,
+    /// The value of the `system` attribute, if any
+    pub(crate) system: Option<String>,
+    /// The index of the schema document the declaration is read from,
+    /// among those the schema is assembled from
+    pub(crate) document: usize,
+    /// The byte offset of the declaration in the schema document
+    pub(crate) position: usize,
+    /// The path of the declaration in the schema document
+    pub(crate) path: String,
+}
+
+impl Notation {
+    /// Returns the expanded name of the notation.
+    pub fn name(&self) -> &QName {
+        &self.name
+    }
+
+    /// Returns the public identifier of the notation, if any.
+    pub fn public(&self) -> Option<&str> {
+        self.public.as_deref()
+    }
+
+    /// Returns the system identifier of the notation, if any.
+    pub fn system(&self) -> Option<&str> {
+        self.system.as_deref()
+    }
+}
+
+/// How the particles of a [`ModelGroup`] are combined.
+///
+/// The content model of an extension is that of its base type followed
+/// by the particles of the extension. In XSD 1.1, extending an `xs:all`
+/// group with another one gives a single `xs:all` group of the particles
+/// of both, which can then occur in any order.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{Schema, XsdVersion};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:complexType name="base">
+///         <xs:all>
+///             <xs:element name="a"/>
+///             <xs:element name="b"/>
+///         </xs:all>
+///     </xs:complexType>
+///     <xs:complexType name="extended">
+///         <xs:complexContent>
+///             <xs:extension base="base">
+///                 <xs:all><xs:element name="c"/></xs:all>
+///             </xs:extension>
+///         </xs:complexContent>
+///     </xs:complexType>
+///     <xs:element name="r" type="extended"/>
+/// </xs:schema>"#;
+/// match Schema::from_str_with_version(input, XsdVersion::V1_1) {
+///     Ok(schema) => {
+///         assert!(schema.validate("<r><c/><a/><b/></r>").is_ok());
+///         assert!(schema.validate("<r><b/><a/><c/></r>").is_ok());
+///         assert!(schema.validate("<r><a/><c/></r>").is_err());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compositor {
+    /// xs:sequence: every particle, in order
+    Sequence,
+    /// xs:choice: exactly one of the particles
+    Choice,
+    /// xs:all: every particle, in any order
+    All,
+}
+
+/// An `xs:any` or `xs:anyAttribute` wildcard.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{NamespaceConstraint, ProcessContents, Schema, Term};
+///
+/// let input = r###"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+///                           targetNamespace="urn:doc">
+///     <xs:element name="doc">
+///         <xs:complexType>
+///             <xs:sequence>
+///                 <xs:any namespace="##other" processContents="skip"/>
+///             </xs:sequence>
+///         </xs:complexType>
+///     </xs:element>
+/// </xs:schema>"###;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let doc = schema.element("doc").unwrap();
+///         let wildcard = match doc.datatype() {
+///             xmlschema::Datatype::AnonymousComplexType(complex_type) => {
+///                 match complex_type.particle().unwrap().term() {
+///                     Term::ModelGroup(group) => match group.particles()[0].term() {
+///                         Term::Wildcard(wildcard) => wildcard.clone(),
+///                         _ => unreachable!(),
+///                     },
+///                     _ => unreachable!(),
+///                 }
+///             }
+///             _ => unreachable!(),
+///         };
+///         assert!(matches!(wildcard.namespace(), NamespaceConstraint::Not(_)));
+///         assert_eq!(wildcard.process_contents(), ProcessContents::Skip);
+///         assert!(wildcard.allows(&"{urn:other}note".parse().unwrap()));
+///         assert!(!wildcard.allows(&"{urn:doc}note".parse().unwrap()));
+///         assert!(!wildcard.allows(&"note".parse().unwrap()));
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wildcard {
+    pub(crate) namespace: NamespaceConstraint,
+    /// The names excluded by the `notQName` attribute
+    pub(crate) not_qnames: Vec<QName>,
+    /// Whether `notQName` excludes the names of global declarations
+    /// (`##defined`)
+    pub(crate) not_defined: bool,
+    /// Whether `notQName` excludes the names of the elements declared in
+    /// the same content model (`##definedSibling`)
+    pub(crate) not_defined_sibling: bool,
+    pub(crate) process_contents: ProcessContents,
+}
+
+impl Wildcard {
+    /// Returns the namespace constraint of the wildcard, from its
+    /// `namespace` or `notNamespace` attribute.
+    pub fn namespace(&self) -> &NamespaceConstraint {
+        &self.namespace
+    }
+
+    /// Returns the names excluded from the wildcard by its `notQName`
+    /// attribute.
+    pub fn not_qnames(&self) -> &[QName] {
+        &self.not_qnames
+    }
+
+    /// Returns how the elements matched by the wildcard are validated.
+    pub fn process_contents(&self) -> ProcessContents {
+        self.process_contents
+    }
+
+    /// Returns `true` if the wildcard allows the name `name`: its
+    /// namespace is allowed by the namespace constraint, and the name is
+    /// not excluded by `notQName`.
+    ///
+    /// The `##defined` and `##definedSibling` exclusions depend on the
+    /// declarations of the schema and are applied during validation.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The expanded name of an element or attribute.
+    ///
+    pub fn allows(&self, name: &QName) -> bool {
+        self.namespace.allows(name.namespace()) && !self.not_qnames.contains(name)
+    }
+
+    /// Returns `true` if every name allowed by the wildcard is allowed by
+    /// `other`.
+    pub(crate) fn is_subset(&self, other: &Wildcard) -> bool {
+        self.namespace.is_subset(&other.namespace)
+            && other.not_qnames.iter().all(|name| !self.allows(name))
+            && (self.not_defined || !other.not_defined)
+            && (self.not_defined_sibling || !other.not_defined_sibling)
+    }
+
+    /// Returns the wildcard allowing the names allowed by either
+    /// wildcard, which validates them like `self`.
+    pub(crate) fn union(&self, other: &Wildcard) -> Wildcard {
+        let mut not_qnames: Vec<QName> = self
+            .not_qnames
+            .iter()
+            .filter(|name| !other.allows(name))
+            .cloned()
+            .collect();
+        for name in &other.not_qnames {
+            if !self.allows(name) && !not_qnames.contains(name) {
+                not_qnames.push(name.clone());
+            }
+        }
+        Wildcard {
+            namespace: self.namespace.union(&other.namespace),
+            not_qnames,
+            not_defined: self.not_defined && other.not_defined,
+            not_defined_sibling: self.not_defined_sibling && other.not_defined_sibling,
+            process_contents: self.process_contents,
+        }
+    }
+
+    /// Returns the wildcard allowing the names allowed by both
+    /// wildcards, which validates them like `self`.
+    pub(crate) fn intersection(&self, other: &Wildcard) -> Wildcard {
+        let namespace = self.namespace.intersection(&other.namespace);
+        let mut not_qnames: Vec<QName> = Vec::new();
+        for name in self.not_qnames.iter().chain(&other.not_qnames) {
+            if namespace.allows(name.namespace()) && !not_qnames.contains(name) {
+                not_qnames.push(name.clone());
+            }
+        }
+        Wildcard {
+            namespace,
+            not_qnames,
+            not_defined: self.not_defined || other.not_defined,
+            not_defined_sibling: self.not_defined_sibling || other.not_defined_sibling,
+            process_contents: self.process_contents,
+        }
+    }
+}
+
+/// The namespace constraint of a [`Wildcard`].
+///
+/// The `##targetNamespace` and `##local` keywords of the `namespace`
+/// attribute are replaced by the namespaces they stand for, and `##other`
+/// is the set of every namespace but the target namespace and no
+/// namespace.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::NamespaceConstraint;
+///
+/// let other = NamespaceConstraint::Not(vec![Some("urn:doc".to_string()), None]);
+/// assert!(other.allows(Some("urn:other")));
+/// assert!(!other.allows(Some("urn:doc")));
+/// assert!(!other.allows(None));
+/// assert_eq!(other.to_string(), "not urn:doc ##local");
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum NamespaceConstraint {
+    /// Every namespace, and no namespace (`##any`)
+    Any,
+    /// Only the listed namespaces, `None` standing for no namespace
+    Enumeration(Vec<Option<String>>),
+    /// Every namespace but the listed ones, `None` standing for no
+    /// namespace (`##other`, or the `notNamespace` attribute of XSD 1.1)
+    Not(Vec<Option<String>>),
+}
+
+impl NamespaceConstraint {
+    /// Returns `true` if the constraint allows the namespace
+    /// `namespace`, `None` standing for no namespace.
+    pub fn allows(&self, namespace: Option<&str>) -> bool {
+        let listed =
+            |namespaces: &[Option<String>]| namespaces.iter().any(|n| n.as_deref() == namespace);
+        match self {
+            NamespaceConstraint::Any => true,
+            NamespaceConstraint::Enumeration(namespaces) => listed(namespaces),
+            NamespaceConstraint::Not(namespaces) => !listed(namespaces),
+        }
+    }
+
+    /// Returns `true` if every namespace allowed by the constraint is
+    /// allowed by `other`.
+    pub(crate) fn is_subset(&self, other: &NamespaceConstraint) -> bool {
+        use NamespaceConstraint::*;
+        match (self, other) {
+            (_, Any) => true,
+            (Any, _) | (Not(_), Enumeration(_)) => false,
+            (Enumeration(namespaces), _) => namespaces
+                .iter()
+                .all(|namespace| other.allows(namespace.as_deref())),
+            (Not(namespaces), Not(others)) => others.iter().all(|n| namespaces.contains(n)),
+        }
+    }
+
+    /// Returns the constraint allowing the namespaces allowed by either
+    /// constraint.
+    pub(crate) fn union(&self, other: &NamespaceConstraint) -> NamespaceConstraint {
+        use NamespaceConstraint::*;
+        let not = |namespaces: Vec<Option<String>>| match namespaces.is_empty() {
+            true => Any,
+            false => Not(namespaces),
+        };
+        match (self, other) {
+            (Any, _) | (_, Any) => Any,
+            (Enumeration(left), Enumeration(right)) => {
+                let mut namespaces = left.clone();
+                namespaces.extend(right.iter().filter(|n| !left.contains(n)).cloned());
+                Enumeration(namespaces)
+            }
+            (Not(left), Not(right)) => {
+                not(left.iter().filter(|n| right.contains(n)).cloned().collect())
+            }
+            (Not(excluded), Enumeration(included)) | (Enumeration(included), Not(excluded)) => {
+                not(excluded
+                    .iter()
+                    .filter(|n| !included.contains(n))
+                    .cloned()
+                    .collect())
+            }
+        }
+    }
+
+    /// Returns the constraint allowing the namespaces allowed by both
+    /// constraints.
+    pub(crate) fn intersection(&self, other: &NamespaceConstraint) -> NamespaceConstraint {
+        use NamespaceConstraint::*;
+        match (self, other) {
+            (Any, constraint) | (constraint, Any) => constraint.clone(),
+            (Enumeration(left), Enumeration(right)) => {
+                Enumeration(left.iter().filter(|n| right.contains(n)).cloned().collect())
+            }
+            (Not(left), Not(right)) => {
+                let mut namespaces = left.clone();
+                namespaces.extend(right.iter().filter(|n| !left.contains(n)).cloned());
+                Not(namespaces)
+            }
+            (Not(excluded), Enumeration(included)) | (Enumeration(included), Not(excluded)) => {
+                Enumeration(
+                    included
+                        .iter()
+                        .filter(|n| !excluded.contains(n))
+                        .cloned()
+                        .collect(),
+                )
+            }
+        }
+    }
+}
+
+impl Display for NamespaceConstraint {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        let namespaces = match self {
+            NamespaceConstraint::Any => return write!(f, "##any"),
+            NamespaceConstraint::Enumeration(namespaces) => namespaces,
+            NamespaceConstraint::Not(namespaces) => {
+                write!(f, "not")?;
+                if namespaces.is_empty() {
+                    return Ok(());
+                }
+                write!(f, " ")?;
+                namespaces
+            }
+        };
+        for (index, namespace) in namespaces.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", namespace.as_deref().unwrap_or("##local"))?;
+        }
+        Ok(())
+    }
+}
+
+/// The `processContents` of a wildcard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessContents {
+    /// Matched items must be valid against a declaration
+    Strict,
+    /// Matched items are validated if a declaration is found
+    Lax,
+    /// Matched items are not validated
+    Skip,
+}
+
+/// The kind of content of a complex type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ComplexContent {
+    /// xs:all
+    All,
+    /// xs:choice
+    Choice,
+    /// No content
+    Empty,
+    /// A single element
+    Element,
+    /// xs:group
+    Group,
+    /// xs:sequence
+    Sequence,
+    /// xs:simpleContent
+    SimpleContent,
+    /// xs:union
+    Union,
+    /// Mixed content
+    MixedContent,
+    /// xs:complexContent
+    ComplexContentExtension,
+}
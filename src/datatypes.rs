@@ -0,0 +1,1392 @@
+//! The built-in datatypes of XML Schema.
+//!
+//! Every primitive and derived built-in simple type of XSD 1.0 and 1.1
+//! is a [`SimpleDatatype`], which knows the type it is derived from, how
+//! whitespace is normalized in its values, and how to parse its lexical
+//! representations into values that can be compared with each other.
+//!
+
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Error, Formatter},
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use crate::{
+    schema::XsdVersion,
+    tokenizer::{is_name_char, is_name_start_char},
+};
+
+/// A built-in XSD datatype.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::SimpleDatatype;
+///
+/// let datatype = SimpleDatatype::from_name("unsignedByte").unwrap();
+/// assert!(datatype.is_derived_from(SimpleDatatype::Integer));
+/// assert_eq!(datatype.primitive(), SimpleDatatype::Decimal);
+/// assert!(datatype.is_valid(" 255 "));
+/// assert!(!datatype.is_valid("256"));
+/// ```
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SimpleDatatype {
+    /// xs:anySimpleType
+    AnySimpleType,
+    /// xs:anyAtomicType
+    AnyAtomicType,
+    /// xs:string
+    String,
+    /// xs:normalizedString
+    NormalizedString,
+    /// xs:token
+    Token,
+    /// xs:language
+    Language,
+    /// xs:Name
+    Name,
+    /// xs:NCName
+    NcName,
+    /// xs:ID
+    Id,
+    /// xs:IDREF
+    IdRef,
+    /// xs:IDREFS
+    IdRefs,
+    /// xs:ENTITY
+    Entity,
+    /// xs:ENTITIES
+    Entities,
+    /// xs:NMTOKEN
+    NmToken,
+    /// xs:NMTOKENS
+    NmTokens,
+    /// xs:boolean
+    Boolean,
+    /// xs:decimal
+    Decimal,
+    /// xs:integer
+    Integer,
+    /// xs:nonPositiveInteger
+    NonPositiveInteger,
+    /// xs:negativeInteger
+    NegativeInteger,
+    /// xs:long
+    Long,
+    /// xs:int
+    Int,
+    /// xs:short
+    Short,
+    /// xs:byte
+    Byte,
+    /// xs:nonNegativeInteger
+    NonNegativeInteger,
+    /// xs:unsignedLong
+    UnsignedLong,
+    /// xs:unsignedInt
+    UnsignedInt,
+    /// xs:unsignedShort
+    UnsignedShort,
+    /// xs:unsignedByte
+    UnsignedByte,
+    /// xs:positiveInteger
+    PositiveInteger,
+    /// xs:float
+    Float,
+    /// xs:double
+    Double,
+    /// xs:duration
+    Duration,
+    /// xs:dayTimeDuration
+    DayTimeDuration,
+    /// xs:yearMonthDuration
+    YearMonthDuration,
+    /// xs:dateTime
+    DateTime,
+    /// xs:dateTimeStamp
+    DateTimeStamp,
+    /// xs:time
+    Time,
+    /// xs:date
+    Date,
+    /// xs:gYearMonth
+    GYearMonth,
+    /// xs:gYear
+    GYear,
+    /// xs:gMonthDay
+    GMonthDay,
+    /// xs:gDay
+    GDay,
+    /// xs:gMonth
+    GMonth,
+    /// xs:hexBinary
+    HexBinary,
+    /// xs:base64Binary
+    Base64Binary,
+    /// xs:anyURI
+    AnyUri,
+    /// xs:QName
+    QName,
+    /// xs:NOTATION
+    Notation,
+}
+
+/// The built-in datatypes with their local names in the XML Schema
+/// namespace.
+const BUILTINS: [(SimpleDatatype, &str); 49] = [
+    (SimpleDatatype::AnySimpleType, "anySimpleType"),
+    (SimpleDatatype::AnyAtomicType, "anyAtomicType"),
+    (SimpleDatatype::String, "string"),
+    (SimpleDatatype::NormalizedString, "normalizedString"),
+    (SimpleDatatype::Token, "token"),
+    (SimpleDatatype::Language, "language"),
+    (SimpleDatatype::Name, "Name"),
+    (SimpleDatatype::NcName, "NCName"),
+    (SimpleDatatype::Id, "ID"),
+    (SimpleDatatype::IdRef, "IDREF"),
+    (SimpleDatatype::IdRefs, "IDREFS"),
+    (SimpleDatatype::Entity, "ENTITY"),
+    (SimpleDatatype::Entities, "ENTITIES"),
+    (SimpleDatatype::NmToken, "NMTOKEN"),
+    (SimpleDatatype::NmTokens, "NMTOKENS"),
+    (SimpleDatatype::Boolean, "boolean"),
+    (SimpleDatatype::Decimal, "decimal"),
+    (SimpleDatatype::Integer, "integer"),
+    (SimpleDatatype::NonPositiveInteger, "nonPositiveInteger"),
+    (SimpleDatatype::NegativeInteger, "negativeInteger"),
+    (SimpleDatatype::Long, "long"),
+    (SimpleDatatype::Int, "int"),
+    (SimpleDatatype::Short, "short"),
+    (SimpleDatatype::Byte, "byte"),
+    (SimpleDatatype::NonNegativeInteger, "nonNegativeInteger"),
+    (SimpleDatatype::UnsignedLong, "unsignedLong"),
+    (SimpleDatatype::UnsignedInt, "unsignedInt"),
+    (SimpleDatatype::UnsignedShort, "unsignedShort"),
+    (SimpleDatatype::UnsignedByte, "unsignedByte"),
+    (SimpleDatatype::PositiveInteger, "positiveInteger"),
+    (SimpleDatatype::Float, "float"),
+    (SimpleDatatype::Double, "double"),
+    (SimpleDatatype::Duration, "duration"),
+    (SimpleDatatype::DayTimeDuration, "dayTimeDuration"),
+    (SimpleDatatype::YearMonthDuration, "yearMonthDuration"),
+    (SimpleDatatype::DateTime, "dateTime"),
+    (SimpleDatatype::DateTimeStamp, "dateTimeStamp"),
+    (SimpleDatatype::Time, "time"),
+    (SimpleDatatype::Date, "date"),
+    (SimpleDatatype::GYearMonth, "gYearMonth"),
+    (SimpleDatatype::GYear, "gYear"),
+    (SimpleDatatype::GMonthDay, "gMonthDay"),
+    (SimpleDatatype::GDay, "gDay"),
+    (SimpleDatatype::GMonth, "gMonth"),
+    (SimpleDatatype::HexBinary, "hexBinary"),
+    (SimpleDatatype::Base64Binary, "base64Binary"),
+    (SimpleDatatype::AnyUri, "anyURI"),
+    (SimpleDatatype::QName, "QName"),
+    (SimpleDatatype::Notation, "NOTATION"),
+];
+
+impl SimpleDatatype {
+    /// Returns the built-in datatype with the local name `name` in the
+    /// XML Schema namespace, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The local name of the datatype, such as `dateTime`.
+    ///
+    pub fn from_name(name: &str) -> Option<SimpleDatatype> {
+        BUILTINS
+            .iter()
+            .find(|(_, builtin)| *builtin == name)
+            .map(|(datatype, _)| *datatype)
+    }
+
+    /// Returns the local name of the datatype in the XML Schema
+    /// namespace.
+    pub fn name(&self) -> &'static str {
+        BUILTINS
+            .iter()
+            .find(|(datatype, _)| datatype == self)
+            .map(|(_, name)| *name)
+            .unwrap_or_default()
+    }
+
+    /// Returns the datatype this one is derived from, or `None` for
+    /// `xs:anySimpleType`.
+    ///
+    /// The primitive datatypes are derived from `xs:anyAtomicType`, and
+    /// the built-in list datatypes from `xs:anySimpleType`.
+    pub fn base(&self) -> Option<SimpleDatatype> {
+        use SimpleDatatype::*;
+        let base = match self {
+            AnySimpleType => return None,
+            AnyAtomicType | IdRefs | Entities | NmTokens => AnySimpleType,
+            String | Boolean | Decimal | Float | Double | Duration | DateTime | Time | Date
+            | GYearMonth | GYear | GMonthDay | GDay | GMonth | HexBinary | Base64Binary
+            | AnyUri | QName | Notation => AnyAtomicType,
+            NormalizedString => String,
+            Token => NormalizedString,
+            Language | Name | NmToken => Token,
+            NcName => Name,
+            Id | IdRef | Entity => NcName,
+            Integer => Decimal,
+            NonPositiveInteger | Long | NonNegativeInteger => Integer,
+            NegativeInteger => NonPositiveInteger,
+            Int => Long,
+            Short => Int,
+            Byte => Short,
+            UnsignedLong | PositiveInteger => NonNegativeInteger,
+            UnsignedInt => UnsignedLong,
+            UnsignedShort => UnsignedInt,
+            UnsignedByte => UnsignedShort,
+            DayTimeDuration | YearMonthDuration => Duration,
+            DateTimeStamp => DateTime,
+        };
+        Some(base)
+    }
+
+    /// Returns the primitive datatype this one is derived from, or the
+    /// datatype itself for primitive, list and special datatypes.
+    pub fn primitive(&self) -> SimpleDatatype {
+        let mut datatype = *self;
+        while let Some(base) = datatype.base() {
+            if base == SimpleDatatype::AnyAtomicType || base == SimpleDatatype::AnySimpleType {
+                break;
+            }
+            datatype = base;
+        }
+        datatype
+    }
+
+    /// Returns `true` if this datatype is `other` or derived from it.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - The candidate ancestor datatype.
+    ///
+    pub fn is_derived_from(&self, other: SimpleDatatype) -> bool {
+        let mut datatype = Some(*self);
+        while let Some(current) = datatype {
+            if current == other {
+                return true;
+            }
+            datatype = current.base();
+        }
+        false
+    }
+
+    /// Returns the item datatype of the built-in list datatypes
+    /// `xs:IDREFS`, `xs:ENTITIES` and `xs:NMTOKENS`.
+    pub fn item_type(&self) -> Option<SimpleDatatype> {
+        match self {
+            SimpleDatatype::IdRefs => Some(SimpleDatatype::IdRef),
+            SimpleDatatype::Entities => Some(SimpleDatatype::Entity),
+            SimpleDatatype::NmTokens => Some(SimpleDatatype::NmToken),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `lexical` is a valid lexical representation of
+    /// a value of the datatype, once its whitespace is normalized, as
+    /// defined by XSD 1.0.
+    ///
+    /// # Arguments
+    ///
+    /// * `lexical` - The text to check.
+    ///
+    pub fn is_valid(&self, lexical: &str) -> bool {
+        self.parse(lexical, XsdVersion::V1_0).is_ok()
+    }
+
+    /// Returns how whitespace is normalized in values of the datatype.
+    pub fn whitespace(&self) -> Whitespace {
+        if !self.is_derived_from(SimpleDatatype::String) {
+            if *self == SimpleDatatype::AnySimpleType || *self == SimpleDatatype::AnyAtomicType {
+                return Whitespace::Preserve;
+            }
+            return Whitespace::Collapse;
+        }
+        match self {
+            SimpleDatatype::String => Whitespace::Preserve,
+            SimpleDatatype::NormalizedString => Whitespace::Replace,
+            _ => Whitespace::Collapse,
+        }
+    }
+
+    /// Normalizes the whitespace of `lexical` and parses it into a value
+    /// of the datatype.
+    ///
+    /// # Arguments
+    ///
+    /// * `lexical` - The text to parse.
+    /// * `version` - The version of XML Schema whose lexical spaces apply:
+    ///   only XSD 1.1 has a year zero.
+    ///
+    pub(crate) fn parse(&self, lexical: &str, version: XsdVersion) -> Result<Value, String> {
+        let normalized = self.whitespace().normalize(lexical);
+        if let Some(item_type) = self.item_type() {
+            let items = normalized
+                .split(' ')
+                .filter(|item| !item.is_empty())
+                .map(|item| item_type.parse(item, version))
+                .collect::<Result<Vec<_>, _>>()?;
+            if items.is_empty() {
+                return Err(format!("'{}' must contain at least one item", lexical));
+            }
+            return Ok(Value::List(items));
+        }
+        self.parse_atomic(&normalized, version)
+            .map_err(|reason| format!("'{}' is not a valid {}: {}", lexical, self, reason))
+    }
+
+    fn parse_atomic(&self, value: &str, version: XsdVersion) -> Result<Value, String> {
+        use SimpleDatatype::*;
+        match self {
+            AnySimpleType | AnyAtomicType | String | NormalizedString | Token | AnyUri => {
+                Ok(Value::String(value.to_string()))
+            }
+            Language => {
+                let mut parts = value.split('-');
+                let valid = parts.next().map_or(false, |primary| {
+                    (1..=8).contains(&primary.len())
+                        && primary.bytes().all(|byte| byte.is_ascii_alphabetic())
+                }) && parts.all(|part| {
+                    (1..=8).contains(&part.len())
+                        && part.bytes().all(|byte| byte.is_ascii_alphanumeric())
+                });
+                valid
+                    .then(|| Value::String(value.to_string()))
+                    .ok_or_else(|| "not a language tag".to_string())
+            }
+            Name => is_name(value)
+                .then(|| Value::String(value.to_string()))
+                .ok_or_else(|| "not an XML name".to_string()),
+            NcName | Id | IdRef | Entity => is_ncname(value)
+                .then(|| Value::String(value.to_string()))
+                .ok_or_else(|| "not a name without colons".to_string()),
+            NmToken => (!value.is_empty() && value.chars().all(is_name_char))
+                .then(|| Value::String(value.to_string()))
+                .ok_or_else(|| "not a name token".to_string()),
+            QName | Notation => {
+                let valid = match value.split_once(':') {
+                    Some((prefix, local)) => is_ncname(prefix) && is_ncname(local),
+                    None => is_ncname(value),
+                };
+                valid
+                    .then(|| Value::QName(value.to_string()))
+                    .ok_or_else(|| "not a qualified name".to_string())
+            }
+            Boolean => match value {
+                "true" | "1" => Ok(Value::Boolean(true)),
+                "false" | "0" => Ok(Value::Boolean(false)),
+                _ => Err("not a boolean".to_string()),
+            },
+            Decimal => self::Decimal::parse(value, true).map(Value::Decimal),
+            Integer | NonPositiveInteger | NegativeInteger | Long | Int | Short | Byte
+            | NonNegativeInteger | UnsignedLong | UnsignedInt | UnsignedShort | UnsignedByte
+            | PositiveInteger => {
+                let decimal = self::Decimal::parse(value, false)?;
+                if let Some((min, max)) = self.integer_range() {
+                    let in_range = decimal
+                        .to_i128()
+                        .map_or(false, |integer| min <= integer && integer <= max);
+                    if !in_range {
+                        return Err(format!("out of range [{}, {}]", min, max));
+                    }
+                }
+                Ok(Value::Decimal(decimal))
+            }
+            Float | Double => parse_float(value).map(Value::Double),
+            Duration | DayTimeDuration | YearMonthDuration => {
+                let duration = self::Duration::parse(value)?;
+                if *self == DayTimeDuration && duration.has_months {
+                    return Err("years and months are not allowed".to_string());
+                }
+                if *self == YearMonthDuration && duration.has_seconds {
+                    return Err("days and times are not allowed".to_string());
+                }
+                Ok(Value::Duration(duration))
+            }
+            DateTime | DateTimeStamp | Time | Date | GYearMonth | GYear | GMonthDay | GDay
+            | GMonth => {
+                let date_time = self::DateTime::parse(value, *self, version)?;
+                if *self == DateTimeStamp && date_time.timezone.is_none() {
+                    return Err("a timezone is required".to_string());
+                }
+                Ok(Value::DateTime(date_time))
+            }
+            HexBinary => parse_hex(value).map(Value::Binary),
+            Base64Binary => parse_base64(value).map(Value::Binary),
+            IdRefs | Entities | NmTokens => unreachable!("list datatypes are parsed as lists"),
+        }
+    }
+
+    /// Returns the bounds of the built-in integer datatypes with a
+    /// bounded value space.
+    fn integer_range(&self) -> Option<(i128, i128)> {
+        use SimpleDatatype::*;
+        let range = match self {
+            NonPositiveInteger => (i128::MIN, 0),
+            NegativeInteger => (i128::MIN, -1),
+            Long => (i64::MIN.into(), i64::MAX.into()),
+            Int => (i32::MIN.into(), i32::MAX.into()),
+            Short => (i16::MIN.into(), i16::MAX.into()),
+            Byte => (i8::MIN.into(), i8::MAX.into()),
+            NonNegativeInteger => (0, i128::MAX),
+            UnsignedLong => (0, u64::MAX.into()),
+            UnsignedInt => (0, u32::MAX.into()),
+            UnsignedShort => (0, u16::MAX.into()),
+            UnsignedByte => (0, u8::MAX.into()),
+            PositiveInteger => (1, i128::MAX),
+            _ => return None,
+        };
+        Some(range)
+    }
+}
+
+impl Display for SimpleDatatype {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How whitespace is normalized in a value before it is parsed, as set
+/// by the `xs:whiteSpace` facet.
+///
+/// The variants are ordered from the least to the most normalizing, and
+/// a restriction can only move up this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Whitespace {
+    /// The value is kept as is
+    Preserve,
+    /// Tabs, line feeds and carriage returns are replaced by spaces
+    Replace,
+    /// Whitespace is replaced, then runs of spaces are collapsed to a
+    /// single one and leading and trailing spaces removed
+    Collapse,
+}
+
+impl Whitespace {
+    /// Returns `value` with its whitespace normalized.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The text to normalize.
+    ///
+    pub fn normalize(&self, value: &str) -> String {
+        match self {
+            Whitespace::Preserve => value.to_string(),
+            Whitespace::Replace => value
+                .chars()
+                .map(|ch| {
+                    if matches!(ch, '\t' | '\n' | '\r') {
+                        ' '
+                    } else {
+                        ch
+                    }
+                })
+                .collect(),
+            Whitespace::Collapse => value
+                .split([' ', '\t', '\n', '\r'])
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+impl Display for Whitespace {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Whitespace::Preserve => write!(f, "preserve"),
+            Whitespace::Replace => write!(f, "replace"),
+            Whitespace::Collapse => write!(f, "collapse"),
+        }
+    }
+}
+
+impl FromStr for Whitespace {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "preserve" => Ok(Whitespace::Preserve),
+            "replace" => Ok(Whitespace::Replace),
+            "collapse" => Ok(Whitespace::Collapse),
+            _ => Err(format!(
+                "'{}' must be 'preserve', 'replace' or 'collapse'",
+                value
+            )),
+        }
+    }
+}
+
+/// A value of a simple datatype.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    /// A string, or a value of a type derived from `xs:string`
+    String(String),
+    /// A boolean
+    Boolean(bool),
+    /// A decimal or integer
+    Decimal(Decimal),
+    /// A float or double
+    Double(f64),
+    /// A duration
+    Duration(Duration),
+    /// A date, a time, or a date and time
+    DateTime(DateTime),
+    /// The octets of a binary value
+    Binary(Vec<u8>),
+    /// A qualified name, as written
+    QName(String),
+    /// The items of a list
+    List(Vec<Value>),
+}
+
+// `NaN` is the only value not equal to itself, so that it never matches
+// another value, such as in the key tables of identity constraints.
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::String(value) | Value::QName(value) => value.hash(state),
+            Value::Boolean(value) => value.hash(state),
+            Value::Decimal(value) => value.hash(state),
+            Value::Double(value) => hash_f64(*value, state),
+            Value::Duration(value) => value.hash(state),
+            Value::DateTime(value) => value.hash(state),
+            Value::Binary(value) => value.hash(state),
+            Value::List(values) => values.hash(state),
+        }
+    }
+}
+
+/// Feeds `value` into `state`, so that the equal values `0.0` and `-0.0`
+/// have the same hash.
+fn hash_f64<H: Hasher>(value: f64, state: &mut H) {
+    let value = if value == 0.0 { 0.0 } else { value };
+    value.to_bits().hash(state);
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Decimal(left), Value::Decimal(right)) => Some(left.cmp(right)),
+            (Value::Double(left), Value::Double(right)) => left.partial_cmp(right),
+            (Value::Duration(left), Value::Duration(right)) => left.partial_cmp(right),
+            (Value::DateTime(left), Value::DateTime(right)) => left.partial_cmp(right),
+            _ => (self == other).then_some(Ordering::Equal),
+        }
+    }
+}
+
+/// An arbitrary-precision decimal number.
+///
+/// The digits are kept without leading zeros in the integer part or
+/// trailing zeros in the fraction, so equal values have equal
+/// representations.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Decimal {
+    negative: bool,
+    integer: String,
+    fraction: String,
+}
+
+impl Decimal {
+    /// The number of digits kept in the fraction of a quotient, beyond
+    /// those of the operands.
+    const DIVISION_DIGITS: usize = 18;
+
+    /// Parses an `xs:decimal`, or an `xs:integer` if `fraction` is
+    /// `false`.
+    pub(crate) fn parse(value: &str, fraction: bool) -> Result<Decimal, String> {
+        let (negative, unsigned) = match value.as_bytes().first() {
+            Some(b'-') => (true, &value[1..]),
+            Some(b'+') => (false, &value[1..]),
+            _ => (false, value),
+        };
+        let (integer, fractional) = match unsigned.split_once('.') {
+            Some(parts) if fraction => parts,
+            _ => (unsigned, ""),
+        };
+        let digits = |digits: &str| digits.bytes().all(|byte| byte.is_ascii_digit());
+        if (integer.is_empty() && fractional.is_empty()) || !digits(integer) || !digits(fractional)
+        {
+            return Err("not a number".to_string());
+        }
+        let integer = integer.trim_start_matches('0').to_string();
+        let fraction = fractional.trim_end_matches('0').to_string();
+        let zero = integer.is_empty() && fraction.is_empty();
+        Ok(Decimal {
+            negative: negative && !zero,
+            integer,
+            fraction,
+        })
+    }
+
+    /// Returns the value as an `i128`, if it is an integer in range.
+    fn to_i128(&self) -> Option<i128> {
+        if !self.fraction.is_empty() {
+            return None;
+        }
+        let magnitude: i128 = match self.integer.as_str() {
+            "" => 0,
+            digits => digits.parse().ok()?,
+        };
+        Some(if self.negative { -magnitude } else { magnitude })
+    }
+
+    /// Returns the number of significant digits of the value.
+    pub(crate) fn total_digits(&self) -> usize {
+        (self.integer.len() + self.fraction.len()).max(1)
+    }
+
+    /// Returns the number of digits of the fraction of the value.
+    pub(crate) fn fraction_digits(&self) -> usize {
+        self.fraction.len()
+    }
+
+    fn cmp_magnitude(&self, other: &Decimal) -> Ordering {
+        self.integer
+            .len()
+            .cmp(&other.integer.len())
+            .then_with(|| self.integer.cmp(&other.integer))
+            .then_with(|| self.fraction.cmp(&other.fraction))
+    }
+
+    /// Returns the decimal closest to the finite number `number`, or
+    /// `None` if it is infinite or `NaN`.
+    pub(crate) fn from_f64(number: f64) -> Option<Decimal> {
+        match number.is_finite() {
+            // Numbers are displayed without an exponent
+            true => Decimal::parse(&number.to_string(), true).ok(),
+            false => None,
+        }
+    }
+
+    /// Returns the nearest `f64` to the value.
+    pub(crate) fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or(f64::NAN)
+    }
+
+    /// Returns the value as an `i64`, if it is an integer in range.
+    pub(crate) fn to_i64(&self) -> Option<i64> {
+        self.to_i128()?.try_into().ok()
+    }
+
+    /// Returns `true` if the value is zero.
+    pub(crate) fn is_zero(&self) -> bool {
+        self.integer.is_empty() && self.fraction.is_empty()
+    }
+
+    /// Returns the value with the opposite sign.
+    pub(crate) fn negate(&self) -> Decimal {
+        Decimal {
+            negative: !self.negative && !self.is_zero(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns the absolute value.
+    pub(crate) fn abs(&self) -> Decimal {
+        Decimal {
+            negative: false,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the sum of the value and `other`.
+    pub(crate) fn add(&self, other: &Decimal) -> Decimal {
+        let scale = self.fraction.len().max(other.fraction.len());
+        let (left, right) = (self.scaled(scale), other.scaled(scale));
+        if self.negative == other.negative {
+            return Decimal::from_scaled(self.negative, &add_digits(&left, &right), scale);
+        }
+        match cmp_digits(&left, &right) {
+            Ordering::Less => {
+                Decimal::from_scaled(other.negative, &subtract_digits(&right, &left), scale)
+            }
+            _ => Decimal::from_scaled(self.negative, &subtract_digits(&left, &right), scale),
+        }
+    }
+
+    /// Returns the difference of the value and `other`.
+    pub(crate) fn subtract(&self, other: &Decimal) -> Decimal {
+        self.add(&other.negate())
+    }
+
+    /// Returns the product of the value and `other`.
+    pub(crate) fn multiply(&self, other: &Decimal) -> Decimal {
+        let (left, right) = (
+            self.scaled(self.fraction.len()),
+            other.scaled(other.fraction.len()),
+        );
+        Decimal::from_scaled(
+            self.negative != other.negative,
+            &multiply_digits(&left, &right),
+            self.fraction.len() + other.fraction.len(),
+        )
+    }
+
+    /// Returns the quotient of the value by `other`, truncated to
+    /// [`Decimal::DIVISION_DIGITS`] more fraction digits than the
+    /// operands have, or `None` if `other` is zero.
+    pub(crate) fn divide(&self, other: &Decimal) -> Option<Decimal> {
+        let scale = self.fraction.len().max(other.fraction.len()) + Self::DIVISION_DIGITS;
+        self.quotient(other, scale)
+    }
+
+    /// Returns the quotient of the value by `other`, truncated to an
+    /// integer, or `None` if `other` is zero.
+    pub(crate) fn integer_divide(&self, other: &Decimal) -> Option<Decimal> {
+        self.quotient(other, 0)
+    }
+
+    /// Returns the remainder of the truncated division of the value by
+    /// `other`, which has the sign of the value, or `None` if `other` is
+    /// zero.
+    pub(crate) fn remainder(&self, other: &Decimal) -> Option<Decimal> {
+        let quotient = self.integer_divide(other)?;
+        Some(self.subtract(&quotient.multiply(other)))
+    }
+
+    /// Returns the largest integer not greater than the value.
+    pub(crate) fn floor(&self) -> Decimal {
+        let truncated = self.truncate();
+        match self.negative && !self.fraction.is_empty() {
+            true => truncated.subtract(&Decimal::from(1)),
+            false => truncated,
+        }
+    }
+
+    /// Returns the smallest integer not less than the value.
+    pub(crate) fn ceiling(&self) -> Decimal {
+        self.negate().floor().negate()
+    }
+
+    /// Returns the nearest integer to the value, halves rounded upwards.
+    pub(crate) fn round(&self) -> Decimal {
+        self.add(&Decimal {
+            negative: false,
+            integer: String::new(),
+            fraction: "5".to_string(),
+        })
+        .floor()
+    }
+
+    /// Returns the integer part of the value.
+    pub(crate) fn truncate(&self) -> Decimal {
+        Decimal::from_scaled(self.negative, &self.integer, 0)
+    }
+
+    /// Returns the quotient of the value by `other` with `scale` fraction
+    /// digits, truncated, or `None` if `other` is zero.
+    fn quotient(&self, other: &Decimal, scale: usize) -> Option<Decimal> {
+        if other.is_zero() {
+            return None;
+        }
+        // Both operands are scaled alike, so the quotient of their digits
+        // is that of the values
+        let common = self.fraction.len().max(other.fraction.len());
+        let dividend = self.scaled(common) + &"0".repeat(scale);
+        let quotient = divide_digits(&dividend, &other.scaled(common));
+        Some(Decimal::from_scaled(
+            self.negative != other.negative,
+            &quotient,
+            scale,
+        ))
+    }
+
+    /// Returns the digits of the magnitude of the value multiplied by
+    /// `10^scale`, which must not be less than its number of fraction
+    /// digits.
+    fn scaled(&self, scale: usize) -> String {
+        let mut digits = self.integer.clone();
+        digits.push_str(&self.fraction);
+        digits.push_str(&"0".repeat(scale - self.fraction.len()));
+        digits
+    }
+
+    /// Returns the decimal whose magnitude multiplied by `10^scale` has
+    /// the digits `digits`.
+    fn from_scaled(negative: bool, digits: &str, scale: usize) -> Decimal {
+        let padded = format!(
+            "{}{}",
+            "0".repeat((scale + 1).saturating_sub(digits.len())),
+            digits
+        );
+        let (integer, fraction) = padded.split_at(padded.len() - scale);
+        let integer = integer.trim_start_matches('0').to_string();
+        let fraction = fraction.trim_end_matches('0').to_string();
+        let zero = integer.is_empty() && fraction.is_empty();
+        Decimal {
+            negative: negative && !zero,
+            integer,
+            fraction,
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(integer: i64) -> Decimal {
+        Decimal {
+            negative: integer < 0,
+            integer: match integer {
+                0 => String::new(),
+                _ => integer.unsigned_abs().to_string(),
+            },
+            fraction: String::new(),
+        }
+    }
+}
+
+impl Display for Decimal {
+    /// Writes the canonical representation of the value: its integer
+    /// part, and its fraction if it is not zero.
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        if self.negative {
+            write!(f, "-")?;
+        }
+        match self.integer.as_str() {
+            "" => write!(f, "0")?,
+            integer => write!(f, "{}", integer)?,
+        }
+        if !self.fraction.is_empty() {
+            write!(f, ".{}", self.fraction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares the unsigned integers written with the decimal digits `left`
+/// and `right`.
+fn cmp_digits(left: &str, right: &str) -> Ordering {
+    let (left, right) = (left.trim_start_matches('0'), right.trim_start_matches('0'));
+    left.len().cmp(&right.len()).then_with(|| left.cmp(right))
+}
+
+/// Returns the digits of the sum of the unsigned integers written with
+/// the decimal digits `left` and `right`.
+fn add_digits(left: &str, right: &str) -> String {
+    let (left, right) = (left.as_bytes(), right.as_bytes());
+    let mut digits = Vec::with_capacity(left.len().max(right.len()) + 1);
+    let mut carry = 0;
+    for index in 0..left.len().max(right.len()) {
+        let digit = |digits: &[u8]| match index < digits.len() {
+            true => digits[digits.len() - 1 - index] - b'0',
+            false => 0,
+        };
+        let sum = digit(left) + digit(right) + carry;
+        digits.push(b'0' + sum % 10);
+        carry = sum / 10;
+    }
+    if carry > 0 {
+        digits.push(b'0' + carry);
+    }
+    digits.iter().rev().map(|&digit| digit as char).collect()
+}
+
+/// Returns the digits of the difference of the unsigned integers written
+/// with the decimal digits `left` and `right`, which must not be greater
+/// than `left`.
+fn subtract_digits(left: &str, right: &str) -> String {
+    let (left, right) = (left.as_bytes(), right.as_bytes());
+    let mut digits = Vec::with_capacity(left.len());
+    let mut borrow = 0;
+    for index in 0..left.len() {
+        let mut digit = (left[left.len() - 1 - index] - b'0') as i8 - borrow;
+        if index < right.len() {
+            digit -= (right[right.len() - 1 - index] - b'0') as i8;
+        }
+        borrow = (digit < 0) as i8;
+        digits.push(b'0' + (digit + 10 * borrow) as u8);
+    }
+    digits.iter().rev().map(|&digit| digit as char).collect()
+}
+
+/// Returns the digits of the product of the unsigned integers written
+/// with the decimal digits `left` and `right`.
+fn multiply_digits(left: &str, right: &str) -> String {
+    let mut product = vec![0u32; left.len() + right.len()];
+    for (i, a) in left.bytes().rev().enumerate() {
+        for (j, b) in right.bytes().rev().enumerate() {
+            product[i + j] += (a - b'0') as u32 * (b - b'0') as u32;
+        }
+        // Carry as the rows are added, so the sums stay small
+        for index in 0..product.len() - 1 {
+            product[index + 1] += product[index] / 10;
+            product[index] %= 10;
+        }
+    }
+    product
+        .iter()
+        .rev()
+        .map(|&digit| (b'0' + digit as u8) as char)
+        .collect()
+}
+
+/// Returns the digits of the quotient of the unsigned integers written
+/// with the decimal digits `dividend` and `divisor`, truncated. The
+/// divisor must not be zero.
+fn divide_digits(dividend: &str, divisor: &str) -> String {
+    let mut quotient = String::with_capacity(dividend.len());
+    let mut remainder = String::new();
+    for digit in dividend.chars() {
+        remainder.push(digit);
+        let mut count = b'0';
+        while cmp_digits(&remainder, divisor) != Ordering::Less {
+            remainder = subtract_digits(&remainder, divisor);
+            count += 1;
+        }
+        quotient.push(count as char);
+    }
+    quotient
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Decimal) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => self.cmp_magnitude(other),
+            (true, true) => other.cmp_magnitude(self),
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A duration: a number of months and a number of seconds, which are
+/// only partially ordered with each other.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Duration {
+    months: i64,
+    seconds: f64,
+    has_months: bool,
+    has_seconds: bool,
+}
+
+impl Hash for Duration {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.months.hash(state);
+        hash_f64(self.seconds, state);
+        self.has_months.hash(state);
+        self.has_seconds.hash(state);
+    }
+}
+
+impl Duration {
+    fn parse(value: &str) -> Result<Duration, String> {
+        let error = || "not a duration".to_string();
+        let (negative, rest) = match value.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let rest = rest.strip_prefix('P').ok_or_else(error)?;
+        let (date, time) = match rest.split_once('T') {
+            Some((_, "")) => return Err(error()),
+            Some((date, time)) => (date, Some(time)),
+            None => (rest, None),
+        };
+        let mut duration = Duration {
+            months: 0,
+            seconds: 0.0,
+            has_months: false,
+            has_seconds: false,
+        };
+        let mut components = 0;
+        for (part, designators) in [(date, "YMD"), (time.unwrap_or(""), "HMS")] {
+            let mut number = String::new();
+            let mut allowed = designators;
+            for ch in part.chars() {
+                if ch.is_ascii_digit() || (ch == '.' && designators == "HMS") {
+                    number.push(ch);
+                    continue;
+                }
+                let index = allowed.find(ch).ok_or_else(error)?;
+                allowed = &allowed[index + 1..];
+                if number.is_empty()
+                    || number.starts_with('.')
+                    || number.ends_with('.')
+                    || (number.contains('.') && ch != 'S')
+                {
+                    return Err(error());
+                }
+                let amount: f64 = number.parse().map_err(|_| error())?;
+                match (designators, ch) {
+                    ("YMD", 'Y') => duration.months += amount as i64 * 12,
+                    ("YMD", 'M') => duration.months += amount as i64,
+                    ("YMD", 'D') => duration.seconds += amount * 86400.0,
+                    (_, 'H') => duration.seconds += amount * 3600.0,
+                    (_, 'M') => duration.seconds += amount * 60.0,
+                    _ => duration.seconds += amount,
+                }
+                if designators == "YMD" && ch != 'D' {
+                    duration.has_months = true;
+                } else {
+                    duration.has_seconds = true;
+                }
+                components += 1;
+                number.clear();
+            }
+            if !number.is_empty() {
+                return Err(error());
+            }
+        }
+        if components == 0 || (time.is_some() && !time.unwrap_or("").ends_with(['H', 'M', 'S'])) {
+            return Err(error());
+        }
+        if negative {
+            duration.months = -duration.months;
+            duration.seconds = -duration.seconds;
+        }
+        Ok(duration)
+    }
+}
+
+impl PartialOrd for Duration {
+    fn partial_cmp(&self, other: &Duration) -> Option<Ordering> {
+        let months = self.months.cmp(&other.months);
+        let seconds = self.seconds.partial_cmp(&other.seconds)?;
+        match (months, seconds) {
+            (months, Ordering::Equal) => Some(months),
+            (Ordering::Equal, seconds) => Some(seconds),
+            (months, seconds) if months == seconds => Some(months),
+            // Months have between 28 and 31 days, so the order is only
+            // known when the months outweigh the seconds.
+            (months, _) => {
+                let difference = (self.months - other.months) as f64;
+                let seconds = self.seconds - other.seconds;
+                let (shortest, longest) =
+                    (difference * 28.0 * 86400.0, difference * 31.0 * 86400.0);
+                let (low, high) = if shortest < longest {
+                    (shortest, longest)
+                } else {
+                    (longest, shortest)
+                };
+                if low + seconds > 0.0 || high + seconds < 0.0 {
+                    Some(months)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// A value of one of the date and time datatypes, with the fields that
+/// are absent from its lexical form set to their earliest value.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct DateTime {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: f64,
+    /// The timezone offset in minutes, if any
+    timezone: Option<i32>,
+}
+
+impl Hash for DateTime {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.year, self.month, self.day, self.hour, self.minute).hash(state);
+        hash_f64(self.second, state);
+        self.timezone.hash(state);
+    }
+}
+
+impl DateTime {
+    /// Parses a value of the date and time datatype `datatype`, whose
+    /// year can only be zero in XSD 1.1.
+    fn parse(
+        value: &str,
+        datatype: SimpleDatatype,
+        version: XsdVersion,
+    ) -> Result<DateTime, String> {
+        let error = || format!("not a valid {}", datatype);
+        let (value, timezone) = split_timezone(value).ok_or_else(error)?;
+        let mut date_time = DateTime {
+            year: 1972,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0.0,
+            timezone,
+        };
+        let (date, time) = match datatype {
+            SimpleDatatype::DateTime | SimpleDatatype::DateTimeStamp => {
+                let (date, time) = value.split_once('T').ok_or_else(error)?;
+                (Some(date), Some(time))
+            }
+            SimpleDatatype::Time => (None, Some(value)),
+            _ => (Some(value), None),
+        };
+        if let Some(date) = date {
+            let fields: Vec<&str> = match datatype {
+                SimpleDatatype::GMonthDay | SimpleDatatype::GMonth => date
+                    .strip_prefix("--")
+                    .ok_or_else(error)?
+                    .split('-')
+                    .collect(),
+                SimpleDatatype::GDay => vec![date.strip_prefix("---").ok_or_else(error)?],
+                _ => {
+                    let (negative, unsigned) = match date.strip_prefix('-') {
+                        Some(rest) => (true, rest),
+                        None => (false, date),
+                    };
+                    let mut fields: Vec<&str> = unsigned.split('-').collect();
+                    let year = fields.remove(0);
+                    if year.len() < 4
+                        || (year.len() > 4 && year.starts_with('0'))
+                        || !year.bytes().all(|byte| byte.is_ascii_digit())
+                    {
+                        return Err(error());
+                    }
+                    date_time.year = year.parse().map_err(|_| error())?;
+                    if date_time.year == 0 && version == XsdVersion::V1_0 {
+                        return Err("year zero is not allowed".to_string());
+                    }
+                    if negative {
+                        date_time.year = -date_time.year;
+                    }
+                    fields
+                }
+            };
+            let expected = match datatype {
+                SimpleDatatype::GYear => 0,
+                SimpleDatatype::GYearMonth | SimpleDatatype::GMonth | SimpleDatatype::GDay => 1,
+                _ => 2,
+            };
+            if fields.len() != expected {
+                return Err(error());
+            }
+            let mut numbers = Vec::new();
+            for field in fields {
+                numbers.push(two_digits(field).ok_or_else(error)?);
+            }
+            match datatype {
+                SimpleDatatype::GDay => date_time.day = numbers[0],
+                _ => {
+                    if let Some(month) = numbers.first() {
+                        date_time.month = *month;
+                    }
+                    if let Some(day) = numbers.get(1) {
+                        date_time.day = *day;
+                    }
+                }
+            }
+            let days = days_in_month(
+                // Month-day values are checked against a leap year, as
+                // they can recur in one
+                if datatype == SimpleDatatype::GMonthDay {
+                    2000
+                } else {
+                    date_time.year
+                },
+                date_time.month,
+            );
+            if !(1..=12).contains(&date_time.month) || date_time.day < 1 || date_time.day > days {
+                return Err(error());
+            }
+        }
+        if let Some(time) = time {
+            let fields: Vec<&str> = time.split(':').collect();
+            if fields.len() != 3 {
+                return Err(error());
+            }
+            date_time.hour = two_digits(fields[0]).ok_or_else(error)?;
+            date_time.minute = two_digits(fields[1]).ok_or_else(error)?;
+            let (whole, fraction) = match fields[2].split_once('.') {
+                Some((whole, fraction)) => (whole, Some(fraction)),
+                None => (fields[2], None),
+            };
+            let whole = two_digits(whole).ok_or_else(error)?;
+            if let Some(fraction) = fraction {
+                if fraction.is_empty() || !fraction.bytes().all(|byte| byte.is_ascii_digit()) {
+                    return Err(error());
+                }
+            }
+            date_time.second = fields[2].parse().map_err(|_| error())?;
+            let midnight = date_time.hour == 24 && date_time.minute == 0 && date_time.second == 0.0;
+            if (date_time.hour > 23 && !midnight) || date_time.minute > 59 || whole > 59 {
+                return Err(error());
+            }
+        }
+        Ok(date_time)
+    }
+
+    /// Returns the number of seconds from a fixed origin to the value,
+    /// in UTC if it has a timezone.
+    fn timeline(&self) -> f64 {
+        // Days from 0000-03-01 in the proleptic Gregorian calendar
+        let (year, month) = if self.month <= 2 {
+            (self.year - 1, self.month + 9)
+        } else {
+            (self.year, self.month - 3)
+        };
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * month as i64 + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        let days = era * 146097 + day_of_era;
+        let minutes =
+            self.hour as i64 * 60 + self.minute as i64 - self.timezone.unwrap_or(0) as i64;
+        days as f64 * 86400.0 + minutes as f64 * 60.0 + self.second
+    }
+}
+
+impl PartialOrd for DateTime {
+    fn partial_cmp(&self, other: &DateTime) -> Option<Ordering> {
+        let (left, right) = (self.timeline(), other.timeline());
+        if self.timezone.is_some() == other.timezone.is_some() {
+            return left.partial_cmp(&right);
+        }
+        // A value without a timezone may be in any timezone from -14:00
+        // to +14:00, so it is only ordered with values far enough apart.
+        let margin = 14.0 * 3600.0;
+        if left + margin < right {
+            Some(Ordering::Less)
+        } else if left > right + margin {
+            Some(Ordering::Greater)
+        } else {
+            None
+        }
+    }
+}
+
+/// Splits the timezone from the end of a date or time value, returning
+/// the rest of the value and the offset in minutes.
+fn split_timezone(value: &str) -> Option<(&str, Option<i32>)> {
+    if let Some(rest) = value.strip_suffix('Z') {
+        return Some((rest, Some(0)));
+    }
+    let bytes = value.as_bytes();
+    if bytes.len() > 6 && bytes[bytes.len() - 3] == b':' {
+        let sign = bytes[bytes.len() - 6];
+        if sign == b'+' || sign == b'-' {
+            let hours = two_digits(&value[value.len() - 5..value.len() - 3])?;
+            let minutes = two_digits(&value[value.len() - 2..])?;
+            if minutes > 59 || hours * 60 + minutes > 14 * 60 {
+                return None;
+            }
+            let offset = (hours * 60 + minutes) as i32;
+            let offset = if sign == b'-' { -offset } else { offset };
+            return Some((&value[..value.len() - 6], Some(offset)));
+        }
+    }
+    Some((value, None))
+}
+
+fn two_digits(field: &str) -> Option<u32> {
+    if field.len() == 2 && field.bytes().all(|byte| byte.is_ascii_digit()) {
+        field.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses an `xs:float` or `xs:double`.
+fn parse_float(value: &str) -> Result<f64, String> {
+    match value {
+        "INF" | "+INF" => return Ok(f64::INFINITY),
+        "-INF" => return Ok(f64::NEG_INFINITY),
+        "NaN" => return Ok(f64::NAN),
+        _ => {}
+    }
+    let mantissa = value.split(['e', 'E']).next().unwrap_or_default();
+    let mantissa = mantissa.strip_prefix(['+', '-']).unwrap_or(mantissa);
+    let valid = !mantissa.is_empty()
+        && mantissa != "."
+        && mantissa
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || byte == b'.');
+    match value.parse() {
+        Ok(number) if valid => Ok(number),
+        _ => Err("not a floating-point number".to_string()),
+    }
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    if value.len() % 2 != 0 || !value.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err("not an even number of hexadecimal digits".to_string());
+    }
+    (0..value.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&value[index..index + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|err| err.to_string())
+}
+
+fn parse_base64(value: &str) -> Result<Vec<u8>, String> {
+    let error = || "not a valid base64 encoding".to_string();
+    let symbols: Vec<u8> = value.bytes().filter(|byte| *byte != b' ').collect();
+    if symbols.len() % 4 != 0 {
+        return Err(error());
+    }
+    let padding = symbols
+        .iter()
+        .rev()
+        .take_while(|byte| **byte == b'=')
+        .count();
+    if padding > 2 {
+        return Err(error());
+    }
+    let mut bytes = Vec::with_capacity(symbols.len() / 4 * 3);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for (index, symbol) in symbols[..symbols.len() - padding].iter().enumerate() {
+        let sextet = match symbol {
+            b'A'..=b'Z' => symbol - b'A',
+            b'a'..=b'z' => symbol - b'a' + 26,
+            b'0'..=b'9' => symbol - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(error()),
+        };
+        // The bits of the last symbol before the padding that are not
+        // part of an octet must be zero
+        let last = index + 1 == symbols.len() - padding;
+        if last && ((padding == 1 && sextet & 0x03 != 0) || (padding == 2 && sextet & 0x0f != 0)) {
+            return Err(error());
+        }
+        buffer = (buffer << 6) | sextet as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Returns `true` if `value` matches the XML `Name` production.
+fn is_name(value: &str) -> bool {
+    let mut chars = value.chars();
+    chars.next().map_or(false, is_name_start_char) && chars.all(is_name_char)
+}
+
+/// Returns `true` if `value` is an XML name without colons.
+pub(crate) fn is_ncname(value: &str) -> bool {
+    is_name(value) && !value.contains(':')
+}
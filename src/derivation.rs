@@ -0,0 +1,481 @@
+//! Checking that the content model of a complex type derived by
+//! restriction restricts the content model of its base type.
+//!
+//! The rules are those of the constraint on particle restrictions of XML
+//! Schema (`cos-particle-restrict`): both content models are stripped of
+//! their pointless groups, then every particle of the derived type must
+//! match a particle of the base type with a narrower occurrence range,
+//! the same or a narrower set of names, and a type derived from that of
+//! the base particle.
+
+use crate::components::{Compositor, Datatype, MaxOccurs, ModelGroup, Particle, Term, Wildcard};
+
+/// Checks particle restrictions in a schema.
+pub(crate) struct ParticleRestriction<'r> {
+    /// Returns `true` if the first type is the second type, or is
+    /// derived from it
+    pub(crate) is_derived: &'r dyn Fn(&Datatype, &Datatype) -> bool,
+}
+
+impl ParticleRestriction<'_> {
+    /// Checks that the content model `derived` is a valid restriction of
+    /// the content model `base`, `None` standing for empty content.
+    ///
+    /// # Arguments
+    ///
+    /// * `derived` - The content model of the derived type.
+    /// * `base` - The content model of the base type.
+    ///
+    /// Errors are returned as a message describing the first particle
+    /// that does not restrict the base content model.
+    ///
+    pub(crate) fn check(
+        &self,
+        derived: Option<&Particle>,
+        base: Option<&Particle>,
+    ) -> Result<(), String> {
+        match (derived.map(reduce), base.map(reduce)) {
+            (None, None) => Ok(()),
+            (None, Some(base)) if min_range(&base) == 0 => Ok(()),
+            (None, Some(_)) => Err(
+                "The content model of the base type is not emptiable, so it cannot be \
+                 restricted to empty content"
+                    .to_string(),
+            ),
+            (Some(derived), None) if max_range(&derived) == MaxOccurs::Bounded(0) => Ok(()),
+            (Some(_), None) => Err(
+                "The base type has empty content, which cannot be restricted to a content model"
+                    .to_string(),
+            ),
+            (Some(derived), Some(base)) => self.particle(&derived, &base),
+        }
+    }
+
+    /// Checks that the particle `derived` restricts the particle `base`.
+    fn particle(&self, derived: &Particle, base: &Particle) -> Result<(), String> {
+        match (&derived.term, &base.term) {
+            // Named groups are resolved in place before they are checked
+            (Term::GroupReference(_), _) | (_, Term::GroupReference(_)) => Ok(()),
+            (Term::Element(element), Term::Element(base_element)) => {
+                if element.name != base_element.name {
+                    return Err(format!(
+                        "Element '{}' does not match element '{}' of the base type",
+                        element.name, base_element.name
+                    ));
+                }
+                check_range(derived, base)?;
+                if element.nillable && !base_element.nillable {
+                    return Err(format!(
+                        "Element '{}' cannot be nillable, because it is not nillable in the \
+                         base type",
+                        element.name
+                    ));
+                }
+                if let Some(fixed) = &base_element.fixed_value {
+                    if element.fixed_value.as_deref().map(str::trim) != Some(fixed.trim()) {
+                        return Err(format!(
+                            "Element '{}' must have the fixed value '{}' of the base type",
+                            element.name, fixed
+                        ));
+                    }
+                }
+                if !base_element
+                    .block_set
+                    .iter()
+                    .all(|derivation| element.block_set.contains(derivation))
+                {
+                    return Err(format!(
+                        "Element '{}' must block the derivations blocked in the base type",
+                        element.name
+                    ));
+                }
+                if element.reference.is_none()
+                    && base_element.reference.is_none()
+                    && !(self.is_derived)(&element.datatype, &base_element.datatype)
+                {
+                    return Err(format!(
+                        "The type of element '{}' is not derived from its type in the base type",
+                        element.name
+                    ));
+                }
+                Ok(())
+            }
+            (Term::Element(element), Term::Wildcard(wildcard)) => {
+                if !wildcard.allows(&element.name) {
+                    return Err(format!(
+                        "Element '{}' is not allowed by the wildcard '{}' of the base type",
+                        element.name, wildcard.namespace
+                    ));
+                }
+                check_range(derived, base)
+            }
+            (Term::Element(_), Term::ModelGroup(group)) => {
+                let wrapped = Particle {
+                    min_occurs: 1,
+                    max_occurs: MaxOccurs::Bounded(1),
+                    term: Term::ModelGroup(ModelGroup {
+                        compositor: group.compositor,
+                        particles: vec![derived.clone()],
+                    }),
+                };
+                self.particle(&wrapped, base)
+            }
+            (Term::Wildcard(wildcard), Term::Wildcard(base_wildcard)) => {
+                check_range(derived, base)?;
+                if !wildcard.is_subset(base_wildcard) {
+                    return Err(format!(
+                        "The wildcard '{}' is not a subset of the wildcard '{}' of the base type",
+                        wildcard.namespace, base_wildcard.namespace
+                    ));
+                }
+                Ok(())
+            }
+            (Term::Wildcard(wildcard), _) => Err(format!(
+                "The wildcard '{}' cannot restrict {} of the base type",
+                wildcard.namespace,
+                describe(&base.term)
+            )),
+            (Term::ModelGroup(_), Term::Element(_)) => Err(format!(
+                "{} cannot restrict {} of the base type",
+                capitalize(&describe(&derived.term)),
+                describe(&base.term)
+            )),
+            (Term::ModelGroup(group), Term::Wildcard(wildcard)) => {
+                self.check_wildcard(group, wildcard)?;
+                check_range_of(
+                    min_range(derived),
+                    max_range(derived),
+                    derived,
+                    base.min_occurs,
+                    base.max_occurs,
+                    base,
+                )
+            }
+            (Term::ModelGroup(group), Term::ModelGroup(base_group)) => {
+                match (group.compositor, base_group.compositor) {
+                    (Compositor::Sequence, Compositor::Sequence)
+                    | (Compositor::All, Compositor::All) => {
+                        check_range(derived, base)?;
+                        self.recurse(group, base_group)
+                    }
+                    (Compositor::Choice, Compositor::Choice) => {
+                        check_range(derived, base)?;
+                        self.recurse_lax(group, base_group)
+                    }
+                    (Compositor::Sequence, Compositor::All) => {
+                        check_range(derived, base)?;
+                        self.recurse_unordered(group, base_group)
+                    }
+                    (Compositor::Sequence, Compositor::Choice) => {
+                        let count = group.particles.len() as u32;
+                        check_range_of(
+                            derived.min_occurs.saturating_mul(count),
+                            multiply(derived.max_occurs, MaxOccurs::Bounded(count)),
+                            derived,
+                            base.min_occurs,
+                            base.max_occurs,
+                            base,
+                        )?;
+                        self.map_and_sum(group, base_group)
+                    }
+                    _ => Err(format!(
+                        "{} cannot restrict {} of the base type",
+                        capitalize(&describe(&derived.term)),
+                        describe(&base.term)
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Checks that every element and wildcard of `group` is allowed by
+    /// the wildcard `wildcard` of the base type.
+    fn check_wildcard(&self, group: &ModelGroup, wildcard: &Wildcard) -> Result<(), String> {
+        for particle in &group.particles {
+            match &particle.term {
+                Term::Element(element) if !wildcard.allows(&element.name) => {
+                    return Err(format!(
+                        "Element '{}' is not allowed by the wildcard '{}' of the base type",
+                        element.name, wildcard.namespace
+                    ))
+                }
+                Term::Wildcard(other) if !other.is_subset(wildcard) => {
+                    return Err(format!(
+                        "The wildcard '{}' is not a subset of the wildcard '{}' of the base type",
+                        other.namespace, wildcard.namespace
+                    ))
+                }
+                Term::ModelGroup(nested) => self.check_wildcard(nested, wildcard)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Maps the particles of `group` in order onto those of `base`, which
+    /// must be emptiable when left out.
+    fn recurse(&self, group: &ModelGroup, base: &ModelGroup) -> Result<(), String> {
+        let mut base_particles = base.particles.iter();
+        for particle in &group.particles {
+            loop {
+                let base_particle = base_particles.next().ok_or_else(|| {
+                    format!(
+                        "{} does not match any remaining particle of the base type",
+                        capitalize(&describe(&particle.term))
+                    )
+                })?;
+                match self.particle(particle, base_particle) {
+                    Ok(()) => break,
+                    Err(_) if min_range(base_particle) == 0 => continue,
+                    Err(message) => return Err(message),
+                }
+            }
+        }
+        match base_particles.find(|particle| min_range(particle) > 0) {
+            Some(particle) => Err(format!(
+                "{} of the base type is required, but missing from the restriction",
+                capitalize(&describe(&particle.term))
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Maps the particles of `group` in order onto those of `base`,
+    /// leaving any of them out.
+    fn recurse_lax(&self, group: &ModelGroup, base: &ModelGroup) -> Result<(), String> {
+        let mut base_particles = base.particles.iter();
+        for particle in &group.particles {
+            let mut last_error = None;
+            let found = base_particles.by_ref().any(|base_particle| {
+                match self.particle(particle, base_particle) {
+                    Ok(()) => true,
+                    Err(message) => {
+                        last_error.get_or_insert(message);
+                        false
+                    }
+                }
+            });
+            if !found {
+                return Err(last_error.unwrap_or_else(|| {
+                    format!(
+                        "{} does not match any remaining particle of the base type",
+                        capitalize(&describe(&particle.term))
+                    )
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Maps the particles of the sequence `group` onto those of the
+    /// `xs:all` group `base`, in any order, each at most once.
+    fn recurse_unordered(&self, group: &ModelGroup, base: &ModelGroup) -> Result<(), String> {
+        let mut used = vec![false; base.particles.len()];
+        for particle in &group.particles {
+            let index = base
+                .particles
+                .iter()
+                .enumerate()
+                .position(|(index, base_particle)| {
+                    !used[index] && self.particle(particle, base_particle).is_ok()
+                })
+                .ok_or_else(|| {
+                    format!(
+                        "{} does not match any particle of the base type",
+                        capitalize(&describe(&particle.term))
+                    )
+                })?;
+            used[index] = true;
+        }
+        match base
+            .particles
+            .iter()
+            .zip(&used)
+            .find(|(particle, used)| !**used && min_range(particle) > 0)
+        {
+            Some((particle, _)) => Err(format!(
+                "{} of the base type is required, but missing from the restriction",
+                capitalize(&describe(&particle.term))
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that every particle of the sequence `group` restricts a
+    /// particle of the choice `base`.
+    fn map_and_sum(&self, group: &ModelGroup, base: &ModelGroup) -> Result<(), String> {
+        for particle in &group.particles {
+            if !base
+                .particles
+                .iter()
+                .any(|base_particle| self.particle(particle, base_particle).is_ok())
+            {
+                return Err(format!(
+                    "{} does not match any particle of the base type",
+                    capitalize(&describe(&particle.term))
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Removes the pointless groups of `particle`: groups with a single
+/// particle occurring once, and nested sequences or choices occurring
+/// once in a group of the same kind.
+fn reduce(particle: &Particle) -> Particle {
+    let group = match &particle.term {
+        Term::ModelGroup(group) => group,
+        _ => return particle.clone(),
+    };
+    let mut particles = Vec::new();
+    for child in &group.particles {
+        let child = reduce(child);
+        let once = child.min_occurs == 1 && child.max_occurs == MaxOccurs::Bounded(1);
+        match &child.term {
+            Term::ModelGroup(nested)
+                if once
+                    && nested.compositor == group.compositor
+                    && group.compositor != Compositor::All =>
+            {
+                particles.extend(nested.particles.iter().cloned())
+            }
+            Term::ModelGroup(nested)
+                if nested.particles.is_empty() && group.compositor != Compositor::Choice => {}
+            _ if child.max_occurs == MaxOccurs::Bounded(0)
+                && group.compositor != Compositor::Choice => {}
+            _ => particles.push(child),
+        }
+    }
+    if particles.len() == 1
+        && particle.min_occurs == 1
+        && particle.max_occurs == MaxOccurs::Bounded(1)
+    {
+        return particles.remove(0);
+    }
+    Particle {
+        min_occurs: particle.min_occurs,
+        max_occurs: particle.max_occurs,
+        term: Term::ModelGroup(ModelGroup {
+            compositor: group.compositor,
+            particles,
+        }),
+    }
+}
+
+/// Returns the minimum number of elements a sequence matching
+/// `particle` can have.
+pub(crate) fn min_range(particle: &Particle) -> u32 {
+    match &particle.term {
+        Term::Element(_) | Term::Wildcard(_) => particle.min_occurs,
+        Term::ModelGroup(group) => {
+            let ranges = group.particles.iter().map(min_range);
+            let min = match group.compositor {
+                Compositor::Sequence | Compositor::All => {
+                    ranges.fold(0u32, |total, min| total.saturating_add(min))
+                }
+                Compositor::Choice => ranges.min().unwrap_or(0),
+            };
+            particle.min_occurs.saturating_mul(min)
+        }
+        Term::GroupReference(_) => 0,
+    }
+}
+
+/// Returns the maximum number of elements a sequence matching
+/// `particle` can have.
+fn max_range(particle: &Particle) -> MaxOccurs {
+    match &particle.term {
+        Term::Element(_) | Term::Wildcard(_) => particle.max_occurs,
+        Term::ModelGroup(group) => {
+            let ranges = group.particles.iter().map(max_range);
+            let max = match group.compositor {
+                Compositor::Sequence | Compositor::All => {
+                    ranges.fold(MaxOccurs::Bounded(0), |total, max| match (total, max) {
+                        (MaxOccurs::Bounded(total), MaxOccurs::Bounded(max)) => {
+                            MaxOccurs::Bounded(total.saturating_add(max))
+                        }
+                        _ => MaxOccurs::Unbounded,
+                    })
+                }
+                Compositor::Choice => ranges.max().unwrap_or(MaxOccurs::Bounded(0)),
+            };
+            multiply(particle.max_occurs, max)
+        }
+        Term::GroupReference(_) => MaxOccurs::Unbounded,
+    }
+}
+
+/// Multiplies two maximum numbers of occurrences.
+fn multiply(left: MaxOccurs, right: MaxOccurs) -> MaxOccurs {
+    match (left, right) {
+        (MaxOccurs::Bounded(0), _) | (_, MaxOccurs::Bounded(0)) => MaxOccurs::Bounded(0),
+        (MaxOccurs::Bounded(left), MaxOccurs::Bounded(right)) => {
+            MaxOccurs::Bounded(left.saturating_mul(right))
+        }
+        _ => MaxOccurs::Unbounded,
+    }
+}
+
+/// Checks that the occurrence range of `derived` is within that of
+/// `base`.
+fn check_range(derived: &Particle, base: &Particle) -> Result<(), String> {
+    check_range_of(
+        derived.min_occurs,
+        derived.max_occurs,
+        derived,
+        base.min_occurs,
+        base.max_occurs,
+        base,
+    )
+}
+
+/// Checks that the range `min..=max` of `derived` is within the range
+/// `base_min..=base_max` of `base`.
+fn check_range_of(
+    min: u32,
+    max: MaxOccurs,
+    derived: &Particle,
+    base_min: u32,
+    base_max: MaxOccurs,
+    base: &Particle,
+) -> Result<(), String> {
+    if min >= base_min && max <= base_max {
+        Ok(())
+    } else {
+        Err(format!(
+            "The occurrence range ({}, {}) of {} is not within the range ({}, {}) of {} in the \
+             base type",
+            min,
+            max,
+            describe(&derived.term),
+            base_min,
+            base_max,
+            describe(&base.term)
+        ))
+    }
+}
+
+/// Describes `term` in error messages.
+fn describe(term: &Term) -> String {
+    match term {
+        Term::Element(element) => format!("element '{}'", element.name),
+        Term::Wildcard(wildcard) => format!("the wildcard '{}'", wildcard.namespace),
+        Term::ModelGroup(group) => match group.compositor {
+            Compositor::Sequence => "a sequence".to_string(),
+            Compositor::Choice => "a choice".to_string(),
+            Compositor::All => "an all group".to_string(),
+        },
+        Term::GroupReference(name) => format!("group '{}'", name),
+    }
+}
+
+/// Capitalizes the first letter of a description, at the start of a
+/// message.
+fn capitalize(description: &str) -> String {
+    let mut chars = description.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
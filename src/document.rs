@@ -0,0 +1,349 @@
+//! A lightweight element tree built from the [`Tokenizer`].
+//!
+//! Schema documents are read into a tree of [`XmlElement`]s before their
+//! components are built, so the parser can look ahead at children and
+//! attributes without re-scanning the input. Namespace declarations are
+//! tracked while the tree is built, and every element records the
+//! namespaces in scope on it, so that element names and `QName` values
+//! can be expanded.
+//!
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+    str::FromStr,
+};
+
+use crate::{
+    error::SchemaError,
+    qname::{QName, XMLNS_NAMESPACE, XML_NAMESPACE},
+    tokenizer::{Token, Tokenizer},
+};
+
+/// The namespace bindings in scope on an element, by prefix. The default
+/// namespace is bound to the empty prefix.
+pub(crate) type Namespaces = HashMap<String, String>;
+
+/// A node of the element tree.
+#[derive(Debug, Clone)]
+pub(crate) enum XmlNode {
+    /// A child element
+    Element(XmlElement),
+    /// Character data, including the content of CDATA sections
+    Text(String),
+}
+
+/// An element of the element tree.
+#[derive(Debug, Clone)]
+pub(crate) struct XmlElement {
+    /// The qualified name of the element, as written in the document
+    pub(crate) name: String,
+    /// The expanded name of the element
+    pub(crate) qname: QName,
+    /// The attributes of the element, in document order
+    pub(crate) attributes: Attributes,
+    /// The namespaces in scope on the element
+    pub(crate) namespaces: Rc<Namespaces>,
+    /// The child nodes of the element
+    pub(crate) children: Vec<XmlNode>,
+    /// The byte offset of the start tag of the element
+    pub(crate) position: usize,
+    /// The path of the element from the document element, such as
+    /// `/xs:schema/xs:element[@name='a']/xs:complexType[1]`
+    pub(crate) path: Rc<ElementPath>,
+}
+
+/// The path of an element from the document element.
+///
+/// Each element only keeps the last step of its path and shares the
+/// path of its parent, and the whole path is only spelled out when it is
+/// displayed, such as in the error reported for the element.
+#[derive(Debug, Default)]
+pub(crate) struct ElementPath {
+    parent: Option<Rc<ElementPath>>,
+    step: String,
+}
+
+impl Display for ElementPath {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        if let Some(parent) = &self.parent {
+            write!(f, "{}", parent)?;
+        }
+        write!(f, "/{}", self.step)
+    }
+}
+
+impl XmlElement {
+    /// Reads the document `input` and returns its document element.
+    ///
+    /// Comments, processing instructions and the document type
+    /// declaration are discarded. Adjacent text and CDATA sections are
+    /// merged into a single text node.
+    pub(crate) fn parse(input: &str) -> Result<XmlElement, SchemaError> {
+        let mut tokenizer = Tokenizer::new(input);
+        // The open elements, with the number of their child elements by
+        // name, from which the paths of the children are built
+        let mut stack: Vec<(XmlElement, HashMap<String, usize>)> = Vec::new();
+        let mut root = None;
+        let mut namespaces = Rc::new(Namespaces::new());
+        while let Some(token) = tokenizer.next() {
+            let position = tokenizer.token_start();
+            let (name, attributes, empty) = match token? {
+                Token::StartTag { name, attributes } => (name, attributes, false),
+                Token::EmptyTag { name, attributes } => (name, attributes, true),
+                Token::EndTag { .. } => {
+                    // The tokenizer guarantees that end tags are balanced
+                    if let Some((element, _)) = stack.pop() {
+                        match stack.last_mut() {
+                            Some((parent, _)) => {
+                                namespaces = Rc::clone(&parent.namespaces);
+                                parent.children.push(XmlNode::Element(element));
+                            }
+                            None => root = Some(element),
+                        }
+                    }
+                    continue;
+                }
+                Token::Text(text) | Token::CData(text) => {
+                    if let Some((parent, _)) = stack.last_mut() {
+                        match parent.children.last_mut() {
+                            Some(XmlNode::Text(previous)) => previous.push_str(&text),
+                            _ => parent.children.push(XmlNode::Text(text)),
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let mut element = XmlElement::new(input, name, attributes, &namespaces, position)?;
+            element.path = Rc::new(match stack.last_mut() {
+                Some((parent, counts)) => {
+                    let count = counts.entry(element.name.clone()).or_insert(0);
+                    *count += 1;
+                    ElementPath {
+                        parent: Some(Rc::clone(&parent.path)),
+                        step: element.step(*count),
+                    }
+                }
+                None => ElementPath {
+                    parent: None,
+                    step: element.name.clone(),
+                },
+            });
+            if !empty {
+                namespaces = Rc::clone(&element.namespaces);
+                stack.push((element, HashMap::new()));
+            } else {
+                match stack.last_mut() {
+                    Some((parent, _)) => parent.children.push(XmlNode::Element(element)),
+                    None => root = Some(element),
+                }
+            }
+        }
+        root.ok_or_else(|| SchemaError::syntax(input, input.len(), "Missing document element"))
+    }
+
+    fn new(
+        input: &str,
+        name: &str,
+        attributes: Vec<(&str, String)>,
+        parent_namespaces: &Rc<Namespaces>,
+        position: usize,
+    ) -> Result<Self, SchemaError> {
+        let error = |message: String| SchemaError::syntax(input, position, &message);
+        let mut namespaces = Rc::clone(parent_namespaces);
+        for (attribute, value) in &attributes {
+            let prefix = match attribute.strip_prefix("xmlns") {
+                Some("") => "",
+                Some(rest) if rest.starts_with(':') => &rest[1..],
+                _ => continue,
+            };
+            if prefix == "xmlns"
+                || (prefix == "xml") != (value == XML_NAMESPACE)
+                || value == XMLNS_NAMESPACE
+            {
+                return Err(error(format!(
+                    "Invalid namespace declaration '{}=\"{}\"'",
+                    attribute, value
+                )));
+            }
+            if value.is_empty() && !prefix.is_empty() {
+                return Err(error(format!(
+                    "Empty namespace name for prefix '{}'",
+                    prefix
+                )));
+            }
+            Rc::make_mut(&mut namespaces).insert(prefix.to_string(), value.clone());
+        }
+        let mut element = Self {
+            name: name.to_string(),
+            qname: QName::default(),
+            attributes: Attributes(
+                attributes
+                    .into_iter()
+                    .map(|(name, value)| (name.to_string(), value))
+                    .collect(),
+            ),
+            namespaces,
+            children: Vec::new(),
+            position,
+            path: Rc::default(),
+        };
+        element.qname = element.resolve_qname(name).map_err(error)?;
+        let mut expanded = Vec::new();
+        for (attribute, _) in element.attributes.iter() {
+            if attribute == "xmlns" || attribute.starts_with("xmlns:") {
+                continue;
+            }
+            let qname = element.attribute_qname(attribute).map_err(error)?;
+            if expanded.contains(&qname) {
+                return Err(error(format!("Duplicate attribute '{}'", qname)));
+            }
+            expanded.push(qname);
+        }
+        Ok(element)
+    }
+
+    /// Returns the step of the path of this element: its qualified name
+    /// with a predicate on its `name` attribute if it has one, or else on
+    /// its position among the siblings of the same name.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The position of the element among its siblings of the
+    ///   same name, starting at 1.
+    ///
+    fn step(&self, index: usize) -> String {
+        match self.attribute("name") {
+            Some(name) if !name.contains('\'') => format!("{}[@name='{}']", self.name, name),
+            _ => format!("{}[{}]", self.name, index),
+        }
+    }
+
+    /// Returns the value of the attribute called `name`, if present.
+    pub(crate) fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes.get(name)
+    }
+
+    /// Returns an iterator over the child elements, skipping text.
+    pub(crate) fn child_elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|child| match child {
+            XmlNode::Element(element) => Some(element),
+            XmlNode::Text(_) => None,
+        })
+    }
+
+    /// Returns the namespace bound to `prefix` on this element, if any.
+    ///
+    /// The empty prefix returns the default namespace.
+    pub(crate) fn namespace(&self, prefix: &str) -> Option<&str> {
+        match prefix {
+            "xml" => Some(XML_NAMESPACE),
+            "xmlns" => Some(XMLNS_NAMESPACE),
+            _ => self
+                .namespaces
+                .get(prefix)
+                .map(String::as_str)
+                .filter(|namespace| !namespace.is_empty()),
+        }
+    }
+
+    /// Expands the prefixed name `value` with the namespaces in scope on
+    /// this element. Unprefixed names are in the default namespace.
+    pub(crate) fn resolve_qname(&self, value: &str) -> Result<QName, String> {
+        let value = value.trim();
+        match value.split_once(':') {
+            Some((prefix, local)) => match self.namespace(prefix) {
+                Some(namespace) => Ok(QName::new(Some(namespace), local)),
+                None => Err(format!(
+                    "Unbound namespace prefix '{}' in '{}'",
+                    prefix, value
+                )),
+            },
+            None => Ok(QName::new(self.namespace(""), value)),
+        }
+    }
+
+    /// Expands the name of an attribute of this element. Unprefixed
+    /// attribute names are in no namespace.
+    pub(crate) fn attribute_qname(&self, name: &str) -> Result<QName, String> {
+        if name.contains(':') {
+            self.resolve_qname(name)
+        } else {
+            Ok(QName::new(None, name))
+        }
+    }
+}
+
+/// The attributes of an element, as read by the [`Tokenizer`], in
+/// document order.
+///
+/// Besides the raw values, the map has typed getters that parse the
+/// values of the attributes of schema elements, such as booleans,
+/// occurrence bounds and derivation sets.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Attributes(Vec<(String, String)>);
+
+impl Attributes {
+    /// Returns the value of the attribute called `name`, if present.
+    pub(crate) fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(attribute, _)| attribute == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns an iterator over the names and values of the attributes.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    /// Returns the value of the `xs:boolean` attribute called `name`, if
+    /// present.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the attribute.
+    ///
+    pub(crate) fn boolean(&self, name: &str) -> Result<Option<bool>, String> {
+        match self.get(name).map(str::trim) {
+            Some("true" | "1") => Ok(Some(true)),
+            Some("false" | "0") => Ok(Some(false)),
+            Some(value) => Err(invalid_value(name, value, "not a boolean")),
+            None => Ok(None),
+        }
+    }
+
+    /// Parses the value of the attribute called `name`, if present.
+    ///
+    /// Leading and trailing whitespace is removed from the value before
+    /// it is parsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the attribute.
+    ///
+    pub(crate) fn parse<T>(&self, name: &str) -> Result<Option<T>, String>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        match self.get(name).map(str::trim) {
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|err| invalid_value(name, value, err)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn invalid_value(name: &str, value: &str, reason: impl Display) -> String {
+    format!(
+        "Invalid value '{}' for attribute '{}': {}",
+        value, name, reason
+    )
+}
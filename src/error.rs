@@ -0,0 +1,289 @@
+//! Errors reported while loading schemas and validating documents.
+
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+};
+
+use crate::document::XmlElement;
+
+/// A position in a document.
+///
+/// Lines and columns are counted from 1, columns in characters. The
+/// byte offset is counted from 0, from the start of the document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Location {
+    line: usize,
+    column: usize,
+    offset: usize,
+}
+
+impl Location {
+    /// Returns the location of the byte offset `offset` in `input`.
+    ///
+    /// The input is scanned up to the offset, so a [`LineIndex`] should
+    /// be used to locate several offsets of the same input.
+    pub(crate) fn new(input: &str, offset: usize) -> Self {
+        let offset = offset.min(input.len());
+        let before = &input.as_bytes()[..offset];
+        let line_start = before
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map_or(0, |index| index + 1);
+        Self {
+            line: before.iter().filter(|&&byte| byte == b'\n').count() + 1,
+            column: input[line_start..offset].chars().count() + 1,
+            offset,
+        }
+    }
+
+    /// Returns the line number, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the column number in characters, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns the byte offset from the start of the document.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// The byte offsets at which the lines of a document start, from which
+/// the locations of offsets are found without scanning the document
+/// again.
+#[derive(Debug)]
+pub(crate) struct LineIndex<'a> {
+    input: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Indexes the lines of `input`.
+    pub(crate) fn new(input: &'a str) -> Self {
+        let mut starts = vec![0];
+        starts.extend(
+            input
+                .bytes()
+                .enumerate()
+                .filter(|(_, byte)| *byte == b'\n')
+                .map(|(index, _)| index + 1),
+        );
+        Self { input, starts }
+    }
+
+    /// Returns the location of the byte offset `offset` of the input.
+    pub(crate) fn location(&self, offset: usize) -> Location {
+        let offset = offset.min(self.input.len());
+        let line = self.starts.partition_point(|&start| start <= offset);
+        Location {
+            line,
+            column: self.input[self.starts[line - 1]..offset].chars().count() + 1,
+            offset,
+        }
+    }
+}
+
+/// An error reported while loading a schema or validating a document
+/// against it.
+///
+/// Errors found in a document carry their [`Location`], and violations
+/// of the XML Schema specification also carry the code of the constraint
+/// or validation rule they break, such as `src-element.1`,
+/// `s4s-elt-invalid-content.1` or `cvc-complex-type.2.4.a`, and the path
+/// of the offending element of the document.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{Schema, SchemaError};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:element name="a" default="x" fixed="y"/>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Err(error) => {
+///         assert_eq!(error.code(), Some("src-element.1"));
+///         assert_eq!(error.location().map(|location| location.line()), Some(2));
+///         assert_eq!(error.path(), Some("/xs:schema/xs:element[@name='a']"));
+///     }
+///     Ok(_) => unreachable!(),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SchemaError {
+    /// A document could not be read
+    Io {
+        /// A description of the failure
+        message: String,
+    },
+    /// A document is not well-formed XML, or not namespace-well-formed
+    Syntax {
+        /// A description of the failure
+        message: String,
+        /// Where the failure was found
+        location: Location,
+    },
+    /// A schema document breaks a constraint of the XML Schema
+    /// specification, or an instance document a validation rule
+    Constraint {
+        /// The code of the constraint
+        code: String,
+        /// A description of the failure
+        message: String,
+        /// Where the failure was found
+        location: Location,
+        /// The path of the offending element of the document
+        path: String,
+    },
+    /// Several errors found in the same document
+    Multiple(Vec<SchemaError>),
+    /// An error found in a schema document included, imported,
+    /// redefined or overridden by the schema being loaded
+    Document {
+        /// The location of the schema document
+        document: String,
+        /// The error found in it
+        error: Box<SchemaError>,
+    },
+}
+
+impl SchemaError {
+    /// Returns the code of the constraint broken, if any.
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            SchemaError::Constraint { code, .. } => Some(code),
+            SchemaError::Multiple(errors) => errors.first().and_then(SchemaError::code),
+            SchemaError::Document { error, .. } => error.code(),
+            _ => None,
+        }
+    }
+
+    /// Returns where the error was found, if known.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            SchemaError::Syntax { location, .. } | SchemaError::Constraint { location, .. } => {
+                Some(*location)
+            }
+            SchemaError::Multiple(errors) => errors.first().and_then(SchemaError::location),
+            SchemaError::Document { error, .. } => error.location(),
+            SchemaError::Io { .. } => None,
+        }
+    }
+
+    /// Returns the path of the offending element of the document, if
+    /// known.
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            SchemaError::Constraint { path, .. } => Some(path),
+            SchemaError::Multiple(errors) => errors.first().and_then(SchemaError::path),
+            SchemaError::Document { error, .. } => error.path(),
+            _ => None,
+        }
+    }
+
+    /// Returns the location of the schema document the error was found
+    /// in, if it is not the document the schema is loaded from.
+    pub fn document(&self) -> Option<&str> {
+        match self {
+            SchemaError::Document { document, .. } => Some(document),
+            SchemaError::Multiple(errors) => errors.first().and_then(SchemaError::document),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the individual errors: the errors of a
+    /// [`SchemaError::Multiple`], or the error itself.
+    pub fn errors(&self) -> impl Iterator<Item = &SchemaError> {
+        match self {
+            SchemaError::Multiple(errors) => errors.iter(),
+            error => std::slice::from_ref(error).iter(),
+        }
+    }
+
+    /// Creates a [`SchemaError::Syntax`] at the byte offset `offset` of
+    /// `input`.
+    pub(crate) fn syntax(input: &str, offset: usize, message: &str) -> Self {
+        SchemaError::Syntax {
+            message: message.to_string(),
+            location: Location::new(input, offset),
+        }
+    }
+
+    /// Creates a [`SchemaError::Constraint`] for the element `node`,
+    /// found at `location`.
+    pub(crate) fn constraint(
+        location: Location,
+        node: &XmlElement,
+        code: &str,
+        message: &str,
+    ) -> Self {
+        SchemaError::Constraint {
+            code: code.to_string(),
+            message: message.to_string(),
+            location,
+            path: node.path.to_string(),
+        }
+    }
+
+    /// Marks the error as found in the schema document at `document`,
+    /// unless it already is marked with the document it was found in.
+    pub(crate) fn in_document(self, document: &str) -> Self {
+        match self {
+            SchemaError::Document { .. } => self,
+            error => SchemaError::Document {
+                document: document.to_string(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Collects `errors` into a single error, if there are any.
+    pub(crate) fn from_errors(mut errors: Vec<SchemaError>) -> Result<(), SchemaError> {
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(SchemaError::Multiple(errors)),
+        }
+    }
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            SchemaError::Io { message } => write!(f, "{}", message),
+            SchemaError::Syntax { message, location } => write!(f, "{} at {}", message, location),
+            SchemaError::Constraint {
+                code,
+                message,
+                location,
+                ..
+            } => write!(f, "{}: {} at {}", code, message, location),
+            SchemaError::Multiple(errors) => {
+                for (index, error) in errors.iter().enumerate() {
+                    if index > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+            SchemaError::Document { document, error } => write!(f, "{} in {}", error, document),
+        }
+    }
+}
+
+impl Error for SchemaError {}
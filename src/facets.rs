@@ -0,0 +1,695 @@
+//! Constraining facets of simple types.
+//!
+//! A simple type restricting another one narrows its value space with
+//! facets such as `xs:maxLength` or `xs:minInclusive`. Which facets
+//! apply to a type depends on its primitive datatype, as listed by the
+//! `hfp:hasFacet` annotations of the schema for schemas. The facets of a
+//! type are those it declares, merged with the facets it inherits from
+//! its base type, and every value of the type must satisfy all of them.
+//!
+
+use std::{
+    cmp::Ordering,
+    fmt::{Display, Error, Formatter},
+};
+
+use crate::{
+    components::{Assertion, SimpleType, Variety},
+    datatypes::{SimpleDatatype, Value, Whitespace},
+    meta::MetaSchema,
+    regex::Regex,
+    schema::XsdVersion,
+    xpath2::Atomic,
+};
+
+/// A constraining facet of a simple type.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{Facet, Schema};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:simpleType name="percent">
+///         <xs:restriction base="xs:integer">
+///             <xs:minInclusive value="0"/>
+///             <xs:maxInclusive value="100"/>
+///         </xs:restriction>
+///     </xs:simpleType>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let percent = schema.simple_types().next().unwrap();
+///         assert!(percent
+///             .facets()
+///             .contains(&Facet::MaxInclusive("100".to_string())));
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Facet {
+    /// xs:length
+    Length(usize),
+    /// xs:minLength
+    MinLength(usize),
+    /// xs:maxLength
+    MaxLength(usize),
+    /// The xs:pattern facets of one restriction, any of which a value
+    /// must match
+    Pattern(Vec<Regex>),
+    /// The xs:enumeration facets of one restriction, one of which a
+    /// value must be equal to
+    Enumeration(Vec<String>),
+    /// xs:whiteSpace
+    WhiteSpace(Whitespace),
+    /// xs:maxInclusive
+    MaxInclusive(String),
+    /// xs:maxExclusive
+    MaxExclusive(String),
+    /// xs:minInclusive
+    MinInclusive(String),
+    /// xs:minExclusive
+    MinExclusive(String),
+    /// xs:totalDigits
+    TotalDigits(usize),
+    /// xs:fractionDigits
+    FractionDigits(usize),
+    /// The XSD 1.1 xs:assertion facets of one restriction, all of which
+    /// a value must satisfy
+    Assertion(Vec<Assertion>),
+}
+
+impl Facet {
+    /// Returns the local name of the element declaring the facet.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Facet::Length(_) => "length",
+            Facet::MinLength(_) => "minLength",
+            Facet::MaxLength(_) => "maxLength",
+            Facet::Pattern(_) => "pattern",
+            Facet::Enumeration(_) => "enumeration",
+            Facet::WhiteSpace(_) => "whiteSpace",
+            Facet::MaxInclusive(_) => "maxInclusive",
+            Facet::MaxExclusive(_) => "maxExclusive",
+            Facet::MinInclusive(_) => "minInclusive",
+            Facet::MinExclusive(_) => "minExclusive",
+            Facet::TotalDigits(_) => "totalDigits",
+            Facet::FractionDigits(_) => "fractionDigits",
+            Facet::Assertion(_) => "assertion",
+        }
+    }
+
+    /// Returns the value of a bound facet.
+    fn bound(&self) -> Option<&str> {
+        match self {
+            Facet::MaxInclusive(value)
+            | Facet::MaxExclusive(value)
+            | Facet::MinInclusive(value)
+            | Facet::MinExclusive(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if declaring `other` in a restriction replaces this
+    /// facet of the base type.
+    fn is_replaced_by(&self, other: &Facet) -> bool {
+        use Facet::*;
+        match (self, other) {
+            (Pattern(_) | Assertion(_), _) => false,
+            (MaxInclusive(_) | MaxExclusive(_), MaxInclusive(_) | MaxExclusive(_)) => true,
+            (MinInclusive(_) | MinExclusive(_), MinInclusive(_) | MinExclusive(_)) => true,
+            _ => self.name() == other.name(),
+        }
+    }
+}
+
+impl Display for Facet {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match self {
+            Facet::Length(value)
+            | Facet::MinLength(value)
+            | Facet::MaxLength(value)
+            | Facet::TotalDigits(value)
+            | Facet::FractionDigits(value) => write!(f, "{} '{}'", self.name(), value),
+            Facet::Pattern(patterns) => {
+                let patterns: Vec<&str> = patterns.iter().map(Regex::as_str).collect();
+                write!(f, "{} '{}'", self.name(), patterns.join("' | '"))
+            }
+            Facet::Enumeration(values) => {
+                write!(f, "{} '{}'", self.name(), values.join("' | '"))
+            }
+            Facet::Assertion(assertions) => {
+                let tests: Vec<&str> = assertions.iter().map(Assertion::test).collect();
+                write!(f, "{} '{}'", self.name(), tests.join("' and '"))
+            }
+            Facet::WhiteSpace(value) => write!(f, "{} '{}'", self.name(), value),
+            Facet::MaxInclusive(value)
+            | Facet::MaxExclusive(value)
+            | Facet::MinInclusive(value)
+            | Facet::MinExclusive(value) => write!(f, "{} '{}'", self.name(), value),
+        }
+    }
+}
+
+/// Returns `true` if `name` is the local name of a facet element of the
+/// XML Schema namespace.
+pub(crate) fn is_facet(name: &str) -> bool {
+    matches!(
+        name,
+        "length"
+            | "minLength"
+            | "maxLength"
+            | "pattern"
+            | "enumeration"
+            | "whiteSpace"
+            | "maxInclusive"
+            | "maxExclusive"
+            | "minInclusive"
+            | "minExclusive"
+            | "totalDigits"
+            | "fractionDigits"
+            | "assertion"
+    )
+}
+
+/// Returns `true` if the facet called `name` applies to the restrictions
+/// of `base`.
+///
+/// List types accept the length facets, and union types only `pattern`
+/// and `enumeration`. The facets of atomic types are those the schema
+/// for schemas lists for their primitive datatype. Assertions apply to
+/// every simple type.
+///
+/// # Arguments
+///
+/// * `name` - The local name of the facet, such as `maxLength`.
+/// * `base` - The simple type being restricted.
+/// * `version` - The version of XML Schema the schema is read as.
+///
+pub(crate) fn is_applicable(name: &str, base: &SimpleType, version: XsdVersion) -> bool {
+    if name == "assertion" {
+        return true;
+    }
+    match base.variety {
+        Variety::List(_) => {
+            return matches!(
+                name,
+                "length" | "minLength" | "maxLength" | "pattern" | "enumeration" | "whiteSpace"
+            )
+        }
+        Variety::Union(_) => return matches!(name, "pattern" | "enumeration"),
+        Variety::Atomic => {}
+    }
+    // The schema for schemas is compiled before any simple type is built
+    MetaSchema::get(version).map_or(false, |meta_schema| {
+        meta_schema.has_facet(base.datatype.primitive().name(), name)
+    })
+}
+
+/// Merges the facets declared by a restriction of a simple type with the
+/// facets of its base type.
+///
+/// This function checks that each declared facet applies to the base
+/// type and narrows its value space rather than widening it, and that
+/// the merged facets are consistent with each other. The declared facets
+/// replace the base facets of the same kind, except for patterns, which
+/// accumulate.
+///
+/// # Arguments
+///
+/// * `base` - The resolved base type.
+/// * `declared` - The facets declared by the restriction.
+/// * `version` - The version of XML Schema the schema is read as.
+///
+/// Errors are returned as the code of the constraint broken and a
+/// message.
+///
+pub(crate) fn restrict(
+    base: &SimpleType,
+    declared: Vec<Facet>,
+    version: XsdVersion,
+) -> Result<Vec<Facet>, (String, String)> {
+    for facet in &declared {
+        if !is_applicable(facet.name(), base, version) {
+            return Err((
+                "cos-applicable-facets".to_string(),
+                format!(
+                    "Facet '{}' is not allowed by the type '{}'",
+                    facet.name(),
+                    base.name
+                ),
+            ));
+        }
+        check_restriction(base, facet, version)
+            .map_err(|message| (format!("{}-valid-restriction", facet.name()), message))?;
+    }
+    let mut facets: Vec<Facet> = base
+        .facets
+        .iter()
+        .filter(|facet| !declared.iter().any(|other| facet.is_replaced_by(other)))
+        .cloned()
+        .collect();
+    facets.extend(declared);
+    check_consistency(base.datatype, &facets, version)?;
+    Ok(facets)
+}
+
+/// Checks that the declared `facet` narrows the facets of the base type
+/// `base`.
+fn check_restriction(base: &SimpleType, facet: &Facet, version: XsdVersion) -> Result<(), String> {
+    let datatype = base.datatype;
+    let widens = |base_facet: &Facet| {
+        format!(
+            "The {} facet of the base type cannot be widened to {}",
+            base_facet, facet
+        )
+    };
+    for base_facet in &base.facets {
+        if base.fixed_facets.contains(&base_facet.name())
+            && base_facet.name() == facet.name()
+            && base_facet != facet
+        {
+            return Err(format!(
+                "The {} facet of the base type is fixed and cannot be changed to {}",
+                base_facet, facet
+            ));
+        }
+        let narrows = match (base_facet, facet) {
+            (Facet::Length(base), Facet::Length(value)) => value == base,
+            (Facet::MinLength(base), Facet::MinLength(value) | Facet::Length(value)) => {
+                value >= base
+            }
+            (Facet::MaxLength(base), Facet::MaxLength(value) | Facet::Length(value))
+            | (Facet::TotalDigits(base), Facet::TotalDigits(value))
+            | (Facet::FractionDigits(base), Facet::FractionDigits(value)) => value <= base,
+            (Facet::WhiteSpace(base), Facet::WhiteSpace(value)) => value >= base,
+            (base_facet, facet) => match (base_facet.bound(), facet.bound()) {
+                (Some(base_value), Some(value)) => {
+                    is_within(datatype, base_facet, base_value, facet, value, version)
+                }
+                _ => true,
+            },
+        };
+        if !narrows {
+            return Err(widens(base_facet));
+        }
+    }
+    let collapsed = !matches!(base.variety, Variety::Atomic)
+        || !datatype.is_derived_from(SimpleDatatype::String);
+    match facet {
+        Facet::WhiteSpace(value) if collapsed && *value != Whitespace::Collapse => {
+            Err(widens(&Facet::WhiteSpace(Whitespace::Collapse)))
+        }
+        Facet::Enumeration(values) => values.iter().try_for_each(|value| {
+            check_value(base, value, version)
+                .map(|_| ())
+                .map_err(|(_, message)| message)
+        }),
+        _ => match facet.bound() {
+            Some(value) => datatype
+                .parse(value, version)
+                .map(|_| ())
+                .map_err(|message| format!("Invalid value of {}: {}", facet, message)),
+            None => Ok(()),
+        },
+    }
+}
+
+/// Returns `true` if the bound `value` of `facet` lies within the bound
+/// `base_value` of the base facet `base_facet`. Bounds that cannot be
+/// compared are accepted.
+fn is_within(
+    datatype: SimpleDatatype,
+    base_facet: &Facet,
+    base_value: &str,
+    facet: &Facet,
+    value: &str,
+    version: XsdVersion,
+) -> bool {
+    use Facet::*;
+    let ordering = match (
+        datatype.parse(value, version),
+        datatype.parse(base_value, version),
+    ) {
+        (Ok(value), Ok(base_value)) => value.partial_cmp(&base_value),
+        _ => None,
+    };
+    let ordering = match ordering {
+        Some(ordering) => ordering,
+        None => return true,
+    };
+    match (facet, base_facet) {
+        (MinInclusive(_), MinExclusive(_)) | (MaxInclusive(_), MinExclusive(_)) => {
+            ordering == Ordering::Greater
+        }
+        (
+            MinInclusive(_) | MinExclusive(_) | MaxInclusive(_) | MaxExclusive(_),
+            MinInclusive(_),
+        )
+        | (MinExclusive(_) | MaxExclusive(_), MinExclusive(_)) => ordering != Ordering::Less,
+        (MinInclusive(_), MaxExclusive(_)) | (MaxInclusive(_), MaxExclusive(_)) => {
+            ordering == Ordering::Less
+        }
+        (MinExclusive(_) | MaxExclusive(_), MaxExclusive(_))
+        | (
+            MinInclusive(_) | MinExclusive(_) | MaxInclusive(_) | MaxExclusive(_),
+            MaxInclusive(_),
+        ) => ordering != Ordering::Greater,
+        _ => true,
+    }
+}
+
+/// Checks that the merged facets of a simple type do not contradict each
+/// other.
+fn check_consistency(
+    datatype: SimpleDatatype,
+    facets: &[Facet],
+    version: XsdVersion,
+) -> Result<(), (String, String)> {
+    let find = |name: &str| facets.iter().find(|facet| facet.name() == name);
+    let error = |code: &str, first: &Facet, second: &Facet| {
+        Err((
+            code.to_string(),
+            format!("The {} facet contradicts the {} facet", first, second),
+        ))
+    };
+    let lengths = (find("length"), find("minLength"), find("maxLength"));
+    if let (Some(length @ Facet::Length(value)), min, max) = lengths {
+        for other in [min, max].into_iter().flatten() {
+            let contradicts = match other {
+                Facet::MinLength(min) => value < min,
+                Facet::MaxLength(max) => value > max,
+                _ => false,
+            };
+            if contradicts {
+                return error("length-minLength-maxLength", length, other);
+            }
+        }
+    }
+    if let (_, Some(min @ Facet::MinLength(low)), Some(max @ Facet::MaxLength(high))) = lengths {
+        if low > high {
+            return error("minLength-less-than-equal-to-maxLength", min, max);
+        }
+    }
+    if let (Some(fraction @ Facet::FractionDigits(low)), Some(total @ Facet::TotalDigits(high))) =
+        (find("fractionDigits"), find("totalDigits"))
+    {
+        if low > high {
+            return error("fractionDigits-totalDigits", fraction, total);
+        }
+    }
+    for (min_name, max_name, strict, code) in [
+        (
+            "minInclusive",
+            "maxInclusive",
+            false,
+            "minInclusive-less-than-equal-to-maxInclusive",
+        ),
+        (
+            "minExclusive",
+            "maxExclusive",
+            false,
+            "minExclusive-less-than-equal-to-maxExclusive",
+        ),
+        (
+            "minInclusive",
+            "maxExclusive",
+            true,
+            "minInclusive-less-than-maxExclusive",
+        ),
+        (
+            "minExclusive",
+            "maxInclusive",
+            true,
+            "minExclusive-less-than-maxInclusive",
+        ),
+    ] {
+        if let (Some(min), Some(max)) = (find(min_name), find(max_name)) {
+            let bounds = (
+                min.bound().map(|value| datatype.parse(value, version)),
+                max.bound().map(|value| datatype.parse(value, version)),
+            );
+            if let (Some(Ok(low)), Some(Ok(high))) = bounds {
+                let contradicts = match low.partial_cmp(&high) {
+                    Some(Ordering::Greater) => true,
+                    Some(Ordering::Equal) => strict,
+                    _ => false,
+                };
+                if contradicts {
+                    return error(code, min, max);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `lexical` is a valid value of `simple_type`, and returns
+/// that value.
+///
+/// The items of a list are separated by whitespace and checked against
+/// the item type, and the value of a union is that of its first member
+/// type that accepts it. The value is then checked against the facets of
+/// the type.
+///
+/// # Arguments
+///
+/// * `simple_type` - The resolved simple type.
+/// * `lexical` - The text to check.
+/// * `version` - The version of XML Schema the schema is read as.
+///
+/// Errors are returned as the code of the validation rule broken and a
+/// message.
+///
+pub(crate) fn check_value(
+    simple_type: &SimpleType,
+    lexical: &str,
+    version: XsdVersion,
+) -> Result<Value, (String, String)> {
+    match &simple_type.variety {
+        Variety::Atomic => {
+            check_atomic(simple_type.datatype, &simple_type.facets, lexical, version)
+        }
+        Variety::List(_) => {
+            let normalized = Whitespace::Collapse.normalize(lexical);
+            let value = parse_list(simple_type, &normalized, version).map_err(|message| {
+                (
+                    "cvc-datatype-valid.1.2.2".to_string(),
+                    format!("'{}' is not a valid list: {}", normalized, message),
+                )
+            })?;
+            let length = match &value {
+                Value::List(items) => Some(items.len()),
+                _ => None,
+            };
+            check_facets(&simple_type.facets, &normalized, &value, length, &|text| {
+                parse_list(simple_type, &Whitespace::Collapse.normalize(text), version).ok()
+            })?;
+            Ok(value)
+        }
+        Variety::Union(_) => {
+            let value = parse_union(simple_type, lexical, version).ok_or_else(|| {
+                (
+                    "cvc-datatype-valid.1.2.3".to_string(),
+                    format!(
+                        "'{}' is not a valid value of any member type of the union",
+                        lexical
+                    ),
+                )
+            })?;
+            check_facets(&simple_type.facets, lexical, &value, None, &|text| {
+                parse_union(simple_type, text, version)
+            })?;
+            Ok(value)
+        }
+    }
+}
+
+/// Parses the whitespace-separated items of a value of the list type
+/// `simple_type`.
+fn parse_list(
+    simple_type: &SimpleType,
+    normalized: &str,
+    version: XsdVersion,
+) -> Result<Value, String> {
+    let item_type = match simple_type.members.first() {
+        Some(item_type) => item_type,
+        None => return Ok(Value::String(normalized.to_string())),
+    };
+    normalized
+        .split(' ')
+        .filter(|item| !item.is_empty())
+        .map(|item| check_value(item_type, item, version).map_err(|(_, message)| message))
+        .collect::<Result<_, _>>()
+        .map(Value::List)
+}
+
+/// Parses a value of the union type `simple_type` with its first member
+/// type that accepts it.
+fn parse_union(simple_type: &SimpleType, lexical: &str, version: XsdVersion) -> Option<Value> {
+    simple_type
+        .members
+        .iter()
+        .find_map(|member| check_value(member, lexical, version).ok())
+}
+
+/// Checks that `lexical` is a valid value of the atomic type based on
+/// `datatype` and restricted by `facets`, and returns that value.
+///
+/// The value is normalized by the `xs:whiteSpace` facet, if any, then
+/// parsed and checked against every other facet. Patterns are matched
+/// on the normalized value.
+///
+/// # Arguments
+///
+/// * `datatype` - The built-in datatype the simple type is based on.
+/// * `facets` - The facets of the simple type.
+/// * `lexical` - The text to check.
+/// * `version` - The version of XML Schema the schema is read as.
+///
+pub(crate) fn check_atomic(
+    datatype: SimpleDatatype,
+    facets: &[Facet],
+    lexical: &str,
+    version: XsdVersion,
+) -> Result<Value, (String, String)> {
+    let whitespace = facets
+        .iter()
+        .find_map(|facet| match facet {
+            Facet::WhiteSpace(whitespace) => Some(*whitespace),
+            _ => None,
+        })
+        .unwrap_or_else(|| datatype.whitespace());
+    let normalized = whitespace.normalize(lexical);
+    let value = datatype
+        .parse(&normalized, version)
+        .map_err(|message| ("cvc-datatype-valid.1.2.1".to_string(), message))?;
+    let length = length(datatype, &normalized, &value);
+    check_facets(facets, &normalized, &value, length, &|text| {
+        datatype.parse(text, version).ok()
+    })?;
+    Ok(value)
+}
+
+/// Checks `value` against `facets`.
+///
+/// # Arguments
+///
+/// * `facets` - The facets of the simple type.
+/// * `normalized` - The lexical form of the value, once its whitespace
+///   is normalized, which patterns are matched against.
+/// * `value` - The value.
+/// * `length` - The length of the value, if it has one.
+/// * `parse` - Parses enumerated values and bounds into values of the
+///   type.
+///
+fn check_facets(
+    facets: &[Facet],
+    normalized: &str,
+    value: &Value,
+    length: Option<usize>,
+    parse: &dyn Fn(&str) -> Option<Value>,
+) -> Result<(), (String, String)> {
+    for facet in facets {
+        let valid = match facet {
+            Facet::Length(expected) => length.map_or(true, |length| length == *expected),
+            Facet::MinLength(min) => length.map_or(true, |length| length >= *min),
+            Facet::MaxLength(max) => length.map_or(true, |length| length <= *max),
+            Facet::Enumeration(values) => values
+                .iter()
+                .any(|enumerated| parse(enumerated).as_ref() == Some(value)),
+            Facet::TotalDigits(max) => match value {
+                Value::Decimal(decimal) => decimal.total_digits() <= *max,
+                _ => true,
+            },
+            Facet::FractionDigits(max) => match value {
+                Value::Decimal(decimal) => decimal.fraction_digits() <= *max,
+                _ => true,
+            },
+            Facet::MaxInclusive(bound)
+            | Facet::MaxExclusive(bound)
+            | Facet::MinInclusive(bound)
+            | Facet::MinExclusive(bound) => match parse(bound) {
+                Some(bound) => {
+                    let ordering = value.partial_cmp(&bound);
+                    match facet {
+                        Facet::MaxInclusive(_) => {
+                            matches!(ordering, Some(Ordering::Less | Ordering::Equal))
+                        }
+                        Facet::MaxExclusive(_) => ordering == Some(Ordering::Less),
+                        Facet::MinInclusive(_) => {
+                            matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+                        }
+                        _ => ordering == Some(Ordering::Greater),
+                    }
+                }
+                None => true,
+            },
+            Facet::Pattern(patterns) => patterns.iter().any(|regex| regex.is_match(normalized)),
+            Facet::WhiteSpace(_) => true,
+            Facet::Assertion(assertions) => {
+                check_assertions(assertions, normalized, value)?;
+                true
+            }
+        };
+        if !valid {
+            return Err((
+                format!("cvc-{}-valid", facet.name()),
+                format!(
+                    "Value '{}' is not facet-valid with respect to {}",
+                    normalized, facet
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Checks `value`, whose lexical form is `normalized`, against the tests
+/// of the `xs:assertion` facets `assertions`, with `$value` bound to the
+/// value.
+fn check_assertions(
+    assertions: &[Assertion],
+    normalized: &str,
+    value: &Value,
+) -> Result<(), (String, String)> {
+    for assertion in assertions {
+        let atomics = Atomic::from_value(value.clone(), normalized);
+        let reason = match assertion.test.test(None, atomics, &|_, _| None) {
+            Ok(true) => continue,
+            Ok(false) => String::new(),
+            Err(error) => format!(": {}", error),
+        };
+        return Err((
+            "cvc-assertion".to_string(),
+            format!(
+                "Value '{}' is not facet-valid with respect to assertion '{}'{}",
+                normalized,
+                assertion.test(),
+                reason
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Returns the length of `value` as measured by the length facets: the
+/// number of characters of strings, of octets of binary values and of
+/// items of the built-in list datatypes. Qualified names have no length.
+fn length(datatype: SimpleDatatype, normalized: &str, value: &Value) -> Option<usize> {
+    match value {
+        Value::Binary(bytes) => Some(bytes.len()),
+        Value::List(items) => Some(items.len()),
+        Value::QName(_) => None,
+        _ if datatype.primitive() == SimpleDatatype::AnyUri
+            || datatype.is_derived_from(SimpleDatatype::String) =>
+        {
+            Some(normalized.chars().count())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,748 @@
+//! The symbol table of a schema, and the index of its global
+//! components.
+//!
+//! Once the schema documents are read, and before the components are
+//! resolved, every global component is entered in the table of its
+//! symbol space: elements, attributes, types (simple and complex types
+//! share theirs), model groups, attribute groups and notations. A name
+//! can only be defined once in each space. Every reference made by the
+//! components, global or local, is then resolved to the handle of the
+//! component it names, and a name with no definition there is reported
+//! at the declaration that makes the reference. The `xml:lang`,
+//! `xml:space`, `xml:base` and `xml:id` attributes are known without
+//! importing the XML namespace.
+//!
+//! The handles are kept alongside the names on the components: element
+//! and attribute references, including those that are particles of
+//! content models and attribute uses, the types of elements and
+//! attributes, the bases of types and the heads of substitution groups.
+//! References to model groups and attribute groups are only checked,
+//! since their content is merged into the components that reference
+//! them. The [`SchemaGraph`] of a loaded schema follows the handles, and
+//! the validator goes through it rather than looking components up by
+//! name.
+//!
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    hash::Hash,
+};
+
+use crate::{
+    components::{
+        Attribute, AttributeGroup, ComplexType, Datatype, Derivation, Element, Group, ModelGroup,
+        Notation, Particle, SimpleType, Term, Variety,
+    },
+    datatypes::SimpleDatatype,
+    error::SchemaError,
+    loader::{definition_error, SchemaDocument},
+    parser::{builtin_datatype, Definition, XmlSchema, XmlSchemaNode},
+    qname::{QName, XML_NAMESPACE},
+};
+
+/// A handle on a global element declaration of a [`SchemaGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ElementId(usize);
+
+/// A handle on a global attribute declaration of a [`SchemaGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AttributeId(usize);
+
+/// A handle on a named model group definition of a [`SchemaGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct GroupId(usize);
+
+/// A handle on an attribute group definition of a [`SchemaGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AttributeGroupId(usize);
+
+/// A handle on a notation declaration of a [`SchemaGraph`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NotationId(usize);
+
+/// A handle on a type definition of a [`SchemaGraph`]: a global simple
+/// or complex type of the schema, or a built-in type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TypeId(TypeHandle);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TypeHandle {
+    /// xs:anyType
+    AnyType,
+    /// A built-in simple type
+    Builtin(SimpleDatatype),
+    /// The global simple type at an index of the components
+    Simple(usize),
+    /// The global complex type at an index of the components
+    Complex(usize),
+}
+
+impl TypeId {
+    /// Returns `true` if the handle is that of a simple type.
+    pub fn is_simple(&self) -> bool {
+        matches!(self.0, TypeHandle::Builtin(_) | TypeHandle::Simple(_))
+    }
+}
+
+/// A type definition of a [`SchemaGraph`], as found through its
+/// [`TypeId`].
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum TypeDefinition<'s> {
+    /// xs:anyType, the root of the type hierarchy
+    AnyType,
+    /// A built-in simple type
+    Builtin(SimpleDatatype),
+    /// A global simple type of the schema
+    Simple(&'s SimpleType),
+    /// A global complex type of the schema
+    Complex(&'s ComplexType),
+}
+
+/// The symbol spaces of a schema, mapping the names of its global
+/// components to their handles.
+///
+/// The handles index the components of the schema, followed by the
+/// original definitions of the components replaced by `xs:redefine`.
+#[derive(Debug, Default)]
+pub(crate) struct SymbolTable {
+    elements: HashMap<QName, ElementId>,
+    attributes: HashMap<QName, AttributeId>,
+    types: HashMap<QName, TypeId>,
+    groups: HashMap<QName, GroupId>,
+    attribute_groups: HashMap<QName, AttributeGroupId>,
+    notations: HashMap<QName, NotationId>,
+}
+
+impl SymbolTable {
+    /// Builds the symbol table of the components of `schema`, and
+    /// resolves every reference they make to the handle of a component
+    /// of the symbol space it names.
+    ///
+    /// This function returns the table wrapped in an `Ok` variant of the
+    /// `Result` type, or the first error found, in the order the
+    /// components are defined, wrapped in an `Err` variant of the
+    /// `Result` type.
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - The schema, whose components are not resolved yet.
+    /// * `documents` - The schema documents the schema is assembled
+    ///   from, for error locations.
+    ///
+    pub(crate) fn build(
+        schema: &mut XmlSchema,
+        documents: &[SchemaDocument],
+    ) -> Result<SymbolTable, SchemaError> {
+        let mut symbols = SymbolTable::default();
+        let components = schema.nodes.iter().chain(&schema.redefined);
+        for (index, node) in components.enumerate() {
+            if !symbols.insert(index, node) {
+                let (kind, name) = node.symbol();
+                return Err(definition_error(
+                    documents,
+                    node.definition(),
+                    "sch-props-correct.2",
+                    &format!("The {} '{}' is defined more than once", kind, name),
+                ));
+            }
+        }
+        let resolver = ReferenceResolver {
+            symbols: &symbols,
+            documents,
+        };
+        for node in schema.nodes.iter_mut().chain(&mut schema.redefined) {
+            resolver.component(node)?;
+        }
+        Ok(symbols)
+    }
+
+    /// Enters the component `node`, at `index` among the components of
+    /// the schema, in the table of its symbol space, and returns `false`
+    /// if a component of that space already has its name.
+    fn insert(&mut self, index: usize, node: &XmlSchemaNode) -> bool {
+        match node {
+            XmlSchemaNode::Element(element) => {
+                insert_new(&mut self.elements, &element.name, ElementId(index))
+            }
+            XmlSchemaNode::Attribute(attribute) => {
+                insert_new(&mut self.attributes, &attribute.name, AttributeId(index))
+            }
+            XmlSchemaNode::SimpleType(simple_type) => insert_new(
+                &mut self.types,
+                &simple_type.name,
+                TypeId(TypeHandle::Simple(index)),
+            ),
+            XmlSchemaNode::ComplexType(complex_type) => insert_new(
+                &mut self.types,
+                &complex_type.name,
+                TypeId(TypeHandle::Complex(index)),
+            ),
+            XmlSchemaNode::Group(group) => {
+                insert_new(&mut self.groups, &group.name, GroupId(index))
+            }
+            XmlSchemaNode::AttributeGroup(attribute_group) => insert_new(
+                &mut self.attribute_groups,
+                &attribute_group.name,
+                AttributeGroupId(index),
+            ),
+            XmlSchemaNode::Notation(notation) => {
+                insert_new(&mut self.notations, &notation.name, NotationId(index))
+            }
+        }
+    }
+
+    /// Returns the handle of the type called `name`, a global type of the
+    /// schema or a built-in type.
+    fn type_id(&self, name: &QName) -> Option<TypeId> {
+        if let Some(id) = self.types.get(name) {
+            return Some(*id);
+        }
+        if name.is_xsd("anyType") {
+            return Some(TypeId(TypeHandle::AnyType));
+        }
+        builtin_datatype(name).map(|datatype| TypeId(TypeHandle::Builtin(datatype)))
+    }
+}
+
+/// Inserts `id` under `name` in `table`, unless the name is taken, and
+/// returns whether it was inserted.
+fn insert_new<T>(table: &mut HashMap<QName, T>, name: &QName, id: T) -> bool
+where
+    T: Copy + Eq + Hash,
+{
+    match table.entry(name.clone()) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(id);
+            true
+        }
+    }
+}
+
+/// Returns `true` if `name` is that of an attribute of the XML namespace
+/// defined by the XML specification itself, which can be referenced
+/// without importing the schema document of the namespace.
+fn is_xml_attribute(name: &QName) -> bool {
+    name.namespace() == Some(XML_NAMESPACE)
+        && matches!(name.local(), "lang" | "space" | "base" | "id")
+}
+
+/// Resolves the references made by the components of a schema to the
+/// handles of the components they name.
+struct ReferenceResolver<'t> {
+    symbols: &'t SymbolTable,
+    documents: &'t [SchemaDocument],
+}
+
+impl ReferenceResolver<'_> {
+    fn component(&self, node: &mut XmlSchemaNode) -> Result<(), SchemaError> {
+        match node {
+            XmlSchemaNode::Element(element) => self.element(element),
+            XmlSchemaNode::Attribute(attribute) => self.attribute(attribute),
+            XmlSchemaNode::SimpleType(simple_type) => self.simple_type(simple_type),
+            XmlSchemaNode::ComplexType(complex_type) => self.complex_type(complex_type),
+            XmlSchemaNode::Group(group) => {
+                self.group_references(&group.model_group.particles, group)?;
+                self.model_group(&mut group.model_group)
+            }
+            XmlSchemaNode::AttributeGroup(attribute_group) => self.attribute_group(attribute_group),
+            XmlSchemaNode::Notation(_) => Ok(()),
+        }
+    }
+
+    fn element(&self, element: &mut Element) -> Result<(), SchemaError> {
+        if let Some(reference) = &element.reference {
+            let found = self.symbols.elements.get(reference).copied();
+            element.reference_id =
+                Some(self.resolve(found, element, reference, "an element declaration")?);
+            return Ok(());
+        }
+        element.head_ids = element
+            .substitution_group
+            .iter()
+            .map(|head| {
+                let found = self.symbols.elements.get(head).copied();
+                self.resolve(found, element, head, "an element declaration")
+            })
+            .collect::<Result<_, _>>()?;
+        element.type_id = self.named_type(&element.datatype, element, false)?;
+        self.anonymous_type(&mut element.datatype)
+    }
+
+    fn attribute(&self, attribute: &mut Attribute) -> Result<(), SchemaError> {
+        match &attribute.reference {
+            Some(reference) => {
+                let found = self.symbols.attributes.get(reference).copied();
+                if found.is_some() || !is_xml_attribute(reference) {
+                    attribute.reference_id = Some(self.resolve(
+                        found,
+                        attribute,
+                        reference,
+                        "an attribute declaration",
+                    )?);
+                }
+                Ok(())
+            }
+            None => {
+                attribute.type_id = self.named_type(&attribute.datatype, attribute, true)?;
+                self.anonymous_type(&mut attribute.datatype)
+            }
+        }
+    }
+
+    /// Returns the handle of the named type `datatype` of a declaration
+    /// or of the item or member types of a simple type `at`, which must
+    /// be a simple type if `simple` is `true`, or `None` if it is
+    /// anonymous.
+    fn named_type(
+        &self,
+        datatype: &Datatype,
+        at: &dyn Definition,
+        simple: bool,
+    ) -> Result<Option<TypeId>, SchemaError> {
+        let name = match datatype {
+            Datatype::SimpleType(name) | Datatype::ComplexType(name) => name,
+            Datatype::AnonymousSimpleType(_) | Datatype::AnonymousComplexType(_) => {
+                return Ok(None)
+            }
+        };
+        let found = self.symbols.type_id(name);
+        match simple {
+            true => {
+                let found = found.filter(TypeId::is_simple);
+                self.resolve(found, at, name, "a simple type definition")
+            }
+            false => self.resolve(found, at, name, "a type definition"),
+        }
+        .map(Some)
+    }
+
+    /// Resolves the references of the anonymous type `datatype`, if it is
+    /// one.
+    fn anonymous_type(&self, datatype: &mut Datatype) -> Result<(), SchemaError> {
+        match datatype {
+            Datatype::AnonymousSimpleType(simple_type) => self.simple_type(simple_type),
+            Datatype::AnonymousComplexType(complex_type) => self.complex_type(complex_type),
+            Datatype::SimpleType(_) | Datatype::ComplexType(_) => Ok(()),
+        }
+    }
+
+    fn simple_type(&self, simple_type: &mut SimpleType) -> Result<(), SchemaError> {
+        if let Some(base) = &simple_type.base_type {
+            let found = self.symbols.type_id(base).filter(TypeId::is_simple);
+            simple_type.base_id =
+                Some(self.resolve(found, simple_type, base, "a simple type definition")?);
+        }
+        if let Some(anonymous) = &mut simple_type.anonymous_base {
+            self.simple_type(anonymous)?;
+        }
+        let members = match &simple_type.variety {
+            Variety::Atomic => return Ok(()),
+            Variety::List(item_type) => std::slice::from_ref(item_type),
+            Variety::Union(member_types) => member_types,
+        };
+        for member in members {
+            self.named_type(member, simple_type, true)?;
+        }
+        match &mut simple_type.variety {
+            Variety::Atomic => Ok(()),
+            Variety::List(item_type) => self.anonymous_type(item_type),
+            Variety::Union(member_types) => member_types
+                .iter_mut()
+                .try_for_each(|member_type| self.anonymous_type(member_type)),
+        }
+    }
+
+    fn complex_type(&self, complex_type: &mut ComplexType) -> Result<(), SchemaError> {
+        if let Some(base) = &complex_type.base_type {
+            let found = self.symbols.type_id(base);
+            complex_type.base_id =
+                Some(self.resolve(found, complex_type, base, "a type definition")?);
+        }
+        if let Some(simple_content) = &mut complex_type.simple_content {
+            self.simple_type(simple_content)?;
+        }
+        self.attributes(complex_type.attributes.values_mut())?;
+        self.attribute_groups(&complex_type.attribute_groups, complex_type)?;
+        if let Some(particle) = &complex_type.particle {
+            self.group_references(std::slice::from_ref(particle), complex_type)?;
+        }
+        match &mut complex_type.particle {
+            Some(particle) => self.particle(particle),
+            None => Ok(()),
+        }
+    }
+
+    fn attribute_group(&self, attribute_group: &mut AttributeGroup) -> Result<(), SchemaError> {
+        self.attributes(attribute_group.attributes.values_mut())?;
+        self.attribute_groups(&attribute_group.attribute_groups, attribute_group)
+    }
+
+    /// Resolves the attribute declarations of a complex type or attribute
+    /// group, in the order they are declared.
+    fn attributes<'c>(
+        &self,
+        attributes: impl Iterator<Item = &'c mut Attribute>,
+    ) -> Result<(), SchemaError> {
+        let mut attributes: Vec<&mut Attribute> = attributes.collect();
+        attributes.sort_by_key(|attribute| (attribute.document, attribute.position));
+        attributes
+            .into_iter()
+            .try_for_each(|attribute| self.attribute(attribute))
+    }
+
+    /// Checks the attribute groups referenced by the complex type or
+    /// attribute group `at`, whose attributes are merged into its own
+    /// when the schema is resolved.
+    fn attribute_groups(&self, names: &[QName], at: &dyn Definition) -> Result<(), SchemaError> {
+        for name in names {
+            let found = self.symbols.attribute_groups.get(name).copied();
+            self.resolve(found, at, name, "an attribute group definition")?;
+        }
+        Ok(())
+    }
+
+    /// Checks the named groups referenced by `particles`, in the complex
+    /// type or model group definition `at`, whose model groups replace
+    /// the references when the schema is resolved.
+    fn group_references(
+        &self,
+        particles: &[Particle],
+        at: &dyn Definition,
+    ) -> Result<(), SchemaError> {
+        for particle in particles {
+            match &particle.term {
+                Term::ModelGroup(model_group) => {
+                    self.group_references(&model_group.particles, at)?
+                }
+                Term::GroupReference(name) => {
+                    let found = self.symbols.groups.get(name).copied();
+                    self.resolve(found, at, name, "a group definition")?;
+                }
+                Term::Element(_) | Term::Wildcard(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn model_group(&self, model_group: &mut ModelGroup) -> Result<(), SchemaError> {
+        model_group
+            .particles
+            .iter_mut()
+            .try_for_each(|particle| self.particle(particle))
+    }
+
+    /// Resolves the element declarations of `particle`.
+    fn particle(&self, particle: &mut Particle) -> Result<(), SchemaError> {
+        match &mut particle.term {
+            Term::Element(element) => self.element(element),
+            Term::ModelGroup(model_group) => self.model_group(model_group),
+            Term::Wildcard(_) | Term::GroupReference(_) => Ok(()),
+        }
+    }
+
+    /// Returns the handle `found` of the component called `name` of
+    /// `kind` referenced by the definition `at`, or an error at the
+    /// definition if there is none.
+    fn resolve<T>(
+        &self,
+        found: Option<T>,
+        at: &dyn Definition,
+        name: &QName,
+        kind: &str,
+    ) -> Result<T, SchemaError> {
+        found.ok_or_else(|| {
+            definition_error(
+                self.documents,
+                at,
+                "src-resolve",
+                &format!("Cannot resolve '{}' to {}", name, kind),
+            )
+        })
+    }
+}
+
+/// The graph of the components of a loaded schema, through typed
+/// handles.
+///
+/// The handles of the global components are looked up by name, and
+/// every reference a component makes to a global component, from the
+/// element and attribute declarations of content models and attribute
+/// uses to the types of declarations, the bases of types and the heads
+/// of substitution groups, is resolved to its handle when the schema is
+/// built. The handles of a graph are only meaningful for the schema they
+/// are taken from: the accessors following them panic, or return other
+/// components, given the handles of another schema.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::{QName, Schema, Term, TypeDefinition};
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:simpleType name="sku">
+///         <xs:restriction base="xs:string"/>
+///     </xs:simpleType>
+///     <xs:element name="item" type="sku"/>
+///     <xs:element name="part" substitutionGroup="item"/>
+///     <xs:complexType name="order">
+///         <xs:sequence><xs:element ref="item"/></xs:sequence>
+///     </xs:complexType>
+/// </xs:schema>"#;
+/// match Schema::from_str(input) {
+///     Ok(schema) => {
+///         let graph = schema.graph();
+///         let part = graph.element_id(&QName::new(None, "part")).unwrap();
+///         let item = graph.substitution_group(part)[0];
+///         assert_eq!(graph.element(item).name().local(), "item");
+///         let sku = graph.element_type(item).unwrap();
+///         assert!(matches!(graph.type_definition(sku), TypeDefinition::Simple(_)));
+///         let string = graph.base_type(sku).unwrap();
+///         assert!(matches!(graph.type_definition(string), TypeDefinition::Builtin(_)));
+///         let order = graph.type_id(&QName::new(None, "order")).unwrap();
+///         let particle = match graph.type_definition(order) {
+///             TypeDefinition::Complex(order) => order.particle().unwrap(),
+///             _ => unreachable!(),
+///         };
+///         let reference = match particle.term() {
+///             Term::ModelGroup(sequence) => match sequence.particles()[0].term() {
+///                 Term::Element(reference) => reference.reference_id(),
+///                 _ => None,
+///             },
+///             _ => None,
+///         };
+///         assert_eq!(reference, Some(item));
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Copy)]
+pub struct SchemaGraph<'s> {
+    schema: &'s XmlSchema,
+}
+
+impl<'s> SchemaGraph<'s> {
+    pub(crate) fn new(schema: &'s XmlSchema) -> Self {
+        Self { schema }
+    }
+
+    /// Returns the handle of the global element declaration called
+    /// `name`, if any.
+    pub fn element_id(&self, name: &QName) -> Option<ElementId> {
+        self.schema.symbols.elements.get(name).copied()
+    }
+
+    /// Returns the handle of the global attribute declaration called
+    /// `name`, if any.
+    pub fn attribute_id(&self, name: &QName) -> Option<AttributeId> {
+        self.schema.symbols.attributes.get(name).copied()
+    }
+
+    /// Returns the handle of the type definition called `name`, a global
+    /// type of the schema or a built-in type, if any.
+    pub fn type_id(&self, name: &QName) -> Option<TypeId> {
+        self.schema.symbols.type_id(name)
+    }
+
+    /// Returns the handle of the model group definition called `name`,
+    /// if any.
+    pub fn group_id(&self, name: &QName) -> Option<GroupId> {
+        self.schema.symbols.groups.get(name).copied()
+    }
+
+    /// Returns the handle of the attribute group definition called
+    /// `name`, if any.
+    pub fn attribute_group_id(&self, name: &QName) -> Option<AttributeGroupId> {
+        self.schema.symbols.attribute_groups.get(name).copied()
+    }
+
+    /// Returns the handle of the notation declaration called `name`, if
+    /// any.
+    pub fn notation_id(&self, name: &QName) -> Option<NotationId> {
+        self.schema.symbols.notations.get(name).copied()
+    }
+
+    /// Returns the element declaration of the handle `id`.
+    pub fn element(&self, id: ElementId) -> &'s Element {
+        match self.node(id.0) {
+            XmlSchemaNode::Element(element) => element,
+            _ => panic!("{:?} is not a handle of the schema", id),
+        }
+    }
+
+    /// Returns the attribute declaration of the handle `id`.
+    pub fn attribute(&self, id: AttributeId) -> &'s Attribute {
+        match self.node(id.0) {
+            XmlSchemaNode::Attribute(attribute) => attribute,
+            _ => panic!("{:?} is not a handle of the schema", id),
+        }
+    }
+
+    /// Returns the type definition of the handle `id`.
+    pub fn type_definition(&self, id: TypeId) -> TypeDefinition<'s> {
+        match (id.0, self.node_of(id.0)) {
+            (TypeHandle::AnyType, _) => TypeDefinition::AnyType,
+            (TypeHandle::Builtin(datatype), _) => TypeDefinition::Builtin(datatype),
+            (TypeHandle::Simple(_), Some(XmlSchemaNode::SimpleType(simple_type))) => {
+                TypeDefinition::Simple(simple_type)
+            }
+            (TypeHandle::Complex(_), Some(XmlSchemaNode::ComplexType(complex_type))) => {
+                TypeDefinition::Complex(complex_type)
+            }
+            _ => panic!("{:?} is not a handle of the schema", id),
+        }
+    }
+
+    /// Returns the model group definition of the handle `id`.
+    pub fn group(&self, id: GroupId) -> &'s Group {
+        match self.node(id.0) {
+            XmlSchemaNode::Group(group) => group,
+            _ => panic!("{:?} is not a handle of the schema", id),
+        }
+    }
+
+    /// Returns the attribute group definition of the handle `id`.
+    pub fn attribute_group(&self, id: AttributeGroupId) -> &'s AttributeGroup {
+        match self.node(id.0) {
+            XmlSchemaNode::AttributeGroup(attribute_group) => attribute_group,
+            _ => panic!("{:?} is not a handle of the schema", id),
+        }
+    }
+
+    /// Returns the notation declaration of the handle `id`.
+    pub fn notation(&self, id: NotationId) -> &'s Notation {
+        match self.node(id.0) {
+            XmlSchemaNode::Notation(notation) => notation,
+            _ => panic!("{:?} is not a handle of the schema", id),
+        }
+    }
+
+    /// Returns the handle of the named type of the global element `id`,
+    /// or `None` if its type is anonymous.
+    pub fn element_type(&self, id: ElementId) -> Option<TypeId> {
+        self.element(id).type_id
+    }
+
+    /// Returns the handle of the named type of the global attribute `id`,
+    /// or `None` if its type is anonymous.
+    pub fn attribute_type(&self, id: AttributeId) -> Option<TypeId> {
+        self.attribute(id).type_id
+    }
+
+    /// Returns the handle of the type the type `id` is derived from, or
+    /// `None` for `xs:anyType`.
+    ///
+    /// The base of a list or union type, and of a type restricting an
+    /// anonymous simple type, is `xs:anySimpleType`.
+    pub fn base_type(&self, id: TypeId) -> Option<TypeId> {
+        let builtin = |datatype| TypeId(TypeHandle::Builtin(datatype));
+        match self.type_definition(id) {
+            TypeDefinition::AnyType => None,
+            TypeDefinition::Builtin(datatype) => match datatype.base() {
+                Some(base) => Some(builtin(base)),
+                None => Some(TypeId(TypeHandle::AnyType)),
+            },
+            TypeDefinition::Simple(simple_type) => Some(match simple_type.base_id {
+                Some(base_id) => base_id,
+                None if simple_type.anonymous_base.is_none()
+                    && matches!(simple_type.variety, Variety::Atomic) =>
+                {
+                    builtin(simple_type.datatype)
+                }
+                None => builtin(SimpleDatatype::AnySimpleType),
+            }),
+            TypeDefinition::Complex(complex_type) => {
+                Some(complex_type.base_id.unwrap_or(TypeId(TypeHandle::AnyType)))
+            }
+        }
+    }
+
+    /// Returns the handles of the heads of the substitution groups of the
+    /// global element `id`.
+    pub fn substitution_group(&self, id: ElementId) -> &'s [ElementId] {
+        &self.element(id).head_ids
+    }
+
+    /// Returns the type definition of a declaration of type `datatype`,
+    /// whose handle is `id` if it is a named type.
+    pub(crate) fn declared_type(
+        &self,
+        datatype: &'s Datatype,
+        id: Option<TypeId>,
+    ) -> Option<TypeDefinition<'s>> {
+        match datatype {
+            Datatype::SimpleType(_) | Datatype::ComplexType(_) => {
+                id.map(|id| self.type_definition(id))
+            }
+            Datatype::AnonymousSimpleType(simple_type) => Some(TypeDefinition::Simple(simple_type)),
+            Datatype::AnonymousComplexType(complex_type) => {
+                Some(TypeDefinition::Complex(complex_type))
+            }
+        }
+    }
+
+    /// Returns the type definition `definition` is derived from, and how,
+    /// or `None` for `xs:anyType`.
+    ///
+    /// Unlike [`SchemaGraph::base_type`], an anonymous base type is
+    /// returned as it is.
+    pub(crate) fn base_definition(
+        &self,
+        definition: TypeDefinition<'s>,
+    ) -> Option<(TypeDefinition<'s>, Derivation)> {
+        match definition {
+            TypeDefinition::AnyType => None,
+            TypeDefinition::Simple(simple_type) => {
+                let base = match (simple_type.base_id, &simple_type.anonymous_base) {
+                    (Some(base_id), _) => self.type_definition(base_id),
+                    (None, Some(anonymous)) => TypeDefinition::Simple(anonymous),
+                    (None, None) => match simple_type.variety {
+                        Variety::Atomic => TypeDefinition::Builtin(simple_type.datatype),
+                        _ => TypeDefinition::Builtin(SimpleDatatype::AnySimpleType),
+                    },
+                };
+                Some((base, Derivation::Restriction))
+            }
+            TypeDefinition::Builtin(datatype) => {
+                let base = match datatype.base() {
+                    Some(base) => TypeDefinition::Builtin(base),
+                    None => TypeDefinition::AnyType,
+                };
+                Some((base, Derivation::Restriction))
+            }
+            TypeDefinition::Complex(complex_type) => {
+                let base = match complex_type.base_id {
+                    Some(base_id) => self.type_definition(base_id),
+                    None => TypeDefinition::AnyType,
+                };
+                Some((
+                    base,
+                    complex_type.derivation.unwrap_or(Derivation::Restriction),
+                ))
+            }
+        }
+    }
+
+    /// Returns the global simple or complex type of the handle `handle`,
+    /// if it is not a built-in type.
+    fn node_of(&self, handle: TypeHandle) -> Option<&'s XmlSchemaNode> {
+        match handle {
+            TypeHandle::Simple(index) | TypeHandle::Complex(index) => Some(self.node(index)),
+            TypeHandle::AnyType | TypeHandle::Builtin(_) => None,
+        }
+    }
+
+    /// Returns the component at `index`, among the components of the
+    /// schema followed by the original definitions of the redefined
+    /// ones.
+    fn node(&self, index: usize) -> &'s XmlSchemaNode {
+        let nodes = &self.schema.nodes;
+        match nodes.get(index) {
+            Some(node) => node,
+            None => &self.schema.redefined[index - nodes.len()],
+        }
+    }
+}
@@ -0,0 +1,493 @@
+//! Assembling a schema from the schema documents it is made of.
+//!
+//! A schema document can include, import, redefine and override other
+//! schema documents. The document a schema is loaded from is parsed
+//! first, then every document it references, recursively, and the
+//! components of all the documents are gathered into a single set, keyed
+//! by their expanded names, before the references between components are
+//! resolved. A document with no target namespace takes on that of the
+//! document including it (a chameleon include). Each document is read
+//! once for each target namespace it is read in, so circular references
+//! between documents are followed only once.
+//!
+
+use crate::{
+    automaton::{check_content_models, compile_content_models},
+    components::{ModelGroup, Term},
+    document::XmlElement,
+    error::{Location, SchemaError},
+    graph::SymbolTable,
+    parser::{
+        check_identity_constraints, resolve_datatypes, Definition, ReferenceKind, XmlSchema,
+        XmlSchemaNode, XmlSchemaParser,
+    },
+    qname::QName,
+    resolver::SchemaResolver,
+    schema::XsdVersion,
+    validator::check_value_constraints,
+};
+
+/// A schema document read while loading a schema.
+#[derive(Debug)]
+pub(crate) struct SchemaDocument {
+    /// The location the document is read from, if known
+    pub(crate) location: Option<String>,
+    /// The text of the document
+    pub(crate) input: String,
+}
+
+/// Loads a schema from a schema document and the documents it
+/// references.
+pub(crate) struct SchemaLoader<'r> {
+    /// The source of the referenced documents
+    resolver: &'r dyn SchemaResolver,
+    /// The schema documents read, in the order they are read
+    documents: Vec<SchemaDocument>,
+    /// The location and target namespace of each document read
+    loaded: Vec<(String, Option<String>)>,
+    /// The components of the imported documents
+    imported: Vec<XmlSchemaNode>,
+    /// The original definitions of the redefined components, under
+    /// hidden names
+    redefined: Vec<XmlSchemaNode>,
+    /// The target namespaces of the documents read
+    namespaces: Vec<Option<String>>,
+    /// The version of XML Schema the documents are read as
+    version: XsdVersion,
+}
+
+impl<'r> SchemaLoader<'r> {
+    /// Creates a loader reading the referenced documents with
+    /// `resolver`, as documents of the XML Schema version `version`.
+    pub(crate) fn new(resolver: &'r dyn SchemaResolver, version: XsdVersion) -> Self {
+        Self {
+            resolver,
+            documents: Vec::new(),
+            loaded: Vec::new(),
+            imported: Vec::new(),
+            redefined: Vec::new(),
+            namespaces: Vec::new(),
+            version,
+        }
+    }
+
+    /// Loads the schema whose document is `input`.
+    ///
+    /// This function parses the document and every document it
+    /// references, checks that no component is defined twice, then
+    /// resolves the references between the components. It returns the
+    /// assembled schema wrapped in an `Ok` variant of the `Result` type,
+    /// or the first error found wrapped in an `Err` variant of the
+    /// `Result` type.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text of the schema document.
+    /// * `location` - The location of the document, against which the
+    ///   `schemaLocation` of its references are resolved, if known.
+    ///
+    pub(crate) fn load(
+        mut self,
+        input: &str,
+        location: Option<&str>,
+    ) -> Result<XmlSchema, SchemaError> {
+        self.documents.push(SchemaDocument {
+            location: location.map(str::to_string),
+            input: input.to_string(),
+        });
+        let (mut schema, mut nodes) = self.document(0, None)?;
+        nodes.append(&mut self.imported);
+        schema.nodes = nodes;
+        schema.redefined = self.redefined;
+        schema.namespaces = self.namespaces;
+        schema.symbols = SymbolTable::build(&mut schema, &self.documents)?;
+        resolve_datatypes(&mut schema, &self.documents)?;
+        check_identity_constraints(&schema, &self.documents)?;
+        compile_content_models(&mut schema.nodes);
+        check_content_models(&schema, &self.documents)?;
+        check_value_constraints(&schema, &self.documents)?;
+        Ok(schema)
+    }
+
+    /// Reads the schema document `index` and the documents it
+    /// references.
+    ///
+    /// This function returns the document, without its components, and
+    /// the components of the document and of the documents it includes,
+    /// redefines and overrides, with the redefinitions and overrides
+    /// applied. The components of imported documents are added to
+    /// `self.imported` instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the document in `self.documents`.
+    /// * `including_namespace` - The target namespace of the document
+    ///   including it, if any.
+    ///
+    fn document(
+        &mut self,
+        index: usize,
+        including_namespace: Option<String>,
+    ) -> Result<(XmlSchema, Vec<XmlSchemaNode>), SchemaError> {
+        let parsed = XmlSchemaParser::new(
+            &self.documents[index].input,
+            index,
+            including_namespace,
+            self.version,
+        )
+        .parse();
+        let (mut schema, references) = parsed.map_err(|error| self.in_document(index, error))?;
+        let namespace = schema.target_namespace.clone();
+        if let Some(location) = &self.documents[index].location {
+            self.loaded.push((location.clone(), namespace.clone()));
+        }
+        if !self.namespaces.contains(&namespace) {
+            self.namespaces.push(namespace.clone());
+        }
+        let mut components = std::mem::take(&mut schema.nodes);
+        for reference in references {
+            let expected = match &reference.kind {
+                ReferenceKind::Import(imported) if *imported == namespace => {
+                    let (code, message) = match imported {
+                        Some(imported) => (
+                            "src-import.1.1",
+                            format!(
+                                "The imported namespace '{}' is the target namespace of the \
+                                 importing schema document",
+                                imported
+                            ),
+                        ),
+                        None => (
+                            "src-import.1.2",
+                            "A schema document with no target namespace cannot import \
+                             no namespace"
+                                .to_string(),
+                        ),
+                    };
+                    return Err(self.reference_error(index, &reference.node, code, &message));
+                }
+                ReferenceKind::Import(imported) => imported.clone(),
+                _ => namespace.clone(),
+            };
+            let base_uri = self.documents[index].location.as_deref();
+            let namespace_hint = match &reference.kind {
+                ReferenceKind::Import(imported) => imported.as_deref(),
+                _ => None,
+            };
+            let source =
+                match self
+                    .resolver
+                    .resolve(base_uri, reference.location.as_deref(), namespace_hint)
+                {
+                    Ok(source) => source,
+                    // The location of an imported document is only a hint: the
+                    // components of the namespace may be known otherwise
+                    Err(_) if matches!(reference.kind, ReferenceKind::Import(_)) => continue,
+                    Err(err) => {
+                        return Err(self.reference_error(
+                            index,
+                            &reference.node,
+                            "schema_reference.4",
+                            &format!(
+                                "Cannot read schema document '{}': {}",
+                                reference.location.as_deref().unwrap_or_default(),
+                                err
+                            ),
+                        ))
+                    }
+                };
+            if self
+                .loaded
+                .contains(&(source.uri().to_string(), expected.clone()))
+            {
+                continue;
+            }
+            let input = String::from_utf8(source.content().to_vec()).map_err(|_| {
+                self.reference_error(
+                    index,
+                    &reference.node,
+                    "schema_reference.4",
+                    &format!("Schema document '{}' is not UTF-8 text", source.uri()),
+                )
+            })?;
+            self.documents.push(SchemaDocument {
+                location: Some(source.uri().to_string()),
+                input,
+            });
+            let referenced = self.documents.len() - 1;
+            let including_namespace = match reference.kind {
+                ReferenceKind::Import(_) => None,
+                _ => namespace.clone(),
+            };
+            let (document, mut nodes) = self.document(referenced, including_namespace)?;
+            if document.target_namespace != expected {
+                let (code, message) = match (&reference.kind, &expected) {
+                    (ReferenceKind::Import(_), Some(expected)) => (
+                        "src-import.3.1",
+                        format!(
+                            "The imported schema document must have the target namespace \
+                             '{}'",
+                            expected
+                        ),
+                    ),
+                    (ReferenceKind::Import(_), None) => (
+                        "src-import.3.2",
+                        "The imported schema document must have no target namespace".to_string(),
+                    ),
+                    (ReferenceKind::Redefine, _) => (
+                        "src-redefine.3.1",
+                        "The redefined schema document must have the same target namespace \
+                         as the redefining one, or none"
+                            .to_string(),
+                    ),
+                    _ => (
+                        "src-include.2.1",
+                        "The included schema document must have the same target namespace \
+                         as the including one, or none"
+                            .to_string(),
+                    ),
+                };
+                return Err(self.reference_error(index, &reference.node, code, &message));
+            }
+            match reference.kind {
+                ReferenceKind::Import(_) => {
+                    self.imported.append(&mut nodes);
+                    continue;
+                }
+                ReferenceKind::Redefine => self.redefine(&mut nodes, reference.components)?,
+                ReferenceKind::Override => override_components(&mut nodes, reference.components),
+                ReferenceKind::Include => {}
+            }
+            components.append(&mut nodes);
+        }
+        Ok((schema, components))
+    }
+
+    /// Replaces the components of a redefined schema document by their
+    /// redefinitions.
+    ///
+    /// A redefined type must be derived from its original definition,
+    /// and a redefined model group or attribute group may reference its
+    /// original definition once. The original definitions they refer to
+    /// are kept under hidden names.
+    ///
+    /// # Arguments
+    ///
+    /// * `components` - The components of the redefined document.
+    /// * `redefinitions` - The children of the `xs:redefine` element.
+    ///
+    fn redefine(
+        &mut self,
+        components: &mut [XmlSchemaNode],
+        redefinitions: Vec<XmlSchemaNode>,
+    ) -> Result<(), SchemaError> {
+        for mut redefinition in redefinitions {
+            let (kind, name) = redefinition.symbol();
+            let name = name.clone();
+            let position = components
+                .iter()
+                .position(|component| component.symbol() == (kind, &name))
+                .ok_or_else(|| {
+                    definition_error(
+                        &self.documents,
+                        redefinition.definition(),
+                        "src-redefine.2",
+                        &format!(
+                            "The {} '{}' is not defined in the redefined schema document",
+                            kind, name
+                        ),
+                    )
+                })?;
+            let hidden = QName::new(
+                name.namespace(),
+                &format!("{}#redefined{}", name.local(), self.redefined.len() + 1),
+            );
+            let keep = match (&mut redefinition, &mut components[position]) {
+                (XmlSchemaNode::SimpleType(simple_type), XmlSchemaNode::SimpleType(original))
+                    if simple_type.base_type.as_ref() == Some(&name) =>
+                {
+                    simple_type.base_type = Some(hidden.clone());
+                    original.name = hidden;
+                    true
+                }
+                (
+                    XmlSchemaNode::ComplexType(complex_type),
+                    XmlSchemaNode::ComplexType(original),
+                ) if complex_type.base_type.as_ref() == Some(&name) => {
+                    complex_type.base_type = Some(hidden.clone());
+                    original.name = hidden;
+                    true
+                }
+                (XmlSchemaNode::Group(group), XmlSchemaNode::Group(original)) => {
+                    match count_group_references(&group.model_group, &name) {
+                        0 => false,
+                        1 => {
+                            rename_group_references(&mut group.model_group, &name, &hidden);
+                            original.name = hidden;
+                            true
+                        }
+                        _ => {
+                            return Err(definition_error(
+                                &self.documents,
+                                &*group,
+                                "src-redefine.6.1.1",
+                                &format!(
+                                    "The redefinition of model group '{}' can reference it \
+                                     only once",
+                                    name
+                                ),
+                            ))
+                        }
+                    }
+                }
+                (
+                    XmlSchemaNode::AttributeGroup(attribute_group),
+                    XmlSchemaNode::AttributeGroup(original),
+                ) => {
+                    let references = attribute_group
+                        .attribute_groups
+                        .iter()
+                        .filter(|reference| **reference == name)
+                        .count();
+                    match references {
+                        0 => false,
+                        1 => {
+                            for reference in &mut attribute_group.attribute_groups {
+                                if *reference == name {
+                                    *reference = hidden.clone();
+                                }
+                            }
+                            original.name = hidden;
+                            true
+                        }
+                        _ => {
+                            return Err(definition_error(
+                                &self.documents,
+                                &*attribute_group,
+                                "src-redefine.7.1",
+                                &format!(
+                                    "The redefinition of attribute group '{}' can reference \
+                                     it only once",
+                                    name
+                                ),
+                            ))
+                        }
+                    }
+                }
+                (redefinition, _) => {
+                    return Err(definition_error(
+                        &self.documents,
+                        redefinition.definition(),
+                        "src-redefine.5",
+                        &format!(
+                            "The redefinition of type '{}' must restrict or extend its \
+                             original definition",
+                            name
+                        ),
+                    ))
+                }
+            };
+            let original = std::mem::replace(&mut components[position], redefinition);
+            if keep {
+                self.redefined.push(original);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `error`, found in the schema document `index`, marked
+    /// with the location of the document.
+    fn in_document(&self, index: usize, error: SchemaError) -> SchemaError {
+        in_document(&self.documents, index, error)
+    }
+
+    /// Returns the error for a violation of the constraint `code` by the
+    /// reference `node` to another document, in the schema document
+    /// `index`.
+    fn reference_error(
+        &self,
+        index: usize,
+        node: &XmlElement,
+        code: &str,
+        message: &str,
+    ) -> SchemaError {
+        let location = Location::new(&self.documents[index].input, node.position);
+        let error = SchemaError::constraint(location, node, code, message);
+        self.in_document(index, error)
+    }
+}
+
+/// Returns the error for a violation of the constraint `code` by the
+/// definition `at`.
+///
+/// # Arguments
+///
+/// * `documents` - The schema documents the schema is assembled from.
+/// * `at` - The definition or declaration breaking the constraint.
+/// * `code` - The code of the constraint.
+/// * `message` - A description of the failure.
+///
+pub(crate) fn definition_error(
+    documents: &[SchemaDocument],
+    at: &dyn Definition,
+    code: &str,
+    message: &str,
+) -> SchemaError {
+    let error = SchemaError::Constraint {
+        code: code.to_string(),
+        message: message.to_string(),
+        location: Location::new(&documents[at.document()].input, at.position()),
+        path: at.path().to_string(),
+    };
+    in_document(documents, at.document(), error)
+}
+
+/// Returns `error`, found in the schema document `index`, marked with
+/// the location of the document unless it is the one the schema is
+/// loaded from.
+fn in_document(documents: &[SchemaDocument], index: usize, error: SchemaError) -> SchemaError {
+    match &documents[index].location {
+        Some(location) if index > 0 => error.in_document(location),
+        _ => error,
+    }
+}
+
+/// Replaces the components of an overridden schema document by the
+/// components of `overrides` with the same name. The components of
+/// `overrides` that override none are ignored.
+fn override_components(components: &mut [XmlSchemaNode], overrides: Vec<XmlSchemaNode>) {
+    for component in overrides {
+        if let Some(position) = components
+            .iter()
+            .position(|original| original.symbol() == component.symbol())
+        {
+            components[position] = component;
+        }
+    }
+}
+
+/// Returns the number of references to the model group `name` in
+/// `model_group`.
+fn count_group_references(model_group: &ModelGroup, name: &QName) -> usize {
+    model_group
+        .particles
+        .iter()
+        .map(|particle| match &particle.term {
+            Term::GroupReference(reference) if reference == name => 1,
+            Term::ModelGroup(nested) => count_group_references(nested, name),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Renames the references to the model group `name` in `model_group`
+/// to `hidden`.
+fn rename_group_references(model_group: &mut ModelGroup, name: &QName, hidden: &QName) {
+    for particle in &mut model_group.particles {
+        match &mut particle.term {
+            Term::GroupReference(reference) if reference == name => *reference = hidden.clone(),
+            Term::ModelGroup(nested) => rename_group_references(nested, name, hidden),
+            _ => {}
+        }
+    }
+}
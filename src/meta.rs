@@ -0,0 +1,1167 @@
+//! The schema for schemas.
+//!
+//! Every schema document is checked against the bundled `XMLSchema.xsd`
+//! before its components are built. The declarations of the schema for
+//! schemas are compiled once into a table of rules stating, for each
+//! kind of schema element, the content model its children must match,
+//! and which attributes and attribute values it accepts. Violations are
+//! reported with the `s4s` codes used by other processors.
+//!
+//! The bundled document is the schema for schemas of XSD 1.0. The rules
+//! for XSD 1.1 documents add to it the elements and attributes of 1.1
+//! that the crate supports: assertions, `xs:override`, the `notNamespace`
+//! and `notQName` attributes of wildcards, the lists of heads of
+//! substitution groups, and the wildcards, group references and repeated
+//! elements of `xs:all` groups.
+//!
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    document::{XmlElement, XmlNode},
+    error::{LineIndex, SchemaError},
+    parser::xsd_name,
+    qname::XSD_NAMESPACE,
+    schema::XsdVersion,
+    tokenizer::{is_name_char, is_name_start_char},
+};
+
+/// The bundled schema for schemas.
+pub(crate) const XML_SCHEMA_XSD: &str = include_str!("../XMLSchema.xsd");
+
+/// The namespace of the annotations listing the facets of datatypes.
+const HFP_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-hasFacetAndProperty";
+
+/// The compiled schemas for schemas, by version.
+static META_SCHEMAS: Mutex<Vec<(XsdVersion, Arc<MetaSchema>)>> = Mutex::new(Vec::new());
+
+/// The compiled rules of the schema for schemas.
+#[derive(Debug)]
+pub(crate) struct MetaSchema {
+    rules: Vec<Rule>,
+    elements: HashMap<String, usize>,
+    /// The facets applicable to the primitive and built-in list
+    /// datatypes, by datatype name, as listed by their `hfp:hasFacet`
+    /// annotations
+    facets: HashMap<String, Vec<String>>,
+}
+
+/// What a schema element accepts, by the type it is declared with.
+#[derive(Debug, Clone, Default)]
+struct Rule {
+    /// The content model of the children
+    content: Particle,
+    /// The automaton `content` is compiled into
+    automaton: Automaton,
+    attributes: HashMap<String, AttributeRule>,
+    any_attribute: bool,
+    text: bool,
+    /// The rule whose content model is extended
+    extends: Option<usize>,
+}
+
+/// A particle of a content model of the schema for schemas: a model
+/// occurring between `min` and `max` times, any number of times if `max`
+/// is `None`.
+#[derive(Debug, Clone)]
+struct Particle {
+    model: Model,
+    min: u32,
+    max: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+enum Model {
+    Leaf(Leaf),
+    Sequence(Vec<Particle>),
+    Choice(Vec<Particle>),
+}
+
+/// What a child element of a schema element is matched by.
+#[derive(Debug, Clone)]
+enum Leaf {
+    /// A schema element, by local name, with the rule of its type
+    Element(String, usize),
+    /// An `xs:any` wildcard
+    Any(Wildcard),
+}
+
+/// The namespaces accepted by an `xs:any` wildcard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Wildcard {
+    Any,
+    Other,
+}
+
+/// The position automaton of a content model, in the Glushkov
+/// construction.
+#[derive(Debug, Clone, Default)]
+struct Automaton {
+    /// The leaf of each position
+    positions: Vec<Leaf>,
+    /// The positions that can start the content
+    first: Vec<usize>,
+    /// The positions that can follow each position
+    follow: Vec<Vec<usize>>,
+    /// Whether each position can end the content
+    last: Vec<bool>,
+    /// Whether the content can be empty
+    nullable: bool,
+}
+
+/// The positions of a part of a content model.
+struct Fragment {
+    first: Vec<usize>,
+    last: Vec<usize>,
+    nullable: bool,
+}
+
+#[derive(Debug, Clone)]
+struct AttributeRule {
+    required: bool,
+    value: ValueRule,
+}
+
+/// The values accepted by an attribute of a schema element.
+#[derive(Debug, Clone)]
+enum ValueRule {
+    Any,
+    Boolean,
+    NonNegativeInteger,
+    NcName,
+    QName,
+    Enumeration(Vec<String>),
+    List(Box<ValueRule>),
+    Union(Vec<ValueRule>),
+}
+
+impl MetaSchema {
+    /// Returns the compiled schema for schemas of the XML Schema version
+    /// `version`, compiling it on first use.
+    pub(crate) fn get(version: XsdVersion) -> Result<Arc<MetaSchema>, SchemaError> {
+        let mut meta_schemas = META_SCHEMAS.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some((_, meta_schema)) = meta_schemas.iter().find(|(found, _)| *found == version) {
+            return Ok(Arc::clone(meta_schema));
+        }
+        let root = XmlElement::parse(XML_SCHEMA_XSD)?;
+        let mut meta_schema = MetaSchemaBuilder::new(&root).build();
+        if version == XsdVersion::V1_1 {
+            meta_schema.add_version_1_1();
+        }
+        for rule in &mut meta_schema.rules {
+            rule.automaton = Automaton::compile(&rule.content);
+        }
+        let compiled = Arc::new(meta_schema);
+        meta_schemas.push((version, Arc::clone(&compiled)));
+        Ok(compiled)
+    }
+
+    /// Adds the elements and attributes of XSD 1.1 to the rules compiled
+    /// from the schema for schemas of XSD 1.0.
+    fn add_version_1_1(&mut self) {
+        let annotation = self.elements["annotation"];
+        let attribute = |required, value| AttributeRule { required, value };
+        self.rules.push(Rule {
+            content: Particle::element("annotation", annotation).optional(),
+            attributes: HashMap::from([
+                ("test".to_string(), attribute(true, ValueRule::Any)),
+                ("id".to_string(), attribute(false, ValueRule::NcName)),
+                (
+                    "xpathDefaultNamespace".to_string(),
+                    attribute(false, ValueRule::Any),
+                ),
+            ]),
+            any_attribute: true,
+            ..Rule::default()
+        });
+        let assertion = self.rules.len() - 1;
+        // The `xs:assert` children of complex types and of the derivations
+        // of their content follow their attribute declarations, and the
+        // `xs:assertion` facets are one of the facets of restrictions.
+        let mut complex_types: Vec<usize> = Vec::new();
+        let mut wildcards: Vec<usize> = Vec::new();
+        for rule in &self.rules {
+            complex_types.extend(rule.content.rule("complexType"));
+            for content in ["complexContent", "simpleContent"] {
+                if let Some(content) = rule.content.rule(content) {
+                    for derivation in ["extension", "restriction"] {
+                        complex_types.extend(self.rules[content].content.rule(derivation));
+                    }
+                }
+            }
+            for wildcard in ["any", "anyAttribute"] {
+                wildcards.extend(rule.content.rule(wildcard));
+            }
+        }
+        complex_types.sort_unstable();
+        complex_types.dedup();
+        for id in complex_types {
+            let assert = Particle::element("assert", assertion).repeated();
+            self.rules[id].content.insert_after("anyAttribute", &assert);
+        }
+        for rule in &mut self.rules {
+            let facet = Particle::element("assertion", assertion);
+            rule.content.add_alternative("enumeration", &facet);
+        }
+        for id in wildcards {
+            let attributes = &mut self.rules[id].attributes;
+            for name in ["notNamespace", "notQName"] {
+                attributes.insert(name.to_string(), attribute(false, ValueRule::Any));
+            }
+        }
+        for rule in &mut self.rules {
+            if let Some(heads) = rule.attributes.get_mut("substitutionGroup") {
+                heads.value = ValueRule::List(Box::new(ValueRule::QName));
+            }
+        }
+        // The particles of an `xs:all` group can occur more than once, and
+        // include wildcards and references to groups, which must occur
+        // exactly once.
+        let mut groups: Vec<(usize, usize)> = self
+            .rules
+            .iter()
+            .filter_map(|rule| Some((rule.content.rule("all")?, rule.content.rule("sequence")?)))
+            .collect();
+        groups.sort_unstable();
+        groups.dedup_by_key(|(all, _)| *all);
+        for (all, sequence) in groups {
+            let particles = self.rules[sequence].content.clone();
+            let mut choice: Vec<Particle> = ["element", "any"]
+                .into_iter()
+                .filter_map(|name| Some(Particle::element(name, particles.rule(name)?)))
+                .collect();
+            if let Some(group) = particles.rule("group") {
+                let mut reference = self.rules[group].clone();
+                for occurs in ["minOccurs", "maxOccurs"] {
+                    let once = ValueRule::Enumeration(vec!["1".to_string()]);
+                    reference
+                        .attributes
+                        .insert(occurs.to_string(), attribute(false, once));
+                }
+                self.rules.push(reference);
+                choice.push(Particle::element("group", self.rules.len() - 1));
+            }
+            self.rules[all].content = Particle::sequence(vec![
+                Particle::element("annotation", annotation).optional(),
+                Particle::choice(choice).repeated(),
+            ]);
+        }
+        // An `xs:override` holds the same attributes as an `xs:redefine`,
+        // and any global declaration or definition, in any order.
+        let schema = self.elements["schema"];
+        let mut overriding = self.rules[self.elements["redefine"]].clone();
+        let definitions = [
+            "annotation",
+            "simpleType",
+            "complexType",
+            "group",
+            "attributeGroup",
+        ]
+        .into_iter()
+        .filter_map(|name| Some(Particle::element(name, overriding.content.rule(name)?)));
+        let declarations = ["element", "attribute", "notation"]
+            .into_iter()
+            .filter_map(|name| {
+                Some(Particle::element(
+                    name,
+                    self.rules[schema].content.rule(name)?,
+                ))
+            });
+        overriding.content = Particle::choice(definitions.chain(declarations).collect()).repeated();
+        self.rules.push(overriding);
+        let overriding = Particle::element("override", self.rules.len() - 1);
+        let schema = &mut self.rules[schema];
+        schema.content.add_alternative("redefine", &overriding);
+        schema.attributes.insert(
+            "xpathDefaultNamespace".to_string(),
+            attribute(false, ValueRule::Any),
+        );
+    }
+
+    /// Returns `true` if the facet `facet` applies to the primitive or
+    /// built-in list datatype `datatype`.
+    ///
+    /// # Arguments
+    ///
+    /// * `datatype` - The local name of the datatype, such as `decimal`.
+    /// * `facet` - The local name of the facet, such as `totalDigits`.
+    ///
+    pub(crate) fn has_facet(&self, datatype: &str, facet: &str) -> bool {
+        self.facets
+            .get(datatype)
+            .map_or(false, |facets| facets.iter().any(|found| found == facet))
+    }
+
+    /// Checks the schema document `root`, read from `input`, against the
+    /// schema for schemas.
+    ///
+    /// All violations are collected and returned in document order, as a
+    /// [`SchemaError::Multiple`] if there are several.
+    pub(crate) fn validate(&self, root: &XmlElement, input: &str) -> Result<(), SchemaError> {
+        let lines = LineIndex::new(input);
+        let mut errors = Vec::new();
+        match xsd_name(root) {
+            Some("schema") => {
+                self.check_element(root, self.elements["schema"], &lines, &mut errors)
+            }
+            Some(_) => errors.push(violation(
+                "s4s-elt-invalid",
+                &format!(
+                    "Element '{}' is not a valid element in a schema document",
+                    root.name
+                ),
+                root,
+                &lines,
+            )),
+            None => errors.push(violation(
+                "s4s-elt-schema-ns",
+                &format!(
+                    "The namespace of element '{}' must be from the schema namespace",
+                    root.name
+                ),
+                root,
+                &lines,
+            )),
+        }
+        SchemaError::from_errors(errors)
+    }
+
+    fn check_element(
+        &self,
+        node: &XmlElement,
+        rule_id: usize,
+        lines: &LineIndex,
+        errors: &mut Vec<SchemaError>,
+    ) {
+        let rule = &self.rules[rule_id];
+        let element_name = xsd_name(node).unwrap_or(&node.name);
+        for (name, value) in node.attributes.iter() {
+            if name == "xmlns" || name.starts_with("xmlns:") {
+                continue;
+            }
+            if name.contains(':') {
+                let foreign = node
+                    .attribute_qname(name)
+                    .map_or(false, |qname| qname.namespace() != Some(XSD_NAMESPACE));
+                if !rule.any_attribute || !foreign {
+                    errors.push(violation(
+                        "s4s-att-not-allowed",
+                        &format!(
+                            "Attribute '{}' cannot appear in element '{}'",
+                            name, element_name
+                        ),
+                        node,
+                        lines,
+                    ));
+                }
+                continue;
+            }
+            match rule.attributes.get(name) {
+                Some(attribute) if !attribute.value.accepts(value) => errors.push(violation(
+                    "s4s-att-invalid-value",
+                    &format!(
+                        "Invalid attribute value '{}' for '{}' in element '{}'",
+                        value, name, element_name
+                    ),
+                    node,
+                    lines,
+                )),
+                Some(_) => {}
+                None => errors.push(violation(
+                    "s4s-att-not-allowed",
+                    &format!(
+                        "Attribute '{}' cannot appear in element '{}'",
+                        name, element_name
+                    ),
+                    node,
+                    lines,
+                )),
+            }
+        }
+        let mut required: Vec<&String> = rule
+            .attributes
+            .iter()
+            .filter(|(name, attribute)| attribute.required && node.attribute(name).is_none())
+            .map(|(name, _)| name)
+            .collect();
+        required.sort();
+        for name in required {
+            errors.push(violation(
+                "s4s-att-must-appear",
+                &format!(
+                    "Attribute '{}' must appear on element '{}'",
+                    name, element_name
+                ),
+                node,
+                lines,
+            ));
+        }
+        let mut positions = None;
+        for child in &node.children {
+            match child {
+                XmlNode::Text(text) => {
+                    if !rule.text && !text.trim().is_empty() {
+                        errors.push(violation(
+                            "s4s-elt-character",
+                            &format!(
+                                "Non-whitespace characters are not allowed in schema \
+                                 elements other than 'appinfo' and 'documentation'. Saw '{}'",
+                                text.trim()
+                            ),
+                            node,
+                            lines,
+                        ));
+                    }
+                }
+                XmlNode::Element(element) => match rule.automaton.step(&mut positions, element) {
+                    Some(Leaf::Element(_, child_rule)) => {
+                        self.check_element(element, *child_rule, lines, errors)
+                    }
+                    Some(Leaf::Any(_)) => {}
+                    None => errors.push(violation(
+                        "s4s-elt-invalid-content.1",
+                        &format!(
+                            "The content of '{}' is invalid. Element '{}' is invalid, \
+                                 misplaced, or occurs too often",
+                            element_name, element.name
+                        ),
+                        element,
+                        lines,
+                    )),
+                },
+            }
+        }
+        if !rule.automaton.is_complete(&positions) {
+            errors.push(violation(
+                "s4s-elt-must-match.1",
+                &format!(
+                    "The content of '{}' must match {}",
+                    element_name, rule.content
+                ),
+                node,
+                lines,
+            ));
+        }
+    }
+}
+
+impl Particle {
+    fn element(name: &str, rule: usize) -> Particle {
+        Particle {
+            model: Model::Leaf(Leaf::Element(name.to_string(), rule)),
+            min: 1,
+            max: Some(1),
+        }
+    }
+
+    fn sequence(particles: Vec<Particle>) -> Particle {
+        Particle {
+            model: Model::Sequence(particles),
+            min: 1,
+            max: Some(1),
+        }
+    }
+
+    fn choice(particles: Vec<Particle>) -> Particle {
+        Particle {
+            model: Model::Choice(particles),
+            min: 1,
+            max: Some(1),
+        }
+    }
+
+    fn optional(self) -> Particle {
+        Particle { min: 0, ..self }
+    }
+
+    fn repeated(self) -> Particle {
+        Particle {
+            min: 0,
+            max: None,
+            ..self
+        }
+    }
+
+    /// Returns the sequence of the particle and `next`, merging the lists
+    /// of the sequences occurring once.
+    fn followed_by(self, next: Particle) -> Particle {
+        let parts = |particle: Particle| match particle {
+            Particle {
+                model: Model::Sequence(particles),
+                min: 1,
+                max: Some(1),
+            } => particles,
+            particle => vec![particle],
+        };
+        let mut particles = parts(self);
+        particles.extend(parts(next));
+        Particle::sequence(particles)
+    }
+
+    /// Returns the rule of the first schema element called `name` in the
+    /// particle.
+    fn rule(&self, name: &str) -> Option<usize> {
+        match &self.model {
+            Model::Leaf(Leaf::Element(found, rule)) if found == name => Some(*rule),
+            Model::Leaf(_) => None,
+            Model::Sequence(particles) | Model::Choice(particles) => {
+                particles.iter().find_map(|particle| particle.rule(name))
+            }
+        }
+    }
+
+    /// Inserts `particle` after the schema elements called `name` in the
+    /// sequences of the particle.
+    fn insert_after(&mut self, name: &str, particle: &Particle) {
+        match &mut self.model {
+            Model::Leaf(_) => {}
+            Model::Sequence(particles) => {
+                match particles.iter().position(|found| found.is_element(name)) {
+                    Some(index) => particles.insert(index + 1, particle.clone()),
+                    None => particles
+                        .iter_mut()
+                        .for_each(|found| found.insert_after(name, particle)),
+                }
+            }
+            Model::Choice(particles) => particles
+                .iter_mut()
+                .for_each(|found| found.insert_after(name, particle)),
+        }
+    }
+
+    /// Adds `particle` to the choices of the particle that include the
+    /// schema element called `name`.
+    fn add_alternative(&mut self, name: &str, particle: &Particle) {
+        match &mut self.model {
+            Model::Leaf(_) => {}
+            Model::Choice(particles) if particles.iter().any(|found| found.is_element(name)) => {
+                particles.push(particle.clone())
+            }
+            Model::Sequence(particles) | Model::Choice(particles) => particles
+                .iter_mut()
+                .for_each(|found| found.add_alternative(name, particle)),
+        }
+    }
+
+    fn is_element(&self, name: &str) -> bool {
+        matches!(&self.model, Model::Leaf(Leaf::Element(found, _)) if found == name)
+    }
+}
+
+impl Default for Particle {
+    /// Returns the particle of an empty content.
+    fn default() -> Particle {
+        Particle::sequence(Vec::new())
+    }
+}
+
+impl Display for Particle {
+    /// Writes the particle in the notation of the messages of other
+    /// processors, such as `(annotation?, (selector, field+))`.
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let (separator, particles) = match &self.model {
+            Model::Leaf(Leaf::Element(name, _)) => {
+                write!(f, "{}", name)?;
+                (", ", &[][..])
+            }
+            Model::Leaf(Leaf::Any(Wildcard::Any)) => {
+                write!(f, "WC[##any]")?;
+                (", ", &[][..])
+            }
+            Model::Leaf(Leaf::Any(Wildcard::Other)) => {
+                write!(f, "WC[##other]")?;
+                (", ", &[][..])
+            }
+            Model::Sequence(particles) => (", ", &particles[..]),
+            Model::Choice(particles) => (" | ", &particles[..]),
+        };
+        if let Model::Sequence(_) | Model::Choice(_) = &self.model {
+            write!(f, "(")?;
+            for (index, particle) in particles.iter().enumerate() {
+                if index > 0 {
+                    write!(f, "{}", separator)?;
+                }
+                write!(f, "{}", particle)?;
+            }
+            write!(f, ")")?;
+        }
+        match (self.min, self.max) {
+            (0, Some(1)) => write!(f, "?"),
+            (0, None) => write!(f, "*"),
+            (1, None) => write!(f, "+"),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Leaf {
+    /// Returns `true` if the leaf matches the child element `element`.
+    fn matches(&self, element: &XmlElement) -> bool {
+        match self {
+            Leaf::Element(name, _) => xsd_name(element) == Some(name),
+            Leaf::Any(Wildcard::Any) => true,
+            Leaf::Any(Wildcard::Other) => {
+                xsd_name(element).is_none() && element.qname.namespace().is_some()
+            }
+        }
+    }
+}
+
+impl Automaton {
+    fn compile(content: &Particle) -> Automaton {
+        let mut automaton = Automaton::default();
+        let fragment = automaton.particle(content);
+        automaton.last = vec![false; automaton.positions.len()];
+        for position in fragment.last {
+            automaton.last[position] = true;
+        }
+        automaton.first = fragment.first;
+        automaton.nullable = fragment.nullable;
+        automaton
+    }
+
+    fn particle(&mut self, particle: &Particle) -> Fragment {
+        // Occurrences are unrolled, as the schema for schemas only bounds
+        // them by zero or one
+        let copies = particle.max.unwrap_or(particle.min.max(1));
+        let mut parts = Vec::new();
+        for copy in 0..copies {
+            let mut part = self.model(&particle.model);
+            if particle.max.is_none() && copy + 1 == copies {
+                self.link(&part.last, &part.first);
+            }
+            part.nullable |= copy >= particle.min;
+            parts.push(part);
+        }
+        self.sequence(parts)
+    }
+
+    fn model(&mut self, model: &Model) -> Fragment {
+        match model {
+            Model::Leaf(leaf) => {
+                let position = self.positions.len();
+                self.positions.push(leaf.clone());
+                self.follow.push(Vec::new());
+                Fragment {
+                    first: vec![position],
+                    last: vec![position],
+                    nullable: false,
+                }
+            }
+            Model::Sequence(particles) => {
+                let parts = particles
+                    .iter()
+                    .map(|particle| self.particle(particle))
+                    .collect();
+                self.sequence(parts)
+            }
+            Model::Choice(particles) => {
+                let mut result = Fragment {
+                    first: Vec::new(),
+                    last: Vec::new(),
+                    nullable: particles.is_empty(),
+                };
+                for particle in particles {
+                    let part = self.particle(particle);
+                    result.first.extend(part.first);
+                    result.last.extend(part.last);
+                    result.nullable |= part.nullable;
+                }
+                result
+            }
+        }
+    }
+
+    fn sequence(&mut self, parts: Vec<Fragment>) -> Fragment {
+        let mut result = Fragment {
+            first: Vec::new(),
+            last: Vec::new(),
+            nullable: true,
+        };
+        for part in parts {
+            self.link(&result.last, &part.first);
+            if result.nullable {
+                result.first.extend(&part.first);
+            }
+            result.last = match part.nullable {
+                true => result.last.into_iter().chain(part.last).collect(),
+                false => part.last,
+            };
+            result.nullable &= part.nullable;
+        }
+        result
+    }
+
+    fn link(&mut self, from: &[usize], to: &[usize]) {
+        for source in from {
+            for target in to {
+                if !self.follow[*source].contains(target) {
+                    self.follow[*source].push(*target);
+                }
+            }
+        }
+    }
+
+    /// Matches `element`, the next child, from `positions`, the positions
+    /// of the children matched so far or `None` before the first, and
+    /// returns the leaf matching it, or `None` if it is not allowed here.
+    /// Schema elements take precedence over wildcards.
+    fn step(&self, positions: &mut Option<Vec<usize>>, element: &XmlElement) -> Option<&Leaf> {
+        let mut next: Vec<usize> = match positions {
+            None => self.first.clone(),
+            Some(positions) => positions
+                .iter()
+                .flat_map(|position| self.follow[*position].iter().copied())
+                .collect(),
+        };
+        next.retain(|position| self.positions[*position].matches(element));
+        next.sort_unstable();
+        next.dedup();
+        let leaf = next
+            .iter()
+            .map(|position| &self.positions[*position])
+            .min_by_key(|leaf| matches!(leaf, Leaf::Any(_)))?;
+        *positions = Some(next);
+        Some(leaf)
+    }
+
+    /// Returns `true` if the children matched up to `positions` make up
+    /// a complete content.
+    fn is_complete(&self, positions: &Option<Vec<usize>>) -> bool {
+        match positions {
+            None => self.nullable,
+            Some(positions) => positions.iter().any(|position| self.last[*position]),
+        }
+    }
+}
+
+impl ValueRule {
+    fn accepts(&self, value: &str) -> bool {
+        let value = value.trim();
+        match self {
+            ValueRule::Any => true,
+            ValueRule::Boolean => matches!(value, "true" | "false" | "1" | "0"),
+            ValueRule::NonNegativeInteger => {
+                let digits = value.strip_prefix('+').unwrap_or(value);
+                !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit())
+            }
+            ValueRule::NcName => is_ncname(value),
+            ValueRule::QName => match value.split_once(':') {
+                Some((prefix, local)) => is_ncname(prefix) && is_ncname(local),
+                None => is_ncname(value),
+            },
+            ValueRule::Enumeration(values) => {
+                let collapsed = value.split_whitespace().collect::<Vec<_>>().join(" ");
+                values.contains(&collapsed)
+            }
+            ValueRule::List(item) => value.split_whitespace().all(|token| item.accepts(token)),
+            ValueRule::Union(members) => members.iter().any(|member| member.accepts(value)),
+        }
+    }
+}
+
+/// Compiles the element tree of `XMLSchema.xsd` into a [`MetaSchema`].
+struct MetaSchemaBuilder<'a> {
+    elements: HashMap<&'a str, &'a XmlElement>,
+    complex_types: HashMap<&'a str, &'a XmlElement>,
+    simple_types: HashMap<&'a str, &'a XmlElement>,
+    groups: HashMap<&'a str, &'a XmlElement>,
+    attribute_groups: HashMap<&'a str, &'a XmlElement>,
+    rules: Vec<Rule>,
+    compiled: HashMap<usize, usize>,
+    /// The complex type rules whose particles are still to be added,
+    /// with the element holding their content model
+    pending: Vec<(usize, &'a XmlElement)>,
+}
+
+impl<'a> MetaSchemaBuilder<'a> {
+    fn new(root: &'a XmlElement) -> Self {
+        let mut builder = Self {
+            elements: HashMap::new(),
+            complex_types: HashMap::new(),
+            simple_types: HashMap::new(),
+            groups: HashMap::new(),
+            attribute_groups: HashMap::new(),
+            rules: Vec::new(),
+            compiled: HashMap::new(),
+            pending: Vec::new(),
+        };
+        for child in root.child_elements() {
+            let name = match child.attribute("name") {
+                Some(name) => name,
+                None => continue,
+            };
+            let table = match xsd_name(child) {
+                Some("element") => &mut builder.elements,
+                Some("complexType") => &mut builder.complex_types,
+                Some("simpleType") => &mut builder.simple_types,
+                Some("group") => &mut builder.groups,
+                Some("attributeGroup") => &mut builder.attribute_groups,
+                _ => continue,
+            };
+            table.insert(name, child);
+        }
+        builder
+    }
+
+    fn build(mut self) -> MetaSchema {
+        let declarations: Vec<(&str, &XmlElement)> = self
+            .elements
+            .iter()
+            .map(|(name, node)| (*name, *node))
+            .collect();
+        let mut elements = HashMap::new();
+        for (name, node) in declarations {
+            let rule = self.element_rule(node);
+            elements.insert(name.to_string(), rule);
+        }
+        while let Some((id, content)) = self.pending.pop() {
+            let particle = content
+                .child_elements()
+                .find_map(|child| match xsd_name(child) {
+                    Some("group" | "sequence" | "choice" | "all") => self.particle(child),
+                    _ => None,
+                });
+            if let Some(particle) = particle {
+                self.rules[id].content = particle;
+            }
+        }
+        // An extension appends its own content model to the one of its
+        // base
+        let contents: Vec<Particle> = (0..self.rules.len())
+            .map(|id| {
+                let mut content = self.rules[id].content.clone();
+                let mut base = self.rules[id].extends;
+                while let Some(base_id) = base {
+                    let base_rule = &self.rules[base_id];
+                    content = base_rule.content.clone().followed_by(content);
+                    base = base_rule.extends;
+                }
+                content
+            })
+            .collect();
+        for (rule, content) in self.rules.iter_mut().zip(contents) {
+            rule.content = content;
+        }
+        let facets = self
+            .simple_types
+            .iter()
+            .map(|(name, node)| (name.to_string(), has_facets(node)))
+            .filter(|(_, facets)| !facets.is_empty())
+            .collect();
+        MetaSchema {
+            rules: self.rules,
+            elements,
+            facets,
+        }
+    }
+
+    fn new_rule(&mut self, key: usize, rule: Rule) -> usize {
+        self.rules.push(rule);
+        let id = self.rules.len() - 1;
+        self.compiled.insert(key, id);
+        id
+    }
+
+    /// Returns the rule for the type of the element declaration `node`.
+    fn element_rule(&mut self, node: &'a XmlElement) -> usize {
+        if let Some(type_name) = node.attribute("type") {
+            return self.type_rule(type_name);
+        }
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some("complexType") => return self.complex_rule(child),
+                Some("simpleType") => return self.text_rule(child.position),
+                _ => {}
+            }
+        }
+        self.type_rule("xs:anyType")
+    }
+
+    fn text_rule(&mut self, key: usize) -> usize {
+        if let Some(&id) = self.compiled.get(&key) {
+            return id;
+        }
+        self.new_rule(
+            key,
+            Rule {
+                text: true,
+                ..Rule::default()
+            },
+        )
+    }
+
+    /// Returns the rule for the type called `type_name`.
+    fn type_rule(&mut self, type_name: &str) -> usize {
+        let local_name = strip_prefix(type_name);
+        if let Some(node) = self.complex_types.get(local_name).copied() {
+            return self.complex_rule(node);
+        }
+        if local_name == "anyType" {
+            // Keys are byte offsets into the document, which never reach
+            // `usize::MAX`.
+            if let Some(&id) = self.compiled.get(&usize::MAX) {
+                return id;
+            }
+            return self.new_rule(
+                usize::MAX,
+                Rule {
+                    content: Particle {
+                        model: Model::Leaf(Leaf::Any(Wildcard::Any)),
+                        min: 0,
+                        max: None,
+                    },
+                    any_attribute: true,
+                    text: true,
+                    ..Rule::default()
+                },
+            );
+        }
+        self.text_rule(usize::MAX - 1)
+    }
+
+    /// Returns the rule for the `xs:complexType` definition `node`.
+    fn complex_rule(&mut self, node: &'a XmlElement) -> usize {
+        if let Some(&id) = self.compiled.get(&node.position) {
+            return id;
+        }
+        let id = self.new_rule(node.position, Rule::default());
+        self.rules[id].text = node.attribute("mixed") == Some("true");
+        let mut content = node;
+        for child in node.child_elements() {
+            let simple = match xsd_name(child) {
+                Some("complexContent") => false,
+                Some("simpleContent") => true,
+                _ => continue,
+            };
+            if child.attribute("mixed") == Some("true") {
+                self.rules[id].text = true;
+            }
+            let derivation = match child
+                .child_elements()
+                .find(|grandchild| xsd_name(grandchild) != Some("annotation"))
+            {
+                Some(derivation) => derivation,
+                None => continue,
+            };
+            let base = self.type_rule(derivation.attribute("base").unwrap_or("xs:anyType"));
+            let base_rule = self.rules[base].clone();
+            let rule = &mut self.rules[id];
+            if xsd_name(derivation) == Some("extension") {
+                if !simple {
+                    rule.extends = Some(base);
+                }
+                rule.any_attribute = base_rule.any_attribute;
+            }
+            rule.attributes = base_rule.attributes;
+            rule.text |= simple;
+            content = derivation;
+        }
+        self.add_attributes(id, content);
+        // Content models can refer back to the type being compiled, so
+        // particles are only added once the attributes of every type
+        // reached so far are complete, and the children inherited by
+        // extension are merged in once every rule is.
+        self.pending.push((id, content));
+        id
+    }
+
+    fn add_attributes(&mut self, id: usize, node: &'a XmlElement) {
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some("attribute") => {
+                    let name = match child.attribute("name") {
+                        Some(name) => name,
+                        None => continue,
+                    };
+                    if child.attribute("use") == Some("prohibited") {
+                        self.rules[id].attributes.remove(name);
+                        continue;
+                    }
+                    let value = match child.attribute("type") {
+                        Some(type_name) => Some(self.value_rule(type_name)),
+                        None => child
+                            .child_elements()
+                            .find(|grandchild| xsd_name(grandchild) == Some("simpleType"))
+                            .map(|simple_type| self.simple_value_rule(simple_type)),
+                    };
+                    let required = child.attribute("use") == Some("required");
+                    let attributes = &mut self.rules[id].attributes;
+                    let value = value
+                        .or_else(|| attributes.get(name).map(|base| base.value.clone()))
+                        .unwrap_or(ValueRule::Any);
+                    attributes.insert(name.to_string(), AttributeRule { required, value });
+                }
+                Some("attributeGroup") => {
+                    let group = child
+                        .attribute("ref")
+                        .and_then(|name| self.attribute_groups.get(strip_prefix(name)))
+                        .copied();
+                    if let Some(group) = group {
+                        self.add_attributes(id, group);
+                    }
+                }
+                Some("anyAttribute") => self.rules[id].any_attribute = true,
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the particle of the `xs:element`, `xs:any`, `xs:group`
+    /// reference or model group `node`.
+    fn particle(&mut self, node: &'a XmlElement) -> Option<Particle> {
+        let model = match xsd_name(node) {
+            Some("element") => {
+                let (name, declaration) = match (node.attribute("ref"), node.attribute("name")) {
+                    (Some(reference), _) => {
+                        let name = strip_prefix(reference);
+                        (name, self.elements.get(name).copied()?)
+                    }
+                    (None, Some(name)) => (name, node),
+                    (None, None) => return None,
+                };
+                let rule = self.element_rule(declaration);
+                Model::Leaf(Leaf::Element(name.to_string(), rule))
+            }
+            Some("group") => {
+                let group = self
+                    .groups
+                    .get(strip_prefix(node.attribute("ref")?))
+                    .copied()?;
+                let particle = group
+                    .child_elements()
+                    .find_map(|child| match xsd_name(child) {
+                        Some("sequence" | "choice" | "all") => self.particle(child),
+                        _ => None,
+                    })?;
+                particle.model
+            }
+            Some("sequence" | "all") => Model::Sequence(self.particles(node)),
+            Some("choice") => Model::Choice(self.particles(node)),
+            Some("any") => Model::Leaf(Leaf::Any(match node.attribute("namespace") {
+                Some("##other") => Wildcard::Other,
+                _ => Wildcard::Any,
+            })),
+            _ => return None,
+        };
+        Some(Particle {
+            model,
+            min: node
+                .attribute("minOccurs")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1),
+            max: match node.attribute("maxOccurs") {
+                Some("unbounded") => None,
+                value => Some(value.and_then(|value| value.parse().ok()).unwrap_or(1)),
+            },
+        })
+    }
+
+    fn particles(&mut self, node: &'a XmlElement) -> Vec<Particle> {
+        node.child_elements()
+            .filter_map(|child| self.particle(child))
+            .collect()
+    }
+
+    /// Returns the rule for the values of the simple type called
+    /// `type_name`.
+    fn value_rule(&mut self, type_name: &str) -> ValueRule {
+        match strip_prefix(type_name) {
+            "boolean" => ValueRule::Boolean,
+            "nonNegativeInteger" => ValueRule::NonNegativeInteger,
+            "NCName" | "ID" | "IDREF" => ValueRule::NcName,
+            "QName" => ValueRule::QName,
+            local_name => match self.simple_types.get(local_name).copied() {
+                Some(node) => self.simple_value_rule(node),
+                None => ValueRule::Any,
+            },
+        }
+    }
+
+    /// Returns the rule for the values of the `xs:simpleType` definition
+    /// `node`.
+    fn simple_value_rule(&mut self, node: &'a XmlElement) -> ValueRule {
+        let inline = |node: &'a XmlElement| {
+            node.child_elements()
+                .filter(|child| xsd_name(child) == Some("simpleType"))
+                .collect::<Vec<_>>()
+        };
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some("restriction") => {
+                    let values: Vec<String> = child
+                        .child_elements()
+                        .filter(|facet| xsd_name(facet) == Some("enumeration"))
+                        .filter_map(|facet| facet.attribute("value"))
+                        .map(str::to_string)
+                        .collect();
+                    if !values.is_empty() {
+                        return ValueRule::Enumeration(values);
+                    }
+                    return match (child.attribute("base"), inline(child).first()) {
+                        (Some(base), _) => self.value_rule(base),
+                        (None, Some(simple_type)) => self.simple_value_rule(simple_type),
+                        (None, None) => ValueRule::Any,
+                    };
+                }
+                Some("list") => {
+                    let item = match (child.attribute("itemType"), inline(child).first()) {
+                        (Some(item_type), _) => self.value_rule(item_type),
+                        (None, Some(simple_type)) => self.simple_value_rule(simple_type),
+                        (None, None) => ValueRule::Any,
+                    };
+                    return ValueRule::List(Box::new(item));
+                }
+                Some("union") => {
+                    let mut members: Vec<ValueRule> = child
+                        .attribute("memberTypes")
+                        .unwrap_or_default()
+                        .split_whitespace()
+                        .map(|member| self.value_rule(member))
+                        .collect();
+                    for simple_type in inline(child) {
+                        members.push(self.simple_value_rule(simple_type));
+                    }
+                    return ValueRule::Union(members);
+                }
+                _ => {}
+            }
+        }
+        ValueRule::Any
+    }
+}
+
+/// Returns the names of the facets listed by the `hfp:hasFacet`
+/// annotations of the simple type definition `node`.
+fn has_facets(node: &XmlElement) -> Vec<String> {
+    node.child_elements()
+        .filter(|child| xsd_name(child) == Some("annotation"))
+        .flat_map(|annotation| annotation.child_elements())
+        .filter(|appinfo| xsd_name(appinfo) == Some("appinfo"))
+        .flat_map(|appinfo| appinfo.child_elements())
+        .filter(|child| {
+            child.qname.namespace() == Some(HFP_NAMESPACE) && child.qname.local() == "hasFacet"
+        })
+        .filter_map(|child| child.attribute("name"))
+        .map(str::to_string)
+        .collect()
+}
+
+fn strip_prefix(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+/// Returns `true` if `value` is a name without a colon.
+pub(crate) fn is_ncname(value: &str) -> bool {
+    let mut chars = value.chars();
+    matches!(chars.next(), Some(ch) if ch != ':' && is_name_start_char(ch))
+        && chars.all(|ch| ch != ':' && is_name_char(ch))
+}
+
+fn violation(code: &str, message: &str, node: &XmlElement, lines: &LineIndex) -> SchemaError {
+    SchemaError::constraint(lines.location(node.position), node, code, message)
+}
@@ -0,0 +1,2756 @@
+//! Building schema components from the element tree of an XSD document.
+
+use std::{collections::HashMap, fmt::Display, str::FromStr};
+
+use crate::{
+    components::{
+        parse_occurs, Assertion, Attribute, AttributeGroup, ComplexContent, ComplexType,
+        Compositor, Datatype, Derivation, DerivationSet, Element, Group, IdentityConstraint,
+        IdentityConstraintCategory, MaxOccurs, ModelGroup, NamespaceConstraint, Notation, Particle,
+        ProcessContents, SimpleType, Term, UseOption, Variety, Wildcard,
+    },
+    datatypes::SimpleDatatype,
+    derivation::ParticleRestriction,
+    document::XmlElement,
+    error::{Location, SchemaError},
+    facets::{self, Facet},
+    graph::{SchemaGraph, SymbolTable, TypeDefinition},
+    loader::{definition_error, SchemaDocument},
+    meta::MetaSchema,
+    qname::{QName, XSD_NAMESPACE},
+    regex::Regex,
+    schema::XsdVersion,
+    xpath::XPath,
+    xpath2::Expression,
+};
+
+#[derive(Debug)]
+pub(crate) enum XmlSchemaNode {
+    /// xs:attribute
+    Attribute(Attribute),
+    /// xs:attributeGroup
+    AttributeGroup(AttributeGroup),
+    /// xs:complexType
+    ComplexType(Box<ComplexType>),
+    /// xs:element
+    Element(Element),
+    /// xs:group
+    Group(Group),
+    /// xs:simpleType
+    SimpleType(SimpleType),
+    /// xs:notation
+    Notation(Notation),
+}
+
+impl XmlSchemaNode {
+    /// Returns the expanded name of the component, and the kind of the
+    /// components that share its symbol space.
+    pub(crate) fn symbol(&self) -> (&'static str, &QName) {
+        match self {
+            XmlSchemaNode::Attribute(attribute) => ("attribute", &attribute.name),
+            XmlSchemaNode::AttributeGroup(attribute_group) => {
+                ("attribute group", &attribute_group.name)
+            }
+            XmlSchemaNode::ComplexType(complex_type) => ("type", &complex_type.name),
+            XmlSchemaNode::Element(element) => ("element", &element.name),
+            XmlSchemaNode::Group(group) => ("model group", &group.name),
+            XmlSchemaNode::SimpleType(simple_type) => ("type", &simple_type.name),
+            XmlSchemaNode::Notation(notation) => ("notation", &notation.name),
+        }
+    }
+
+    /// Returns the component as a definition errors can be reported at.
+    pub(crate) fn definition(&self) -> &dyn Definition {
+        match self {
+            XmlSchemaNode::Attribute(attribute) => attribute,
+            XmlSchemaNode::AttributeGroup(attribute_group) => attribute_group,
+            XmlSchemaNode::ComplexType(complex_type) => complex_type.as_ref(),
+            XmlSchemaNode::Element(element) => element,
+            XmlSchemaNode::Group(group) => group,
+            XmlSchemaNode::SimpleType(simple_type) => simple_type,
+            XmlSchemaNode::Notation(notation) => notation,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct XmlSchema {
+    pub(crate) target_namespace: Option<String>,
+    pub(crate) element_form_default: Option<String>,
+    pub(crate) attribute_form_default: Option<String>,
+    pub(crate) nodes: Vec<XmlSchemaNode>,
+    /// The original definitions of the components replaced by
+    /// `xs:redefine`, under hidden names, which the redefinitions derive
+    /// from or reference
+    pub(crate) redefined: Vec<XmlSchemaNode>,
+    /// The target namespaces of the schema documents the schema is
+    /// assembled from, in the order they are read
+    pub(crate) namespaces: Vec<Option<String>>,
+    /// The symbol spaces of the global components, once the schema is
+    /// assembled
+    pub(crate) symbols: SymbolTable,
+    /// The version of XML Schema the schema is read as
+    pub(crate) version: XsdVersion,
+}
+
+/// A reference of a schema document to another schema document.
+#[derive(Debug)]
+pub(crate) struct Reference {
+    pub(crate) kind: ReferenceKind,
+    /// The `xs:include`, `xs:import`, `xs:redefine` or `xs:override`
+    /// element of the referencing document
+    pub(crate) node: XmlElement,
+    /// The value of the `schemaLocation` attribute, if any
+    pub(crate) location: Option<String>,
+    /// The components defined by an `xs:redefine` or `xs:override`, that
+    /// replace those of the referenced document
+    pub(crate) components: Vec<XmlSchemaNode>,
+}
+
+/// The kind of a [`Reference`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ReferenceKind {
+    /// xs:include
+    Include,
+    /// xs:import, with the namespace imported
+    Import(Option<String>),
+    /// xs:redefine
+    Redefine,
+    /// xs:override
+    Override,
+}
+
+pub(crate) struct XmlSchemaParser<'a> {
+    input: &'a str,
+    /// The index of the document among those the schema is assembled
+    /// from
+    document: usize,
+    /// The target namespace of the document including this one, if it
+    /// has none of its own and takes on that of the including document
+    /// (a chameleon include)
+    chameleon_namespace: Option<String>,
+    target_namespace: Option<String>,
+    element_form_default: Option<String>,
+    attribute_form_default: Option<String>,
+    block_default: DerivationSet,
+    final_default: DerivationSet,
+    /// The version of XML Schema the document is read as
+    version: XsdVersion,
+    /// The `xpathDefaultNamespace` attribute of the `xs:schema` element
+    xpath_default_namespace: Option<String>,
+}
+
+impl<'a> XmlSchemaParser<'a> {
+    /// Creates a parser for the schema document `input`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text of the schema document.
+    /// * `document` - The index of the document among those the schema
+    ///   is assembled from.
+    /// * `including_namespace` - The target namespace of the document
+    ///   including `input`, if any, which a document with no target
+    ///   namespace takes on.
+    /// * `version` - The version of XML Schema the document is read as.
+    ///   XSD 1.1 documents can declare assertions and override other
+    ///   documents.
+    ///
+    pub(crate) fn new(
+        input: &'a str,
+        document: usize,
+        including_namespace: Option<String>,
+        version: XsdVersion,
+    ) -> Self {
+        Self {
+            input,
+            document,
+            chameleon_namespace: including_namespace,
+            target_namespace: None,
+            element_form_default: None,
+            attribute_form_default: None,
+            block_default: DerivationSet::default(),
+            final_default: DerivationSet::default(),
+            version,
+            xpath_default_namespace: None,
+        }
+    }
+
+    /// Parses a schema document from the input.
+    ///
+    /// This function reads the input into an element tree with the
+    /// [`Tokenizer`](crate::Tokenizer) and checks it against
+    /// the schema for schemas, then walks the children of the `xs:schema`
+    /// document element, parsing the global components and adding them
+    /// to an `XmlSchema` object, and recording its references to other
+    /// schema documents. The components are not resolved: the
+    /// [`SchemaLoader`](crate::loader::SchemaLoader) resolves them once
+    /// every document of the schema is read. The function returns the
+    /// `XmlSchema` object and the references wrapped in an `Ok` variant
+    /// of the `Result` type, or the error found wrapped in an `Err`
+    /// variant of the `Result` type.
+    ///
+    pub(crate) fn parse(&mut self) -> Result<(XmlSchema, Vec<Reference>), SchemaError> {
+        let root = XmlElement::parse(self.input)?;
+        MetaSchema::get(self.version)?.validate(&root, self.input)?;
+        let target_namespace = root
+            .attribute("targetNamespace")
+            .filter(|namespace| !namespace.is_empty())
+            .map(str::to_string);
+        if target_namespace.is_some() {
+            self.chameleon_namespace = None;
+        }
+        let mut schema = XmlSchema {
+            target_namespace: target_namespace.or_else(|| self.chameleon_namespace.clone()),
+            element_form_default: root.attribute("elementFormDefault").map(str::to_string),
+            attribute_form_default: root.attribute("attributeFormDefault").map(str::to_string),
+            nodes: Vec::new(),
+            redefined: Vec::new(),
+            namespaces: Vec::new(),
+            symbols: SymbolTable::default(),
+            version: self.version,
+        };
+        self.target_namespace = schema.target_namespace.clone();
+        self.element_form_default = schema.element_form_default.clone();
+        self.attribute_form_default = schema.attribute_form_default.clone();
+        self.xpath_default_namespace = root.attribute("xpathDefaultNamespace").map(str::to_string);
+        self.block_default = self
+            .typed_attribute(&root, "blockDefault")?
+            .unwrap_or_default();
+        self.final_default = self
+            .typed_attribute(&root, "finalDefault")?
+            .unwrap_or_default();
+
+        let mut references = Vec::new();
+        for child in root.child_elements() {
+            match xsd_name(child) {
+                Some(kind @ ("include" | "import" | "redefine" | "override")) => {
+                    let kind = match kind {
+                        "include" => ReferenceKind::Include,
+                        "import" => {
+                            ReferenceKind::Import(child.attribute("namespace").map(str::to_string))
+                        }
+                        "redefine" => ReferenceKind::Redefine,
+                        _ => ReferenceKind::Override,
+                    };
+                    let mut components = Vec::new();
+                    for grandchild in child.child_elements() {
+                        if let Some(component) = self.parse_component(grandchild)? {
+                            components.push(component);
+                        }
+                    }
+                    references.push(Reference {
+                        kind,
+                        node: child.clone(),
+                        location: child.attribute("schemaLocation").map(str::to_string),
+                        components,
+                    });
+                }
+                Some("annotation") => {}
+                _ => match self.parse_component(child)? {
+                    Some(component) => schema.nodes.push(component),
+                    None => return Err(self.unexpected_tag(child)),
+                },
+            }
+        }
+        Ok((schema, references))
+    }
+
+    /// Parses the global component defined by `node`, or returns `None`
+    /// if `node` does not define a component.
+    fn parse_component(&mut self, node: &XmlElement) -> Result<Option<XmlSchemaNode>, SchemaError> {
+        let component = match xsd_name(node) {
+            Some("element") => XmlSchemaNode::Element(self.parse_element(node, true)?),
+            Some("attribute") => XmlSchemaNode::Attribute(self.parse_attribute(node, true)?),
+            Some("simpleType") => XmlSchemaNode::SimpleType(self.parse_simple_type(node)?),
+            Some("complexType") => {
+                XmlSchemaNode::ComplexType(Box::new(self.parse_complex_type(node)?))
+            }
+            Some("group") => XmlSchemaNode::Group(self.parse_group(node)?),
+            Some("attributeGroup") => {
+                XmlSchemaNode::AttributeGroup(self.parse_attribute_group(node)?)
+            }
+            Some("notation") => XmlSchemaNode::Notation(Notation {
+                name: self.component_name(node, true),
+                public: node.attribute("public").map(str::to_string),
+                system: node.attribute("system").map(str::to_string),
+                document: self.document,
+                position: node.position,
+                path: node.path.to_string(),
+            }),
+            _ => return Ok(None),
+        };
+        Ok(Some(component))
+    }
+
+    /// Returns the error message for a schema element that is not
+    /// allowed where it appears.
+    fn unexpected_tag(&self, node: &XmlElement) -> SchemaError {
+        self.error_at(
+            node,
+            "s4s-elt-invalid-content.1",
+            &format!("Unexpected tag: {}", node.name),
+        )
+    }
+
+    /// Returns the error for a violation of the constraint `code` by the
+    /// element `node` of the schema document.
+    fn error_at(&self, node: &XmlElement, code: &str, message: &str) -> SchemaError {
+        SchemaError::constraint(
+            Location::new(self.input, node.position),
+            node,
+            code,
+            message,
+        )
+    }
+
+    /// Expands the `QName` value of the attribute `attribute` of `node`,
+    /// if present.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    /// * `attribute` - The name of an attribute of type `xs:QName`, such
+    ///   as `type`, `base` or `ref`.
+    ///
+    fn qname_attribute(
+        &self,
+        node: &XmlElement,
+        attribute: &str,
+    ) -> Result<Option<QName>, SchemaError> {
+        match node.attribute(attribute) {
+            Some(value) => self.resolve_qname(node, value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Expands the `QName` value `value` in the scope of `node`.
+    ///
+    /// In a document included with a chameleon include, names in no
+    /// namespace are taken to be in the target namespace of the
+    /// including document.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    /// * `value` - The qualified name.
+    ///
+    fn resolve_qname(&self, node: &XmlElement, value: &str) -> Result<QName, SchemaError> {
+        let name = node
+            .resolve_qname(value)
+            .map_err(|message| self.error_at(node, "s4s-att-invalid-value", &message))?;
+        match &self.chameleon_namespace {
+            Some(namespace) if name.namespace().is_none() => {
+                Ok(QName::new(Some(namespace), name.local()))
+            }
+            _ => Ok(name),
+        }
+    }
+
+    /// Parses the value of the attribute `attribute` of `node`, if
+    /// present.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    /// * `attribute` - The name of the attribute.
+    ///
+    fn typed_attribute<T>(
+        &self,
+        node: &XmlElement,
+        attribute: &str,
+    ) -> Result<Option<T>, SchemaError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        node.attributes
+            .parse(attribute)
+            .map_err(|message| self.error_at(node, "s4s-att-invalid-value", &message))
+    }
+
+    /// Reads the `xs:boolean` attribute `attribute` of `node`, which is
+    /// `false` when absent.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    /// * `attribute` - The name of the attribute.
+    ///
+    fn boolean_attribute(&self, node: &XmlElement, attribute: &str) -> Result<bool, SchemaError> {
+        node.attributes
+            .boolean(attribute)
+            .map(Option::unwrap_or_default)
+            .map_err(|message| self.error_at(node, "s4s-att-invalid-value", &message))
+    }
+
+    /// Reads the `minOccurs` and `maxOccurs` attributes of `node`, which
+    /// both default to 1.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    ///
+    fn occurs(&self, node: &XmlElement) -> Result<(u32, MaxOccurs), SchemaError> {
+        let min_occurs = match node.attribute("minOccurs") {
+            Some(value) => parse_occurs(value.trim()).map_err(|reason| {
+                self.error_at(
+                    node,
+                    "s4s-att-invalid-value",
+                    &format!(
+                        "Invalid value '{}' for attribute 'minOccurs': {}",
+                        value, reason
+                    ),
+                )
+            })?,
+            None => 1,
+        };
+        let max_occurs = self
+            .typed_attribute(node, "maxOccurs")?
+            .unwrap_or(MaxOccurs::Bounded(1));
+        if MaxOccurs::Bounded(min_occurs) > max_occurs {
+            return Err(self.error_at(
+                node,
+                "p-props-correct.2.1",
+                &format!(
+                    "minOccurs ({}) must not be greater than maxOccurs ({})",
+                    min_occurs, max_occurs
+                ),
+            ));
+        }
+        Ok((min_occurs, max_occurs))
+    }
+
+    /// Reads the `block` or `final` attribute `attribute` of a complex
+    /// type or element declaration, narrowed to extension and
+    /// restriction, which defaults to the `blockDefault` or
+    /// `finalDefault` of the schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    /// * `attribute` - The name of the attribute.
+    /// * `default` - The default set of the schema.
+    ///
+    fn derivation_attribute(
+        &self,
+        node: &XmlElement,
+        attribute: &str,
+        default: DerivationSet,
+    ) -> Result<DerivationSet, SchemaError> {
+        Ok(self
+            .typed_attribute(node, attribute)?
+            .unwrap_or(default)
+            .intersection(DerivationSet::new(&[
+                Derivation::Extension,
+                Derivation::Restriction,
+            ])))
+    }
+
+    /// Returns whether the local declaration `node` is qualified, from
+    /// its `form` attribute or else the form default of the schema.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:element` or `xs:attribute` element.
+    /// * `form_default` - The `elementFormDefault` or
+    ///   `attributeFormDefault` of the schema.
+    ///
+    fn is_qualified(&self, node: &XmlElement, form_default: Option<&str>) -> bool {
+        node.attribute("form").or(form_default).map(str::trim) == Some("qualified")
+    }
+
+    /// Returns the expanded name of the component defined by `node`.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    /// * `qualified` - Whether the name is in the target namespace.
+    ///
+    fn component_name(&self, node: &XmlElement, qualified: bool) -> QName {
+        let namespace = if qualified {
+            self.target_namespace.as_deref()
+        } else {
+            None
+        };
+        QName::new(namespace, node.attribute("name").unwrap_or_default())
+    }
+
+    /// Returns the type of the element or attribute declaration `node`:
+    /// the type named by its `type` attribute, the anonymous type defined
+    /// inside it, or else the built-in type `default`.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:element` or `xs:attribute` element.
+    /// * `default` - The local name of the default XSD type.
+    ///
+    fn declaration_type(
+        &mut self,
+        node: &XmlElement,
+        default: &str,
+    ) -> Result<Datatype, SchemaError> {
+        let name = self.qname_attribute(node, "type")?;
+        let inline = node
+            .child_elements()
+            .find(|child| matches!(xsd_name(child), Some("simpleType" | "complexType")));
+        match (name, inline) {
+            (Some(_), Some(inline)) => {
+                let code = match xsd_name(node) {
+                    Some("attribute") => "src-attribute.4",
+                    _ => "src-element.3",
+                };
+                Err(self.error_at(
+                    inline,
+                    code,
+                    "'type' and an anonymous type definition must not both be present",
+                ))
+            }
+            (Some(name), None) => Ok(Datatype::SimpleType(name)),
+            (None, Some(inline)) if xsd_name(inline) == Some("simpleType") => Ok(
+                Datatype::AnonymousSimpleType(Box::new(self.parse_simple_type(inline)?)),
+            ),
+            (None, Some(inline)) => Ok(Datatype::AnonymousComplexType(Box::new(
+                self.parse_complex_type(inline)?,
+            ))),
+            (None, None) => Ok(Datatype::SimpleType(QName::new(
+                Some(XSD_NAMESPACE),
+                default,
+            ))),
+        }
+    }
+
+    /// Parses an `xs:element` XML element.
+    ///
+    /// The `block` and `final` sets default to the `blockDefault` and
+    /// `finalDefault` of the schema, narrowed to the derivations that
+    /// apply to element declarations.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:element` element of the schema document.
+    /// * `global` - Whether the element is declared at the top level of
+    ///   the schema.
+    ///
+    fn parse_element(&mut self, node: &XmlElement, global: bool) -> Result<Element, SchemaError> {
+        let qualified = global || self.is_qualified(node, self.element_form_default.as_deref());
+        let datatype = self.declaration_type(node, "anyType")?;
+        let (min_occurs, max_occurs) = self.occurs(node)?;
+        let default_value = node.attribute("default").map(str::to_string);
+        let fixed_value = node.attribute("fixed").map(str::to_string);
+        if default_value.is_some() && fixed_value.is_some() {
+            return Err(self.error_at(
+                node,
+                "src-element.1",
+                "'default' and 'fixed' must not both be present",
+            ));
+        }
+        let mut substitution_group = Vec::new();
+        if let Some(heads) = node.attribute("substitutionGroup") {
+            for head in heads.split_whitespace() {
+                substitution_group.push(self.resolve_qname(node, head)?);
+            }
+        }
+        let block_set = self
+            .typed_attribute(node, "block")?
+            .unwrap_or(self.block_default)
+            .intersection(DerivationSet::new(&[
+                Derivation::Extension,
+                Derivation::Restriction,
+                Derivation::Substitution,
+            ]));
+        let final_set = self.derivation_attribute(node, "final", self.final_default)?;
+        let type_from_head = !substitution_group.is_empty()
+            && node.attribute("type").is_none()
+            && !node
+                .child_elements()
+                .any(|child| matches!(xsd_name(child), Some("simpleType" | "complexType")));
+        let reference = self.qname_attribute(node, "ref")?;
+        if reference.is_some() == node.attribute("name").is_some() {
+            return Err(self.error_at(
+                node,
+                "src-element.2.1",
+                "Exactly one of 'name' and 'ref' must be present",
+            ));
+        }
+        let mut identity_constraints = Vec::new();
+        for child in node.child_elements() {
+            if let Some("unique" | "key" | "keyref") = xsd_name(child) {
+                identity_constraints.push(self.parse_identity_constraint(child)?);
+            }
+        }
+        Ok(Element {
+            name: match &reference {
+                Some(reference) => reference.clone(),
+                None => self.component_name(node, qualified),
+            },
+            reference,
+            reference_id: None,
+            datatype,
+            type_id: None,
+            max_occurs,
+            min_occurs,
+            default_value,
+            fixed_value,
+            nillable: self.boolean_attribute(node, "nillable")?,
+            is_abstract: self.boolean_attribute(node, "abstract")?,
+            substitution_group,
+            head_ids: Vec::new(),
+            type_from_head,
+            block_set,
+            final_set,
+            identity_constraints,
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        })
+    }
+
+    /// Parses an `xs:unique`, `xs:key` or `xs:keyref` XML element.
+    ///
+    /// The `xpath` of its selector and fields are compiled here, while
+    /// the constraint a keyref refers to is only resolved once every
+    /// element declaration of the schema is known.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The identity constraint element of the schema
+    ///   document.
+    ///
+    fn parse_identity_constraint(
+        &mut self,
+        node: &XmlElement,
+    ) -> Result<IdentityConstraint, SchemaError> {
+        let category = match xsd_name(node) {
+            Some("unique") => IdentityConstraintCategory::Unique,
+            Some("key") => IdentityConstraintCategory::Key,
+            _ => IdentityConstraintCategory::KeyRef,
+        };
+        let xpath = |child: &XmlElement, field: bool| {
+            XPath::parse(child.attribute("xpath").unwrap_or_default(), child, field)
+                .map_err(|message| self.error_at(child, "c-general-xpath", &message))
+        };
+        let mut selector = None;
+        let mut fields = Vec::new();
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some("selector") if selector.is_none() && fields.is_empty() => {
+                    selector = Some(xpath(child, false)?);
+                }
+                Some("field") if selector.is_some() => fields.push(xpath(child, true)?),
+                Some("annotation") if selector.is_none() => {}
+                _ => return Err(self.unexpected_tag(child)),
+            }
+        }
+        let selector = match selector {
+            Some(selector) if !fields.is_empty() => selector,
+            _ => {
+                return Err(self.error_at(
+                    node,
+                    "s4s-elt-must-match.1",
+                    &format!(
+                        "The content of '{}' must match (annotation?, (selector, field+))",
+                        category
+                    ),
+                ))
+            }
+        };
+        let refer = match category {
+            IdentityConstraintCategory::KeyRef => self.qname_attribute(node, "refer")?,
+            _ => None,
+        };
+        Ok(IdentityConstraint {
+            name: self.component_name(node, true),
+            category,
+            selector,
+            fields,
+            refer,
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        })
+    }
+
+    /// Parses the `xs:attribute` element `node` declared in the complex
+    /// type `complex_type`, and adds it to the attributes of the type.
+    ///
+    /// No two attributes of a complex type can have the same name.
+    fn parse_local_attribute(
+        &mut self,
+        node: &XmlElement,
+        complex_type: &mut ComplexType,
+    ) -> Result<(), SchemaError> {
+        let attribute = self.parse_attribute(node, false)?;
+        let key = attribute.key();
+        if complex_type.attributes.contains_key(&key) {
+            return Err(self.error_at(
+                node,
+                "ct-props-correct.4",
+                &format!("Attribute '{}' is declared more than once", key),
+            ));
+        }
+        complex_type.attributes.insert(key, attribute);
+        Ok(())
+    }
+
+    /// Parses an `xs:attribute` XML element.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:attribute` element of the schema document.
+    /// * `global` - Whether the attribute is declared at the top level of
+    ///   the schema.
+    ///
+    fn parse_attribute(
+        &mut self,
+        node: &XmlElement,
+        global: bool,
+    ) -> Result<Attribute, SchemaError> {
+        let qualified = global || self.is_qualified(node, self.attribute_form_default.as_deref());
+        let datatype = self.declaration_type(node, "anySimpleType")?;
+        let use_option = match node.attribute("use").map(str::trim) {
+            Some("required") => UseOption::Required,
+            Some("prohibited") => UseOption::Prohibited,
+            _ => UseOption::Optional,
+        };
+        let default_value = node.attribute("default").map(str::to_string);
+        let fixed_value = node.attribute("fixed").map(str::to_string);
+        if default_value.is_some() && fixed_value.is_some() {
+            return Err(self.error_at(
+                node,
+                "src-attribute.1",
+                "'default' and 'fixed' must not both be present",
+            ));
+        }
+        if default_value.is_some() && use_option != UseOption::Optional {
+            return Err(self.error_at(
+                node,
+                "src-attribute.2",
+                "'use' must be 'optional' when 'default' is present",
+            ));
+        }
+        let reference = self.qname_attribute(node, "ref")?;
+        if reference.is_some() == node.attribute("name").is_some() {
+            return Err(self.error_at(
+                node,
+                "src-attribute.3.1",
+                "Exactly one of 'name' and 'ref' must be present",
+            ));
+        }
+        if node.attribute("name").map(str::trim) == Some("xmlns") {
+            return Err(self.error_at(
+                node,
+                "no-xmlns",
+                "The name of an attribute declaration must not match 'xmlns'",
+            ));
+        }
+        Ok(Attribute {
+            name: self.component_name(node, qualified),
+            reference,
+            reference_id: None,
+            datatype,
+            type_id: None,
+            default_value,
+            fixed_value,
+            use_option,
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        })
+    }
+
+    /// Parses an `xs:simpleType` XML element.
+    ///
+    /// The datatype of a restriction is read from the `base` attribute of
+    /// its `xs:restriction` child, or from an anonymous simple type
+    /// nested in it, and its facets from the other children of the
+    /// restriction. A base that is not a built-in datatype is recorded
+    /// and resolved by `resolve_datatypes` once the whole schema is
+    /// read, as are the item type of an `xs:list` and the member types of
+    /// an `xs:union`.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:simpleType` element of the schema document.
+    ///
+    fn parse_simple_type(&mut self, node: &XmlElement) -> Result<SimpleType, SchemaError> {
+        let mut simple_type = SimpleType {
+            name: self.component_name(node, true),
+            base_type: None,
+            base_id: None,
+            anonymous_base: None,
+            datatype: SimpleDatatype::String,
+            variety: Variety::Atomic,
+            members: Vec::new(),
+            facets: Vec::new(),
+            fixed_facets: Vec::new(),
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        };
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some("restriction") => {
+                    match self.qname_attribute(child, "base")? {
+                        Some(base) => match builtin_datatype(&base) {
+                            Some(datatype) => simple_type.datatype = datatype,
+                            None => simple_type.base_type = Some(base),
+                        },
+                        None => {
+                            if let Some(inline) = child
+                                .child_elements()
+                                .find(|grandchild| xsd_name(grandchild) == Some("simpleType"))
+                            {
+                                let inline = self.parse_simple_type(inline)?;
+                                simple_type.anonymous_base = Some(Box::new(inline));
+                            }
+                        }
+                    }
+                    self.parse_facets(child, &mut simple_type)?;
+                }
+                Some("list") => {
+                    let item_type = self.qname_attribute(child, "itemType")?;
+                    let mut inline = self.inline_simple_types(child)?;
+                    let item_type = match (item_type, inline.len()) {
+                        (Some(name), 0) => Datatype::SimpleType(name),
+                        (None, 1) => Datatype::AnonymousSimpleType(Box::new(inline.remove(0))),
+                        _ => {
+                            return Err(self.error_at(
+                                child,
+                                "src-list-itemType-or-simpleType",
+                                "A list must have either an 'itemType' attribute or a \
+                                 'simpleType' child, but not both",
+                            ))
+                        }
+                    };
+                    simple_type.datatype = SimpleDatatype::AnySimpleType;
+                    simple_type.variety = Variety::List(item_type);
+                }
+                Some("union") => {
+                    let mut members = Vec::new();
+                    if let Some(names) = child.attribute("memberTypes") {
+                        for name in names.split_whitespace() {
+                            let name = self.resolve_qname(child, name)?;
+                            members.push(Datatype::SimpleType(name));
+                        }
+                    }
+                    for inline in self.inline_simple_types(child)? {
+                        members.push(Datatype::AnonymousSimpleType(Box::new(inline)));
+                    }
+                    if members.is_empty() {
+                        return Err(self.error_at(
+                            child,
+                            "src-union-memberTypes-or-simpleTypes",
+                            "A union must have a non-empty 'memberTypes' attribute or at \
+                             least one 'simpleType' child",
+                        ));
+                    }
+                    simple_type.datatype = SimpleDatatype::AnySimpleType;
+                    simple_type.variety = Variety::Union(members);
+                }
+                Some("annotation") => {}
+                _ => return Err(self.unexpected_tag(child)),
+            }
+        }
+        Ok(simple_type)
+    }
+
+    /// Parses the anonymous `xs:simpleType` children of `node`.
+    fn inline_simple_types(&mut self, node: &XmlElement) -> Result<Vec<SimpleType>, SchemaError> {
+        node.child_elements()
+            .filter(|child| xsd_name(child) == Some("simpleType"))
+            .map(|child| self.parse_simple_type(child))
+            .collect()
+    }
+
+    /// Parses the facets declared by the `xs:restriction` element `node`
+    /// into `simple_type`.
+    ///
+    /// The `xs:pattern` and `xs:enumeration` children are gathered into a
+    /// single facet each, and any other facet can only appear once
+    /// (`src-single-facet-value`).
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:restriction` element of the schema document.
+    /// * `simple_type` - The simple type being parsed.
+    ///
+    fn parse_facets(
+        &self,
+        node: &XmlElement,
+        simple_type: &mut SimpleType,
+    ) -> Result<(), SchemaError> {
+        let mut patterns = Vec::new();
+        let mut enumerations = Vec::new();
+        let mut assertions = Vec::new();
+        for child in node.child_elements() {
+            let facet = match xsd_name(child) {
+                Some("length") => Facet::Length(self.facet_value(child)?),
+                Some("minLength") => Facet::MinLength(self.facet_value(child)?),
+                Some("maxLength") => Facet::MaxLength(self.facet_value(child)?),
+                Some("totalDigits") => match self.facet_value(child)? {
+                    0 => {
+                        return Err(self.error_at(
+                            child,
+                            "s4s-att-invalid-value",
+                            "The value of totalDigits must be a positive integer",
+                        ))
+                    }
+                    digits => Facet::TotalDigits(digits),
+                },
+                Some("fractionDigits") => Facet::FractionDigits(self.facet_value(child)?),
+                Some("whiteSpace") => Facet::WhiteSpace(self.facet_value(child)?),
+                Some("maxInclusive") => Facet::MaxInclusive(self.facet_value(child)?),
+                Some("maxExclusive") => Facet::MaxExclusive(self.facet_value(child)?),
+                Some("minInclusive") => Facet::MinInclusive(self.facet_value(child)?),
+                Some("minExclusive") => Facet::MinExclusive(self.facet_value(child)?),
+                Some("pattern") => {
+                    let pattern: String = self.facet_value(child)?;
+                    let regex = Regex::new(&pattern, self.version).map_err(|message| {
+                        self.error_at(
+                            child,
+                            "s4s-att-invalid-value",
+                            &format!(
+                                "Invalid regular expression '{}' in facet 'pattern': {}",
+                                pattern, message
+                            ),
+                        )
+                    })?;
+                    patterns.push(regex);
+                    continue;
+                }
+                Some("enumeration") => {
+                    enumerations.push(self.facet_value(child)?);
+                    continue;
+                }
+                Some("assertion") => {
+                    assertions.push(self.parse_assertion(child)?);
+                    continue;
+                }
+                _ => continue,
+            };
+            if simple_type
+                .facets
+                .iter()
+                .any(|declared| declared.name() == facet.name())
+            {
+                return Err(self.error_at(
+                    child,
+                    "src-single-facet-value",
+                    &format!("Facet '{}' is defined more than once", facet.name()),
+                ));
+            }
+            if self.boolean_attribute(child, "fixed")? {
+                simple_type.fixed_facets.push(facet.name());
+            }
+            simple_type.facets.push(facet);
+        }
+        if !patterns.is_empty() {
+            simple_type.facets.push(Facet::Pattern(patterns));
+        }
+        if !enumerations.is_empty() {
+            simple_type.facets.push(Facet::Enumeration(enumerations));
+        }
+        if !assertions.is_empty() {
+            simple_type.facets.push(Facet::Assertion(assertions));
+        }
+        Ok(())
+    }
+
+    /// Parses an XSD 1.1 `xs:assert` or `xs:assertion` XML element.
+    ///
+    /// The test is compiled with the namespaces in scope on the element.
+    /// Unprefixed element names in the test are in the namespace named by
+    /// the `xpathDefaultNamespace` attribute of the element, or else of
+    /// the `xs:schema` element, and in no namespace if neither has one.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:assert` or `xs:assertion` element of the schema
+    ///   document.
+    ///
+    fn parse_assertion(&self, node: &XmlElement) -> Result<Assertion, SchemaError> {
+        let test = node.attribute("test").ok_or_else(|| {
+            self.error_at(
+                node,
+                "s4s-att-must-appear",
+                &format!("Attribute 'test' must appear in element '{}'", node.name),
+            )
+        })?;
+        let default_namespace = match node
+            .attribute("xpathDefaultNamespace")
+            .or(self.xpath_default_namespace.as_deref())
+            .map(str::trim)
+        {
+            Some("##targetNamespace") => self.target_namespace.clone(),
+            Some("##defaultNamespace") => node.namespace("").map(str::to_string),
+            Some("##local" | "") | None => None,
+            Some(namespace) => Some(namespace.to_string()),
+        };
+        let test = Expression::parse(
+            test,
+            &|prefix| node.namespace(prefix).map(str::to_string),
+            default_namespace.as_deref(),
+        )
+        .map_err(|reason| {
+            self.error_at(
+                node,
+                "s4s-att-invalid-value",
+                &format!(
+                    "Invalid XPath expression '{}' in assertion: {}",
+                    test, reason
+                ),
+            )
+        })?;
+        Ok(Assertion {
+            test,
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        })
+    }
+
+    /// Reads the required `value` attribute of the facet element `node`.
+    fn facet_value<T>(&self, node: &XmlElement) -> Result<T, SchemaError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        self.typed_attribute(node, "value")?.ok_or_else(|| {
+            self.error_at(
+                node,
+                "s4s-att-must-appear",
+                &format!("Attribute 'value' must appear in element '{}'", node.name),
+            )
+        })
+    }
+
+    /// Parses an `xs:complexType` XML element.
+    ///
+    /// The kind of content of the type is taken from its first content
+    /// child, the base type from the `base` attribute of an
+    /// `xs:extension` or `xs:restriction` inside `xs:simpleContent` or
+    /// `xs:complexContent`, the content model from its model group or
+    /// group reference, and the attributes from its `xs:attribute`
+    /// children.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:complexType` element of the schema document.
+    ///
+    fn parse_complex_type(&mut self, node: &XmlElement) -> Result<ComplexType, SchemaError> {
+        let mut complex_type = ComplexType {
+            name: self.component_name(node, true),
+            base_type: None,
+            base_id: None,
+            derivation: None,
+            attributes: HashMap::new(),
+            attribute_groups: Vec::new(),
+            content: ComplexContent::Empty,
+            mixed_content: node.attribute("mixed").map(str::to_string),
+            particle: None,
+            content_model: None,
+            simple_content: None,
+            attribute_wildcard: None,
+            assertions: Vec::new(),
+            is_abstract: self.boolean_attribute(node, "abstract")?,
+            block_set: self.derivation_attribute(node, "block", self.block_default)?,
+            final_set: self.derivation_attribute(node, "final", self.final_default)?,
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        };
+        if complex_type.mixed_content.as_deref() == Some("true") {
+            complex_type.content = ComplexContent::MixedContent;
+        }
+
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some(kind @ ("sequence" | "choice" | "all" | "group")) => {
+                    complex_type.content = match kind {
+                        "sequence" => ComplexContent::Sequence,
+                        "choice" => ComplexContent::Choice,
+                        "all" => ComplexContent::All,
+                        _ => ComplexContent::Group,
+                    };
+                    complex_type.particle = self.parse_particle(child)?;
+                }
+                Some("attribute") => self.parse_local_attribute(child, &mut complex_type)?,
+                Some(kind @ ("simpleContent" | "complexContent")) => {
+                    complex_type.content = if kind == "simpleContent" {
+                        ComplexContent::SimpleContent
+                    } else {
+                        ComplexContent::ComplexContentExtension
+                    };
+                    if let Some(mixed) = child.attribute("mixed") {
+                        complex_type.mixed_content = Some(mixed.to_string());
+                    }
+                    self.parse_derivation(child, &mut complex_type)?;
+                }
+                Some("anyAttribute") => {
+                    complex_type.attribute_wildcard = Some(self.parse_wildcard(child)?);
+                }
+                Some("attributeGroup") => {
+                    if let Some(name) = self.qname_attribute(child, "ref")? {
+                        complex_type.attribute_groups.push(name);
+                    }
+                }
+                Some("assert") => complex_type.assertions.push(self.parse_assertion(child)?),
+                Some("annotation") => {}
+                _ => return Err(self.unexpected_tag(child)),
+            }
+        }
+        Ok(complex_type)
+    }
+
+    /// Parses the `xs:extension` or `xs:restriction` of an
+    /// `xs:simpleContent` or `xs:complexContent` XML element into
+    /// `complex_type`.
+    ///
+    /// The facets of a restriction of simple content, and the anonymous
+    /// simple type it may restrict, are kept as the simple content of the
+    /// type, which is resolved against the base type with the rest of
+    /// the derivation once the whole schema is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:simpleContent` or `xs:complexContent` element.
+    /// * `complex_type` - The complex type being parsed.
+    ///
+    fn parse_derivation(
+        &mut self,
+        node: &XmlElement,
+        complex_type: &mut ComplexType,
+    ) -> Result<(), SchemaError> {
+        for child in node.child_elements() {
+            let derivation = match xsd_name(child) {
+                Some("extension") => Derivation::Extension,
+                Some("restriction") => Derivation::Restriction,
+                Some("annotation") => continue,
+                _ => return Err(self.unexpected_tag(child)),
+            };
+            complex_type.base_type = self.qname_attribute(child, "base")?;
+            complex_type.derivation = Some(derivation);
+            if xsd_name(node) == Some("simpleContent") && derivation == Derivation::Restriction {
+                let mut simple_type = SimpleType {
+                    name: complex_type.name.clone(),
+                    base_type: None,
+                    base_id: None,
+                    anonymous_base: None,
+                    datatype: SimpleDatatype::AnySimpleType,
+                    variety: Variety::Atomic,
+                    members: Vec::new(),
+                    facets: Vec::new(),
+                    fixed_facets: Vec::new(),
+                    document: self.document,
+                    position: child.position,
+                    path: child.path.to_string(),
+                };
+                if let Some(inline) = self.inline_simple_types(child)?.pop() {
+                    simple_type.anonymous_base = Some(Box::new(inline));
+                }
+                self.parse_facets(child, &mut simple_type)?;
+                complex_type.simple_content = Some(Box::new(simple_type));
+            }
+            for grandchild in child.child_elements() {
+                match xsd_name(grandchild) {
+                    Some("sequence" | "choice" | "all" | "group") => {
+                        complex_type.particle = self.parse_particle(grandchild)?;
+                    }
+                    Some("anyAttribute") => {
+                        complex_type.attribute_wildcard = Some(self.parse_wildcard(grandchild)?);
+                    }
+                    Some("attributeGroup") => {
+                        if let Some(name) = self.qname_attribute(grandchild, "ref")? {
+                            complex_type.attribute_groups.push(name);
+                        }
+                    }
+                    Some("attribute") => self.parse_local_attribute(grandchild, complex_type)?,
+                    Some("assert") => {
+                        complex_type
+                            .assertions
+                            .push(self.parse_assertion(grandchild)?);
+                    }
+                    Some("annotation" | "simpleType") => {}
+                    Some(facet) if facets::is_facet(facet) => {}
+                    _ => return Err(self.unexpected_tag(grandchild)),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a global `xs:group` XML element.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:group` element of the schema document.
+    ///
+    fn parse_group(&mut self, node: &XmlElement) -> Result<Group, SchemaError> {
+        let mut model_group = ModelGroup {
+            compositor: Compositor::Sequence,
+            particles: Vec::new(),
+        };
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some("sequence" | "choice" | "all") => {
+                    if let Some(Particle {
+                        term: Term::ModelGroup(group),
+                        ..
+                    }) = self.parse_particle(child)?
+                    {
+                        model_group = group;
+                    }
+                }
+                Some("annotation") => {}
+                _ => return Err(self.unexpected_tag(child)),
+            }
+        }
+        Ok(Group {
+            name: self.component_name(node, true),
+            model_group,
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        })
+    }
+
+    /// Parses a global `xs:attributeGroup` XML element.
+    ///
+    /// The attribute groups it references are recorded and merged into
+    /// its attributes once the whole schema is read.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:attributeGroup` element of the schema document.
+    ///
+    fn parse_attribute_group(&mut self, node: &XmlElement) -> Result<AttributeGroup, SchemaError> {
+        let mut attribute_group = AttributeGroup {
+            name: self.component_name(node, true),
+            attributes: HashMap::new(),
+            attribute_groups: Vec::new(),
+            attribute_wildcard: None,
+            document: self.document,
+            position: node.position,
+            path: node.path.to_string(),
+        };
+        for child in node.child_elements() {
+            match xsd_name(child) {
+                Some("attribute") => {
+                    let attribute = self.parse_attribute(child, false)?;
+                    let key = attribute.key();
+                    if attribute_group.attributes.contains_key(&key) {
+                        return Err(self.error_at(
+                            child,
+                            "ag-props-correct.2",
+                            &format!("Attribute '{}' is declared more than once", key),
+                        ));
+                    }
+                    attribute_group.attributes.insert(key, attribute);
+                }
+                Some("attributeGroup") => {
+                    if let Some(name) = self.qname_attribute(child, "ref")? {
+                        attribute_group.attribute_groups.push(name);
+                    }
+                }
+                Some("anyAttribute") => {
+                    attribute_group.attribute_wildcard = Some(self.parse_wildcard(child)?);
+                }
+                Some("annotation") => {}
+                _ => return Err(self.unexpected_tag(child)),
+            }
+        }
+        Ok(attribute_group)
+    }
+
+    /// Parses a particle of a content model.
+    ///
+    /// This function reads the occurrence bounds of `node` and its term:
+    /// a local element declaration or element reference, a nested
+    /// `xs:sequence`, `xs:choice` or `xs:all` model group, an `xs:any`
+    /// wildcard or a reference to a named group. Particles that can
+    /// never occur (`maxOccurs="0"`) are kept, so that the content model
+    /// mirrors the schema document. Annotations are not particles and
+    /// return `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the schema document.
+    ///
+    fn parse_particle(&mut self, node: &XmlElement) -> Result<Option<Particle>, SchemaError> {
+        let (min_occurs, max_occurs) = self.occurs(node)?;
+        let term = match xsd_name(node) {
+            Some("element") => Term::Element(Box::new(self.parse_element(node, false)?)),
+            Some(kind @ ("sequence" | "choice" | "all")) => {
+                let compositor = match kind {
+                    "sequence" => Compositor::Sequence,
+                    "choice" => Compositor::Choice,
+                    _ => Compositor::All,
+                };
+                let mut particles = Vec::new();
+                for child in node.child_elements() {
+                    if let Some(particle) = self.parse_particle(child)? {
+                        particles.push(particle);
+                    }
+                }
+                Term::ModelGroup(ModelGroup {
+                    compositor,
+                    particles,
+                })
+            }
+            Some("any") => Term::Wildcard(self.parse_wildcard(node)?),
+            Some("group") => match self.qname_attribute(node, "ref")? {
+                Some(name) => Term::GroupReference(name),
+                None => return Err(self.unexpected_tag(node)),
+            },
+            Some("annotation") => return Ok(None),
+            _ => return Err(self.unexpected_tag(node)),
+        };
+        Ok(Some(Particle {
+            min_occurs,
+            max_occurs,
+            term,
+        }))
+    }
+
+    /// Parses an `xs:any` or `xs:anyAttribute` XML element.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The `xs:any` or `xs:anyAttribute` element.
+    ///
+    fn parse_wildcard(&self, node: &XmlElement) -> Result<Wildcard, SchemaError> {
+        let process_contents = match node.attribute("processContents").map(str::trim) {
+            Some("lax") => ProcessContents::Lax,
+            Some("skip") => ProcessContents::Skip,
+            _ => ProcessContents::Strict,
+        };
+        let target_namespace = self.target_namespace.clone();
+        let namespaces = |value: &str| {
+            let mut namespaces: Vec<Option<String>> = Vec::new();
+            for token in value.split_whitespace() {
+                let namespace = match token {
+                    "##targetNamespace" => target_namespace.clone(),
+                    "##local" => None,
+                    _ => Some(token.to_string()),
+                };
+                if !namespaces.contains(&namespace) {
+                    namespaces.push(namespace);
+                }
+            }
+            namespaces
+        };
+        let namespace = match (node.attribute("namespace"), node.attribute("notNamespace")) {
+            (Some(_), Some(_)) => {
+                return Err(self.error_at(
+                    node,
+                    "src-wildcard.1",
+                    "The 'namespace' and 'notNamespace' attributes cannot both be present",
+                ))
+            }
+            (None, Some(value)) => NamespaceConstraint::Not(namespaces(value)),
+            (Some(value), None) => match value.trim() {
+                "##any" => NamespaceConstraint::Any,
+                "##other" => {
+                    let mut excluded = vec![target_namespace.clone()];
+                    if target_namespace.is_some() {
+                        excluded.push(None);
+                    }
+                    NamespaceConstraint::Not(excluded)
+                }
+                value => NamespaceConstraint::Enumeration(namespaces(value)),
+            },
+            (None, None) => NamespaceConstraint::Any,
+        };
+        let mut wildcard = Wildcard {
+            namespace,
+            not_qnames: Vec::new(),
+            not_defined: false,
+            not_defined_sibling: false,
+            process_contents,
+        };
+        for token in node
+            .attribute("notQName")
+            .unwrap_or_default()
+            .split_whitespace()
+        {
+            match token {
+                "##defined" => wildcard.not_defined = true,
+                "##definedSibling" => wildcard.not_defined_sibling = true,
+                _ => {
+                    wildcard.not_qnames.push(self.resolve_qname(node, token)?);
+                }
+            }
+        }
+        Ok(wildcard)
+    }
+}
+
+/// Returns `true` if `particle` is a non-empty `xs:all` group.
+fn is_all_group(particle: &Particle) -> bool {
+    matches!(&particle.term, Term::ModelGroup(group)
+        if group.compositor == Compositor::All && !group.particles.is_empty())
+}
+
+/// Resolves the datatypes of the components of the schema.
+///
+/// The base of a simple type, or the type of a declaration, may be
+/// defined after it, or in another schema document, so these are
+/// resolved once every global type is known. This function follows the
+/// chain of bases of each simple type to a built-in datatype, derives
+/// each complex type from its base type, and turns the type references
+/// naming a complex type of the schema, or `xs:anyType`, into complex
+/// type references. Anonymous types and the local declarations of
+/// content models are resolved too.
+///
+/// # Arguments
+///
+/// * `schema` - The schema being loaded.
+/// * `documents` - The schema documents it is assembled from.
+///
+pub(crate) fn resolve_datatypes(
+    schema: &mut XmlSchema,
+    documents: &[SchemaDocument],
+) -> Result<(), SchemaError> {
+    let mut resolver = DatatypeResolver {
+        documents,
+        simple_types: HashMap::new(),
+        complex_types: HashMap::new(),
+        groups: HashMap::new(),
+        attribute_groups: HashMap::new(),
+        version: schema.version,
+    };
+    for node in schema.nodes.iter().chain(&schema.redefined) {
+        match node {
+            XmlSchemaNode::SimpleType(simple_type) => {
+                resolver
+                    .simple_types
+                    .insert(simple_type.name.clone(), simple_type.clone());
+            }
+            XmlSchemaNode::ComplexType(complex_type) => {
+                resolver
+                    .complex_types
+                    .insert(complex_type.name.clone(), complex_type.as_ref().clone());
+            }
+            XmlSchemaNode::Group(group) => {
+                resolver.groups.insert(group.name.clone(), group.clone());
+            }
+            XmlSchemaNode::AttributeGroup(attribute_group) => {
+                resolver
+                    .attribute_groups
+                    .insert(attribute_group.name.clone(), attribute_group.clone());
+            }
+            _ => {}
+        }
+    }
+    for node in &mut schema.nodes {
+        match node {
+            XmlSchemaNode::Element(element) => resolver.element(element)?,
+            XmlSchemaNode::Attribute(attribute) => resolver.datatype(&mut attribute.datatype)?,
+            XmlSchemaNode::SimpleType(simple_type) => resolver.simple_type(simple_type)?,
+            XmlSchemaNode::ComplexType(complex_type) => resolver.complex_type(complex_type)?,
+            XmlSchemaNode::Group(group) => resolver.group(group)?,
+            XmlSchemaNode::AttributeGroup(attribute_group) => {
+                let name = attribute_group.name.clone();
+                *attribute_group =
+                    resolver.resolve_attribute_group(attribute_group, &mut vec![name])?;
+            }
+            XmlSchemaNode::Notation(_) => {}
+        }
+    }
+    resolver.substitution_groups(&mut schema.nodes)?;
+    resolver.substitution_group_types(schema)
+}
+
+/// Checks the identity constraints of the element declarations of the
+/// resolved schema `schema`.
+///
+/// Identity constraints share a symbol space of their own, so their
+/// names must be unique across the schema. The constraint a keyref
+/// refers to must be a key or unique constraint of the schema, with as
+/// many fields as the keyref.
+///
+/// # Arguments
+///
+/// * `schema` - The schema being loaded, once resolved.
+/// * `documents` - The schema documents it is assembled from.
+///
+pub(crate) fn check_identity_constraints(
+    schema: &XmlSchema,
+    documents: &[SchemaDocument],
+) -> Result<(), SchemaError> {
+    let mut constraints: Vec<&IdentityConstraint> = Vec::new();
+    for node in &schema.nodes {
+        match node {
+            XmlSchemaNode::Element(element) => {
+                collect_identity_constraints(element, &mut constraints)
+            }
+            XmlSchemaNode::ComplexType(complex_type) => {
+                if let Some(particle) = &complex_type.particle {
+                    collect_particle_constraints(particle, &mut constraints);
+                }
+            }
+            XmlSchemaNode::Group(group) => {
+                for particle in &group.model_group.particles {
+                    collect_particle_constraints(particle, &mut constraints);
+                }
+            }
+            _ => {}
+        }
+    }
+    let mut names: HashMap<&QName, &IdentityConstraint> = HashMap::new();
+    for constraint in &constraints {
+        if names.insert(&constraint.name, constraint).is_some() {
+            return Err(definition_error(
+                documents,
+                *constraint,
+                "sch-props-correct.2",
+                &format!(
+                    "The identity constraint '{}' is defined more than once",
+                    constraint.name
+                ),
+            ));
+        }
+    }
+    for constraint in &constraints {
+        let refer = match &constraint.refer {
+            Some(refer) => refer,
+            None => continue,
+        };
+        match names.get(refer) {
+            Some(key) if key.category != IdentityConstraintCategory::KeyRef => {
+                if key.fields.len() != constraint.fields.len() {
+                    return Err(definition_error(
+                        documents,
+                        *constraint,
+                        "c-props-correct.2",
+                        &format!(
+                            "The keyref '{}' has {} fields, but the {} '{}' it refers to has {}",
+                            constraint.name,
+                            constraint.fields.len(),
+                            key.category,
+                            refer,
+                            key.fields.len()
+                        ),
+                    ));
+                }
+            }
+            _ => {
+                return Err(definition_error(
+                    documents,
+                    *constraint,
+                    "src-resolve",
+                    &format!("Cannot resolve '{}' to a key or unique constraint", refer),
+                ))
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Adds the identity constraints of `element` and of the local element
+/// declarations of its anonymous type to `constraints`.
+///
+/// Model groups are inlined into every content model referencing them,
+/// so the same declaration can be reached more than once: it is only
+/// added the first time.
+fn collect_identity_constraints<'s>(
+    element: &'s Element,
+    constraints: &mut Vec<&'s IdentityConstraint>,
+) {
+    for constraint in &element.identity_constraints {
+        let seen = constraints.iter().any(|other| {
+            other.document == constraint.document && other.position == constraint.position
+        });
+        if !seen {
+            constraints.push(constraint);
+        }
+    }
+    if let Datatype::AnonymousComplexType(complex_type) = &element.datatype {
+        if let Some(particle) = &complex_type.particle {
+            collect_particle_constraints(particle, constraints);
+        }
+    }
+}
+
+/// Adds the identity constraints of the element declarations of
+/// `particle` to `constraints`.
+fn collect_particle_constraints<'s>(
+    particle: &'s Particle,
+    constraints: &mut Vec<&'s IdentityConstraint>,
+) {
+    match &particle.term {
+        Term::Element(element) => collect_identity_constraints(element, constraints),
+        Term::ModelGroup(group) => {
+            for particle in &group.particles {
+                collect_particle_constraints(particle, constraints);
+            }
+        }
+        Term::Wildcard(_) | Term::GroupReference(_) => {}
+    }
+}
+
+/// Resolves type references once every global type of the schema is
+/// known.
+struct DatatypeResolver<'a> {
+    /// The schema documents the schema is assembled from
+    documents: &'a [SchemaDocument],
+    /// The global simple types, as declared, by name
+    simple_types: HashMap<QName, SimpleType>,
+    /// The global complex types, as declared, by name
+    complex_types: HashMap<QName, ComplexType>,
+    /// The named model groups, as declared, by name
+    groups: HashMap<QName, Group>,
+    /// The attribute groups, as declared, by name
+    attribute_groups: HashMap<QName, AttributeGroup>,
+    /// The version of XML Schema the schema is read as
+    version: XsdVersion,
+}
+
+/// A definition or declaration errors can be reported at.
+pub(crate) trait Definition {
+    /// Returns the index of the schema document of the definition.
+    fn document(&self) -> usize;
+
+    /// Returns the byte offset of the definition in the schema document.
+    fn position(&self) -> usize;
+
+    /// Returns the path of the definition in the schema document.
+    fn path(&self) -> &str;
+}
+
+impl Definition for Element {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Definition for Notation {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Definition for Attribute {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Definition for SimpleType {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Definition for ComplexType {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Definition for Group {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Definition for AttributeGroup {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl Definition for IdentityConstraint {
+    fn document(&self) -> usize {
+        self.document
+    }
+
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+impl DatatypeResolver<'_> {
+    fn element(&self, element: &mut Element) -> Result<(), SchemaError> {
+        self.datatype(&mut element.datatype)
+    }
+
+    fn datatype(&self, datatype: &mut Datatype) -> Result<(), SchemaError> {
+        match datatype {
+            Datatype::SimpleType(name) => {
+                if name.is_xsd("anyType") || self.complex_types.contains_key(name) {
+                    *datatype = Datatype::ComplexType(name.clone());
+                }
+            }
+            Datatype::AnonymousSimpleType(simple_type) => self.simple_type(simple_type)?,
+            Datatype::AnonymousComplexType(complex_type) => self.complex_type(complex_type)?,
+            Datatype::ComplexType(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Follows the chain of bases of `simple_type` to a built-in
+    /// datatype, and merges the facets it inherits along the way.
+    fn simple_type(&self, simple_type: &mut SimpleType) -> Result<(), SchemaError> {
+        *simple_type = self.resolve(simple_type, &mut Vec::new())?;
+        Ok(())
+    }
+
+    /// Returns `simple_type` once resolved: with the built-in datatype,
+    /// variety and facets it inherits from its base types, and the
+    /// resolved item type of a list or member types of a union.
+    ///
+    /// # Arguments
+    ///
+    /// * `simple_type` - The simple type to resolve.
+    /// * `seen` - The names of the global types already followed, to
+    ///   detect circular definitions.
+    ///
+    fn resolve(
+        &self,
+        simple_type: &SimpleType,
+        seen: &mut Vec<QName>,
+    ) -> Result<SimpleType, SchemaError> {
+        let mut resolved = simple_type.clone();
+        // The schema for schemas defines the built-in datatypes themselves,
+        // whose facets are taken as they are declared
+        if let Some(datatype) = builtin_datatype(&simple_type.name) {
+            resolved.datatype = datatype;
+            return Ok(resolved);
+        }
+        match &simple_type.variety {
+            Variety::List(item_type) => {
+                let item_type = self.member(simple_type, item_type, seen)?;
+                // The built-in list datatypes, and the types restricting
+                // them, are lists too
+                let is_list = |member: &SimpleType| {
+                    matches!(member.variety, Variety::List(_))
+                        || member.datatype.item_type().is_some()
+                };
+                if is_list(&item_type)
+                    || (matches!(item_type.variety, Variety::Union(_))
+                        && item_type.members.iter().any(is_list))
+                {
+                    return Err(self.error(
+                        simple_type,
+                        "cos-st-restricts.2.1",
+                        &format!(
+                            "The item type '{}' of a list cannot be a list, or a union of lists",
+                            item_type.name
+                        ),
+                    ));
+                }
+                resolved.members = vec![item_type];
+            }
+            Variety::Union(member_types) => {
+                resolved.members = member_types
+                    .iter()
+                    .map(|member_type| self.member(simple_type, member_type, seen))
+                    .collect::<Result<_, _>>()?;
+            }
+            Variety::Atomic => {
+                let base = match (&simple_type.base_type, &simple_type.anonymous_base) {
+                    (Some(base), _) => self.named(simple_type, base, seen)?,
+                    (None, Some(anonymous)) => self.resolve(anonymous, seen)?,
+                    (None, None) => SimpleType::builtin(simple_type.datatype),
+                };
+                resolved = self.restrict(simple_type, base)?;
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Returns the restriction of the resolved simple type `base` by the
+    /// facets declared by `simple_type`.
+    fn restrict(
+        &self,
+        simple_type: &SimpleType,
+        base: SimpleType,
+    ) -> Result<SimpleType, SchemaError> {
+        let mut resolved = simple_type.clone();
+        resolved.datatype = base.datatype;
+        resolved.variety = base.variety.clone();
+        resolved.members = base.members.clone();
+        resolved.facets = facets::restrict(&base, simple_type.facets.clone(), self.version)
+            .map_err(|(code, message)| self.error(simple_type, &code, &message))?;
+        resolved.fixed_facets = base.fixed_facets;
+        for name in &simple_type.fixed_facets {
+            if !resolved.fixed_facets.contains(name) {
+                resolved.fixed_facets.push(name);
+            }
+        }
+        Ok(resolved)
+    }
+
+    /// Resolves the simple type called `name`, referenced by the
+    /// definition `at`.
+    fn named(
+        &self,
+        at: &dyn Definition,
+        name: &QName,
+        seen: &mut Vec<QName>,
+    ) -> Result<SimpleType, SchemaError> {
+        if let Some(datatype) = builtin_datatype(name) {
+            return Ok(SimpleType::builtin(datatype));
+        }
+        match self.simple_types.get(name) {
+            Some(_) if seen.contains(name) => Err(self.error(
+                at,
+                "st-props-correct.2",
+                &format!("Circular definition of simple type '{}'", name),
+            )),
+            Some(global) => {
+                seen.push(name.clone());
+                let resolved = self.resolve(global, seen);
+                seen.pop();
+                resolved
+            }
+            None => Err(self.error(
+                at,
+                "src-resolve",
+                &format!("Cannot resolve '{}' to a simple type definition", name),
+            )),
+        }
+    }
+
+    /// Resolves the item type of a list, or a member type of a union,
+    /// `simple_type`.
+    fn member(
+        &self,
+        simple_type: &SimpleType,
+        member: &Datatype,
+        seen: &mut Vec<QName>,
+    ) -> Result<SimpleType, SchemaError> {
+        match member {
+            Datatype::SimpleType(name) | Datatype::ComplexType(name) => {
+                self.named(simple_type, name, seen)
+            }
+            Datatype::AnonymousSimpleType(anonymous) => self.resolve(anonymous, seen),
+            Datatype::AnonymousComplexType(_) => Err(self.error(
+                simple_type,
+                "src-resolve",
+                "The item and member types of a simple type must be simple types",
+            )),
+        }
+    }
+
+    fn error(&self, at: &dyn Definition, code: &str, message: &str) -> SchemaError {
+        definition_error(self.documents, at, code, message)
+    }
+
+    /// Returns the content model of an extension of a type with the
+    /// content model `base_particle`, which adds `particle` to it.
+    ///
+    /// The particle of the extension follows that of its base type in a
+    /// sequence. XSD 1.0 only allows an `xs:all` group as the whole
+    /// content model of a type, so neither can be one, while XSD 1.1
+    /// extends an `xs:all` group with the particles of another one.
+    ///
+    /// # Arguments
+    ///
+    /// * `complex_type` - The extension.
+    /// * `base_name` - The name of its base type.
+    /// * `base_particle` - The content model of its base type.
+    /// * `particle` - The particle of the extension.
+    ///
+    fn extended_particle(
+        &self,
+        complex_type: &ComplexType,
+        base_name: &QName,
+        base_particle: &Particle,
+        particle: Particle,
+    ) -> Result<Particle, SchemaError> {
+        if !is_all_group(base_particle) && !is_all_group(&particle) {
+            return Ok(Particle {
+                min_occurs: 1,
+                max_occurs: MaxOccurs::Bounded(1),
+                term: Term::ModelGroup(ModelGroup {
+                    compositor: Compositor::Sequence,
+                    particles: vec![base_particle.clone(), particle],
+                }),
+            });
+        }
+        match (&base_particle.term, particle.term) {
+            (
+                Term::ModelGroup(base_group),
+                Term::ModelGroup(ModelGroup {
+                    compositor: Compositor::All,
+                    particles,
+                }),
+            ) if self.version == XsdVersion::V1_1 && base_group.compositor == Compositor::All => {
+                Ok(Particle {
+                    min_occurs: particle.min_occurs,
+                    max_occurs: MaxOccurs::Bounded(1),
+                    term: Term::ModelGroup(ModelGroup {
+                        compositor: Compositor::All,
+                        particles: base_group
+                            .particles
+                            .iter()
+                            .cloned()
+                            .chain(particles)
+                            .collect(),
+                    }),
+                })
+            }
+            _ => {
+                let message = match self.version {
+                    XsdVersion::V1_0 => format!(
+                        "The content model of an extension of '{}' cannot be built from an \
+                         'all' model group",
+                        base_name
+                    ),
+                    XsdVersion::V1_1 => format!(
+                        "The content model of an extension of '{}' can only be built from \
+                         'all' model groups if both the base and the extension are one",
+                        base_name
+                    ),
+                };
+                Err(self.error(complex_type, "cos-all-limited.1.2", &message))
+            }
+        }
+    }
+
+    /// Derives `complex_type` from its base type, and resolves the types
+    /// of its local declarations.
+    fn complex_type(&self, complex_type: &mut ComplexType) -> Result<(), SchemaError> {
+        *complex_type = self.resolve_complex(complex_type, &mut Vec::new())?;
+        Ok(())
+    }
+
+    /// Returns `complex_type` once resolved: with the attributes and
+    /// content it inherits from its base type, whose derivation it is
+    /// checked against.
+    ///
+    /// An extension appends its content model to that of its base type,
+    /// in a sequence, and adds its attributes to those of the base type.
+    /// A restriction declares its whole content model, which must
+    /// restrict that of the base type, and inherits the attributes it
+    /// does not redeclare. The simple content of a type is derived from
+    /// a simple type, or from the simple content of its base type.
+    ///
+    /// # Arguments
+    ///
+    /// * `complex_type` - The complex type to resolve.
+    /// * `seen` - The names of the global types already followed, to
+    ///   detect circular definitions.
+    ///
+    fn resolve_complex(
+        &self,
+        complex_type: &ComplexType,
+        seen: &mut Vec<QName>,
+    ) -> Result<ComplexType, SchemaError> {
+        let mut resolved = complex_type.clone();
+        for attribute in resolved.attributes.values_mut() {
+            self.datatype(&mut attribute.datatype)?;
+        }
+        self.include_attribute_groups(
+            &mut resolved.attributes,
+            &mut resolved.attribute_wildcard,
+            &complex_type.attribute_groups,
+            complex_type,
+            &mut Vec::new(),
+        )?;
+        if let Some(particle) = &mut resolved.particle {
+            self.particle(particle, complex_type, &mut Vec::new())?;
+        }
+        let base_name = match &complex_type.base_type {
+            Some(base_name) => base_name,
+            None => return Ok(resolved),
+        };
+        let derivation = complex_type.derivation.unwrap_or(Derivation::Restriction);
+        let simple_content = complex_type.content == ComplexContent::SimpleContent;
+        if base_name.is_xsd("anyType") {
+            return match simple_content {
+                true => Err(self.base_error(complex_type, base_name, true)),
+                false => Ok(resolved),
+            };
+        }
+        let base = match self.complex_types.get(base_name) {
+            Some(_) if seen.contains(base_name) => {
+                return Err(self.error(
+                    complex_type,
+                    "ct-props-correct.3",
+                    &format!("Circular definition of complex type '{}'", base_name),
+                ))
+            }
+            Some(global) if global.final_set.contains(derivation) => {
+                let code = match derivation {
+                    Derivation::Extension => "cos-ct-extends.1.1",
+                    _ => "derivation-ok-restriction.1",
+                };
+                return Err(self.error(
+                    complex_type,
+                    code,
+                    &format!(
+                        "The type '{}' cannot be derived by {}, because its final set \
+                         excludes it",
+                        base_name, derivation
+                    ),
+                ));
+            }
+            Some(global) => {
+                seen.push(base_name.clone());
+                let base = self.resolve_complex(global, seen);
+                seen.pop();
+                base?
+            }
+            None => {
+                let simple_type = self.named(complex_type, base_name, &mut Vec::new())?;
+                if !simple_content || derivation == Derivation::Restriction {
+                    return Err(self.base_error(complex_type, base_name, simple_content));
+                }
+                resolved.simple_content = Some(Box::new(simple_type));
+                return Ok(resolved);
+            }
+        };
+        match (&base.simple_content, simple_content) {
+            (Some(base_content), true) => {
+                resolved.simple_content = Some(match (derivation, &complex_type.simple_content) {
+                    (Derivation::Restriction, Some(declared)) => {
+                        let base_content = match &declared.anonymous_base {
+                            Some(anonymous) => self.resolve(anonymous, &mut Vec::new())?,
+                            None => base_content.as_ref().clone(),
+                        };
+                        Box::new(self.restrict(declared, base_content)?)
+                    }
+                    _ => base_content.clone(),
+                });
+            }
+            (None, true) => return Err(self.base_error(complex_type, base_name, true)),
+            (Some(base_content), false) => {
+                if derivation == Derivation::Restriction || complex_type.particle.is_some() {
+                    return Err(self.error(
+                        complex_type,
+                        "cos-ct-extends.1.4",
+                        &format!(
+                            "The type '{}' has simple content, which cannot be derived into \
+                             a content model",
+                            base_name
+                        ),
+                    ));
+                }
+                resolved.content = ComplexContent::SimpleContent;
+                resolved.simple_content = Some(base_content.clone());
+            }
+            (None, false) => match derivation {
+                Derivation::Extension => match (&base.particle, resolved.particle.take()) {
+                    (None, particle) => resolved.particle = particle,
+                    (Some(base_particle), None) => {
+                        resolved.particle = Some(base_particle.clone());
+                        resolved.mixed_content = base.mixed_content.clone();
+                    }
+                    (Some(base_particle), Some(particle)) => {
+                        if resolved.is_mixed() != base.is_mixed() {
+                            return Err(self.error(
+                                complex_type,
+                                "cos-ct-extends.1.4.3.2.2.1",
+                                &format!(
+                                    "The content of an extension of '{}' must be mixed if, \
+                                     and only if, that of its base type is mixed",
+                                    base_name
+                                ),
+                            ));
+                        }
+                        resolved.particle = Some(self.extended_particle(
+                            complex_type,
+                            base_name,
+                            base_particle,
+                            particle,
+                        )?);
+                    }
+                },
+                _ => {
+                    if resolved.is_mixed() && !base.is_mixed() {
+                        return Err(self.error(
+                            complex_type,
+                            "derivation-ok-restriction.5.4.1.2",
+                            &format!(
+                                "The content of a restriction of '{}' cannot be mixed, \
+                                 because that of its base type is not",
+                                base_name
+                            ),
+                        ));
+                    }
+                    let is_derived =
+                        |derived: &Datatype, base: &Datatype| self.is_derived(derived, base);
+                    ParticleRestriction {
+                        is_derived: &is_derived,
+                    }
+                    .check(resolved.particle.as_ref(), base.particle.as_ref())
+                    .map_err(|message| {
+                        self.error(complex_type, "derivation-ok-restriction.5.4.2", &message)
+                    })?;
+                }
+            },
+        }
+        match derivation {
+            Derivation::Extension => self.extend_attributes(&mut resolved, &base)?,
+            _ => self.restrict_attributes(&mut resolved, &base)?,
+        }
+        let declared = std::mem::take(&mut resolved.assertions);
+        resolved.assertions = base.assertions.iter().cloned().chain(declared).collect();
+        Ok(resolved)
+    }
+
+    /// Returns the error for a type with simple content whose base type
+    /// does not have simple content, or with complex content whose base
+    /// type is a simple type.
+    fn base_error(
+        &self,
+        complex_type: &ComplexType,
+        base_name: &QName,
+        simple_content: bool,
+    ) -> SchemaError {
+        if simple_content {
+            self.error(
+                complex_type,
+                "src-ct.2.1",
+                &format!(
+                    "The base type '{}' of a type with simple content must be a complex type \
+                     with simple content, or a simple type extended with attributes",
+                    base_name
+                ),
+            )
+        } else {
+            self.error(
+                complex_type,
+                "src-ct.1",
+                &format!(
+                    "The base type '{}' of a type with complex content must be a complex type",
+                    base_name
+                ),
+            )
+        }
+    }
+
+    /// Adds the attributes and attribute wildcard of the resolved base
+    /// type `base` to those declared by the extension `complex_type`.
+    fn extend_attributes(
+        &self,
+        complex_type: &mut ComplexType,
+        base: &ComplexType,
+    ) -> Result<(), SchemaError> {
+        for (name, attribute) in &base.attributes {
+            if complex_type.attributes.contains_key(name) {
+                return Err(self.error(
+                    complex_type,
+                    "ct-props-correct.4",
+                    &format!(
+                        "Attribute '{}' is already declared by the base type '{}'",
+                        name, base.name
+                    ),
+                ));
+            }
+            complex_type
+                .attributes
+                .insert(name.clone(), attribute.clone());
+        }
+        complex_type.attribute_wildcard =
+            match (&complex_type.attribute_wildcard, &base.attribute_wildcard) {
+                (Some(wildcard), Some(base_wildcard)) => Some(wildcard.union(base_wildcard)),
+                (wildcard, base_wildcard) => wildcard.clone().or_else(|| base_wildcard.clone()),
+            };
+        Ok(())
+    }
+
+    /// Checks that the attributes and attribute wildcard declared by the
+    /// restriction `complex_type` restrict those of the resolved base
+    /// type `base`, and adds the attributes it does not redeclare.
+    fn restrict_attributes(
+        &self,
+        complex_type: &mut ComplexType,
+        base: &ComplexType,
+    ) -> Result<(), SchemaError> {
+        for (name, attribute) in &complex_type.attributes {
+            let prohibited = attribute.use_option == UseOption::Prohibited;
+            match base.attributes.get(name) {
+                Some(base_attribute) => {
+                    if base_attribute.use_option == UseOption::Required
+                        && attribute.use_option != UseOption::Required
+                    {
+                        return Err(self.error(
+                            complex_type,
+                            "derivation-ok-restriction.3",
+                            &format!(
+                                "Attribute '{}' is required by the base type, and must remain \
+                                 required",
+                                name
+                            ),
+                        ));
+                    }
+                    if prohibited {
+                        continue;
+                    }
+                    if attribute.reference.is_none()
+                        && base_attribute.reference.is_none()
+                        && !self.is_derived(&attribute.datatype, &base_attribute.datatype)
+                    {
+                        return Err(self.error(
+                            complex_type,
+                            "derivation-ok-restriction.2.1.2",
+                            &format!(
+                                "The type of attribute '{}' is not derived from its type in \
+                                 the base type",
+                                name
+                            ),
+                        ));
+                    }
+                    if let Some(fixed) = &base_attribute.fixed_value {
+                        if attribute.fixed_value.as_deref().map(str::trim) != Some(fixed.trim()) {
+                            return Err(self.error(
+                                complex_type,
+                                "derivation-ok-restriction.2.1.3",
+                                &format!(
+                                    "Attribute '{}' must have the fixed value '{}' of the base \
+                                     type",
+                                    name, fixed
+                                ),
+                            ));
+                        }
+                    }
+                }
+                None if prohibited => {}
+                None => {
+                    let allowed = base
+                        .attribute_wildcard
+                        .as_ref()
+                        .map_or(false, |wildcard| wildcard.allows(name));
+                    if !allowed {
+                        return Err(self.error(
+                            complex_type,
+                            "derivation-ok-restriction.2.2",
+                            &format!(
+                                "Attribute '{}' is not declared by the base type '{}', and not \
+                                 allowed by its attribute wildcard",
+                                name, base.name
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(wildcard) = &complex_type.attribute_wildcard {
+            match &base.attribute_wildcard {
+                None => {
+                    return Err(self.error(
+                        complex_type,
+                        "derivation-ok-restriction.4.1",
+                        &format!(
+                            "The base type '{}' has no attribute wildcard to restrict",
+                            base.name
+                        ),
+                    ))
+                }
+                Some(base_wildcard) if !wildcard.is_subset(base_wildcard) => {
+                    return Err(self.error(
+                        complex_type,
+                        "derivation-ok-restriction.4.2",
+                        &format!(
+                            "The attribute wildcard '{}' is not a subset of the wildcard '{}' \
+                             of the base type",
+                            wildcard.namespace, base_wildcard.namespace
+                        ),
+                    ))
+                }
+                Some(_) => {}
+            }
+        }
+        for (name, attribute) in &base.attributes {
+            if !complex_type.attributes.contains_key(name) {
+                complex_type
+                    .attributes
+                    .insert(name.clone(), attribute.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns `true` if the type `derived` is the type `base`, or is
+    /// derived from it. Anonymous base types are assumed to be.
+    fn is_derived(&self, derived: &Datatype, base: &Datatype) -> bool {
+        let base = match base {
+            Datatype::SimpleType(name) | Datatype::ComplexType(name) => name,
+            _ => return true,
+        };
+        let name = match derived {
+            Datatype::SimpleType(name) | Datatype::ComplexType(name) => name.clone(),
+            Datatype::AnonymousSimpleType(simple_type) => simple_base(simple_type),
+            Datatype::AnonymousComplexType(complex_type) => complex_base(complex_type),
+        };
+        if matches!(derived, Datatype::AnonymousSimpleType(_)) && base.is_xsd("anySimpleType") {
+            return true;
+        }
+        self.is_named_derived(name, base)
+    }
+
+    /// Returns `true` if the global type called `name` is the type
+    /// called `base`, or is derived from it.
+    fn is_named_derived(&self, mut name: QName, base: &QName) -> bool {
+        for _ in 0..=self.simple_types.len() + self.complex_types.len() {
+            if &name == base || base.is_xsd("anyType") {
+                return true;
+            }
+            if let Some(datatype) = builtin_datatype(&name) {
+                return builtin_datatype(base).map_or(false, |base| datatype.is_derived_from(base));
+            }
+            name = match (self.simple_types.get(&name), self.complex_types.get(&name)) {
+                (Some(simple_type), _) => simple_base(simple_type),
+                (None, Some(complex_type)) => complex_base(complex_type),
+                (None, None) => return false,
+            };
+        }
+        false
+    }
+
+    /// Resolves the substitution groups of the global element
+    /// declarations among `nodes`.
+    ///
+    /// The heads of every substitution group must be declared, and no
+    /// element can be a member of its own group, directly or not; both are
+    /// checked for every element before any type is inherited. An element
+    /// that declares no type takes on that of the head of its substitution
+    /// group.
+    fn substitution_groups(&self, nodes: &mut [XmlSchemaNode]) -> Result<(), SchemaError> {
+        let elements: HashMap<&QName, &Element> = global_elements(nodes)
+            .map(|element| (&element.name, element))
+            .collect();
+        let mut inherited = Vec::new();
+        for element in global_elements(nodes) {
+            for head in &element.substitution_group {
+                if !elements.contains_key(head) {
+                    return Err(self.error(
+                        element,
+                        "src-resolve",
+                        &format!("Cannot resolve '{}' to an element declaration", head),
+                    ));
+                }
+            }
+            let mut heads: Vec<&QName> = element.substitution_group.iter().collect();
+            let mut seen: Vec<&QName> = Vec::new();
+            while let Some(head) = heads.pop() {
+                if head == &element.name {
+                    return Err(self.error(
+                        element,
+                        "e-props-correct.6",
+                        &format!("Circular substitution group of element '{}'", element.name),
+                    ));
+                }
+                if !seen.contains(&head) {
+                    seen.push(head);
+                    heads.extend(
+                        elements
+                            .get(head)
+                            .into_iter()
+                            .flat_map(|head| &head.substitution_group),
+                    );
+                }
+            }
+        }
+        for element in global_elements(nodes) {
+            if element.type_from_head {
+                let mut head = element;
+                while head.type_from_head {
+                    head = elements[&head.substitution_group[0]];
+                }
+                inherited.push((element.name.clone(), head.datatype.clone(), head.type_id));
+            }
+        }
+        for node in nodes.iter_mut() {
+            if let XmlSchemaNode::Element(element) = node {
+                if let Some((_, datatype, type_id)) =
+                    inherited.iter().find(|(name, _, _)| name == &element.name)
+                {
+                    element.datatype = datatype.clone();
+                    element.type_id = *type_id;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the type of each member of a substitution group of
+    /// `schema` is derived from that of each of its heads, by derivations
+    /// the final set of the head allows.
+    fn substitution_group_types(&self, schema: &XmlSchema) -> Result<(), SchemaError> {
+        let graph = SchemaGraph::new(schema);
+        for element in global_elements(&schema.nodes) {
+            let derived = graph.declared_type(&element.datatype, element.type_id);
+            for head_id in &element.head_ids {
+                let head = graph.element(*head_id);
+                let base = graph.declared_type(&head.datatype, head.type_id);
+                let excluded = derived
+                    .zip(base)
+                    .and_then(|(derived, base)| derivation_path(&graph, derived, base))
+                    .map(|(methods, _)| methods.intersection(head.final_set));
+                let message = match excluded {
+                    Some(excluded) if excluded.is_empty() => continue,
+                    Some(excluded) => format!(
+                        "The type of element '{}' is derived by {} from that of the head '{}' \
+                         of its substitution group, which its final set excludes",
+                        element.name,
+                        excluded
+                            .iter()
+                            .map(|derivation| derivation.to_string())
+                            .collect::<Vec<_>>()
+                            .join(" and "),
+                        head.name
+                    ),
+                    None => format!(
+                        "The type of element '{}' is not derived from that of the head '{}' of \
+                         its substitution group",
+                        element.name, head.name
+                    ),
+                };
+                return Err(self.error(element, "e-props-correct.4", &message));
+            }
+        }
+        Ok(())
+    }
+
+    /// Resolves the model group of the named group `group`.
+    fn group(&self, group: &mut Group) -> Result<(), SchemaError> {
+        let mut seen = vec![group.name.clone()];
+        let mut model_group = group.model_group.clone();
+        for particle in &mut model_group.particles {
+            self.particle(particle, group, &mut seen)?;
+        }
+        group.model_group = model_group;
+        Ok(())
+    }
+
+    /// Resolves the types of the element declarations of `particle`, and
+    /// replaces the references to named groups with their model group.
+    ///
+    /// # Arguments
+    ///
+    /// * `particle` - The particle to resolve.
+    /// * `at` - The definition the particle belongs to.
+    /// * `seen` - The names of the groups already followed, to detect
+    ///   circular definitions.
+    ///
+    fn particle(
+        &self,
+        particle: &mut Particle,
+        at: &dyn Definition,
+        seen: &mut Vec<QName>,
+    ) -> Result<(), SchemaError> {
+        let name = match &mut particle.term {
+            Term::Element(element) => return self.element(element),
+            Term::ModelGroup(group) => {
+                group
+                    .particles
+                    .iter_mut()
+                    .try_for_each(|particle| self.particle(particle, at, seen))?;
+                // The particles of the `xs:all` groups referenced by an
+                // `xs:all` group of XSD 1.1 are its own
+                if group.compositor == Compositor::All {
+                    group.particles = std::mem::take(&mut group.particles)
+                        .into_iter()
+                        .flat_map(|particle| match particle.term {
+                            Term::ModelGroup(nested) if nested.compositor == Compositor::All => {
+                                nested.particles
+                            }
+                            term => vec![Particle { term, ..particle }],
+                        })
+                        .collect();
+                }
+                return Ok(());
+            }
+            Term::Wildcard(_) => return Ok(()),
+            Term::GroupReference(name) => name.clone(),
+        };
+        let group = self.groups.get(&name).ok_or_else(|| {
+            self.error(
+                at,
+                "src-resolve",
+                &format!("Cannot resolve '{}' to a group definition", name),
+            )
+        })?;
+        if seen.contains(&name) {
+            return Err(self.error(
+                group,
+                "mg-props-correct.2",
+                &format!("Circular definition of group '{}'", name),
+            ));
+        }
+        seen.push(name);
+        let mut model_group = group.model_group.clone();
+        let resolved = model_group
+            .particles
+            .iter_mut()
+            .try_for_each(|particle| self.particle(particle, group, seen));
+        seen.pop();
+        resolved?;
+        particle.term = Term::ModelGroup(model_group);
+        Ok(())
+    }
+
+    /// Returns `attribute_group` once resolved: with the types of its
+    /// attributes resolved, and the attributes of the groups it
+    /// references.
+    ///
+    /// # Arguments
+    ///
+    /// * `attribute_group` - The attribute group to resolve.
+    /// * `seen` - The names of the attribute groups already followed, to
+    ///   detect circular definitions.
+    ///
+    fn resolve_attribute_group(
+        &self,
+        attribute_group: &AttributeGroup,
+        seen: &mut Vec<QName>,
+    ) -> Result<AttributeGroup, SchemaError> {
+        let mut resolved = attribute_group.clone();
+        for attribute in resolved.attributes.values_mut() {
+            self.datatype(&mut attribute.datatype)?;
+        }
+        self.include_attribute_groups(
+            &mut resolved.attributes,
+            &mut resolved.attribute_wildcard,
+            &attribute_group.attribute_groups,
+            attribute_group,
+            seen,
+        )?;
+        Ok(resolved)
+    }
+
+    /// Adds the attributes and attribute wildcard of the attribute groups
+    /// called `names` to those of the definition `at`.
+    ///
+    /// # Arguments
+    ///
+    /// * `attributes` - The attributes of the definition.
+    /// * `attribute_wildcard` - The attribute wildcard of the definition.
+    /// * `names` - The names of the attribute groups it references.
+    /// * `at` - The complex type or attribute group definition.
+    /// * `seen` - The names of the attribute groups already followed, to
+    ///   detect circular definitions.
+    ///
+    fn include_attribute_groups(
+        &self,
+        attributes: &mut HashMap<QName, Attribute>,
+        attribute_wildcard: &mut Option<Wildcard>,
+        names: &[QName],
+        at: &dyn Definition,
+        seen: &mut Vec<QName>,
+    ) -> Result<(), SchemaError> {
+        for name in names {
+            let attribute_group = self.attribute_groups.get(name).ok_or_else(|| {
+                self.error(
+                    at,
+                    "src-resolve",
+                    &format!("Cannot resolve '{}' to an attribute group definition", name),
+                )
+            })?;
+            if seen.contains(name) {
+                return Err(self.error(
+                    attribute_group,
+                    "src-attribute_group.3",
+                    &format!("Circular definition of attribute group '{}'", name),
+                ));
+            }
+            seen.push(name.clone());
+            let resolved = self.resolve_attribute_group(attribute_group, seen);
+            seen.pop();
+            let resolved = resolved?;
+            for (key, attribute) in resolved.attributes {
+                if attributes.contains_key(&key) {
+                    return Err(self.error(
+                        at,
+                        "ct-props-correct.4",
+                        &format!("Attribute '{}' is declared more than once", key),
+                    ));
+                }
+                attributes.insert(key, attribute);
+            }
+            if let Some(wildcard) = resolved.attribute_wildcard {
+                *attribute_wildcard = match attribute_wildcard.take() {
+                    Some(local) => Some(local.intersection(&wildcard)),
+                    None => Some(wildcard),
+                };
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of the base type of the simple type `simple_type`,
+/// following anonymous base types.
+fn simple_base(simple_type: &SimpleType) -> QName {
+    match (
+        &simple_type.base_type,
+        &simple_type.anonymous_base,
+        &simple_type.variety,
+    ) {
+        (Some(base), _, _) => base.clone(),
+        (None, Some(anonymous), _) => simple_base(anonymous),
+        (None, None, Variety::Atomic) => {
+            QName::new(Some(XSD_NAMESPACE), simple_type.datatype.name())
+        }
+        _ => QName::new(Some(XSD_NAMESPACE), "anySimpleType"),
+    }
+}
+
+/// Returns an iterator over the global element declarations among
+/// `nodes`, in document order.
+fn global_elements(nodes: &[XmlSchemaNode]) -> impl Iterator<Item = &Element> {
+    nodes.iter().filter_map(|node| match node {
+        XmlSchemaNode::Element(element) => Some(element),
+        _ => None,
+    })
+}
+
+/// Returns how the type `derived` is derived from the type `base`: the
+/// derivations used along the chain of base types leading from one to
+/// the other, and the derivations blocked by `base` and the types in
+/// between. Returns `None` if `derived` is not `base` and not derived
+/// from it.
+///
+/// # Arguments
+///
+/// * `graph` - The graph of the schema `derived` is defined in.
+/// * `derived` - The derived type.
+/// * `base` - The base type.
+///
+pub(crate) fn derivation_path<'s>(
+    graph: &SchemaGraph<'s>,
+    derived: TypeDefinition<'s>,
+    base: TypeDefinition<'s>,
+) -> Option<(DerivationSet, DerivationSet)> {
+    let block_set = |definition: TypeDefinition| match definition {
+        TypeDefinition::Complex(complex_type) => complex_type.block_set,
+        _ => DerivationSet::default(),
+    };
+    let mut methods = DerivationSet::default();
+    let mut blocked = block_set(base);
+    let mut seen: Vec<TypeDefinition> = Vec::new();
+    let mut definition = derived;
+    loop {
+        if is_same_type(definition, base) {
+            return Some((methods, blocked));
+        }
+        if seen.iter().any(|other| is_same_type(*other, definition)) {
+            return None;
+        }
+        // The types in between block derivations too
+        if !methods.is_empty() {
+            blocked = blocked.union(block_set(definition));
+        }
+        let (next, method) = graph.base_definition(definition)?;
+        methods = methods.union(DerivationSet::new(&[method]));
+        seen.push(definition);
+        definition = next;
+    }
+}
+
+/// Returns `true` if `first` and `second` are the same type definition:
+/// the same built-in or named type, or the same anonymous type.
+fn is_same_type(first: TypeDefinition, second: TypeDefinition) -> bool {
+    let same = |first: (&QName, usize, usize), second: (&QName, usize, usize)| {
+        first.0 == second.0 && (!first.0.local().is_empty() || first == second)
+    };
+    match (first, second) {
+        (TypeDefinition::AnyType, TypeDefinition::AnyType) => true,
+        (TypeDefinition::Builtin(first), TypeDefinition::Builtin(second)) => first == second,
+        (TypeDefinition::Simple(first), TypeDefinition::Simple(second)) => same(
+            (&first.name, first.document, first.position),
+            (&second.name, second.document, second.position),
+        ),
+        (TypeDefinition::Complex(first), TypeDefinition::Complex(second)) => same(
+            (&first.name, first.document, first.position),
+            (&second.name, second.document, second.position),
+        ),
+        _ => false,
+    }
+}
+
+/// Returns the name of the base type of the complex type
+/// `complex_type`, `xs:anyType` when it is not derived.
+fn complex_base(complex_type: &ComplexType) -> QName {
+    complex_type
+        .base_type
+        .clone()
+        .unwrap_or_else(|| QName::new(Some(XSD_NAMESPACE), "anyType"))
+}
+
+/// Returns the local name of `node` if it is an element of the XML
+/// Schema namespace.
+pub(crate) fn xsd_name(node: &XmlElement) -> Option<&str> {
+    match node.qname.namespace() {
+        Some(XSD_NAMESPACE) => Some(node.qname.local()),
+        _ => None,
+    }
+}
+
+/// Returns the built-in datatype called `name`, if any.
+///
+/// # Arguments
+///
+/// * `name` - The expanded name of the datatype.
+///
+pub(crate) fn builtin_datatype(name: &QName) -> Option<SimpleDatatype> {
+    match name.namespace() {
+        Some(XSD_NAMESPACE) => SimpleDatatype::from_name(name.local()),
+        _ => None,
+    }
+}
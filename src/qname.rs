@@ -0,0 +1,100 @@
+//! Expanded names of schema components.
+
+use std::{
+    fmt::{Display, Error, Formatter},
+    str::FromStr,
+};
+
+/// The XML Schema namespace.
+pub const XSD_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema";
+
+/// The XML Schema instance namespace.
+pub const XSI_NAMESPACE: &str = "http://www.w3.org/2001/XMLSchema-instance";
+
+/// The namespace bound to the `xml` prefix.
+pub const XML_NAMESPACE: &str = "http://www.w3.org/XML/1998/namespace";
+
+/// The namespace of namespace declarations, bound to the `xmlns` prefix.
+pub const XMLNS_NAMESPACE: &str = "http://www.w3.org/2000/xmlns/";
+
+/// An expanded name: a namespace name, if any, and a local name.
+///
+/// Names are displayed, and can be parsed, in the `{namespace}local`
+/// notation.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{QName, XSD_NAMESPACE};
+///
+/// let name = QName::new(Some(XSD_NAMESPACE), "string");
+/// assert_eq!(name.to_string(), "{http://www.w3.org/2001/XMLSchema}string");
+/// assert_eq!(name, "{http://www.w3.org/2001/XMLSchema}string".parse().unwrap());
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QName {
+    namespace: Option<String>,
+    local: String,
+}
+
+impl QName {
+    /// Creates an expanded name.
+    ///
+    /// An empty namespace name is the same as no namespace.
+    ///
+    /// # Arguments
+    ///
+    /// * `namespace` - The namespace name, if any.
+    /// * `local` - The local name.
+    ///
+    pub fn new(namespace: Option<&str>, local: &str) -> Self {
+        Self {
+            namespace: namespace.filter(|ns| !ns.is_empty()).map(str::to_string),
+            local: local.to_string(),
+        }
+    }
+
+    /// Returns the namespace name, if any.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Returns the local name.
+    pub fn local(&self) -> &str {
+        &self.local
+    }
+
+    /// Returns `true` if the name is `local` in the XML Schema namespace.
+    pub(crate) fn is_xsd(&self, local: &str) -> bool {
+        self.namespace() == Some(XSD_NAMESPACE) && self.local == local
+    }
+}
+
+impl Display for QName {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        match &self.namespace {
+            Some(namespace) => write!(f, "{{{}}}{}", namespace, self.local),
+            None => write!(f, "{}", self.local),
+        }
+    }
+}
+
+impl FromStr for QName {
+    type Err = String;
+
+    /// Parses a name in the `{namespace}local` notation, or a local name
+    /// in no namespace.
+    fn from_str(name: &str) -> Result<QName, String> {
+        match name.strip_prefix('{') {
+            Some(rest) => match rest.split_once('}') {
+                Some((namespace, local)) if !local.is_empty() => {
+                    Ok(QName::new(Some(namespace), local))
+                }
+                _ => Err(format!("Invalid expanded name: {}", name)),
+            },
+            None if !name.is_empty() => Ok(QName::new(None, name)),
+            None => Err("Invalid expanded name: empty name".to_string()),
+        }
+    }
+}
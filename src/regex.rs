@@ -0,0 +1,574 @@
+//! The regular expressions of the `pattern` facet.
+//!
+//! XML Schema defines its own dialect of regular expressions: a pattern
+//! always matches the whole value, `^` and `$` are ordinary characters,
+//! and character classes support the `\i` and `\c` escapes of XML names,
+//! Unicode categories and blocks with `\p{..}`, and subtraction, as in
+//! `[a-z-[aeiou]]`. Patterns are compiled into a program for a
+//! Thompson-style automaton, so matching takes a time linear in the
+//! length of the value.
+//!
+
+use std::fmt::{Display, Error, Formatter};
+
+use crate::{
+    schema::XsdVersion,
+    tokenizer::{is_name_char, is_name_start_char},
+    unicode::{block, Category},
+};
+
+/// The largest number of instructions a compiled pattern may have, which
+/// bounds the expansion of counted repetitions such as `a{1,1000}`.
+const MAX_PROGRAM_SIZE: usize = 100_000;
+
+/// A compiled XML Schema regular expression.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{Regex, XsdVersion};
+///
+/// match Regex::new(r"[A-Z]{2}-\d+", XsdVersion::V1_0) {
+///     Ok(regex) => {
+///         assert!(regex.is_match("AB-123"));
+///         assert!(!regex.is_match("xAB-123"));
+///     }
+///     Err(error) => eprintln!("Invalid pattern: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone)]
+pub struct Regex {
+    pattern: String,
+    program: Vec<Instruction>,
+    sets: Vec<CharSet>,
+}
+
+impl Regex {
+    /// Compiles the regular expression `pattern`.
+    ///
+    /// This function parses the pattern with the syntax of the version
+    /// `version` of XML Schema, which decides the Unicode block names
+    /// understood by `\p{Is..}` escapes, and returns an error describing
+    /// the first syntax error found otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The regular expression.
+    /// * `version` - The version of XML Schema the pattern is written
+    ///   for.
+    ///
+    pub fn new(pattern: &str, version: XsdVersion) -> Result<Regex, String> {
+        let mut parser = RegexParser {
+            chars: pattern.chars().collect(),
+            position: 0,
+            version,
+        };
+        let node = parser.parse_regex()?;
+        if let Some(ch) = parser.peek() {
+            return Err(parser.error(&format!("unexpected '{}'", ch)));
+        }
+        let mut compiler = Compiler {
+            program: Vec::new(),
+            sets: Vec::new(),
+        };
+        compiler.compile(&node)?;
+        compiler.push(Instruction::Match)?;
+        Ok(Regex {
+            pattern: pattern.to_string(),
+            program: compiler.program,
+            sets: compiler.sets,
+        })
+    }
+
+    /// Returns the pattern the regular expression was compiled from.
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Returns `true` if the regular expression matches the whole of
+    /// `text`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to match.
+    ///
+    pub fn is_match(&self, text: &str) -> bool {
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        let mut visited = vec![usize::MAX; self.program.len()];
+        self.add_state(&mut current, &mut visited, 0, 0);
+        for (step, ch) in text.chars().enumerate() {
+            for &pc in &current {
+                if let Instruction::Set(set) = self.program[pc] {
+                    if self.sets[set].contains(ch) {
+                        self.add_state(&mut next, &mut visited, step + 1, pc + 1);
+                    }
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+            if current.is_empty() {
+                return false;
+            }
+        }
+        current
+            .iter()
+            .any(|&pc| matches!(self.program[pc], Instruction::Match))
+    }
+
+    /// Adds the instruction `pc` to `states`, following jumps and splits,
+    /// unless it was already added for the current `step`.
+    fn add_state(&self, states: &mut Vec<usize>, visited: &mut [usize], step: usize, pc: usize) {
+        if visited[pc] == step {
+            return;
+        }
+        visited[pc] = step;
+        match self.program[pc] {
+            Instruction::Jump(target) => self.add_state(states, visited, step, target),
+            Instruction::Split(first, second) => {
+                self.add_state(states, visited, step, first);
+                self.add_state(states, visited, step, second);
+            }
+            Instruction::Set(_) | Instruction::Match => states.push(pc),
+        }
+    }
+}
+
+impl PartialEq for Regex {
+    fn eq(&self, other: &Regex) -> bool {
+        self.pattern == other.pattern
+    }
+}
+
+impl Eq for Regex {}
+
+impl Display for Regex {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+/// An instruction of a compiled pattern.
+#[derive(Debug, Clone, Copy)]
+enum Instruction {
+    /// Consumes a character of the set with the given index
+    Set(usize),
+    /// Continues at both instructions
+    Split(usize, usize),
+    /// Continues at the instruction
+    Jump(usize),
+    /// The whole value matched
+    Match,
+}
+
+/// A set of characters, as matched by a character class.
+#[derive(Debug, Clone)]
+enum CharSet {
+    /// The characters of the ranges
+    Ranges(Vec<(char, char)>),
+    /// The characters of the general categories
+    Categories(Vec<Category>),
+    /// The characters that can start an XML name, matched by `\i`
+    NameStart,
+    /// The characters of XML names, matched by `\c`
+    Name,
+    /// The characters of any of the sets
+    Union(Vec<CharSet>),
+    /// The characters not in the set
+    Not(Box<CharSet>),
+    /// The characters of the first set that are not in the second
+    Subtract(Box<CharSet>, Box<CharSet>),
+}
+
+impl CharSet {
+    fn contains(&self, ch: char) -> bool {
+        match self {
+            CharSet::Ranges(ranges) => ranges.iter().any(|(start, end)| *start <= ch && ch <= *end),
+            CharSet::Categories(categories) => categories.contains(&Category::of(ch)),
+            CharSet::NameStart => is_name_start_char(ch),
+            CharSet::Name => is_name_char(ch),
+            CharSet::Union(sets) => sets.iter().any(|set| set.contains(ch)),
+            CharSet::Not(set) => !set.contains(ch),
+            CharSet::Subtract(set, excluded) => set.contains(ch) && !excluded.contains(ch),
+        }
+    }
+
+    fn single(ch: char) -> CharSet {
+        CharSet::Ranges(vec![(ch, ch)])
+    }
+}
+
+/// A node of the syntax tree of a pattern.
+#[derive(Debug)]
+enum Node {
+    /// Matches one character of the set
+    Set(CharSet),
+    /// Matches the nodes one after the other
+    Concat(Vec<Node>),
+    /// Matches any of the nodes
+    Alternate(Vec<Node>),
+    /// Matches the node at least `min` times, and at most `max` times
+    /// if bounded
+    Repeat {
+        node: Box<Node>,
+        min: u32,
+        max: Option<u32>,
+    },
+}
+
+/// Parses a pattern into its syntax tree.
+struct RegexParser {
+    chars: Vec<char>,
+    position: usize,
+    version: XsdVersion,
+}
+
+impl RegexParser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.position += 1;
+        Some(ch)
+    }
+
+    fn error(&self, message: &str) -> String {
+        format!("{} at position {}", message, self.position)
+    }
+
+    /// Parses `regExp ::= branch ( '|' branch )*`.
+    fn parse_regex(&mut self) -> Result<Node, String> {
+        let mut branches = vec![self.parse_branch()?];
+        while self.peek() == Some('|') {
+            self.position += 1;
+            branches.push(self.parse_branch()?);
+        }
+        Ok(match branches.len() {
+            1 => branches.remove(0),
+            _ => Node::Alternate(branches),
+        })
+    }
+
+    /// Parses `branch ::= piece*`.
+    fn parse_branch(&mut self) -> Result<Node, String> {
+        let mut pieces = Vec::new();
+        while let Some(ch) = self.peek() {
+            if ch == '|' || ch == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            pieces.push(self.parse_quantifier(atom)?);
+        }
+        Ok(Node::Concat(pieces))
+    }
+
+    /// Parses `atom ::= NormalChar | charClass | '(' regExp ')'`.
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        let ch = self.next().unwrap_or_default();
+        match ch {
+            '(' => {
+                let node = self.parse_regex()?;
+                if self.next() != Some(')') {
+                    return Err(self.error("missing ')'"));
+                }
+                Ok(node)
+            }
+            '[' => Ok(Node::Set(self.parse_class_expr()?)),
+            '.' => Ok(Node::Set(CharSet::Not(Box::new(CharSet::Ranges(vec![
+                ('\n', '\n'),
+                ('\r', '\r'),
+            ]))))),
+            '\\' => Ok(Node::Set(self.parse_escape()?)),
+            '?' | '*' | '+' | '{' => Err(self.error(&format!("quantifier '{}' without atom", ch))),
+            '}' | ']' => Err(self.error(&format!("unescaped '{}'", ch))),
+            ch => Ok(Node::Set(CharSet::single(ch))),
+        }
+    }
+
+    /// Parses an optional quantifier applying to `atom`.
+    fn parse_quantifier(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('?') => (0, Some(1)),
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('{') => {
+                self.position += 1;
+                let min = self.parse_number()?;
+                let max = if self.peek() == Some(',') {
+                    self.position += 1;
+                    match self.peek() {
+                        Some('}') => None,
+                        _ => Some(self.parse_number()?),
+                    }
+                } else {
+                    Some(min)
+                };
+                if self.peek() != Some('}') {
+                    return Err(self.error("missing '}' in quantifier"));
+                }
+                if max.map_or(false, |max| max < min) {
+                    return Err(self.error("the maximum of a quantifier is less than its minimum"));
+                }
+                (min, max)
+            }
+            _ => return Ok(atom),
+        };
+        self.position += 1;
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    fn parse_number(&mut self) -> Result<u32, String> {
+        let start = self.position;
+        while self.peek().map_or(false, |ch| ch.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let digits: String = self.chars[start..self.position].iter().collect();
+        digits
+            .parse()
+            .map_err(|_| self.error("expected a number in quantifier"))
+    }
+
+    /// Parses a character class expression, after its opening `[`:
+    /// a positive or negative group, optionally followed by a
+    /// subtraction, then the closing `]`.
+    fn parse_class_expr(&mut self) -> Result<CharSet, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.position += 1;
+        }
+        let mut items = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("missing ']'")),
+                Some(']') if !items.is_empty() => {
+                    self.position += 1;
+                    break;
+                }
+                Some('-')
+                    if !items.is_empty() && self.chars.get(self.position + 1) == Some(&'[') =>
+                {
+                    self.position += 2;
+                    let excluded = self.parse_class_expr()?;
+                    if self.next() != Some(']') {
+                        return Err(self.error("a subtraction must end the character class"));
+                    }
+                    let set = group(items, negated);
+                    return Ok(CharSet::Subtract(Box::new(set), Box::new(excluded)));
+                }
+                Some(_) => items.push(self.parse_class_item(items.is_empty())?),
+            }
+        }
+        Ok(group(items, negated))
+    }
+
+    /// Parses a range, a single character or an escape of a character
+    /// class.
+    fn parse_class_item(&mut self, first: bool) -> Result<CharSet, String> {
+        let start = match self.next().unwrap_or_default() {
+            '\\' => match self.parse_escape()? {
+                CharSet::Ranges(ranges) if ranges.len() == 1 && ranges[0].0 == ranges[0].1 => {
+                    ranges[0].0
+                }
+                set => return Ok(set),
+            },
+            '[' => return Err(self.error("unescaped '[' in character class")),
+            '-' if !first && !matches!(self.peek(), Some(']') | None) => {
+                return Err(self.error("unescaped '-' in character class"))
+            }
+            ch => ch,
+        };
+        let is_range = self.peek() == Some('-')
+            && !matches!(self.chars.get(self.position + 1), Some('[' | ']') | None);
+        if !is_range {
+            return Ok(CharSet::single(start));
+        }
+        self.position += 1;
+        let end = match self.next().unwrap_or_default() {
+            '\\' => match self.parse_escape()? {
+                CharSet::Ranges(ranges) if ranges.len() == 1 && ranges[0].0 == ranges[0].1 => {
+                    ranges[0].0
+                }
+                _ => return Err(self.error("a range cannot end with a multi-character escape")),
+            },
+            '[' => return Err(self.error("unescaped '[' in character class")),
+            ch => ch,
+        };
+        if end < start {
+            return Err(self.error(&format!("invalid range '{}-{}'", start, end)));
+        }
+        Ok(CharSet::Ranges(vec![(start, end)]))
+    }
+
+    /// Parses an escape, after its backslash.
+    fn parse_escape(&mut self) -> Result<CharSet, String> {
+        let ch = self.next().ok_or_else(|| self.error("incomplete escape"))?;
+        let set = match ch {
+            'n' => CharSet::single('\n'),
+            'r' => CharSet::single('\r'),
+            't' => CharSet::single('\t'),
+            '\\' | '|' | '.' | '?' | '*' | '+' | '(' | ')' | '{' | '}' | '-' | '[' | ']' | '^' => {
+                CharSet::single(ch)
+            }
+            's' | 'S' => negate(
+                CharSet::Ranges(vec![(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')]),
+                ch == 'S',
+            ),
+            'i' | 'I' => negate(CharSet::NameStart, ch == 'I'),
+            'c' | 'C' => negate(CharSet::Name, ch == 'C'),
+            'd' | 'D' => negate(CharSet::Categories(vec![Category::Nd]), ch == 'D'),
+            'w' | 'W' => {
+                let mut excluded = Vec::new();
+                for major in ["P", "Z", "C"] {
+                    excluded.extend(Category::named(major).unwrap_or_default());
+                }
+                negate(
+                    CharSet::Not(Box::new(CharSet::Categories(excluded))),
+                    ch == 'W',
+                )
+            }
+            'p' | 'P' => negate(self.parse_property()?, ch == 'P'),
+            ch => return Err(self.error(&format!("unknown escape '\\{}'", ch))),
+        };
+        Ok(set)
+    }
+
+    /// Parses the `{..}` of a `\p` or `\P` escape: a general category or
+    /// a Unicode block.
+    fn parse_property(&mut self) -> Result<CharSet, String> {
+        if self.next() != Some('{') {
+            return Err(self.error("expected '{' after '\\p'"));
+        }
+        let start = self.position;
+        while self.peek().map_or(false, |ch| ch != '}') {
+            self.position += 1;
+        }
+        if self.next() != Some('}') {
+            return Err(self.error("missing '}' in property escape"));
+        }
+        let name: String = self.chars[start..self.position - 1].iter().collect();
+        if let Some(block_name) = name.strip_prefix("Is") {
+            let ranges = block(block_name, self.version);
+            if ranges.is_empty() {
+                return Err(self.error(&format!("unknown block name '{}'", name)));
+            }
+            return Ok(CharSet::Ranges(
+                ranges
+                    .into_iter()
+                    .filter_map(|(start, end)| {
+                        // Surrogate blocks hold no characters
+                        Some((char::from_u32(start)?, char::from_u32(end)?))
+                    })
+                    .collect(),
+            ));
+        }
+        Category::named(&name)
+            .map(CharSet::Categories)
+            .ok_or_else(|| self.error(&format!("unknown category '{}'", name)))
+    }
+}
+
+/// Returns the set of the characters of `items`, or of the characters
+/// not in them if `negated`.
+fn group(mut items: Vec<CharSet>, negated: bool) -> CharSet {
+    let set = match items.len() {
+        1 => items.remove(0),
+        _ => CharSet::Union(items),
+    };
+    negate(set, negated)
+}
+
+fn negate(set: CharSet, negated: bool) -> CharSet {
+    if negated {
+        CharSet::Not(Box::new(set))
+    } else {
+        set
+    }
+}
+
+/// Compiles a syntax tree into a program.
+struct Compiler {
+    program: Vec<Instruction>,
+    sets: Vec<CharSet>,
+}
+
+impl Compiler {
+    fn push(&mut self, instruction: Instruction) -> Result<usize, String> {
+        if self.program.len() >= MAX_PROGRAM_SIZE {
+            return Err("the pattern is too large once its repetitions are expanded".to_string());
+        }
+        self.program.push(instruction);
+        Ok(self.program.len() - 1)
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Set(set) => {
+                self.sets.push(set.clone());
+                self.push(Instruction::Set(self.sets.len() - 1))?;
+            }
+            Node::Concat(nodes) => {
+                for node in nodes {
+                    self.compile(node)?;
+                }
+            }
+            Node::Alternate(nodes) => {
+                let mut jumps = Vec::new();
+                for (index, node) in nodes.iter().enumerate() {
+                    if index + 1 < nodes.len() {
+                        let split = self.push(Instruction::Split(0, 0))?;
+                        self.compile(node)?;
+                        jumps.push(self.push(Instruction::Jump(0))?);
+                        self.program[split] = Instruction::Split(split + 1, self.program.len());
+                    } else {
+                        self.compile(node)?;
+                    }
+                }
+                for jump in jumps {
+                    self.program[jump] = Instruction::Jump(self.program.len());
+                }
+            }
+            Node::Repeat { node, min, max } => {
+                let (start, sets) = (self.program.len(), self.sets.len());
+                self.compile(node)?;
+                if self.program.len() == start {
+                    // The node matches the empty string only
+                    return Ok(());
+                }
+                if *min == 0 {
+                    self.program.truncate(start);
+                    self.sets.truncate(sets);
+                }
+                for _ in 1..*min {
+                    self.compile(node)?;
+                }
+                match max {
+                    None => {
+                        // node* : split to the node or past the loop
+                        let split = self.push(Instruction::Split(0, 0))?;
+                        self.compile(node)?;
+                        self.push(Instruction::Jump(split))?;
+                        self.program[split] = Instruction::Split(split + 1, self.program.len());
+                    }
+                    Some(max) => {
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.push(Instruction::Split(0, 0))?);
+                            self.compile(node)?;
+                        }
+                        for split in splits {
+                            self.program[split] = Instruction::Split(split + 1, self.program.len());
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,405 @@
+//! Resolving the locations of schema documents.
+//!
+//! The schema documents a schema includes, imports, redefines or
+//! overrides are read through a [`SchemaResolver`], which turns the
+//! `schemaLocation` of a reference, resolved against the URI of the
+//! referencing document, into the content of the document. The crate
+//! provides resolvers reading from the filesystem, optionally sandboxed
+//! to a root directory, from an in-memory map, and from documents
+//! embedded in the program with `include_str!`. None of them accesses
+//! the network.
+//!
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Debug, Formatter},
+    fs,
+    io::{self, ErrorKind},
+    path::PathBuf,
+};
+
+/// A source of schema documents.
+///
+/// A resolver is asked for each schema document referenced while a
+/// schema is loaded. It returns the content of the document with the URI
+/// it is read from, against which the references of the document are
+/// resolved in turn. Failures are reported as `schema_reference.4`
+/// violations, except for `xs:import`, whose location is only a hint.
+///
+/// # Examples
+///
+/// ```
+/// use std::{io, str::FromStr};
+/// use xmlschema::{Schema, SchemaResolver, SchemaSource};
+///
+/// /// Serves every schema location from the same document.
+/// struct Fixed;
+///
+/// impl SchemaResolver for Fixed {
+///     fn resolve(
+///         &self,
+///         _base_uri: Option<&str>,
+///         location: Option<&str>,
+///         _namespace: Option<&str>,
+///     ) -> io::Result<SchemaSource> {
+///         let content = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///             <xs:element name="note" type="xs:string"/>
+///         </xs:schema>"#;
+///         Ok(SchemaSource::new(location.unwrap_or("fixed.xsd"), content))
+///     }
+/// }
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:include schemaLocation="note.xsd"/>
+/// </xs:schema>"#;
+/// match Schema::from_str_with_resolver(input, None, &Fixed) {
+///     Ok(schema) => assert!(schema.element("note").is_some()),
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+pub trait SchemaResolver {
+    /// Returns the schema document referenced by `location`, or by
+    /// `namespace` for an `xs:import` with no `schemaLocation`.
+    ///
+    /// # Arguments
+    ///
+    /// * `base_uri` - The URI of the referencing document, against which
+    ///   a relative `location` is resolved, if known.
+    /// * `location` - The `schemaLocation` of the reference, if any.
+    /// * `namespace` - The namespace of an `xs:import`, if any.
+    ///
+    fn resolve(
+        &self,
+        base_uri: Option<&str>,
+        location: Option<&str>,
+        namespace: Option<&str>,
+    ) -> io::Result<SchemaSource>;
+}
+
+/// A schema document returned by a [`SchemaResolver`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaSource {
+    uri: String,
+    content: Vec<u8>,
+}
+
+impl SchemaSource {
+    /// Creates a schema document.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI the document is read from, against which its
+    ///   own references are resolved.
+    /// * `content` - The content of the document, as UTF-8 text.
+    ///
+    pub fn new(uri: impl Into<String>, content: impl Into<Vec<u8>>) -> Self {
+        Self {
+            uri: uri.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Returns the URI the document is read from.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Returns the content of the document.
+    pub fn content(&self) -> &[u8] {
+        &self.content
+    }
+}
+
+/// A resolver reading schema documents from the filesystem.
+///
+/// Locations are file paths or `file:` URIs. Any other URI, such as an
+/// `http:` URL, is refused with an error of the kind
+/// [`ErrorKind::PermissionDenied`]. A resolver created with
+/// [`FileResolver::new`] only reads files inside its root directory, and
+/// resolves relative locations with no base URI against it; the default
+/// resolver reads any file, and resolves them against the current
+/// directory. The default resolver is the one used by
+/// [`Schema::from_file`](crate::Schema::from_file) and
+/// [`Schema::from_str`](std::str::FromStr::from_str).
+///
+/// # Examples
+///
+/// ```no_run
+/// use xmlschema::{FileResolver, Schema};
+///
+/// let resolver = FileResolver::new("schemas");
+/// match Schema::from_location("order.xsd", &resolver) {
+///     Ok(schema) => {
+///         // Do something with the loaded schema
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct FileResolver {
+    root: Option<PathBuf>,
+}
+
+impl FileResolver {
+    /// Creates a resolver reading only the files inside the directory
+    /// `root`.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The directory the files read must be in.
+    ///
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: Some(root.into()),
+        }
+    }
+}
+
+impl SchemaResolver for FileResolver {
+    fn resolve(
+        &self,
+        base_uri: Option<&str>,
+        location: Option<&str>,
+        _namespace: Option<&str>,
+    ) -> io::Result<SchemaSource> {
+        let location = location
+            .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "No schema location given"))?;
+        let uri = resolve_uri(base_uri, location);
+        let path = match uri
+            .strip_prefix("file://")
+            .or_else(|| uri.strip_prefix("file:"))
+        {
+            Some(path) => path,
+            None if has_scheme(&uri) => {
+                return Err(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!("Network access is disabled: refusing '{}'", uri),
+                ))
+            }
+            None => &uri,
+        };
+        let mut path = PathBuf::from(path);
+        if let Some(root) = &self.root {
+            if path.is_relative() && base_uri.is_none() {
+                path = root.join(path);
+            }
+            if !path.canonicalize()?.starts_with(root.canonicalize()?) {
+                return Err(io::Error::new(
+                    ErrorKind::PermissionDenied,
+                    format!(
+                        "'{}' is outside the root directory '{}'",
+                        path.display(),
+                        root.display()
+                    ),
+                ));
+            }
+        }
+        let content = fs::read(&path)?;
+        Ok(SchemaSource::new(path.to_string_lossy(), content))
+    }
+}
+
+/// A resolver serving schema documents from an in-memory map.
+///
+/// Documents are looked up by the location of the reference resolved
+/// against the base URI, then by the location as written, then, for an
+/// `xs:import` with no `schemaLocation`, by the imported namespace.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{MemoryResolver, Schema};
+///
+/// let mut resolver = MemoryResolver::new();
+/// resolver.insert(
+///     "main.xsd",
+///     r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///         <xs:include schemaLocation="types/code.xsd"/>
+///         <xs:element name="code" type="code"/>
+///     </xs:schema>"#,
+/// );
+/// resolver.insert(
+///     "types/code.xsd",
+///     r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///         <xs:simpleType name="code">
+///             <xs:restriction base="xs:string">
+///                 <xs:pattern value="[A-Z]{3}"/>
+///             </xs:restriction>
+///         </xs:simpleType>
+///     </xs:schema>"#,
+/// );
+/// match Schema::from_location("main.xsd", &resolver) {
+///     Ok(schema) => {
+///         assert!(schema.validate("<code>ABC</code>").is_ok());
+///         assert!(schema.validate("<code>abc</code>").is_err());
+///     }
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Debug, Clone, Default)]
+pub struct MemoryResolver {
+    documents: HashMap<String, Vec<u8>>,
+}
+
+impl MemoryResolver {
+    /// Creates a resolver with no documents.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the document `content` at `uri`, replacing any document
+    /// already there.
+    ///
+    /// # Arguments
+    ///
+    /// * `uri` - The URI of the document, or, for a document imported
+    ///   with no `schemaLocation`, its target namespace.
+    /// * `content` - The content of the document, as UTF-8 text.
+    ///
+    pub fn insert(&mut self, uri: impl Into<String>, content: impl Into<Vec<u8>>) {
+        self.documents.insert(uri.into(), content.into());
+    }
+}
+
+impl SchemaResolver for MemoryResolver {
+    fn resolve(
+        &self,
+        base_uri: Option<&str>,
+        location: Option<&str>,
+        namespace: Option<&str>,
+    ) -> io::Result<SchemaSource> {
+        let (uri, content) = lookup(base_uri, location, namespace, |uri| {
+            self.documents.get(uri).map(Vec::as_slice)
+        })?;
+        Ok(SchemaSource::new(uri, content))
+    }
+}
+
+/// A resolver serving schema documents embedded in the program.
+///
+/// The documents are static strings, typically read at compile time
+/// with `include_str!`, so that no file needs to be shipped with the
+/// program. They are looked up like those of a [`MemoryResolver`].
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{EmbeddedResolver, Schema};
+///
+/// // Typically include_str!("schemas/note.xsd")
+/// static NOTE_XSD: &str = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:element name="note" type="xs:string"/>
+/// </xs:schema>"#;
+/// static RESOLVER: EmbeddedResolver = EmbeddedResolver::new(&[("note.xsd", NOTE_XSD)]);
+///
+/// match Schema::from_location("note.xsd", &RESOLVER) {
+///     Ok(schema) => assert!(schema.validate("<note>Hello</note>").is_ok()),
+///     Err(error) => eprintln!("Error loading schema: {}", error),
+/// }
+/// ```
+///
+#[derive(Clone, Copy)]
+pub struct EmbeddedResolver {
+    documents: &'static [(&'static str, &'static str)],
+}
+
+impl EmbeddedResolver {
+    /// Creates a resolver serving the documents `documents`.
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - The URI and content of each document.
+    ///
+    pub const fn new(documents: &'static [(&'static str, &'static str)]) -> Self {
+        Self { documents }
+    }
+}
+
+impl Debug for EmbeddedResolver {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.documents.iter().map(|(uri, _)| uri))
+            .finish()
+    }
+}
+
+impl SchemaResolver for EmbeddedResolver {
+    fn resolve(
+        &self,
+        base_uri: Option<&str>,
+        location: Option<&str>,
+        namespace: Option<&str>,
+    ) -> io::Result<SchemaSource> {
+        let (uri, content) = lookup(base_uri, location, namespace, |uri| {
+            self.documents
+                .iter()
+                .find(|(key, _)| *key == uri)
+                .map(|(_, content)| content.as_bytes())
+        })?;
+        Ok(SchemaSource::new(uri, content))
+    }
+}
+
+/// Looks up a document with `get` by the location of a reference
+/// resolved against `base_uri`, then by the location as written, then by
+/// `namespace` if there is no location, and returns the key it was found
+/// at with its content.
+fn lookup<'d>(
+    base_uri: Option<&str>,
+    location: Option<&str>,
+    namespace: Option<&str>,
+    get: impl Fn(&str) -> Option<&'d [u8]>,
+) -> io::Result<(String, &'d [u8])> {
+    let keys = match location {
+        Some(location) => vec![resolve_uri(base_uri, location), location.to_string()],
+        None => namespace.map(str::to_string).into_iter().collect(),
+    };
+    keys.into_iter()
+        .find_map(|key| get(&key).map(|content| (key, content)))
+        .ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::NotFound,
+                format!("No document for '{}'", location.or(namespace).unwrap_or("")),
+            )
+        })
+}
+
+/// Resolves the location `location` against the URI `base` of the
+/// referencing document, and removes its `.` and `..` segments.
+pub(crate) fn resolve_uri(base: Option<&str>, location: &str) -> String {
+    let is_absolute = location.starts_with('/') || has_scheme(location);
+    let directory = base.and_then(|base| base.rfind(['/', '\\']).map(|end| &base[..=end]));
+    let location = match directory {
+        Some(directory) if !is_absolute => format!("{}{}", directory, location),
+        _ => location.to_string(),
+    };
+    let mut segments: Vec<&str> = Vec::new();
+    for segment in location.split('/') {
+        match segment {
+            "." => {}
+            ".." if matches!(segments.last(), Some(last) if !last.is_empty() && *last != "..") => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+/// Returns `true` if `location` starts with a URI scheme, such as
+/// `http:` or `file:`. Single letters are taken to be drive letters.
+fn has_scheme(location: &str) -> bool {
+    match location.find(':') {
+        Some(end) if end > 1 => {
+            let scheme = &location[..end];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        _ => false,
+    }
+}
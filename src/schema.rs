@@ -0,0 +1,757 @@
+//! The public entry point for loading schemas.
+
+use std::{fs, io::Read, path::Path, str::FromStr};
+
+use crate::{
+    components::{
+        Attribute, AttributeGroup, ComplexType, Element, Group, Notation, SimpleType, Term,
+    },
+    document::XmlElement,
+    error::SchemaError,
+    graph::SchemaGraph,
+    loader::SchemaLoader,
+    parser::{XmlSchema, XmlSchemaNode},
+    qname::QName,
+    resolver::{FileResolver, SchemaResolver},
+    validator::{schema_locations, Validator},
+};
+
+/// A version of the XML Schema specification.
+///
+/// The version decides which features and syntax a schema may use, such
+/// as the Unicode block names understood by the regular expressions of
+/// `pattern` facets. Schemas are read as XSD 1.0 documents unless loaded
+/// with [`Schema::from_str_with_version`] or
+/// [`Schema::from_location_with_version`]; XSD 1.1 documents can also
+/// declare assertions, checked with an XPath 2.0 evaluator, and override
+/// other documents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum XsdVersion {
+    /// XML Schema 1.0
+    #[default]
+    V1_0,
+    /// XML Schema 1.1
+    V1_1,
+}
+
+/// A loaded XML Schema.
+///
+/// A `Schema` is created from the text of an XSD document with
+/// [`Schema::from_str`], [`Schema::from_file`] or [`Schema::from_reader`],
+/// and gives read-only access to the global element declarations,
+/// attribute declarations, simple types, complex types, model groups and
+/// attribute groups of the schema.
+///
+/// The documents a schema document includes, imports, redefines or
+/// overrides are read by a [`SchemaResolver`] from the locations given by
+/// their `schemaLocation` attributes, relative to the location of the
+/// referencing document, and their components are gathered with its own.
+/// A document that cannot be read is reported as a `schema_reference.4`
+/// error, except for imports, whose locations are only hints. Errors
+/// found in a document other than the one loaded carry its location,
+/// returned by [`SchemaError::document`].
+///
+/// Schema documents that do not conform to the schema for schemas are
+/// rejected before any component is built.
+///
+/// # Examples
+///
+/// ```
+/// use std::str::FromStr;
+/// use xmlschema::Schema;
+///
+/// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+///     <xs:element type="xs:string"/>
+/// </xs:schema>"#;
+/// let error = Schema::from_str(input).unwrap_err();
+/// assert_eq!(error.code(), Some("s4s-att-must-appear"));
+/// ```
+///
+#[derive(Debug)]
+pub struct Schema {
+    schema: XmlSchema,
+}
+
+impl Schema {
+    /// Loads a schema from the XSD document stored at `path`.
+    ///
+    /// The documents it references are read from the filesystem, relative
+    /// to `path`, by the default [`FileResolver`], which refuses network
+    /// locations.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the XSD file to load.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use xmlschema::Schema;
+    ///
+    /// match Schema::from_file("schema.xsd") {
+    ///     Ok(schema) => {
+    ///         // Do something with the loaded schema
+    ///     }
+    ///     Err(error) => {
+    ///         eprintln!("Error loading schema: {}", error);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Schema, SchemaError> {
+        let path = path.as_ref();
+        let input = fs::read_to_string(path).map_err(|err| SchemaError::Io {
+            message: format!("Cannot read {}: {}", path.display(), err),
+        })?;
+        let schema = SchemaLoader::new(&FileResolver::default(), XsdVersion::default())
+            .load(&input, Some(&path.to_string_lossy()))?;
+        Ok(Schema { schema })
+    }
+
+    /// Loads a schema from an XSD document read from `reader`.
+    ///
+    /// The reader is consumed to its end and must yield UTF-8 text.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Any type implementing [`std::io::Read`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xmlschema::Schema;
+    ///
+    /// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"/>"#;
+    /// let result = Schema::from_reader(input.as_bytes());
+    /// match result {
+    ///     Ok(schema) => {
+    ///         // Do something with the loaded schema
+    ///     }
+    ///     Err(error) => {
+    ///         eprintln!("Error loading schema: {}", error);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Schema, SchemaError> {
+        let mut input = String::new();
+        reader
+            .read_to_string(&mut input)
+            .map_err(|err| SchemaError::Io {
+                message: format!("Cannot read schema: {}", err),
+            })?;
+        Schema::from_str(&input)
+    }
+
+    /// Returns the target namespace of the schema, if any.
+    pub fn target_namespace(&self) -> Option<&str> {
+        self.schema.target_namespace.as_deref()
+    }
+
+    /// Returns the version of XML Schema the schema documents were read
+    /// as.
+    pub fn version(&self) -> XsdVersion {
+        self.schema.version
+    }
+
+    /// Loads a schema from the XSD document at `location`, reading it and
+    /// the documents it references with `resolver`.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - The location of the XSD document, as understood by
+    ///   the resolver.
+    /// * `resolver` - The source of the schema documents.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use xmlschema::{FileResolver, Schema};
+    ///
+    /// // Only read schema documents from the schemas directory
+    /// let resolver = FileResolver::new("schemas");
+    /// match Schema::from_location("order.xsd", &resolver) {
+    ///     Ok(schema) => {
+    ///         // Do something with the loaded schema
+    ///     }
+    ///     Err(error) => {
+    ///         eprintln!("Error loading schema: {}", error);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn from_location(
+        location: &str,
+        resolver: &dyn SchemaResolver,
+    ) -> Result<Schema, SchemaError> {
+        Schema::from_location_with_version(location, resolver, XsdVersion::default())
+    }
+
+    /// Loads a schema from the XSD document at `location`, reading it and
+    /// the documents it references with `resolver`, as documents of the
+    /// XML Schema version `version`.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - The location of the XSD document, as understood by
+    ///   the resolver.
+    /// * `resolver` - The source of the schema documents.
+    /// * `version` - The version of XML Schema to read the documents as.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use xmlschema::{FileResolver, Schema, XsdVersion};
+    ///
+    /// let resolver = FileResolver::new("schemas");
+    /// match Schema::from_location_with_version("order.xsd", &resolver, XsdVersion::V1_1) {
+    ///     Ok(schema) => {
+    ///         // Do something with the loaded schema
+    ///     }
+    ///     Err(error) => {
+    ///         eprintln!("Error loading schema: {}", error);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn from_location_with_version(
+        location: &str,
+        resolver: &dyn SchemaResolver,
+        version: XsdVersion,
+    ) -> Result<Schema, SchemaError> {
+        let source = resolver
+            .resolve(None, Some(location), None)
+            .map_err(|err| SchemaError::Io {
+                message: format!("Cannot read {}: {}", location, err),
+            })?;
+        let input = std::str::from_utf8(source.content()).map_err(|err| SchemaError::Io {
+            message: format!("Cannot read {}: {}", source.uri(), err),
+        })?;
+        let schema = SchemaLoader::new(resolver, version).load(input, Some(source.uri()))?;
+        Ok(Schema { schema })
+    }
+
+    /// Loads a schema from the text of an XSD document, reading the
+    /// documents it references with `resolver`.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text of the XSD document.
+    /// * `base_uri` - The URI of the document, against which the
+    ///   locations of the documents it references are resolved, if known.
+    /// * `resolver` - The source of the referenced schema documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xmlschema::{MemoryResolver, Schema};
+    ///
+    /// let mut resolver = MemoryResolver::new();
+    /// resolver.insert(
+    ///     "urn:example:note",
+    ///     r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+    ///                   targetNamespace="urn:example:note">
+    ///         <xs:element name="note" type="xs:string"/>
+    ///     </xs:schema>"#,
+    /// );
+    /// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+    ///                           xmlns:n="urn:example:note">
+    ///     <xs:import namespace="urn:example:note"/>
+    ///     <xs:element name="notes">
+    ///         <xs:complexType>
+    ///             <xs:sequence>
+    ///                 <xs:element ref="n:note" maxOccurs="unbounded"/>
+    ///             </xs:sequence>
+    ///         </xs:complexType>
+    ///     </xs:element>
+    /// </xs:schema>"#;
+    /// match Schema::from_str_with_resolver(input, None, &resolver) {
+    ///     Ok(schema) => {
+    ///         let valid = r#"<notes xmlns:n="urn:example:note"><n:note>Hi</n:note></notes>"#;
+    ///         assert!(schema.validate(valid).is_ok());
+    ///     }
+    ///     Err(error) => {
+    ///         eprintln!("Error loading schema: {}", error);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn from_str_with_resolver(
+        input: &str,
+        base_uri: Option<&str>,
+        resolver: &dyn SchemaResolver,
+    ) -> Result<Schema, SchemaError> {
+        let schema = SchemaLoader::new(resolver, XsdVersion::default()).load(input, base_uri)?;
+        Ok(Schema { schema })
+    }
+
+    /// Loads a schema from the text of an XSD document, reading it and
+    /// the documents it references as documents of the XML Schema
+    /// version `version`.
+    ///
+    /// XSD 1.1 documents can declare `xs:assert` constraints on complex
+    /// types and `xs:assertion` facets on simple types, whose XPath 2.0
+    /// tests every valid instance satisfies.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The text of the XSD document.
+    /// * `version` - The version of XML Schema to read the documents as.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use xmlschema::{Schema, XsdVersion};
+    ///
+    /// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+    ///     <xs:simpleType name="even">
+    ///         <xs:restriction base="xs:integer">
+    ///             <xs:assertion test="$value mod 2 = 0"/>
+    ///         </xs:restriction>
+    ///     </xs:simpleType>
+    ///     <xs:element name="count" type="even"/>
+    /// </xs:schema>"#;
+    /// match Schema::from_str_with_version(input, XsdVersion::V1_1) {
+    ///     Ok(schema) => {
+    ///         assert_eq!(schema.version(), XsdVersion::V1_1);
+    ///         assert!(schema.validate("<count>42</count>").is_ok());
+    ///         let error = schema.validate("<count>7</count>").unwrap_err();
+    ///         assert_eq!(error.code(), Some("cvc-assertion"));
+    ///     }
+    ///     Err(error) => {
+    ///         eprintln!("Error loading schema: {}", error);
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn from_str_with_version(input: &str, version: XsdVersion) -> Result<Schema, SchemaError> {
+        let schema = SchemaLoader::new(&FileResolver::default(), version).load(input, None)?;
+        Ok(Schema { schema })
+    }
+
+    /// Returns an iterator over the target namespaces of the schema
+    /// documents the schema is assembled from, in the order they are
+    /// read, `None` standing for no namespace.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use xmlschema::Schema;
+    ///
+    /// // order.xsd imports the schema document of the namespace
+    /// // urn:example:address
+    /// match Schema::from_file("order.xsd") {
+    ///     Ok(schema) => {
+    ///         for namespace in schema.namespaces() {
+    ///             println!("{}", namespace.unwrap_or("(no namespace)"));
+    ///         }
+    ///         let address = schema.complex_type("{urn:example:address}address");
+    ///     }
+    ///     Err(error) => eprintln!("Error loading schema: {}", error),
+    /// }
+    /// ```
+    ///
+    pub fn namespaces(&self) -> impl Iterator<Item = Option<&str>> {
+        self.schema.namespaces.iter().map(Option::as_deref)
+    }
+
+    /// Returns the value of the `elementFormDefault` attribute, if any.
+    pub fn element_form_default(&self) -> Option<&str> {
+        self.schema.element_form_default.as_deref()
+    }
+
+    /// Returns the value of the `attributeFormDefault` attribute, if any.
+    pub fn attribute_form_default(&self) -> Option<&str> {
+        self.schema.attribute_form_default.as_deref()
+    }
+
+    /// Returns an iterator over the global element declarations.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.schema.nodes.iter().filter_map(|node| match node {
+            XmlSchemaNode::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Returns an iterator over the global attribute declarations.
+    pub fn attributes(&self) -> impl Iterator<Item = &Attribute> {
+        self.schema.nodes.iter().filter_map(|node| match node {
+            XmlSchemaNode::Attribute(attribute) => Some(attribute),
+            _ => None,
+        })
+    }
+
+    /// Returns an iterator over the global simple type definitions.
+    pub fn simple_types(&self) -> impl Iterator<Item = &SimpleType> {
+        self.schema.nodes.iter().filter_map(|node| match node {
+            XmlSchemaNode::SimpleType(simple_type) => Some(simple_type),
+            _ => None,
+        })
+    }
+
+    /// Returns an iterator over the global complex type definitions.
+    pub fn complex_types(&self) -> impl Iterator<Item = &ComplexType> {
+        self.schema.nodes.iter().filter_map(|node| match node {
+            XmlSchemaNode::ComplexType(complex_type) => Some(complex_type.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// Returns an iterator over the named model group definitions.
+    pub fn groups(&self) -> impl Iterator<Item = &Group> {
+        self.schema.nodes.iter().filter_map(|node| match node {
+            XmlSchemaNode::Group(group) => Some(group),
+            _ => None,
+        })
+    }
+
+    /// Returns an iterator over the attribute group definitions.
+    pub fn attribute_groups(&self) -> impl Iterator<Item = &AttributeGroup> {
+        self.schema.nodes.iter().filter_map(|node| match node {
+            XmlSchemaNode::AttributeGroup(attribute_group) => Some(attribute_group),
+            _ => None,
+        })
+    }
+
+    /// Returns an iterator over the notation declarations.
+    pub fn notations(&self) -> impl Iterator<Item = &Notation> {
+        self.schema.nodes.iter().filter_map(|node| match node {
+            XmlSchemaNode::Notation(notation) => Some(notation),
+            _ => None,
+        })
+    }
+
+    /// Returns an index of the global components of the schema, through
+    /// typed handles.
+    pub fn graph(&self) -> SchemaGraph<'_> {
+        SchemaGraph::new(&self.schema)
+    }
+
+    /// Returns the global element declaration called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name in the target namespace.
+    pub fn element(&self, name: &str) -> Option<&Element> {
+        let name = self.expand_name(name)?;
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Returns the global attribute declaration called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name in the target namespace.
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        let name = self.expand_name(name)?;
+        self.attributes().find(|attribute| attribute.name == name)
+    }
+
+    /// Returns the global simple type definition called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name in the target namespace.
+    pub fn simple_type(&self, name: &str) -> Option<&SimpleType> {
+        let name = self.expand_name(name)?;
+        self.simple_types()
+            .find(|simple_type| simple_type.name == name)
+    }
+
+    /// Returns the global complex type definition called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name in the target namespace.
+    pub fn complex_type(&self, name: &str) -> Option<&ComplexType> {
+        let name = self.expand_name(name)?;
+        self.complex_types()
+            .find(|complex_type| complex_type.name == name)
+    }
+
+    /// Returns the named model group definition called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name in the target namespace.
+    pub fn group(&self, name: &str) -> Option<&Group> {
+        let name = self.expand_name(name)?;
+        self.groups().find(|group| group.name == name)
+    }
+
+    /// Returns the attribute group definition called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name in the target namespace.
+    pub fn attribute_group(&self, name: &str) -> Option<&AttributeGroup> {
+        let name = self.expand_name(name)?;
+        self.attribute_groups()
+            .find(|attribute_group| attribute_group.name == name)
+    }
+
+    /// Returns the notation declaration called `name`, if any.
+    ///
+    /// The name is given in the `{namespace}local` notation, or as a
+    /// local name in the target namespace.
+    pub fn notation(&self, name: &str) -> Option<&Notation> {
+        let name = self.expand_name(name)?;
+        self.notations().find(|notation| notation.name == name)
+    }
+
+    /// Returns the element declarations and wildcards that can match the
+    /// next child of an element of type `complex_type`, after the
+    /// children `children`.
+    ///
+    /// The answer comes from the compiled content model of the type,
+    /// and is the one reported in validation errors: element
+    /// declarations stand for the members of their substitution groups
+    /// as well, and are listed in the order of the model. This function
+    /// returns `None` if the content model does not allow `children`, and
+    /// an empty list if no more children are allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `complex_type` - The type of the parent element, from this
+    ///   schema.
+    /// * `children` - The expanded names of the children of the parent
+    ///   element so far.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::str::FromStr;
+    /// use xmlschema::{QName, Schema, Term};
+    ///
+    /// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+    ///     <xs:complexType name="address">
+    ///         <xs:sequence>
+    ///             <xs:element name="street" type="xs:string" maxOccurs="2"/>
+    ///             <xs:choice>
+    ///                 <xs:element name="city" type="xs:string"/>
+    ///                 <xs:element name="zip" type="xs:string"/>
+    ///             </xs:choice>
+    ///         </xs:sequence>
+    ///     </xs:complexType>
+    /// </xs:schema>"#;
+    /// match Schema::from_str(input) {
+    ///     Ok(schema) => {
+    ///         let address = schema.complex_type("address").unwrap();
+    ///         let street = QName::new(None, "street");
+    ///         let next = schema.next_elements(address, &[street]).unwrap();
+    ///         let names: Vec<String> = next
+    ///             .iter()
+    ///             .filter_map(|term| match term {
+    ///                 Term::Element(element) => Some(element.name().to_string()),
+    ///                 _ => None,
+    ///             })
+    ///             .collect();
+    ///         assert_eq!(names, ["street", "city", "zip"]);
+    ///     }
+    ///     Err(error) => eprintln!("Error loading schema: {}", error),
+    /// }
+    /// ```
+    ///
+    pub fn next_elements<'s>(
+        &'s self,
+        complex_type: &'s ComplexType,
+        children: &[QName],
+    ) -> Option<Vec<&'s Term>> {
+        Validator::new(&self.schema, "").next_elements(complex_type, children)
+    }
+
+    /// Validates the XML document `xml` against the schema.
+    ///
+    /// The document element must match a global element declaration of
+    /// the schema. Elements are checked against their declarations,
+    /// attributes against the attribute declarations of their type,
+    /// children against its content model and text against its simple
+    /// type. Every violation is collected, and returned as a
+    /// [`SchemaError::Multiple`] if there are several; each one carries
+    /// the location and path of the offending element of the document
+    /// and the code of the validation rule it breaks.
+    ///
+    /// # Arguments
+    ///
+    /// * `xml` - The text of the XML document to validate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::str::FromStr;
+    /// use xmlschema::Schema;
+    ///
+    /// let schema = Schema::from_str(r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+    ///     <xs:element name="quantity" type="xs:integer"/>
+    /// </xs:schema>"#).unwrap();
+    /// assert!(schema.validate("<quantity>12</quantity>").is_ok());
+    ///
+    /// let error = schema.validate("<quantity>twelve</quantity>").unwrap_err();
+    /// assert_eq!(error.code(), Some("cvc-datatype-valid.1.2.1"));
+    /// assert_eq!(error.path(), Some("/quantity"));
+    /// ```
+    ///
+    pub fn validate(&self, xml: &str) -> Result<(), SchemaError> {
+        Validator::new(&self.schema, xml).validate()
+    }
+
+    /// Validates the XML document `xml` against the schema, extended with
+    /// the schema documents its `xsi:schemaLocation` and
+    /// `xsi:noNamespaceSchemaLocation` attributes point to.
+    ///
+    /// The hints are only followed for the namespaces the schema has no
+    /// components for, and read with `resolver`. Hints that cannot be
+    /// read, or whose document has another target namespace, are
+    /// ignored; a document that is not a valid schema is an error. The
+    /// components of the schema are used over those of the hinted
+    /// documents. See [`Schema::validate`] for the checks made.
+    ///
+    /// # Arguments
+    ///
+    /// * `xml` - The text of the XML document to validate.
+    /// * `base_uri` - The URI of the document, against which the hinted
+    ///   locations are resolved, if known.
+    /// * `resolver` - The source of the hinted schema documents.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::str::FromStr;
+    /// use xmlschema::{MemoryResolver, Schema};
+    ///
+    /// let mut resolver = MemoryResolver::new();
+    /// resolver.insert(
+    ///     "note.xsd",
+    ///     r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"
+    ///                   targetNamespace="urn:example:note">
+    ///         <xs:element name="note" type="xs:string"/>
+    ///     </xs:schema>"#,
+    /// );
+    /// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+    ///     <xs:element name="notes">
+    ///         <xs:complexType>
+    ///             <xs:sequence>
+    ///                 <xs:any namespace="urn:example:note" maxOccurs="unbounded"/>
+    ///             </xs:sequence>
+    ///         </xs:complexType>
+    ///     </xs:element>
+    /// </xs:schema>"#;
+    /// match Schema::from_str(input) {
+    ///     Ok(schema) => {
+    ///         let xml = r#"<notes xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance"
+    ///                             xmlns:n="urn:example:note"
+    ///                             xsi:schemaLocation="urn:example:note note.xsd">
+    ///             <n:note>Hi</n:note>
+    ///         </notes>"#;
+    ///         assert!(schema.validate(xml).is_err());
+    ///         assert!(schema.validate_with_resolver(xml, None, &resolver).is_ok());
+    ///     }
+    ///     Err(error) => eprintln!("Error loading schema: {}", error),
+    /// }
+    /// ```
+    ///
+    pub fn validate_with_resolver(
+        &self,
+        xml: &str,
+        base_uri: Option<&str>,
+        resolver: &dyn SchemaResolver,
+    ) -> Result<(), SchemaError> {
+        let root = XmlElement::parse(xml)?;
+        let mut hinted: Vec<XmlSchema> = Vec::new();
+        for (namespace, location) in schema_locations(&root) {
+            if self.schema.namespaces.contains(&namespace)
+                || hinted
+                    .iter()
+                    .any(|schema| schema.target_namespace == namespace)
+            {
+                continue;
+            }
+            let source = match resolver.resolve(base_uri, Some(&location), namespace.as_deref()) {
+                Ok(source) => source,
+                Err(_) => continue,
+            };
+            let input = match std::str::from_utf8(source.content()) {
+                Ok(input) => input,
+                Err(_) => continue,
+            };
+            let schema =
+                SchemaLoader::new(resolver, self.schema.version).load(input, Some(source.uri()))?;
+            if schema.target_namespace == namespace {
+                hinted.push(schema);
+            }
+        }
+        let mut validator = Validator::new(&self.schema, xml);
+        for schema in &hinted {
+            validator.add_components(schema);
+        }
+        validator.validate_document(&root)
+    }
+
+    /// Validates the XML document read from `reader` against the schema.
+    ///
+    /// The reader is consumed to its end and must yield UTF-8 text. See
+    /// [`Schema::validate`] for the checks made.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Any type implementing [`std::io::Read`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use std::fs::File;
+    /// use xmlschema::Schema;
+    ///
+    /// let schema = Schema::from_file("order.xsd").unwrap();
+    /// match schema.validate_reader(File::open("order.xml").unwrap()) {
+    ///     Ok(()) => println!("order.xml is valid"),
+    ///     Err(error) => {
+    ///         for violation in error.errors() {
+    ///             eprintln!("{}", violation);
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    ///
+    pub fn validate_reader<R: Read>(&self, mut reader: R) -> Result<(), SchemaError> {
+        let mut xml = String::new();
+        reader
+            .read_to_string(&mut xml)
+            .map_err(|err| SchemaError::Io {
+                message: format!("Cannot read document: {}", err),
+            })?;
+        self.validate(&xml)
+    }
+
+    fn expand_name(&self, name: &str) -> Option<QName> {
+        if name.starts_with('{') {
+            name.parse().ok()
+        } else {
+            Some(QName::new(self.target_namespace(), name))
+        }
+    }
+}
+
+impl FromStr for Schema {
+    type Err = SchemaError;
+
+    /// Loads a schema from the text of an XSD document.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::str::FromStr;
+    /// use xmlschema::Schema;
+    ///
+    /// let input = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema"/>"#;
+    /// match Schema::from_str(input) {
+    ///     Ok(schema) => {
+    ///         // Do something with the loaded schema
+    ///     }
+    ///     Err(error) => {
+    ///         eprintln!("Error loading schema: {}", error);
+    ///     }
+    /// }
+    /// ```
+    ///
+    fn from_str(input: &str) -> Result<Schema, SchemaError> {
+        let schema =
+            SchemaLoader::new(&FileResolver::default(), XsdVersion::default()).load(input, None)?;
+        Ok(Schema { schema })
+    }
+}
@@ -0,0 +1,742 @@
+//! A pull tokenizer for XML 1.0 documents.
+//!
+//! The [`Tokenizer`] reads a document held in memory and yields one
+//! [`Token`] at a time: the XML declaration, the document type
+//! declaration, start, end and empty-element tags, character data,
+//! CDATA sections, comments and processing instructions. Character and
+//! entity references are expanded, line endings and attribute values are
+//! normalized, and the well-formedness of the document (matching tags, a
+//! single root element, valid names and characters) is checked as the
+//! tokens are read.
+//!
+
+use std::collections::HashMap;
+
+use crate::error::SchemaError;
+
+/// The largest number of characters an entity reference may expand to.
+const MAX_ENTITY_EXPANSION: usize = 1 << 20;
+
+/// The deepest nesting of entity references inside entity values.
+const MAX_ENTITY_DEPTH: usize = 16;
+
+/// The largest number of entity references expanded in a document.
+const MAX_ENTITY_REFERENCES: usize = 1 << 16;
+
+/// A single syntactic unit of an XML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Token<'a> {
+    /// The `<?xml ...?>` declaration
+    XmlDeclaration {
+        /// The value of the `version` pseudo-attribute
+        version: &'a str,
+        /// The value of the `encoding` pseudo-attribute, if any
+        encoding: Option<&'a str>,
+        /// The value of the `standalone` pseudo-attribute, if any
+        standalone: Option<bool>,
+    },
+    /// A `<!DOCTYPE ...>` declaration
+    Doctype {
+        /// The name of the document element
+        name: &'a str,
+        /// The internal subset, without the enclosing brackets, if any
+        internal_subset: Option<&'a str>,
+    },
+    /// A start tag such as `<a href="...">`
+    StartTag {
+        /// The qualified name of the element
+        name: &'a str,
+        /// The attributes of the element, in document order
+        attributes: Vec<(&'a str, String)>,
+    },
+    /// An empty-element tag such as `<br/>`
+    EmptyTag {
+        /// The qualified name of the element
+        name: &'a str,
+        /// The attributes of the element, in document order
+        attributes: Vec<(&'a str, String)>,
+    },
+    /// An end tag such as `</a>`
+    EndTag {
+        /// The qualified name of the element
+        name: &'a str,
+    },
+    /// Character data, with references expanded
+    Text(String),
+    /// The content of a `<![CDATA[...]]>` section
+    CData(String),
+    /// The content of a `<!--...-->` comment
+    Comment(&'a str),
+    /// A `<?target data?>` processing instruction
+    ProcessingInstruction {
+        /// The target of the instruction
+        target: &'a str,
+        /// The data of the instruction, if any
+        data: &'a str,
+    },
+}
+
+/// A pull tokenizer over an XML 1.0 document.
+///
+/// The tokenizer implements [`Iterator`], yielding `Ok(token)` for each
+/// token of the document and `Err(error)` for the first well-formedness
+/// error, a [`SchemaError::Syntax`], after which it stops.
+///
+/// # Examples
+///
+/// ```
+/// use xmlschema::{Token, Tokenizer};
+///
+/// let mut tokenizer = Tokenizer::new("<greeting lang='en'>Hello &amp; welcome</greeting>");
+/// while let Some(token) = tokenizer.next() {
+///     match token {
+///         Ok(Token::StartTag { name, attributes }) => {
+///             // Do something with the start tag
+///         }
+///         Ok(Token::Text(text)) => {
+///             // Do something with the text
+///         }
+///         Ok(_) => {}
+///         Err(message) => {
+///             eprintln!("Error reading document: {}", message);
+///         }
+///     }
+/// }
+/// ```
+///
+#[derive(Debug)]
+pub struct Tokenizer<'a> {
+    input: &'a str,
+    position: usize,
+    token_start: usize,
+    open_tags: Vec<&'a str>,
+    seen_root: bool,
+    seen_doctype: bool,
+    entities: HashMap<&'a str, &'a str>,
+    expanded: usize,
+    failed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    /// Creates a tokenizer reading `input` from its beginning.
+    ///
+    /// A leading byte order mark is skipped.
+    pub fn new(input: &'a str) -> Self {
+        let position = if input.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        };
+        Self {
+            input,
+            position,
+            token_start: position,
+            open_tags: Vec::new(),
+            seen_root: false,
+            seen_doctype: false,
+            entities: HashMap::new(),
+            expanded: 0,
+            failed: false,
+        }
+    }
+
+    /// Returns the byte offset at which the last token returned started.
+    pub fn token_start(&self) -> usize {
+        self.token_start
+    }
+
+    /// Returns the byte offset of the next character to be read.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the replacement text of the general entity called `name`,
+    /// as declared in the internal subset of the document type
+    /// declaration.
+    pub fn entity(&self, name: &str) -> Option<&'a str> {
+        self.entities.get(name).copied()
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.rest().starts_with(prefix)
+    }
+
+    fn advance(&mut self, len: usize) {
+        self.position += len;
+    }
+
+    fn error(&self, offset: usize, message: &str) -> SchemaError {
+        SchemaError::syntax(self.input, offset, message)
+    }
+
+    fn expect(&mut self, literal: &str) -> Result<(), SchemaError> {
+        if self.starts_with(literal) {
+            self.advance(literal.len());
+            Ok(())
+        } else {
+            Err(self.error(self.position, &format!("Expected '{}'", literal)))
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let rest = self.rest();
+        let trimmed = rest.trim_start_matches(is_whitespace);
+        self.advance(rest.len() - trimmed.len());
+        rest.len() != trimmed.len()
+    }
+
+    fn require_whitespace(&mut self) -> Result<(), SchemaError> {
+        if self.skip_whitespace() {
+            Ok(())
+        } else {
+            Err(self.error(self.position, "Expected whitespace"))
+        }
+    }
+
+    /// Reads up to `delimiter`, consuming the delimiter but not returning
+    /// it.
+    fn read_until(&mut self, delimiter: &str, construct: &str) -> Result<&'a str, SchemaError> {
+        match self.rest().find(delimiter) {
+            Some(index) => {
+                let content = &self.rest()[..index];
+                self.advance(index + delimiter.len());
+                Ok(content)
+            }
+            None => Err(self.error(self.token_start, &format!("Unterminated {}", construct))),
+        }
+    }
+
+    fn read_name(&mut self) -> Result<&'a str, SchemaError> {
+        let rest = self.rest();
+        let mut chars = rest.char_indices();
+        match chars.next() {
+            Some((_, ch)) if is_name_start_char(ch) => {}
+            Some((_, ch)) => {
+                return Err(self.error(self.position, &format!("Unexpected character '{}'", ch)))
+            }
+            None => return Err(self.error(self.position, "Unexpected end of input")),
+        }
+        let end = chars
+            .find(|&(_, ch)| !is_name_char(ch))
+            .map_or(rest.len(), |(index, _)| index);
+        self.advance(end);
+        Ok(&rest[..end])
+    }
+
+    fn read_quoted(&mut self) -> Result<&'a str, SchemaError> {
+        let quote = match self.peek() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => return Err(self.error(self.position, "Expected a quoted value")),
+        };
+        self.advance(1);
+        let rest = self.rest();
+        match rest.find(quote) {
+            Some(index) => {
+                self.advance(index + 1);
+                Ok(&rest[..index])
+            }
+            None => Err(self.error(self.position - 1, "Unterminated quoted value")),
+        }
+    }
+
+    fn check_chars(&self, text: &str, offset: usize) -> Result<(), SchemaError> {
+        match text.char_indices().find(|&(_, ch)| !is_xml_char(ch)) {
+            Some((index, ch)) => Err(self.error(
+                offset + index,
+                &format!("Invalid character U+{:04X}", ch as u32),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Expands the character and entity references of `raw`, which
+    /// starts at byte `offset` of the input, appending the result to
+    /// `output`.
+    ///
+    /// Besides the size and nesting of each expansion, the number of
+    /// entity references expanded across the whole document is capped, so
+    /// that entities expanding to nothing cannot take exponential time.
+    fn expand(
+        &mut self,
+        raw: &str,
+        offset: usize,
+        in_attribute: bool,
+        depth: usize,
+        output: &mut String,
+    ) -> Result<(), SchemaError> {
+        let mut rest = raw;
+        while let Some(index) = rest.find(['&', '\r', '\t', '\n']) {
+            output.push_str(&rest[..index]);
+            let at = offset + (raw.len() - rest.len()) + index;
+            match rest.as_bytes()[index] {
+                b'\r' => {
+                    output.push(if in_attribute { ' ' } else { '\n' });
+                    rest = &rest[index + 1..];
+                    if rest.starts_with('\n') {
+                        rest = &rest[1..];
+                    }
+                }
+                b'\t' | b'\n' => {
+                    output.push(if in_attribute {
+                        ' '
+                    } else {
+                        rest.as_bytes()[index] as char
+                    });
+                    rest = &rest[index + 1..];
+                }
+                _ => {
+                    let end = match rest[index..].find(';') {
+                        Some(end) => index + end,
+                        None => return Err(self.error(at, "Unterminated reference")),
+                    };
+                    let reference = &rest[index + 1..end];
+                    rest = &rest[end + 1..];
+                    if let Some(code) = reference.strip_prefix('#') {
+                        let value = match code.strip_prefix('x') {
+                            Some(hex) => u32::from_str_radix(hex, 16),
+                            None => code.parse::<u32>(),
+                        };
+                        match value.ok().and_then(char::from_u32) {
+                            Some(ch) if is_xml_char(ch) && !code.starts_with(['+', '-']) => {
+                                output.push(ch)
+                            }
+                            _ => {
+                                return Err(self.error(
+                                    at,
+                                    &format!("Invalid character reference '&{};'", reference),
+                                ))
+                            }
+                        }
+                        continue;
+                    }
+                    match reference {
+                        "lt" => output.push('<'),
+                        "gt" => output.push('>'),
+                        "amp" => output.push('&'),
+                        "apos" => output.push('\''),
+                        "quot" => output.push('"'),
+                        _ => {
+                            let value = match self.entities.get(reference) {
+                                Some(value) => *value,
+                                None => {
+                                    return Err(self
+                                        .error(at, &format!("Undefined entity '&{};'", reference)))
+                                }
+                            };
+                            if depth >= MAX_ENTITY_DEPTH {
+                                return Err(self.error(
+                                    at,
+                                    &format!("Recursive entity reference '&{};'", reference),
+                                ));
+                            }
+                            self.expanded += 1;
+                            if self.expanded > MAX_ENTITY_REFERENCES {
+                                return Err(self.error(at, "Entity reference limit exceeded"));
+                            }
+                            if value.contains('<') {
+                                return Err(self.error(
+                                    at,
+                                    &format!("Markup in entity '&{};' is not supported", reference),
+                                ));
+                            }
+                            self.expand(value, at, in_attribute, depth + 1, output)?;
+                            if output.len() > MAX_ENTITY_EXPANSION {
+                                return Err(self.error(at, "Entity expansion limit exceeded"));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        output.push_str(rest);
+        Ok(())
+    }
+
+    fn read_text(&mut self) -> Result<Token<'a>, SchemaError> {
+        let rest = self.rest();
+        let end = rest.find('<').unwrap_or(rest.len());
+        let raw = &rest[..end];
+        self.advance(end);
+        self.check_chars(raw, self.token_start)?;
+        if let Some(index) = raw.find("]]>") {
+            return Err(self.error(self.token_start + index, "Unexpected ']]>' in text"));
+        }
+        if self.open_tags.is_empty() {
+            if let Some(index) = raw.find(|ch| !is_whitespace(ch)) {
+                return Err(self.error(
+                    self.token_start + index,
+                    "Unexpected text outside of the document element",
+                ));
+            }
+        }
+        let mut text = String::with_capacity(raw.len());
+        self.expand(raw, self.token_start, false, 0, &mut text)?;
+        Ok(Token::Text(text))
+    }
+
+    fn read_comment(&mut self) -> Result<Token<'a>, SchemaError> {
+        self.advance("<!--".len());
+        let content = self.read_until("-->", "comment")?;
+        self.check_chars(content, self.token_start)?;
+        if content.contains("--") || content.ends_with('-') {
+            return Err(self.error(self.token_start, "Unexpected '--' in comment"));
+        }
+        Ok(Token::Comment(content))
+    }
+
+    fn read_cdata(&mut self) -> Result<Token<'a>, SchemaError> {
+        if self.open_tags.is_empty() {
+            return Err(self.error(
+                self.token_start,
+                "Unexpected CDATA section outside of the document element",
+            ));
+        }
+        self.advance("<![CDATA[".len());
+        let content = self.read_until("]]>", "CDATA section")?;
+        self.check_chars(content, self.token_start)?;
+        Ok(Token::CData(
+            content.replace("\r\n", "\n").replace('\r', "\n"),
+        ))
+    }
+
+    fn read_processing_instruction(&mut self) -> Result<Token<'a>, SchemaError> {
+        self.advance("<?".len());
+        let target = self.read_name()?;
+        if target.eq_ignore_ascii_case("xml") {
+            if target == "xml" && self.token_start == self.initial_position() {
+                return self.read_xml_declaration();
+            }
+            return Err(self.error(
+                self.token_start,
+                "The XML declaration must be at the start of the document",
+            ));
+        }
+        if target.contains(':') {
+            return Err(self.error(self.token_start, "Colon in processing instruction target"));
+        }
+        if self.starts_with("?>") {
+            self.advance(2);
+            return Ok(Token::ProcessingInstruction { target, data: "" });
+        }
+        self.require_whitespace()?;
+        let data = self.read_until("?>", "processing instruction")?;
+        self.check_chars(data, self.token_start)?;
+        Ok(Token::ProcessingInstruction { target, data })
+    }
+
+    fn initial_position(&self) -> usize {
+        if self.input.starts_with('\u{feff}') {
+            '\u{feff}'.len_utf8()
+        } else {
+            0
+        }
+    }
+
+    fn read_pseudo_attribute(&mut self, name: &str) -> Result<Option<&'a str>, SchemaError> {
+        let saved = self.position;
+        if !self.skip_whitespace() || !self.starts_with(name) {
+            self.position = saved;
+            return Ok(None);
+        }
+        self.advance(name.len());
+        self.skip_whitespace();
+        self.expect("=")?;
+        self.skip_whitespace();
+        self.read_quoted().map(Some)
+    }
+
+    fn read_xml_declaration(&mut self) -> Result<Token<'a>, SchemaError> {
+        let version = match self.read_pseudo_attribute("version")? {
+            Some(version) => version,
+            None => return Err(self.error(self.position, "Expected 'version'")),
+        };
+        if !version.starts_with("1.") || version[2..].is_empty() {
+            return Err(self.error(
+                self.token_start,
+                &format!("Unsupported XML version '{}'", version),
+            ));
+        }
+        let encoding = self.read_pseudo_attribute("encoding")?;
+        let standalone = match self.read_pseudo_attribute("standalone")? {
+            Some("yes") => Some(true),
+            Some("no") => Some(false),
+            Some(value) => {
+                return Err(self.error(
+                    self.token_start,
+                    &format!("Invalid standalone value '{}'", value),
+                ))
+            }
+            None => None,
+        };
+        self.skip_whitespace();
+        self.expect("?>")?;
+        Ok(Token::XmlDeclaration {
+            version,
+            encoding,
+            standalone,
+        })
+    }
+
+    fn read_doctype(&mut self) -> Result<Token<'a>, SchemaError> {
+        if self.seen_doctype || self.seen_root {
+            return Err(self.error(self.token_start, "Unexpected document type declaration"));
+        }
+        self.seen_doctype = true;
+        self.advance("<!DOCTYPE".len());
+        self.require_whitespace()?;
+        let name = self.read_name()?;
+        self.skip_whitespace();
+        if self.starts_with("SYSTEM") {
+            self.advance("SYSTEM".len());
+            self.require_whitespace()?;
+            self.read_quoted()?;
+        } else if self.starts_with("PUBLIC") {
+            self.advance("PUBLIC".len());
+            self.require_whitespace()?;
+            self.read_quoted()?;
+            self.require_whitespace()?;
+            self.read_quoted()?;
+        }
+        self.skip_whitespace();
+        let mut internal_subset = None;
+        if self.starts_with("[") {
+            self.advance(1);
+            let start = self.position;
+            self.read_internal_subset()?;
+            internal_subset = Some(&self.input[start..self.position]);
+            self.expect("]")?;
+            self.skip_whitespace();
+        }
+        self.expect(">")?;
+        Ok(Token::Doctype {
+            name,
+            internal_subset,
+        })
+    }
+
+    /// Reads the markup declarations of an internal subset, recording
+    /// the general entities it declares, up to the closing `]`.
+    fn read_internal_subset(&mut self) -> Result<(), SchemaError> {
+        loop {
+            self.skip_whitespace();
+            let start = self.position;
+            if self.starts_with("]") {
+                return Ok(());
+            } else if self.starts_with("<!--") {
+                self.advance("<!--".len());
+                self.read_until("-->", "comment")?;
+            } else if self.starts_with("<?") {
+                self.advance("<?".len());
+                self.read_until("?>", "processing instruction")?;
+            } else if self.starts_with("%") {
+                self.advance(1);
+                self.read_name()?;
+                self.expect(";")?;
+            } else if self.starts_with("<!ENTITY") {
+                self.advance("<!ENTITY".len());
+                self.require_whitespace()?;
+                let parameter = self.starts_with("%");
+                if parameter {
+                    self.advance(1);
+                    self.require_whitespace()?;
+                }
+                let name = self.read_name()?;
+                self.require_whitespace()?;
+                if matches!(self.peek(), Some('"' | '\'')) {
+                    let value = self.read_quoted()?;
+                    if !parameter {
+                        self.entities.entry(name).or_insert(value);
+                    }
+                }
+                self.skip_markup_declaration(start)?;
+            } else if self.starts_with("<!") {
+                self.advance(2);
+                self.skip_markup_declaration(start)?;
+            } else if self.position >= self.input.len() {
+                return Err(self.error(self.token_start, "Unterminated document type declaration"));
+            } else {
+                return Err(self.error(start, "Unexpected content in internal subset"));
+            }
+        }
+    }
+
+    /// Skips to the `>` closing a markup declaration, ignoring any `>`
+    /// inside quoted literals.
+    fn skip_markup_declaration(&mut self, start: usize) -> Result<(), SchemaError> {
+        while let Some(ch) = self.peek() {
+            match ch {
+                '"' | '\'' => {
+                    self.read_quoted()?;
+                }
+                '>' => {
+                    self.advance(1);
+                    return Ok(());
+                }
+                _ => self.advance(ch.len_utf8()),
+            }
+        }
+        Err(self.error(start, "Unterminated markup declaration"))
+    }
+
+    fn read_start_tag(&mut self) -> Result<Token<'a>, SchemaError> {
+        if self.open_tags.is_empty() && self.seen_root {
+            return Err(self.error(self.token_start, "Unexpected second document element"));
+        }
+        self.advance(1);
+        let name = self.read_name()?;
+        let mut attributes: Vec<(&'a str, String)> = Vec::new();
+        loop {
+            let had_whitespace = self.skip_whitespace();
+            if self.starts_with("/>") {
+                self.advance(2);
+                self.seen_root = true;
+                return Ok(Token::EmptyTag { name, attributes });
+            }
+            if self.starts_with(">") {
+                self.advance(1);
+                self.seen_root = true;
+                self.open_tags.push(name);
+                return Ok(Token::StartTag { name, attributes });
+            }
+            if !had_whitespace {
+                return match self.peek() {
+                    Some(ch) => {
+                        Err(self.error(self.position, &format!("Unexpected character '{}'", ch)))
+                    }
+                    None => Err(self.error(self.token_start, "Unterminated start tag")),
+                };
+            }
+            let attribute_start = self.position;
+            let attribute_name = self.read_name()?;
+            self.skip_whitespace();
+            self.expect("=")?;
+            self.skip_whitespace();
+            let value_start = self.position + 1;
+            let raw = self.read_quoted()?;
+            if let Some(index) = raw.find('<') {
+                return Err(self.error(value_start + index, "Unexpected '<' in attribute value"));
+            }
+            self.check_chars(raw, value_start)?;
+            if attributes.iter().any(|(name, _)| *name == attribute_name) {
+                return Err(self.error(
+                    attribute_start,
+                    &format!("Duplicate attribute '{}'", attribute_name),
+                ));
+            }
+            let mut value = String::with_capacity(raw.len());
+            self.expand(raw, value_start, true, 0, &mut value)?;
+            attributes.push((attribute_name, value));
+        }
+    }
+
+    fn read_end_tag(&mut self) -> Result<Token<'a>, SchemaError> {
+        self.advance(2);
+        let name = self.read_name()?;
+        self.skip_whitespace();
+        self.expect(">")?;
+        match self.open_tags.pop() {
+            Some(open) if open == name => Ok(Token::EndTag { name }),
+            Some(open) => Err(self.error(
+                self.token_start,
+                &format!("Expected '</{}>', found '</{}>'", open, name),
+            )),
+            None => Err(self.error(
+                self.token_start,
+                &format!("Unexpected end tag '</{}>'", name),
+            )),
+        }
+    }
+
+    fn read_token(&mut self) -> Option<Result<Token<'a>, SchemaError>> {
+        self.token_start = self.position;
+        if self.position >= self.input.len() {
+            return match self.open_tags.last() {
+                Some(name) => Some(Err(self.error(
+                    self.position,
+                    &format!("Unexpected end of input, expected '</{}>'", name),
+                ))),
+                None if !self.seen_root => {
+                    Some(Err(self.error(self.position, "Missing document element")))
+                }
+                None => None,
+            };
+        }
+        let result = if !self.starts_with("<") {
+            self.read_text()
+        } else if self.starts_with("<!--") {
+            self.read_comment()
+        } else if self.starts_with("<![CDATA[") {
+            self.read_cdata()
+        } else if self.starts_with("<!DOCTYPE") {
+            self.read_doctype()
+        } else if self.starts_with("<?") {
+            self.read_processing_instruction()
+        } else if self.starts_with("</") {
+            self.read_end_tag()
+        } else {
+            self.read_start_tag()
+        };
+        Some(result)
+    }
+}
+
+impl<'a> Iterator for Tokenizer<'a> {
+    type Item = Result<Token<'a>, SchemaError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let token = self.read_token();
+        if let Some(Err(_)) = token {
+            self.failed = true;
+        }
+        token
+    }
+}
+
+/// Returns `true` if `ch` matches the XML `S` production.
+pub(crate) fn is_whitespace(ch: char) -> bool {
+    matches!(ch, ' ' | '\t' | '\n' | '\r')
+}
+
+/// Returns `true` if `ch` matches the XML `Char` production.
+pub(crate) fn is_xml_char(ch: char) -> bool {
+    matches!(ch, '\t' | '\n' | '\r' | '\u{20}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' | '\u{10000}'..='\u{10FFFF}')
+}
+
+/// Returns `true` if `ch` matches the XML `NameStartChar` production.
+pub(crate) fn is_name_start_char(ch: char) -> bool {
+    matches!(ch,
+        ':' | 'A'..='Z' | '_' | 'a'..='z'
+        | '\u{C0}'..='\u{D6}'
+        | '\u{D8}'..='\u{F6}'
+        | '\u{F8}'..='\u{2FF}'
+        | '\u{370}'..='\u{37D}'
+        | '\u{37F}'..='\u{1FFF}'
+        | '\u{200C}'..='\u{200D}'
+        | '\u{2070}'..='\u{218F}'
+        | '\u{2C00}'..='\u{2FEF}'
+        | '\u{3001}'..='\u{D7FF}'
+        | '\u{F900}'..='\u{FDCF}'
+        | '\u{FDF0}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{EFFFF}')
+}
+
+/// Returns `true` if `ch` matches the XML `NameChar` production.
+pub(crate) fn is_name_char(ch: char) -> bool {
+    is_name_start_char(ch)
+        || matches!(ch,
+            '-' | '.' | '0'..='9' | '\u{B7}'
+            | '\u{300}'..='\u{36F}'
+            | '\u{203F}'..='\u{2040}')
+}
@@ -0,0 +1,1621 @@
+//! Validation of instance documents against a loaded schema.
+//!
+//! The instance document is read into the same element tree as schema
+//! documents, then walked from its document element: each element is
+//! checked against its declaration, its attributes against the attribute
+//! declarations of its type, its children against the content model of
+//! its type, and its text against the simple type of its content. Every
+//! violation is collected rather than stopping at the first one, and
+//! reported with the location and path of the offending element and the
+//! code of the validation rule it breaks.
+//!
+//! Children are matched against the automaton compiled from the content
+//! model of the type when the schema is loaded. The element declaration
+//! or wildcard each child matches is the one it is then validated
+//! against, and the elements the automaton expects where a child is
+//! rejected, or where the content ends too early, are listed in the
+//! error.
+//!
+//! Once the content of an element is validated, the identity constraints
+//! of its declaration are evaluated: the values of the fields of each
+//! node picked out by a selector are gathered into a key table, in which
+//! unique and key values must not repeat, and keyref values are looked
+//! up in the tables of the constraint they refer to, built on the
+//! element or its descendants.
+//!
+//! The XSD 1.1 assertions of the type of an element are evaluated once
+//! its content is validated, with the element as the context node of
+//! their tests, and the typed values of its attributes and descendants
+//! at hand.
+//!
+//! The attributes of the XML Schema instance namespace are honoured: an
+//! `xsi:type` replaces the declared type of an element with one derived
+//! from it, unless the declaration or the types along the way block the
+//! derivation, and an `xsi:nil` of `true` leaves an element of a
+//! nillable declaration empty, with only its attributes validated.
+//!
+
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    automaton::{leaf_name, leaves, ContentModel},
+    components::{
+        Attribute, ComplexContent, ComplexType, Datatype, Derivation, Element, IdentityConstraint,
+        IdentityConstraintCategory, Particle, ProcessContents, SimpleType, Term, UseOption,
+        Wildcard,
+    },
+    datatypes::{SimpleDatatype, Value},
+    document::{ElementPath, XmlElement, XmlNode},
+    error::{LineIndex, SchemaError},
+    facets,
+    graph::{ElementId, SchemaGraph, TypeDefinition, TypeId},
+    loader::{definition_error, SchemaDocument},
+    parser::{derivation_path, XmlSchema, XmlSchemaNode},
+    qname::{QName, XMLNS_NAMESPACE, XSI_NAMESPACE},
+    schema::XsdVersion,
+    xpath::Selected,
+    xpath2::Atomic,
+};
+
+/// The type a simple value is checked against: a built-in datatype, or a
+/// resolved simple type of the schema.
+#[derive(Clone, Copy)]
+enum ValueType<'a> {
+    Builtin(SimpleDatatype),
+    Defined(&'a SimpleType),
+}
+
+impl<'a> ValueType<'a> {
+    fn of(simple_type: &'a SimpleType) -> Self {
+        ValueType::Defined(simple_type)
+    }
+
+    /// Returns the built-in datatype the type is based on.
+    fn datatype(&self) -> SimpleDatatype {
+        match self {
+            ValueType::Builtin(datatype) => *datatype,
+            ValueType::Defined(simple_type) => simple_type.datatype,
+        }
+    }
+
+    /// Checks that `value` is a valid value of the type in the version
+    /// `version` of XML Schema, and returns it.
+    fn check(&self, value: &str, version: XsdVersion) -> Result<Value, (String, String)> {
+        match self {
+            ValueType::Builtin(datatype) => facets::check_atomic(*datatype, &[], value, version),
+            ValueType::Defined(simple_type) => facets::check_value(simple_type, value, version),
+        }
+    }
+}
+
+/// The type an element is validated against: a simple type, or a
+/// complex type of the schema.
+#[derive(Clone, Copy)]
+enum ElementType<'a> {
+    Simple(ValueType<'a>),
+    Complex(&'a ComplexType),
+}
+
+impl<'a> ElementType<'a> {
+    /// Returns the type an element of the type definition `definition`
+    /// is validated against, or `None` if it is `xs:anyType` and the
+    /// content of the element is assessed laxly.
+    fn of(definition: TypeDefinition<'a>) -> Option<Self> {
+        match definition {
+            TypeDefinition::AnyType => None,
+            TypeDefinition::Builtin(datatype) => {
+                Some(ElementType::Simple(ValueType::Builtin(datatype)))
+            }
+            TypeDefinition::Simple(simple_type) => {
+                Some(ElementType::Simple(ValueType::of(simple_type)))
+            }
+            TypeDefinition::Complex(complex_type) => Some(ElementType::Complex(complex_type)),
+        }
+    }
+}
+
+/// Validates instance documents against the components of a schema.
+pub(crate) struct Validator<'a> {
+    input: &'a str,
+    /// The lines of the input, by which errors are located
+    lines: LineIndex<'a>,
+    /// The version of XML Schema the schema is read as
+    version: XsdVersion,
+    /// The schemas the document is validated against: the schema, then
+    /// those added from the schema location hints of the document
+    schemas: Vec<&'a XmlSchema>,
+    /// The index in `schemas` of the schema of the components being
+    /// validated, whose handles their references are
+    schema: usize,
+    /// The typed values of the elements and attributes validated so far,
+    /// by the position of the element and the name of the attribute,
+    /// which the fields of identity constraints compare
+    values: HashMap<(usize, Option<QName>), Value>,
+    /// The key tables of the unique and key constraints evaluated so far
+    tables: Vec<KeyTable<'a>>,
+    errors: Vec<SchemaError>,
+}
+
+/// The values of a unique or key constraint on one element of the
+/// instance document.
+struct KeyTable<'a> {
+    /// The name of the constraint
+    constraint: &'a QName,
+    /// The position of the element the constraint is evaluated on
+    scope: usize,
+    /// The rows of the table, by the values of their fields
+    rows: HashMap<Vec<Value>, KeyRow>,
+}
+
+/// A node picked out by the selector of an identity constraint, which
+/// has a value for each of its fields.
+struct KeyRow {
+    /// The values of the fields as written, for error messages
+    text: Vec<String>,
+    /// The position of the node in the instance document
+    position: usize,
+    /// The path of the node in the instance document
+    path: Rc<ElementPath>,
+}
+
+impl<'a> Validator<'a> {
+    pub(crate) fn new(schema: &'a XmlSchema, input: &'a str) -> Self {
+        let mut validator = Self {
+            input,
+            lines: LineIndex::new(input),
+            version: schema.version,
+            schemas: Vec::new(),
+            schema: 0,
+            values: HashMap::new(),
+            tables: Vec::new(),
+            errors: Vec::new(),
+        };
+        validator.add_components(schema);
+        validator
+    }
+
+    /// Adds the global components of `schema` to those the document is
+    /// validated against, after the components already known under the
+    /// same names.
+    pub(crate) fn add_components(&mut self, schema: &'a XmlSchema) {
+        self.schemas.push(schema);
+    }
+
+    /// Returns the graph of the schema of the components being
+    /// validated, through which their references are followed.
+    fn graph(&self) -> SchemaGraph<'a> {
+        SchemaGraph::new(self.schemas[self.schema])
+    }
+
+    /// Returns the index of the first schema in which `find` finds a
+    /// global component, with what it found.
+    fn find_global<T>(&self, find: impl Fn(SchemaGraph<'a>) -> Option<T>) -> Option<(usize, T)> {
+        self.schemas
+            .iter()
+            .enumerate()
+            .find_map(|(index, schema)| find(SchemaGraph::new(schema)).map(|found| (index, found)))
+    }
+
+    /// Returns the global element declaration called `name`, with the
+    /// index of its schema.
+    fn global_element(&self, name: &QName) -> Option<(usize, &'a Element)> {
+        self.find_global(|graph| graph.element_id(name).map(|id| graph.element(id)))
+    }
+
+    /// Returns the global attribute declaration called `name`, with the
+    /// index of its schema.
+    fn global_attribute(&self, name: &QName) -> Option<(usize, &'a Attribute)> {
+        self.find_global(|graph| graph.attribute_id(name).map(|id| graph.attribute(id)))
+    }
+
+    /// Runs `validate` on the components of the schema at `schema`, then
+    /// returns to those of the current schema.
+    fn in_schema(&mut self, schema: usize, validate: impl FnOnce(&mut Self)) {
+        let current = std::mem::replace(&mut self.schema, schema);
+        validate(self);
+        self.schema = current;
+    }
+
+    /// Validates the instance document.
+    ///
+    /// This function reads the input into an element tree, looks up the
+    /// global element declaration of its document element and validates
+    /// the document element against it. It returns `Ok(())` if the
+    /// document is valid, or every violation found, in document order,
+    /// wrapped in an `Err` variant of the `Result` type.
+    ///
+    pub(crate) fn validate(self) -> Result<(), SchemaError> {
+        let root = XmlElement::parse(self.input)?;
+        self.validate_document(&root)
+    }
+
+    /// Validates the instance document, already read into the element
+    /// tree `root`.
+    pub(crate) fn validate_document(mut self, root: &XmlElement) -> Result<(), SchemaError> {
+        match self.global_element(&root.qname) {
+            Some((schema, declaration)) => {
+                self.in_schema(schema, |validator| {
+                    validator.validate_element(root, declaration)
+                });
+            }
+            None => self.error(
+                root,
+                "cvc-elt.1.a",
+                &format!("Cannot find the declaration of element '{}'", root.name),
+            ),
+        }
+        SchemaError::from_errors(self.errors)
+    }
+
+    fn error(&mut self, node: &XmlElement, code: &str, message: &str) {
+        self.errors.push(SchemaError::constraint(
+            self.lines.location(node.position),
+            node,
+            code,
+            message,
+        ));
+    }
+
+    /// Validates the element `node` against its declaration.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `declaration` - The element declaration it matched.
+    ///
+    fn validate_element(&mut self, node: &XmlElement, declaration: &'a Element) {
+        let declaration = match declaration.reference_id {
+            Some(reference) => self.graph().element(reference),
+            None => declaration,
+        };
+        if declaration.is_abstract {
+            self.error(
+                node,
+                "cvc-elt.2",
+                &format!("Element '{}' is abstract", node.name),
+            );
+        }
+        let xsi_type = xsi_attribute(node, "type")
+            .and_then(|value| self.xsi_type(node, value, Some(declaration)));
+        // The content is validated against the schema of its type
+        let (schema, element_type) = match xsi_type {
+            Some((schema, id)) => {
+                let definition = SchemaGraph::new(self.schemas[schema]).type_definition(id);
+                (schema, ElementType::of(definition))
+            }
+            None => (
+                self.schema,
+                self.element_type(&declaration.datatype, declaration.type_id),
+            ),
+        };
+        let nilled = match xsi_attribute(node, "nil") {
+            Some(value) => self.xsi_nil(node, value, declaration),
+            None => false,
+        };
+        if let Some(fixed) = &declaration.fixed_value {
+            let text = text_content(node);
+            let datatype = match element_type {
+                Some(ElementType::Simple(datatype)) => Some(datatype),
+                Some(ElementType::Complex(complex_type)) => {
+                    self.simple_content_datatype(complex_type)
+                }
+                None => None,
+            };
+            if nilled {
+                self.error(
+                    node,
+                    "cvc-elt.3.2.2",
+                    &format!(
+                        "Element '{}' cannot be nil, because it has the fixed value '{}'",
+                        node.name, fixed
+                    ),
+                );
+            } else if node.child_elements().next().is_none()
+                && !text.is_empty()
+                && !is_same_value(datatype, &text, fixed, self.version)
+            {
+                self.error(
+                    node,
+                    "cvc-elt.5.2.2.2",
+                    &format!(
+                        "The value '{}' of element '{}' does not match its fixed value '{}'",
+                        text, node.name, fixed
+                    ),
+                );
+            }
+        }
+        // An empty element takes on its default or fixed value
+        let default = if node.children.is_empty() {
+            declaration
+                .default_value
+                .as_deref()
+                .or(declaration.fixed_value.as_deref())
+        } else {
+            None
+        };
+        self.in_schema(schema, |validator| {
+            if nilled {
+                validator.validate_nilled(node, element_type);
+            } else {
+                validator.validate_content(node, element_type, default);
+            }
+        });
+        if !declaration.identity_constraints.is_empty() {
+            self.validate_identity_constraints(node, declaration);
+        }
+    }
+
+    /// Validates the attributes and content of `node` against its type,
+    /// or assesses them laxly if it is `xs:anyType` or unknown.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `element_type` - The type of the element, if known.
+    /// * `default` - The value that stands for the content of the element,
+    ///   if it is empty and its declaration has a default or fixed value.
+    ///
+    fn validate_content(
+        &mut self,
+        node: &XmlElement,
+        element_type: Option<ElementType<'a>>,
+        default: Option<&str>,
+    ) {
+        match element_type {
+            Some(ElementType::Simple(datatype)) => {
+                self.validate_simple_content(node, datatype, default)
+            }
+            Some(ElementType::Complex(complex_type)) => {
+                if complex_type.is_abstract {
+                    self.error(
+                        node,
+                        "cvc-type.2",
+                        &format!(
+                            "The type definition of element '{}' cannot be abstract",
+                            node.name
+                        ),
+                    );
+                }
+                self.validate_complex_content(node, complex_type, default);
+                if !complex_type.assertions.is_empty() {
+                    self.validate_assertions(node, complex_type);
+                }
+            }
+            None => self.validate_lax(node),
+        }
+    }
+
+    /// Evaluates the XSD 1.1 assertions of `complex_type` on the element
+    /// `node`, once its attributes and content are validated.
+    ///
+    /// The element is the context node of the tests, which see the typed
+    /// values of the attributes and elements validated against simple
+    /// types, and `$value` is the value of its simple content, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `complex_type` - The type of the element.
+    ///
+    fn validate_assertions(&mut self, node: &XmlElement, complex_type: &ComplexType) {
+        let value = match self.values.get(&(node.position, None)) {
+            Some(value) if complex_type.content == ComplexContent::SimpleContent => {
+                Atomic::from_value(value.clone(), &text_content(node))
+            }
+            _ => Vec::new(),
+        };
+        let values = &self.values;
+        let typed = |element: &XmlElement, attribute: Option<&QName>| {
+            values.get(&(element.position, attribute.cloned())).cloned()
+        };
+        let description = match complex_type.name.local() {
+            "" => format!("the anonymous type of element '{}'", node.name),
+            _ => format!("schema type '{}'", complex_type.name),
+        };
+        let mut failures = Vec::new();
+        for assertion in &complex_type.assertions {
+            let reason = match assertion.test.test(Some(node), value.clone(), &typed) {
+                Ok(true) => continue,
+                Ok(false) => String::new(),
+                Err(error) => format!(": {}", error),
+            };
+            failures.push(format!(
+                "Assertion evaluation ('{}') for element '{}' on {} did not succeed{}",
+                assertion.test(),
+                node.name,
+                description,
+                reason
+            ));
+        }
+        for message in failures {
+            self.error(node, "cvc-assertion", &message);
+        }
+    }
+
+    /// Validates an element whose `xsi:nil` is `true`: it must be empty,
+    /// and only its attributes are validated against its type.
+    fn validate_nilled(&mut self, node: &XmlElement, element_type: Option<ElementType<'a>>) {
+        if node.child_elements().next().is_some() || !text_content(node).trim().is_empty() {
+            self.error(
+                node,
+                "cvc-elt.3.2.1",
+                &format!(
+                    "Element '{}' cannot have character or element children, because it is nil",
+                    node.name
+                ),
+            );
+        }
+        if let Some(ElementType::Complex(complex_type)) = element_type {
+            self.validate_attributes(node, complex_type);
+        }
+    }
+
+    /// Returns the type of an element declared with the datatype
+    /// `datatype` of the handle `type_id`, or `None` if it is
+    /// `xs:anyType`, and the content of the element is assessed laxly.
+    fn element_type(
+        &self,
+        datatype: &'a Datatype,
+        type_id: Option<TypeId>,
+    ) -> Option<ElementType<'a>> {
+        self.graph()
+            .declared_type(datatype, type_id)
+            .and_then(ElementType::of)
+    }
+
+    /// Resolves the `xsi:type` attribute `value` of the element `node`
+    /// and returns the handle of the type it selects, with the index of
+    /// its schema, if it is one the element can be validated against.
+    ///
+    /// The type must be derived from the type of the declaration, if
+    /// any, without going through the derivations blocked by the
+    /// declaration or the types along the way.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `value` - The value of its `xsi:type` attribute.
+    /// * `declaration` - The element declaration it matched, if any.
+    ///
+    fn xsi_type(
+        &mut self,
+        node: &XmlElement,
+        value: &str,
+        declaration: Option<&'a Element>,
+    ) -> Option<(usize, TypeId)> {
+        let name = match node.resolve_qname(value.trim()) {
+            Ok(name) => name,
+            Err(reason) => {
+                self.error(
+                    node,
+                    "cvc-elt.4.1",
+                    &format!(
+                        "The value '{}' of attribute 'xsi:type' of element '{}' is not a valid \
+                         QName: {}",
+                        value, node.name, reason
+                    ),
+                );
+                return None;
+            }
+        };
+        let (schema, id) = match self.find_global(|graph| graph.type_id(&name)) {
+            Some(found) => found,
+            None => {
+                self.error(
+                    node,
+                    "cvc-elt.4.2",
+                    &format!(
+                        "Cannot resolve '{}' to a type definition for element '{}'",
+                        value, node.name
+                    ),
+                );
+                return None;
+            }
+        };
+        let declaration = match declaration {
+            Some(declaration) => declaration,
+            None => return Some((schema, id)),
+        };
+        // The type is derived through the schema it is found in, and
+        // matched by name against the declared type
+        let graph = SchemaGraph::new(self.schemas[schema]);
+        let derived = graph.type_definition(id);
+        let declared = self
+            .graph()
+            .declared_type(&declaration.datatype, declaration.type_id);
+        match declared.and_then(|declared| derivation_path(&graph, derived, declared)) {
+            None => self.error(
+                node,
+                "cvc-elt.4.3",
+                &format!(
+                    "Type '{}' is not validly derived from the type of element '{}'",
+                    value, node.name
+                ),
+            ),
+            Some((methods, blocked)) => {
+                let excluded = methods.intersection(blocked.union(declaration.block_set));
+                let derivation = match excluded.iter().next() {
+                    Some(derivation) => derivation,
+                    None => return Some((schema, id)),
+                };
+                self.error(
+                    node,
+                    "cvc-elt.4.3",
+                    &format!(
+                        "Type '{}' cannot be used in place of the type of element '{}', \
+                         because derivation by {} is blocked",
+                        value, node.name, derivation
+                    ),
+                );
+            }
+        }
+        None
+    }
+
+    /// Checks the `xsi:nil` attribute `value` of the element `node`, and
+    /// returns `true` if the element is nil.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `value` - The value of its `xsi:nil` attribute.
+    /// * `declaration` - The element declaration it matched.
+    ///
+    fn xsi_nil(&mut self, node: &XmlElement, value: &str, declaration: &Element) -> bool {
+        let nil = match value.trim() {
+            "true" | "1" => true,
+            "false" | "0" => false,
+            _ => {
+                self.error(
+                    node,
+                    "cvc-datatype-valid.1.2.1",
+                    &format!(
+                        "'{}' is not a valid value for 'boolean' in attribute 'xsi:nil' of \
+                         element '{}'",
+                        value, node.name
+                    ),
+                );
+                return false;
+            }
+        };
+        if !declaration.nillable {
+            self.error(
+                node,
+                "cvc-elt.3.1",
+                &format!(
+                    "Attribute 'xsi:nil' must not appear on element '{}', because it is not \
+                     nillable",
+                    node.name
+                ),
+            );
+            return false;
+        }
+        nil
+    }
+
+    /// Evaluates the identity constraints of `declaration` on the element
+    /// `node`, once its content is validated.
+    ///
+    /// The unique and key constraints are evaluated first, so that the
+    /// keyrefs of the element can refer to them.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `declaration` - The element declaration it matched.
+    ///
+    fn validate_identity_constraints(&mut self, node: &XmlElement, declaration: &'a Element) {
+        let (keyrefs, keys): (Vec<_>, Vec<_>) = declaration
+            .identity_constraints
+            .iter()
+            .partition(|constraint| constraint.category == IdentityConstraintCategory::KeyRef);
+        for constraint in keys {
+            let mut table = KeyTable {
+                constraint: &constraint.name,
+                scope: node.position,
+                rows: HashMap::new(),
+            };
+            for (selected, values, row) in self.key_rows(node, constraint) {
+                if let Some(first) = table.rows.get(&values) {
+                    let code = match constraint.category {
+                        IdentityConstraintCategory::Key => "cvc-identity-constraint.4.2.2",
+                        _ => "cvc-identity-constraint.4.1",
+                    };
+                    let message = format!(
+                        "Duplicate {} value [{}] declared for identity constraint '{}' of \
+                         element '{}', first declared at {} ({})",
+                        constraint.category,
+                        row.text.join(", "),
+                        constraint.name,
+                        node.name,
+                        first.path,
+                        self.lines.location(first.position)
+                    );
+                    self.error(selected, code, &message);
+                } else {
+                    table.rows.insert(values, row);
+                }
+            }
+            self.tables.push(table);
+        }
+        // The tables of the element and of its descendants, which all
+        // have positions up to that of its last descendant
+        let end = last_descendant(node).position;
+        for constraint in keyrefs {
+            let refer = match &constraint.refer {
+                Some(refer) => refer,
+                None => continue,
+            };
+            let rows = self.key_rows(node, constraint);
+            let keys: Vec<&HashMap<Vec<Value>, KeyRow>> = self
+                .tables
+                .iter()
+                .filter(|table| table.constraint == refer)
+                .filter(|table| (node.position..=end).contains(&table.scope))
+                .map(|table| &table.rows)
+                .collect();
+            let missing: Vec<(&XmlElement, KeyRow)> = rows
+                .into_iter()
+                .filter(|(_, values, _)| !keys.iter().any(|rows| rows.contains_key(values)))
+                .map(|(selected, _, row)| (selected, row))
+                .collect();
+            for (selected, row) in missing {
+                self.error(
+                    selected,
+                    "cvc-identity-constraint.4.3",
+                    &format!(
+                        "Key '{}' with value [{}] not found for identity constraint of \
+                         element '{}'",
+                        constraint.name,
+                        row.text.join(", "),
+                        node.name
+                    ),
+                );
+            }
+        }
+    }
+
+    /// Returns the nodes the selector of `constraint` picks out from
+    /// `node` that have a value for every field, with the values of their
+    /// fields and their rows.
+    ///
+    /// Fields matching more than one node, or an element with element
+    /// children, are reported, and so are the nodes of a key with no
+    /// value for a field.
+    fn key_rows<'n>(
+        &mut self,
+        node: &'n XmlElement,
+        constraint: &IdentityConstraint,
+    ) -> Vec<(&'n XmlElement, Vec<Value>, KeyRow)> {
+        let mut rows = Vec::new();
+        'selected: for selected in constraint.selector.select(node) {
+            let selected = match selected {
+                Selected::Element(element) => element,
+                Selected::Attribute(..) => continue,
+            };
+            let mut values = Vec::new();
+            let mut row = KeyRow {
+                text: Vec::new(),
+                position: selected.position,
+                path: Rc::clone(&selected.path),
+            };
+            for field in &constraint.fields {
+                let found = field.select(selected);
+                let (key, text) = match found.as_slice() {
+                    [] => {
+                        if constraint.category == IdentityConstraintCategory::Key {
+                            self.error(
+                                selected,
+                                "cvc-identity-constraint.4.2.1",
+                                &format!(
+                                    "Element '{}' has no value for the key '{}'",
+                                    selected.name, constraint.name
+                                ),
+                            );
+                        }
+                        continue 'selected;
+                    }
+                    [Selected::Element(element)] if element.child_elements().next().is_some() => {
+                        self.error(
+                            element,
+                            "cvc-identity-constraint.3",
+                            &format!(
+                                "The field '{}' of identity constraint '{}' matches element \
+                                 '{}', which does not have a simple type",
+                                field.source, constraint.name, element.name
+                            ),
+                        );
+                        continue 'selected;
+                    }
+                    [Selected::Element(element)] if is_nilled(element) => {
+                        if constraint.category == IdentityConstraintCategory::Key {
+                            self.error(
+                                element,
+                                "cvc-identity-constraint.4.2.3",
+                                &format!(
+                                    "The field '{}' of key '{}' matches element '{}', which is nil",
+                                    field.source, constraint.name, element.name
+                                ),
+                            );
+                        }
+                        continue 'selected;
+                    }
+                    [Selected::Element(element)] => {
+                        ((element.position, None), text_content(element))
+                    }
+                    [Selected::Attribute(element, name, value)] => {
+                        ((element.position, Some(name.clone())), value.to_string())
+                    }
+                    _ => {
+                        self.error(
+                            selected,
+                            "cvc-identity-constraint.3",
+                            &format!(
+                                "The field '{}' of identity constraint '{}' matches more than \
+                                 one value within the scope of its selector",
+                                field.source, constraint.name
+                            ),
+                        );
+                        continue 'selected;
+                    }
+                };
+                let value = match self.values.get(&key) {
+                    Some(value) => value.clone(),
+                    None => Value::String(text.clone()),
+                };
+                values.push(value);
+                row.text.push(text);
+            }
+            rows.push((selected, values, row));
+        }
+        rows
+    }
+
+    /// Validates an element whose type is a simple type: it has no
+    /// attributes other than those of the XML Schema instance namespace,
+    /// no child elements, and its text is a valid value of `datatype`.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `datatype` - The datatype and facets of its type.
+    /// * `default` - The default or fixed value of the element, if it is
+    ///   empty.
+    ///
+    fn validate_simple_content(
+        &mut self,
+        node: &XmlElement,
+        datatype: ValueType,
+        default: Option<&str>,
+    ) {
+        for (name, _) in node.attributes.iter() {
+            if !is_special_attribute(node, name) {
+                self.error(
+                    node,
+                    "cvc-type.3.1.1",
+                    &format!(
+                        "Element '{}' has a simple type and cannot have attribute '{}'",
+                        node.name, name
+                    ),
+                );
+            }
+        }
+        if node.child_elements().next().is_some() {
+            self.error(
+                node,
+                "cvc-type.3.1.2",
+                &format!(
+                    "Element '{}' has a simple type and cannot have element children",
+                    node.name
+                ),
+            );
+            return;
+        }
+        let text = match default {
+            Some(default) => default.to_string(),
+            None => text_content(node),
+        };
+        self.validate_value(node, &text, datatype);
+    }
+
+    /// Checks that `value` is a valid value of `datatype` and satisfies
+    /// its facets, and that the prefix of a qualified name is bound in
+    /// the scope of `node`.
+    fn validate_value(&mut self, node: &XmlElement, value: &str, datatype: ValueType) {
+        match datatype.check(value, self.version) {
+            Ok(checked) => {
+                self.values.insert((node.position, None), checked);
+            }
+            Err((code, message)) => {
+                self.error(node, &code, &message);
+                return;
+            }
+        }
+        if datatype.datatype().is_derived_from(SimpleDatatype::QName)
+            || datatype
+                .datatype()
+                .is_derived_from(SimpleDatatype::Notation)
+        {
+            if let Err(message) = node.resolve_qname(value) {
+                self.error(node, "cvc-datatype-valid.1.2.1", &message);
+            }
+        }
+    }
+
+    /// Validates an element whose type is the complex type
+    /// `complex_type`: its attributes, then its content.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `complex_type` - The type of the element.
+    /// * `default` - The default or fixed value of the element, if it is
+    ///   empty.
+    ///
+    fn validate_complex_content(
+        &mut self,
+        node: &XmlElement,
+        complex_type: &'a ComplexType,
+        default: Option<&str>,
+    ) {
+        self.validate_attributes(node, complex_type);
+        let children: Vec<&XmlElement> = node.child_elements().collect();
+        let text = match default {
+            Some(default) if complex_type.content == ComplexContent::SimpleContent => {
+                default.to_string()
+            }
+            _ => text_content(node),
+        };
+        if complex_type.content == ComplexContent::SimpleContent {
+            if let Some(child) = children.first() {
+                self.error(
+                    child,
+                    "cvc-complex-type.2.2",
+                    &format!(
+                        "Element '{}' has simple content and cannot have element children",
+                        node.name
+                    ),
+                );
+            } else if let Some(datatype) = self.simple_content_datatype(complex_type) {
+                self.validate_value(node, &text, datatype);
+            }
+            return;
+        }
+        let mixed = complex_type.is_mixed();
+        let particle = match &complex_type.particle {
+            Some(particle) => particle,
+            None => {
+                if !children.is_empty() || (!mixed && !text.trim().is_empty()) {
+                    self.error(
+                        node,
+                        "cvc-complex-type.2.1",
+                        &format!(
+                            "Element '{}' must have no character or element children",
+                            node.name
+                        ),
+                    );
+                }
+                return;
+            }
+        };
+        if !mixed && !text.trim().is_empty() {
+            self.error(
+                node,
+                "cvc-complex-type.2.3",
+                &format!(
+                    "Element '{}' cannot have character children, because its type's \
+                     content type is element-only",
+                    node.name
+                ),
+            );
+        }
+        let compiled;
+        let model = match &complex_type.content_model {
+            Some(model) => model,
+            None => {
+                compiled = ContentModel::compile(particle);
+                &compiled
+            }
+        };
+        let leaves = leaves(particle);
+        let mut run = model.start();
+        let mut matched = Vec::new();
+        let mut invalid = false;
+        for child in &children {
+            let accepts = |term: &Term| self.accepts_child(term, &child.qname, particle);
+            match run.step(&leaves, &accepts) {
+                Some(leaf) => matched.push((*child, leaves[leaf])),
+                None => {
+                    let expected = expected_elements(&run.expected(), &leaves);
+                    let message = match expected {
+                        Some(expected) => (
+                            "cvc-complex-type.2.4.a",
+                            format!(
+                                "Invalid content was found starting with element '{}'. \
+                                 One of '{}' is expected",
+                                child.name, expected
+                            ),
+                        ),
+                        None => (
+                            "cvc-complex-type.2.4.d",
+                            format!(
+                                "Invalid content was found starting with element '{}'. \
+                                 No child element is expected at this point",
+                                child.name
+                            ),
+                        ),
+                    };
+                    self.error(child, message.0, &message.1);
+                    invalid = true;
+                    break;
+                }
+            }
+        }
+        if !invalid && !run.is_complete() {
+            let mut message = format!("The content of element '{}' is not complete", node.name);
+            if let Some(expected) = expected_elements(&run.expected(), &leaves) {
+                message.push_str(&format!(". One of '{}' is expected", expected));
+            }
+            self.error(node, "cvc-complex-type.2.4.b", &message);
+        }
+        // The children matched before the content went wrong are still
+        // validated against the particles they matched
+        for (child, term) in matched {
+            match term {
+                Term::Element(element) if element.name == child.qname => {
+                    self.validate_element(child, element)
+                }
+                Term::Element(element) => {
+                    if let Some(member) = self.substitution(element, &child.qname) {
+                        self.validate_element(child, member);
+                    }
+                }
+                Term::Wildcard(wildcard) => self.validate_wildcard_element(child, wildcard),
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the element declarations and wildcards of the content
+    /// model of `complex_type` that can match the element following the
+    /// children `children`, or `None` if the model does not allow these
+    /// children.
+    ///
+    /// # Arguments
+    ///
+    /// * `complex_type` - The type of the parent element.
+    /// * `children` - The expanded names of the children of the parent
+    ///   element so far.
+    ///
+    pub(crate) fn next_elements(
+        &self,
+        complex_type: &'a ComplexType,
+        children: &[QName],
+    ) -> Option<Vec<&'a Term>> {
+        let particle = match &complex_type.particle {
+            Some(particle) if complex_type.content != ComplexContent::SimpleContent => particle,
+            _ => return children.is_empty().then(Vec::new),
+        };
+        let compiled;
+        let model = match &complex_type.content_model {
+            Some(model) => model,
+            None => {
+                compiled = ContentModel::compile(particle);
+                &compiled
+            }
+        };
+        let leaves = leaves(particle);
+        let mut run = model.start();
+        for name in children {
+            run.step(&leaves, &|term| self.accepts_child(term, name, particle))?;
+        }
+        Some(
+            run.expected()
+                .into_iter()
+                .map(|leaf| leaves[leaf])
+                .collect(),
+        )
+    }
+
+    /// Returns `true` if an element called `name` is accepted by the
+    /// element declaration or wildcard `term` of the content model
+    /// `model`: if it has the name of the declaration or is in its
+    /// substitution group, or if the wildcard allows it.
+    pub(crate) fn accepts_child(&self, term: &Term, name: &QName, model: &Particle) -> bool {
+        match term {
+            Term::Element(element) => {
+                &element.name == name || self.substitution(element, name).is_some()
+            }
+            Term::Wildcard(wildcard) => {
+                let defined = self.global_element(name).is_some();
+                wildcard_matches(wildcard, name, defined, model)
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the global declaration of the element called `name`, if it
+    /// can appear in place of the element declaration `element`.
+    ///
+    /// Only references to global declarations can be substituted, by the
+    /// non-abstract members of the substitution group of the referenced
+    /// element, directly or through other members, unless its `block`
+    /// set excludes substitutions or the derivations its type goes
+    /// through to reach that of the head.
+    pub(crate) fn substitution(&self, element: &Element, name: &QName) -> Option<&'a Element> {
+        let graph = self.graph();
+        let head_id = element.reference_id?;
+        let head = graph.element(head_id);
+        let member = graph.element(graph.element_id(name)?);
+        if member.is_abstract
+            || head.block_set.contains(Derivation::Substitution)
+            || !is_member(&graph, member, head_id)
+        {
+            return None;
+        }
+        let derived = graph.declared_type(&member.datatype, member.type_id)?;
+        let base = graph.declared_type(&head.datatype, head.type_id)?;
+        let (methods, blocked) = derivation_path(&graph, derived, base)?;
+        methods
+            .intersection(blocked.union(head.block_set))
+            .is_empty()
+            .then_some(member)
+    }
+
+    /// Validates an element matched by the wildcard `wildcard` according
+    /// to its `processContents`.
+    fn validate_wildcard_element(&mut self, node: &XmlElement, wildcard: &Wildcard) {
+        if wildcard.process_contents == ProcessContents::Skip {
+            return;
+        }
+        match self.global_element(&node.qname) {
+            Some((schema, declaration)) => {
+                self.in_schema(schema, |validator| {
+                    validator.validate_element(node, declaration)
+                });
+            }
+            None if wildcard.process_contents == ProcessContents::Strict => self.error(
+                node,
+                "cvc-complex-type.2.4.c",
+                &format!(
+                    "The matching wildcard is strict, but no declaration can be found for \
+                     element '{}'",
+                    node.name
+                ),
+            ),
+            None => self.validate_undeclared(node),
+        }
+    }
+
+    /// Assesses an undeclared element matched by a lax wildcard: against
+    /// the type its `xsi:type` selects, if any, or laxly otherwise.
+    fn validate_undeclared(&mut self, node: &XmlElement) {
+        let xsi_type = xsi_attribute(node, "type")
+            .and_then(|value| self.xsi_type(node, value, None))
+            .and_then(|(schema, id)| {
+                let definition = SchemaGraph::new(self.schemas[schema]).type_definition(id);
+                ElementType::of(definition).map(|element_type| (schema, element_type))
+            });
+        match xsi_type {
+            Some((schema, element_type)) => self.in_schema(schema, |validator| {
+                validator.validate_content(node, Some(element_type), None)
+            }),
+            None => self.validate_lax(node),
+        }
+    }
+
+    /// Assesses the attributes and children of an element of type
+    /// `xs:anyType`, or of an undeclared element matched by a lax
+    /// wildcard: those with a global declaration are validated against
+    /// it, and the other children are assessed laxly in turn.
+    fn validate_lax(&mut self, node: &XmlElement) {
+        for (name, value) in node.attributes.iter() {
+            if is_special_attribute(node, name) {
+                continue;
+            }
+            let declaration = node
+                .attribute_qname(name)
+                .ok()
+                .and_then(|qname| self.global_attribute(&qname));
+            if let Some((schema, attribute)) = declaration {
+                self.in_schema(schema, |validator| {
+                    validator.validate_attribute(node, name, value, attribute)
+                });
+            }
+        }
+        for child in node.child_elements() {
+            match self.global_element(&child.qname) {
+                Some((schema, declaration)) => self.in_schema(schema, |validator| {
+                    validator.validate_element(child, declaration)
+                }),
+                None => self.validate_undeclared(child),
+            }
+        }
+    }
+
+    /// Returns the type of the content of a complex type with simple
+    /// content.
+    fn simple_content_datatype(&self, complex_type: &'a ComplexType) -> Option<ValueType<'a>> {
+        complex_type.simple_content.as_deref().map(ValueType::of)
+    }
+
+    /// Returns the global attribute declaration `attribute` refers to, or
+    /// `attribute` itself if it is not a reference.
+    fn attribute_declaration(&self, attribute: &'a Attribute) -> &'a Attribute {
+        match attribute.reference_id {
+            Some(reference) => self.graph().attribute(reference),
+            None => attribute,
+        }
+    }
+
+    /// Returns the simple type of the attribute declaration `declaration`,
+    /// or `None` if it cannot be resolved.
+    fn attribute_type(&self, declaration: &'a Attribute) -> Option<ValueType<'a>> {
+        match self.element_type(&declaration.datatype, declaration.type_id) {
+            Some(ElementType::Simple(datatype)) => Some(datatype),
+            _ => None,
+        }
+    }
+
+    /// Validates the attributes of `node` against the attribute
+    /// declarations and attribute wildcard of `complex_type`, and checks
+    /// that the required attributes are present.
+    ///
+    /// # Arguments
+    ///
+    /// * `node` - The element of the instance document.
+    /// * `complex_type` - The type of the element.
+    ///
+    fn validate_attributes(&mut self, node: &XmlElement, complex_type: &'a ComplexType) {
+        for (name, value) in node.attributes.iter() {
+            if is_special_attribute(node, name) {
+                continue;
+            }
+            let qname = match node.attribute_qname(name) {
+                Ok(qname) => qname,
+                Err(_) => continue,
+            };
+            match complex_type.attributes.get(&qname) {
+                Some(attribute) if attribute.use_option == UseOption::Prohibited => self.error(
+                    node,
+                    "cvc-complex-type.3.2.1",
+                    &format!(
+                        "Attribute '{}' is prohibited in element '{}'",
+                        name, node.name
+                    ),
+                ),
+                Some(attribute) => self.validate_attribute(node, name, value, attribute),
+                None => match &complex_type.attribute_wildcard {
+                    Some(wildcard)
+                        if wildcard.allows(&qname)
+                            && !(wildcard.not_defined
+                                && self.global_attribute(&qname).is_some()) =>
+                    {
+                        if wildcard.process_contents != ProcessContents::Skip {
+                            if let Some((schema, attribute)) = self.global_attribute(&qname) {
+                                self.in_schema(schema, |validator| {
+                                    validator.validate_attribute(node, name, value, attribute)
+                                });
+                            } else if wildcard.process_contents == ProcessContents::Strict {
+                                self.error(
+                                    node,
+                                    "cvc-complex-type.3.2.2",
+                                    &format!(
+                                        "The matching wildcard is strict, but no declaration \
+                                         can be found for attribute '{}'",
+                                        name
+                                    ),
+                                );
+                            }
+                        }
+                    }
+                    _ => self.error(
+                        node,
+                        "cvc-complex-type.3.2.2",
+                        &format!(
+                            "Attribute '{}' is not allowed to appear in element '{}'",
+                            name, node.name
+                        ),
+                    ),
+                },
+            }
+        }
+        let mut missing: Vec<&QName> = complex_type
+            .attributes
+            .iter()
+            .filter(|(_, attribute)| attribute.use_option == UseOption::Required)
+            .map(|(name, _)| name)
+            .filter(|name| {
+                !node
+                    .attributes
+                    .iter()
+                    .any(|(attribute, _)| node.attribute_qname(attribute).as_ref() == Ok(*name))
+            })
+            .collect();
+        missing.sort();
+        for name in missing {
+            self.error(
+                node,
+                "cvc-complex-type.4",
+                &format!(
+                    "Attribute '{}' must appear on element '{}'",
+                    name, node.name
+                ),
+            );
+        }
+    }
+
+    /// Validates the value of the attribute `name` of `node` against its
+    /// declaration, following attribute references to the global
+    /// declaration.
+    fn validate_attribute(
+        &mut self,
+        node: &XmlElement,
+        name: &str,
+        value: &str,
+        attribute: &'a Attribute,
+    ) {
+        let declaration = self.attribute_declaration(attribute);
+        let datatype = self.attribute_type(declaration);
+        if let Some(datatype) = datatype {
+            match datatype.check(value, self.version) {
+                Ok(checked) => {
+                    if let Ok(qname) = node.attribute_qname(name) {
+                        self.values.insert((node.position, Some(qname)), checked);
+                    }
+                }
+                Err((_, message)) => {
+                    self.error(
+                        node,
+                        "cvc-attribute.3",
+                        &format!(
+                            "The value '{}' of attribute '{}' is not valid: {}",
+                            value, name, message
+                        ),
+                    );
+                    return;
+                }
+            }
+        }
+        let fixed = attribute
+            .fixed_value
+            .as_deref()
+            .or(declaration.fixed_value.as_deref());
+        if let Some(fixed) = fixed {
+            if !is_same_value(datatype, value, fixed, self.version) {
+                self.error(
+                    node,
+                    "cvc-attribute.4",
+                    &format!(
+                        "The value '{}' of attribute '{}' does not match its fixed value '{}'",
+                        value, name, fixed
+                    ),
+                );
+            }
+        }
+    }
+}
+
+/// Checks that the default and fixed values of the element and attribute
+/// declarations of `schema`, global and local, are valid values of their
+/// types.
+///
+/// An element whose type is a complex type can only have such a value if
+/// its type has simple content, which the value is checked against, or
+/// mixed content that can be empty.
+///
+/// # Arguments
+///
+/// * `schema` - The schema, once resolved and its content models
+///   compiled.
+/// * `documents` - The schema documents the schema is assembled from.
+///
+pub(crate) fn check_value_constraints(
+    schema: &XmlSchema,
+    documents: &[SchemaDocument],
+) -> Result<(), SchemaError> {
+    let validator = Validator::new(schema, "");
+    let mut elements = Vec::new();
+    let mut attributes = Vec::new();
+    for node in &schema.nodes {
+        match node {
+            XmlSchemaNode::Element(element) => {
+                collect_element(element, &mut elements, &mut attributes)
+            }
+            XmlSchemaNode::Attribute(attribute) => attributes.push(attribute),
+            XmlSchemaNode::ComplexType(complex_type) => {
+                collect_complex_type(complex_type, &mut elements, &mut attributes)
+            }
+            XmlSchemaNode::Group(group) => {
+                for particle in &group.model_group.particles {
+                    collect_particle(particle, &mut elements, &mut attributes);
+                }
+            }
+            XmlSchemaNode::AttributeGroup(attribute_group) => {
+                attributes.extend(attribute_group.attributes.values())
+            }
+            XmlSchemaNode::SimpleType(_) | XmlSchemaNode::Notation(_) => {}
+        }
+    }
+    for element in elements {
+        let (constraint, value) = match (&element.default_value, &element.fixed_value) {
+            _ if element.reference_id.is_some() => continue,
+            (Some(value), _) => ("default", value),
+            (None, Some(value)) => ("fixed", value),
+            (None, None) => continue,
+        };
+        let datatype = match validator.element_type(&element.datatype, element.type_id) {
+            Some(ElementType::Simple(datatype)) => datatype,
+            Some(ElementType::Complex(complex_type))
+                if complex_type.content == ComplexContent::SimpleContent =>
+            {
+                match validator.simple_content_datatype(complex_type) {
+                    Some(datatype) => datatype,
+                    None => continue,
+                }
+            }
+            Some(ElementType::Complex(complex_type)) => {
+                let emptiable = complex_type
+                    .content_model
+                    .as_ref()
+                    .map_or(true, |model| model.start().is_complete());
+                if complex_type.is_mixed() && emptiable {
+                    continue;
+                }
+                return Err(definition_error(
+                    documents,
+                    element,
+                    "e-props-correct.2",
+                    &format!(
+                        "Element '{}' cannot have a {} value, because its type has neither \
+                         simple content nor mixed content that can be empty",
+                        element.name, constraint
+                    ),
+                ));
+            }
+            None => continue,
+        };
+        if let Err((_, message)) = datatype.check(value, validator.version) {
+            return Err(definition_error(
+                documents,
+                element,
+                "e-props-correct.2",
+                &format!(
+                    "The {} value '{}' of element '{}' is not valid: {}",
+                    constraint, value, element.name, message
+                ),
+            ));
+        }
+    }
+    for attribute in attributes {
+        let (constraint, value) = match (&attribute.default_value, &attribute.fixed_value) {
+            (Some(value), _) => ("default", value),
+            (None, Some(value)) => ("fixed", value),
+            (None, None) => continue,
+        };
+        let declaration = validator.attribute_declaration(attribute);
+        let datatype = match validator.attribute_type(declaration) {
+            Some(datatype) => datatype,
+            None => continue,
+        };
+        if let Err((_, message)) = datatype.check(value, validator.version) {
+            return Err(definition_error(
+                documents,
+                attribute,
+                "a-props-correct.2",
+                &format!(
+                    "The {} value '{}' of attribute '{}' is not valid: {}",
+                    constraint, value, attribute.name, message
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` if `member` is in the substitution group of the
+/// element of the handle `head`, directly or through other members.
+fn is_member(graph: &SchemaGraph, member: &Element, head: ElementId) -> bool {
+    member
+        .head_ids
+        .iter()
+        .any(|id| *id == head || is_member(graph, graph.element(*id), head))
+}
+
+/// Adds `element` and the local element and attribute declarations of
+/// its anonymous type to `elements` and `attributes`.
+fn collect_element<'s>(
+    element: &'s Element,
+    elements: &mut Vec<&'s Element>,
+    attributes: &mut Vec<&'s Attribute>,
+) {
+    elements.push(element);
+    if let Datatype::AnonymousComplexType(complex_type) = &element.datatype {
+        collect_complex_type(complex_type, elements, attributes);
+    }
+}
+
+/// Adds the local element and attribute declarations of `complex_type`
+/// to `elements` and `attributes`.
+fn collect_complex_type<'s>(
+    complex_type: &'s ComplexType,
+    elements: &mut Vec<&'s Element>,
+    attributes: &mut Vec<&'s Attribute>,
+) {
+    attributes.extend(complex_type.attributes.values());
+    if let Some(particle) = &complex_type.particle {
+        collect_particle(particle, elements, attributes);
+    }
+}
+
+/// Adds the element declarations of `particle`, and the local element
+/// and attribute declarations of their anonymous types, to `elements`
+/// and `attributes`.
+fn collect_particle<'s>(
+    particle: &'s Particle,
+    elements: &mut Vec<&'s Element>,
+    attributes: &mut Vec<&'s Attribute>,
+) {
+    match &particle.term {
+        Term::Element(element) => collect_element(element, elements, attributes),
+        Term::ModelGroup(group) => {
+            for particle in &group.particles {
+                collect_particle(particle, elements, attributes);
+            }
+        }
+        Term::Wildcard(_) | Term::GroupReference(_) => {}
+    }
+}
+
+/// Returns the first element declaration of the content model
+/// `particle` accepted by `matches`, if any.
+fn find_element<'p>(
+    particle: &'p Particle,
+    matches: &dyn Fn(&Element) -> bool,
+) -> Option<&'p Element> {
+    match &particle.term {
+        Term::Element(element) if matches(element) => Some(element),
+        Term::ModelGroup(group) => group
+            .particles
+            .iter()
+            .find_map(|particle| find_element(particle, matches)),
+        _ => None,
+    }
+}
+
+/// Returns the element declarations and wildcards `expected` among the
+/// leaves `leaves` of a content model, as listed in error messages, or
+/// `None` if there are none.
+fn expected_elements(expected: &[usize], leaves: &[&Term]) -> Option<String> {
+    let mut names: Vec<String> = Vec::new();
+    for name in expected.iter().filter_map(|leaf| leaf_name(leaves[*leaf])) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    (!names.is_empty()).then(|| format!("{{{}}}", names.join(", ")))
+}
+
+/// Returns `true` if the wildcard `wildcard` of the content model `model`
+/// matches elements called `name`.
+///
+/// # Arguments
+///
+/// * `wildcard` - The wildcard.
+/// * `name` - The expanded name of the element.
+/// * `defined` - Whether the element has a global declaration, which
+///   `##defined` excludes.
+/// * `model` - The content model, whose element declarations
+///   `##definedSibling` excludes.
+///
+fn wildcard_matches(wildcard: &Wildcard, name: &QName, defined: bool, model: &Particle) -> bool {
+    wildcard.allows(name)
+        && !(wildcard.not_defined && defined)
+        && !(wildcard.not_defined_sibling
+            && find_element(model, &|element| &element.name == name).is_some())
+}
+
+/// Returns `true` if the attribute `name` of `node` is a namespace
+/// declaration or an attribute of the XML Schema instance namespace,
+/// which are not declared by schemas.
+fn is_special_attribute(node: &XmlElement, name: &str) -> bool {
+    if name == "xmlns" {
+        return true;
+    }
+    match node.attribute_qname(name) {
+        Ok(qname) => matches!(qname.namespace(), Some(XSI_NAMESPACE | XMLNS_NAMESPACE)),
+        Err(_) => true,
+    }
+}
+
+/// Returns the schema location hints of the instance document `root`:
+/// the namespace and location pairs of its `xsi:schemaLocation`
+/// attributes, and the locations of its `xsi:noNamespaceSchemaLocation`
+/// attributes, in document order and without duplicates.
+pub(crate) fn schema_locations(root: &XmlElement) -> Vec<(Option<String>, String)> {
+    let mut hints = Vec::new();
+    let mut elements = vec![root];
+    while let Some(node) = elements.pop() {
+        if let Some(value) = xsi_attribute(node, "schemaLocation") {
+            let tokens: Vec<&str> = value.split_whitespace().collect();
+            for pair in tokens.chunks_exact(2) {
+                hints.push((Some(pair[0].to_string()), pair[1].to_string()));
+            }
+        }
+        if let Some(value) = xsi_attribute(node, "noNamespaceSchemaLocation") {
+            hints.push((None, value.trim().to_string()));
+        }
+        let mut children: Vec<&XmlElement> = node.child_elements().collect();
+        children.reverse();
+        elements.extend(children);
+    }
+    let mut unique = Vec::new();
+    for hint in hints {
+        if !unique.contains(&hint) {
+            unique.push(hint);
+        }
+    }
+    unique
+}
+
+/// Returns the value of the attribute of `node` called `local` in the
+/// XML Schema instance namespace, if any.
+fn xsi_attribute<'n>(node: &'n XmlElement, local: &str) -> Option<&'n str> {
+    node.attributes
+        .iter()
+        .find(|(name, _)| {
+            node.attribute_qname(name).map_or(false, |qname| {
+                qname.namespace() == Some(XSI_NAMESPACE) && qname.local() == local
+            })
+        })
+        .map(|(_, value)| value)
+}
+
+/// Returns `true` if the element `node` is nil.
+fn is_nilled(node: &XmlElement) -> bool {
+    matches!(
+        xsi_attribute(node, "nil").map(str::trim),
+        Some("true" | "1")
+    )
+}
+
+/// Returns `true` if `value` and `fixed` are the same value of
+/// `datatype` in the version `version` of XML Schema, or the same text
+/// once trimmed if the datatype is unknown or either is not a valid
+/// value.
+fn is_same_value(
+    datatype: Option<ValueType>,
+    value: &str,
+    fixed: &str,
+    version: XsdVersion,
+) -> bool {
+    let check = |datatype: ValueType| {
+        (
+            datatype.check(value, version),
+            datatype.check(fixed, version),
+        )
+    };
+    match datatype.map(check) {
+        Some((Ok(value), Ok(fixed))) => value == fixed,
+        _ => value.trim() == fixed.trim(),
+    }
+}
+
+/// Returns the last element of the subtree of `node` in document order.
+fn last_descendant(node: &XmlElement) -> &XmlElement {
+    match node.child_elements().last() {
+        Some(child) => last_descendant(child),
+        None => node,
+    }
+}
+
+/// Returns the text content of `node`, without that of its children.
+fn text_content(node: &XmlElement) -> String {
+    node.children
+        .iter()
+        .filter_map(|child| match child {
+            XmlNode::Text(text) => Some(text.as_str()),
+            XmlNode::Element(_) => None,
+        })
+        .collect()
+}
@@ -0,0 +1,222 @@
+//! The XPath subset of identity constraints.
+//!
+//! The `xpath` of an `xs:selector` or `xs:field` is a union of paths
+//! going down from the element the constraint is declared on: an
+//! optional `.//` followed by child steps, each a name test, a `*` or a
+//! `prefix:*` wildcard, or `.` for the current node. The paths of a field
+//! may end with an attribute step, written `@name` or `attribute::name`.
+//! Prefixes are expanded with the namespaces in scope on the `xs:selector`
+//! or `xs:field` element, and unprefixed names are in no namespace.
+//!
+
+use std::collections::HashSet;
+
+use crate::{document::XmlElement, meta::is_ncname, qname::QName};
+
+/// A compiled `xpath` of a selector or field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct XPath {
+    /// The expression, as written
+    pub(crate) source: String,
+    /// The alternatives of the union
+    paths: Vec<Path>,
+}
+
+/// One path of an [`XPath`] union.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Path {
+    /// Whether the path starts with `.//`, selecting from every
+    /// descendant of the current element as well as from itself
+    descendants: bool,
+    /// The child steps, without the `.` steps
+    steps: Vec<NameTest>,
+    /// The final attribute step of a field, if any
+    attribute: Option<NameTest>,
+}
+
+/// The test of a step on the names of the nodes it selects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NameTest {
+    /// `*`: any name
+    Any,
+    /// `prefix:*`: any name in a namespace
+    Namespace(String),
+    /// A qualified name
+    Name(QName),
+}
+
+/// A node selected by an [`XPath`].
+#[derive(Debug, Clone)]
+pub(crate) enum Selected<'n> {
+    /// An element
+    Element(&'n XmlElement),
+    /// An attribute, with the element it belongs to, its expanded name
+    /// and its value
+    Attribute(&'n XmlElement, QName, &'n str),
+}
+
+impl XPath {
+    /// Compiles the `xpath` attribute `value` of the `xs:selector` or
+    /// `xs:field` element `node`, or returns why it is not a valid
+    /// expression of the subset.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The expression.
+    /// * `node` - The element of the schema document, whose namespaces
+    ///   expand the prefixes of the expression.
+    /// * `field` - Whether the expression is that of a field, whose paths
+    ///   can end with an attribute step.
+    ///
+    pub(crate) fn parse(value: &str, node: &XmlElement, field: bool) -> Result<XPath, String> {
+        let paths = value
+            .split('|')
+            .map(|path| Path::parse(path.trim(), node, field))
+            .collect::<Result<Vec<Path>, String>>()
+            .map_err(|reason| format!("Invalid XPath expression '{}': {}", value, reason))?;
+        Ok(XPath {
+            source: value.to_string(),
+            paths,
+        })
+    }
+
+    /// Returns the nodes the expression selects from `node`, in document
+    /// order and without duplicates.
+    pub(crate) fn select<'n>(&self, node: &'n XmlElement) -> Vec<Selected<'n>> {
+        let mut selected: Vec<Selected> = Vec::new();
+        // The nodes already selected, by the position of the element and
+        // the name of the attribute
+        let mut seen: HashSet<(usize, Option<QName>)> = HashSet::new();
+        for path in &self.paths {
+            for found in path.select(node) {
+                let key = match &found {
+                    Selected::Element(element) => (element.position, None),
+                    Selected::Attribute(element, name, _) => (element.position, Some(name.clone())),
+                };
+                if seen.insert(key) {
+                    selected.push(found);
+                }
+            }
+        }
+        selected.sort_by_key(|found| match found {
+            Selected::Element(element) => (element.position, false),
+            Selected::Attribute(element, _, _) => (element.position, true),
+        });
+        selected
+    }
+}
+
+impl Path {
+    fn parse(value: &str, node: &XmlElement, field: bool) -> Result<Path, String> {
+        let (descendants, rest) = match value.strip_prefix(".//") {
+            Some(rest) => (true, rest),
+            None => (false, value),
+        };
+        let mut path = Path {
+            descendants,
+            steps: Vec::new(),
+            attribute: None,
+        };
+        let steps: Vec<&str> = rest.split('/').map(str::trim).collect();
+        for (index, step) in steps.iter().enumerate() {
+            let attribute = step
+                .strip_prefix('@')
+                .or_else(|| step.strip_prefix("attribute::"));
+            match attribute {
+                Some(_) if !field => {
+                    return Err("a selector cannot select attributes".to_string());
+                }
+                Some(_) if index + 1 < steps.len() => {
+                    return Err("an attribute step must be the last step".to_string());
+                }
+                Some(test) => path.attribute = Some(NameTest::parse(test.trim(), node, true)?),
+                None if *step == "." => {}
+                None => {
+                    let test = step.strip_prefix("child::").unwrap_or(step).trim();
+                    path.steps.push(NameTest::parse(test, node, false)?);
+                }
+            }
+        }
+        Ok(path)
+    }
+
+    fn select<'n>(&self, node: &'n XmlElement) -> Vec<Selected<'n>> {
+        let mut elements = vec![node];
+        if self.descendants {
+            let mut index = 0;
+            while index < elements.len() {
+                let children: Vec<&XmlElement> = elements[index].child_elements().collect();
+                elements.extend(children);
+                index += 1;
+            }
+        }
+        for step in &self.steps {
+            elements = elements
+                .iter()
+                .flat_map(|element| element.child_elements())
+                .filter(|child| step.matches(&child.qname))
+                .collect();
+        }
+        match &self.attribute {
+            Some(test) => elements
+                .iter()
+                .flat_map(|element| {
+                    element.attributes.iter().filter_map(move |(name, value)| {
+                        if name == "xmlns" || name.starts_with("xmlns:") {
+                            return None;
+                        }
+                        let qname = element.attribute_qname(name).ok()?;
+                        test.matches(&qname)
+                            .then_some(Selected::Attribute(element, qname, value))
+                    })
+                })
+                .collect(),
+            None => elements.into_iter().map(Selected::Element).collect(),
+        }
+    }
+}
+
+impl NameTest {
+    /// Compiles the name test `value`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The name test.
+    /// * `node` - The element whose namespaces expand the prefix.
+    /// * `attribute` - Whether the test is that of an attribute step.
+    ///
+    fn parse(value: &str, node: &XmlElement, attribute: bool) -> Result<NameTest, String> {
+        if value == "*" {
+            return Ok(NameTest::Any);
+        }
+        let (prefix, local) = match value.split_once(':') {
+            Some((prefix, local)) => (Some(prefix), local),
+            None => (None, value),
+        };
+        if !prefix.map_or(true, is_ncname)
+            || !(local == "*" && prefix.is_some() || is_ncname(local))
+        {
+            let kind = if attribute { "attribute" } else { "element" };
+            return Err(format!("'{}' is not a valid {} name test", value, kind));
+        }
+        let namespace = match prefix {
+            Some(prefix) => Some(
+                node.namespace(prefix)
+                    .ok_or_else(|| format!("the prefix '{}' is not bound", prefix))?,
+            ),
+            None => None,
+        };
+        match (namespace, local) {
+            (Some(namespace), "*") => Ok(NameTest::Namespace(namespace.to_string())),
+            (namespace, local) => Ok(NameTest::Name(QName::new(namespace, local))),
+        }
+    }
+
+    fn matches(&self, name: &QName) -> bool {
+        match self {
+            NameTest::Any => true,
+            NameTest::Namespace(namespace) => name.namespace() == Some(namespace.as_str()),
+            NameTest::Name(test) => test == name,
+        }
+    }
+}
@@ -0,0 +1,2562 @@
+//! The XPath 2.0 subset of XSD 1.1 assertions.
+//!
+//! The `test` of an `xs:assert` or of an `xs:assertion` facet is compiled
+//! into an expression tree when the schema is read, then evaluated on
+//! every element or value it applies to. The subset covers what the
+//! assertions of XSD 1.1 use: paths along the child, attribute, self,
+//! descendant, parent, ancestor and sibling axes, with name tests, kind
+//! tests and predicates; general, value and node comparisons; arithmetic
+//! and ranges; `if`, `for`, `some` and `every` expressions; casts to the
+//! built-in datatypes; and the core functions of the `fn:` namespace
+//! listed in [`FUNCTIONS`]. As [`Regex`] only matches whole strings,
+//! `fn:matches` is the only regular expression function. The variable
+//! `$value` holds the typed value being checked.
+//!
+//! An assertion sees the element it is evaluated on as the root of its
+//! own tree, without a document node above it. Elements and attributes
+//! whose value was validated against a simple type are atomized into
+//! that typed value, and the others into untyped values, which compare
+//! as numbers against numbers and as strings against strings.
+//!
+
+use std::{cmp::Ordering, ptr};
+
+use crate::{
+    datatypes::{Decimal, SimpleDatatype, Value},
+    document::{XmlElement, XmlNode},
+    meta::is_ncname,
+    qname::{QName, XSD_NAMESPACE},
+    regex::Regex,
+    schema::XsdVersion,
+};
+
+/// The namespace of the XPath functions.
+const FN_NAMESPACE: &str = "http://www.w3.org/2005/xpath-functions";
+
+/// A compiled XPath expression.
+#[derive(Debug, Clone)]
+pub(crate) struct Expression {
+    /// The expression, as written
+    pub(crate) source: String,
+    root: Expr,
+}
+
+/// An atomic value.
+#[derive(Debug, Clone)]
+pub(crate) enum Atomic {
+    /// An `xs:untypedAtomic`: the value of a node without a simple type
+    Untyped(String),
+    /// A string, or a value of a type derived from `xs:string`
+    String(String),
+    /// A boolean
+    Boolean(bool),
+    /// An integer
+    Integer(Decimal),
+    /// A decimal, kept exactly
+    Decimal(Decimal),
+    /// A float or double
+    Double(f64),
+    /// A value of another datatype, such as a date or a duration, with
+    /// its lexical form
+    Typed(Value, String),
+}
+
+/// A node of the tree an expression is evaluated on.
+#[derive(Debug, Clone, Copy)]
+enum Node<'n> {
+    Element(&'n XmlElement),
+    /// An attribute, by its index among the attributes of the element,
+    /// namespace declarations excluded
+    Attribute(&'n XmlElement, usize),
+    /// A text node, by its index among the children of the element
+    Text(&'n XmlElement, usize),
+}
+
+/// An item of a sequence.
+#[derive(Debug, Clone)]
+enum Item<'n> {
+    Node(Node<'n>),
+    Atomic(Atomic),
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Sequence(Vec<Expr>),
+    Literal(Atomic),
+    Variable(String),
+    ContextItem,
+    Root,
+    /// `E1/E2`
+    Path(Box<Expr>, Box<Expr>),
+    Step(Axis, NodeTest, Vec<Expr>),
+    Filter(Box<Expr>, Vec<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    General(Comparison, Box<Expr>, Box<Expr>),
+    Value(Comparison, Box<Expr>, Box<Expr>),
+    /// `is`, `<<` and `>>`
+    Node(Ordering, bool, Box<Expr>, Box<Expr>),
+    Arithmetic(Operator, Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Range(Box<Expr>, Box<Expr>),
+    Union(Box<Expr>, Box<Expr>),
+    Intersect(bool, Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `some` or `every`, with the variables and the sequences they range
+    /// over, and the condition
+    Quantified(bool, Vec<(String, Expr)>, Box<Expr>),
+    For(Vec<(String, Expr)>, Box<Expr>),
+    Call(Function, Vec<Expr>),
+    /// `cast as` or `castable as`, with whether the empty sequence is
+    /// allowed
+    Cast(Box<Expr>, SequenceType, bool),
+    Castable(Box<Expr>, SequenceType, bool),
+    InstanceOf(Box<Expr>, SequenceType, Occurrence),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Axis {
+    Child,
+    Attribute,
+    Context,
+    Descendant,
+    DescendantOrSelf,
+    Parent,
+    Ancestor,
+    AncestorOrSelf,
+    FollowingSibling,
+    PrecedingSibling,
+}
+
+#[derive(Debug, Clone)]
+enum NodeTest {
+    /// `*`
+    Any,
+    /// `prefix:*`
+    Namespace(Option<String>),
+    /// `*:local`
+    Local(String),
+    Name(QName),
+    Kind(NodeKind, Option<Box<NodeTest>>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Any,
+    Element,
+    Attribute,
+    Text,
+    /// Comments, processing instructions and document nodes, which the
+    /// tree does not keep
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    IntegerDivide,
+    Modulo,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Occurrence {
+    One,
+    Optional,
+    Many,
+    AtLeastOne,
+}
+
+/// The type of a `cast`, `castable` or `instance of` expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SequenceType {
+    Atomic(SimpleDatatype),
+    Untyped,
+    Item,
+    Node(NodeKind),
+    Empty,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Function {
+    Builtin(&'static str),
+    Constructor(SimpleDatatype),
+    Untyped,
+}
+
+/// The functions of the `fn:` namespace, with their minimum and maximum
+/// numbers of arguments.
+const FUNCTIONS: [(&str, usize, usize); 45] = [
+    ("abs", 1, 1),
+    ("avg", 1, 1),
+    ("boolean", 1, 1),
+    ("ceiling", 1, 1),
+    ("compare", 2, 2),
+    ("concat", 2, usize::MAX),
+    ("contains", 2, 2),
+    ("count", 1, 1),
+    ("data", 1, 1),
+    ("distinct-values", 1, 1),
+    ("empty", 1, 1),
+    ("ends-with", 2, 2),
+    ("exactly-one", 1, 1),
+    ("exists", 1, 1),
+    ("false", 0, 0),
+    ("floor", 1, 1),
+    ("index-of", 2, 2),
+    ("last", 0, 0),
+    ("local-name", 0, 1),
+    ("lower-case", 1, 1),
+    ("matches", 2, 2),
+    ("max", 1, 1),
+    ("min", 1, 1),
+    ("name", 0, 1),
+    ("namespace-uri", 0, 1),
+    ("normalize-space", 0, 1),
+    ("not", 1, 1),
+    ("number", 0, 1),
+    ("one-or-more", 1, 1),
+    ("position", 0, 0),
+    ("reverse", 1, 1),
+    ("round", 1, 1),
+    ("starts-with", 2, 2),
+    ("string", 0, 1),
+    ("string-join", 2, 2),
+    ("string-length", 0, 1),
+    ("subsequence", 2, 3),
+    ("substring", 2, 3),
+    ("substring-after", 2, 2),
+    ("substring-before", 2, 2),
+    ("sum", 1, 2),
+    ("translate", 3, 3),
+    ("true", 0, 0),
+    ("upper-case", 1, 1),
+    ("zero-or-one", 1, 1),
+];
+
+impl Expression {
+    /// Compiles the expression `source`, or returns why it is not a valid
+    /// expression of the subset.
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - The expression.
+    /// * `namespace` - Returns the namespace bound to a prefix.
+    /// * `default_namespace` - The namespace of unprefixed element names,
+    ///   if any.
+    ///
+    pub(crate) fn parse(
+        source: &str,
+        namespace: &dyn Fn(&str) -> Option<String>,
+        default_namespace: Option<&str>,
+    ) -> Result<Expression, String> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser {
+            tokens,
+            index: 0,
+            namespace,
+            default_namespace,
+        };
+        let root = parser.expr()?;
+        if let Some(token) = parser.tokens.get(parser.index) {
+            return Err(format!("unexpected {}", token));
+        }
+        Ok(Expression {
+            source: source.to_string(),
+            root,
+        })
+    }
+
+    /// Evaluates the expression and returns its effective boolean value.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The element the expression is evaluated on, which
+    ///   is the root of its tree, if any.
+    /// * `value` - The value of `$value`.
+    /// * `typed` - Returns the typed value of an element, or of one of
+    ///   its attributes, if it was validated against a simple type.
+    ///
+    pub(crate) fn test(
+        &self,
+        context: Option<&XmlElement>,
+        value: Vec<Atomic>,
+        typed: &dyn Fn(&XmlElement, Option<&QName>) -> Option<Value>,
+    ) -> Result<bool, String> {
+        let evaluator = Evaluator {
+            typed,
+            root: context,
+        };
+        let focus = Focus {
+            item: context.map(|element| Item::Node(Node::Element(element))),
+            position: 1,
+            size: 1,
+        };
+        let value = value.into_iter().map(Item::Atomic).collect();
+        let mut variables = vec![("value".to_string(), value)];
+        let result = evaluator.eval(&self.root, &focus, &mut variables)?;
+        effective_boolean_value(&result)
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Expression) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Eq for Expression {}
+
+impl Atomic {
+    /// Returns the atomic values of the typed value `value`, whose
+    /// lexical form is `lexical`.
+    pub(crate) fn from_value(value: Value, lexical: &str) -> Vec<Atomic> {
+        let lexical = lexical.trim();
+        match value {
+            Value::String(string) => vec![Atomic::String(string)],
+            Value::Boolean(boolean) => vec![Atomic::Boolean(boolean)],
+            Value::Decimal(decimal) => match lexical.contains('.') {
+                true => vec![Atomic::Decimal(decimal)],
+                false => vec![Atomic::Integer(decimal)],
+            },
+            Value::Double(double) => vec![Atomic::Double(double)],
+            Value::List(items) => items
+                .into_iter()
+                .zip(lexical.split_whitespace())
+                .flat_map(|(item, lexical)| Atomic::from_value(item, lexical))
+                .collect(),
+            value => vec![Atomic::Typed(value, lexical.to_string())],
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        matches!(
+            self,
+            Atomic::Integer(_) | Atomic::Decimal(_) | Atomic::Double(_)
+        )
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        match self {
+            Atomic::Integer(decimal) | Atomic::Decimal(decimal) => Some(decimal.to_f64()),
+            Atomic::Double(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// Returns the string value of the atomic value.
+    fn string(&self) -> String {
+        match self {
+            Atomic::Untyped(string) | Atomic::String(string) | Atomic::Typed(_, string) => {
+                string.clone()
+            }
+            Atomic::Boolean(boolean) => boolean.to_string(),
+            Atomic::Integer(decimal) | Atomic::Decimal(decimal) => decimal.to_string(),
+            Atomic::Double(number) => format_double(*number),
+        }
+    }
+
+    /// Returns the name of the type of the atomic value.
+    fn type_name(&self) -> &'static str {
+        match self {
+            Atomic::Untyped(_) => "xs:untypedAtomic",
+            Atomic::String(_) => "xs:string",
+            Atomic::Boolean(_) => "xs:boolean",
+            Atomic::Integer(_) => "xs:integer",
+            Atomic::Decimal(_) => "xs:decimal",
+            Atomic::Double(_) => "xs:double",
+            Atomic::Typed(_, _) => "xs:anyAtomicType",
+        }
+    }
+}
+
+fn format_double(number: f64) -> String {
+    if number.is_nan() {
+        return "NaN".to_string();
+    }
+    if number.is_infinite() {
+        return if number > 0.0 { "INF" } else { "-INF" }.to_string();
+    }
+    if number == 0.0 {
+        return if number.is_sign_negative() { "-0" } else { "0" }.to_string();
+    }
+    if (1e-6..1e6).contains(&number.abs()) {
+        return number.to_string();
+    }
+    let formatted = format!("{:E}", number);
+    match formatted.split_once('E') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') => {
+            format!("{}.0E{}", mantissa, exponent)
+        }
+        _ => formatted,
+    }
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "name '{}'", name),
+            Token::Variable(name) => write!(f, "variable '${}'", name),
+            Token::String(string) => write!(f, "string \"{}\"", string),
+            Token::Number(number) => write!(f, "number '{}'", number),
+            Token::Symbol(symbol) => write!(f, "'{}'", symbol),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A name, `prefix:*` or `*:local`
+    Name(String),
+    /// A variable reference, without the `$`
+    Variable(String),
+    String(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 24] = [
+    "//", "::", "..", "!=", "<=", ">=", "<<", ">>", "(", ")", "[", "]", ",", "/", "@", ".", "*",
+    "+", "-", "=", "<", ">", "|", "?",
+];
+
+/// Splits the expression `source` into tokens.
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+    let name_start = |ch: char| ch != ':' && crate::tokenizer::is_name_start_char(ch);
+    let name_char = |ch: char| ch != ':' && crate::tokenizer::is_name_char(ch);
+    let read_name = |index: &mut usize| {
+        let start = *index;
+        while *index < chars.len() && name_char(chars[*index]) {
+            *index += 1;
+        }
+        chars[start..*index].iter().collect::<String>()
+    };
+    while index < chars.len() {
+        let ch = chars[index];
+        let next = chars.get(index + 1).copied();
+        if ch.is_whitespace() {
+            index += 1;
+        } else if ch == '(' && next == Some(':') {
+            let mut depth = 0;
+            loop {
+                match (chars.get(index), chars.get(index + 1)) {
+                    (Some('('), Some(':')) => {
+                        depth += 1;
+                        index += 2;
+                    }
+                    (Some(':'), Some(')')) => {
+                        depth -= 1;
+                        index += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    (Some(_), _) => index += 1,
+                    (None, _) => return Err("unterminated comment".to_string()),
+                }
+            }
+        } else if ch.is_ascii_digit() || ch == '.' && next.map_or(false, |ch| ch.is_ascii_digit()) {
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+                index += 1;
+            }
+            if matches!(chars.get(index), Some('e' | 'E')) {
+                index += 1;
+                if matches!(chars.get(index), Some('+' | '-')) {
+                    index += 1;
+                }
+                while index < chars.len() && chars[index].is_ascii_digit() {
+                    index += 1;
+                }
+            }
+            tokens.push(Token::Number(chars[start..index].iter().collect()));
+        } else if ch == '"' || ch == '\'' {
+            let mut string = String::new();
+            index += 1;
+            loop {
+                match chars.get(index) {
+                    Some(&quote) if quote == ch && chars.get(index + 1) == Some(&ch) => {
+                        string.push(ch);
+                        index += 2;
+                    }
+                    Some(&quote) if quote == ch => {
+                        index += 1;
+                        break;
+                    }
+                    Some(&other) => {
+                        string.push(other);
+                        index += 1;
+                    }
+                    None => return Err("unterminated string literal".to_string()),
+                }
+            }
+            tokens.push(Token::String(string));
+        } else if ch == '$' {
+            index += 1;
+            while chars.get(index).map_or(false, |ch| ch.is_whitespace()) {
+                index += 1;
+            }
+            let mut name = read_name(&mut index);
+            if chars.get(index) == Some(&':')
+                && chars.get(index + 1).map_or(false, |ch| name_start(*ch))
+            {
+                index += 1;
+                name = format!("{}:{}", name, read_name(&mut index));
+            }
+            if name.is_empty() {
+                return Err("'$' must be followed by a variable name".to_string());
+            }
+            tokens.push(Token::Variable(name));
+        } else if name_start(ch) {
+            let mut name = read_name(&mut index);
+            if chars.get(index) == Some(&':') && chars.get(index + 1) != Some(&':') {
+                match chars.get(index + 1) {
+                    Some('*') => {
+                        index += 2;
+                        name.push_str(":*");
+                    }
+                    Some(ch) if name_start(*ch) => {
+                        index += 1;
+                        name = format!("{}:{}", name, read_name(&mut index));
+                    }
+                    _ => {}
+                }
+            }
+            tokens.push(Token::Name(name));
+        } else if ch == '*'
+            && next == Some(':')
+            && chars.get(index + 2).map_or(false, |ch| name_start(*ch))
+        {
+            index += 2;
+            tokens.push(Token::Name(format!("*:{}", read_name(&mut index))));
+        } else {
+            let rest: String = chars[index..chars.len().min(index + 2)].iter().collect();
+            let symbol = SYMBOLS
+                .iter()
+                .find(|symbol| rest.starts_with(**symbol))
+                .ok_or_else(|| format!("unexpected character '{}'", ch))?;
+            index += symbol.chars().count();
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parses the tokens of an expression into an expression tree.
+struct Parser<'p> {
+    tokens: Vec<Token>,
+    index: usize,
+    /// Returns the namespace bound to a prefix
+    namespace: &'p dyn Fn(&str) -> Option<String>,
+    /// The namespace of unprefixed element names
+    default_namespace: Option<&'p str>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.index)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens.get(self.index + offset)
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(found)) if *found == symbol)
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Name(found)) if found == keyword)
+    }
+
+    fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let found = self.is_symbol(symbol);
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.is_keyword(keyword);
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        match self.eat_symbol(symbol) {
+            true => Ok(()),
+            false => Err(self.expected(&format!("'{}'", symbol))),
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        match self.eat_keyword(keyword) {
+            true => Ok(()),
+            false => Err(self.expected(&format!("'{}'", keyword))),
+        }
+    }
+
+    fn expected(&self, what: &str) -> String {
+        match self.peek() {
+            Some(token) => format!("expected {}, found {}", what, token),
+            None => format!("expected {} at the end of the expression", what),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut items = vec![self.expr_single()?];
+        while self.eat_symbol(",") {
+            items.push(self.expr_single()?);
+        }
+        Ok(match items.len() {
+            1 => items.remove(0),
+            _ => Expr::Sequence(items),
+        })
+    }
+
+    fn expr_single(&mut self) -> Result<Expr, String> {
+        let binds = matches!(self.peek_at(1), Some(Token::Variable(_)));
+        if binds && self.is_keyword("for") {
+            self.index += 1;
+            let bindings = self.bindings("in")?;
+            self.expect_keyword("return")?;
+            return Ok(Expr::For(bindings, Box::new(self.expr_single()?)));
+        }
+        if binds && (self.is_keyword("some") || self.is_keyword("every")) {
+            let every = self.is_keyword("every");
+            self.index += 1;
+            let bindings = self.bindings("in")?;
+            self.expect_keyword("satisfies")?;
+            return Ok(Expr::Quantified(
+                every,
+                bindings,
+                Box::new(self.expr_single()?),
+            ));
+        }
+        if self.is_keyword("if") && matches!(self.peek_at(1), Some(Token::Symbol("("))) {
+            self.index += 2;
+            let condition = self.expr()?;
+            self.expect_symbol(")")?;
+            self.expect_keyword("then")?;
+            let then = self.expr_single()?;
+            self.expect_keyword("else")?;
+            let otherwise = self.expr_single()?;
+            return Ok(Expr::If(
+                Box::new(condition),
+                Box::new(then),
+                Box::new(otherwise),
+            ));
+        }
+        self.or_expr()
+    }
+
+    /// Parses the `$name in expr` bindings of a `for`, `some` or `every`
+    /// expression.
+    fn bindings(&mut self, keyword: &str) -> Result<Vec<(String, Expr)>, String> {
+        let mut bindings = Vec::new();
+        loop {
+            let name = match self.peek() {
+                Some(Token::Variable(name)) => name.clone(),
+                _ => return Err(self.expected("a variable")),
+            };
+            self.index += 1;
+            self.expect_keyword(keyword)?;
+            bindings.push((name, self.expr_single()?));
+            if !self.eat_symbol(",") {
+                return Ok(bindings);
+            }
+        }
+    }
+
+    fn or_expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and_expr()?;
+        while self.eat_keyword("or") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and_expr()?));
+        }
+        Ok(expr)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, String> {
+        let mut expr = self.comparison()?;
+        while self.eat_keyword("and") {
+            expr = Expr::And(Box::new(expr), Box::new(self.comparison()?));
+        }
+        Ok(expr)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.range()?;
+        let general = match self.peek() {
+            Some(Token::Symbol(symbol)) => match *symbol {
+                "=" => Some(Comparison::Equal),
+                "!=" => Some(Comparison::NotEqual),
+                "<" => Some(Comparison::Less),
+                "<=" => Some(Comparison::LessOrEqual),
+                ">" => Some(Comparison::Greater),
+                ">=" => Some(Comparison::GreaterOrEqual),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(comparison) = general {
+            self.index += 1;
+            let right = self.range()?;
+            return Ok(Expr::General(comparison, Box::new(left), Box::new(right)));
+        }
+        let value = match self.peek() {
+            Some(Token::Name(name)) => match name.as_str() {
+                "eq" => Some(Comparison::Equal),
+                "ne" => Some(Comparison::NotEqual),
+                "lt" => Some(Comparison::Less),
+                "le" => Some(Comparison::LessOrEqual),
+                "gt" => Some(Comparison::Greater),
+                "ge" => Some(Comparison::GreaterOrEqual),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(comparison) = value {
+            self.index += 1;
+            let right = self.range()?;
+            return Ok(Expr::Value(comparison, Box::new(left), Box::new(right)));
+        }
+        let node = if self.eat_keyword("is") {
+            Some((Ordering::Equal, true))
+        } else if self.eat_symbol("<<") {
+            Some((Ordering::Less, false))
+        } else if self.eat_symbol(">>") {
+            Some((Ordering::Greater, false))
+        } else {
+            None
+        };
+        match node {
+            Some((ordering, identity)) => {
+                let right = self.range()?;
+                Ok(Expr::Node(
+                    ordering,
+                    identity,
+                    Box::new(left),
+                    Box::new(right),
+                ))
+            }
+            None => Ok(left),
+        }
+    }
+
+    fn range(&mut self) -> Result<Expr, String> {
+        let left = self.additive()?;
+        if self.eat_keyword("to") {
+            return Ok(Expr::Range(Box::new(left), Box::new(self.additive()?)));
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, String> {
+        let mut expr = self.multiplicative()?;
+        loop {
+            let operator = if self.eat_symbol("+") {
+                Operator::Add
+            } else if self.eat_symbol("-") {
+                Operator::Subtract
+            } else {
+                return Ok(expr);
+            };
+            let right = self.multiplicative()?;
+            expr = Expr::Arithmetic(operator, Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, String> {
+        let mut expr = self.union()?;
+        loop {
+            let operator = if self.eat_symbol("*") {
+                Operator::Multiply
+            } else if self.eat_keyword("div") {
+                Operator::Divide
+            } else if self.eat_keyword("idiv") {
+                Operator::IntegerDivide
+            } else if self.eat_keyword("mod") {
+                Operator::Modulo
+            } else {
+                return Ok(expr);
+            };
+            let right = self.union()?;
+            expr = Expr::Arithmetic(operator, Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn union(&mut self) -> Result<Expr, String> {
+        let mut expr = self.intersect()?;
+        while self.eat_keyword("union") || self.eat_symbol("|") {
+            expr = Expr::Union(Box::new(expr), Box::new(self.intersect()?));
+        }
+        Ok(expr)
+    }
+
+    fn intersect(&mut self) -> Result<Expr, String> {
+        let mut expr = self.instance_of()?;
+        loop {
+            let intersect = if self.eat_keyword("intersect") {
+                true
+            } else if self.eat_keyword("except") {
+                false
+            } else {
+                return Ok(expr);
+            };
+            let right = self.instance_of()?;
+            expr = Expr::Intersect(intersect, Box::new(expr), Box::new(right));
+        }
+    }
+
+    fn instance_of(&mut self) -> Result<Expr, String> {
+        let expr = self.castable()?;
+        if self.is_keyword("instance")
+            && matches!(self.peek_at(1), Some(Token::Name(name)) if name == "of")
+        {
+            self.index += 2;
+            let sequence_type = self.sequence_type()?;
+            let occurrence = if self.eat_symbol("*") {
+                Occurrence::Many
+            } else if self.eat_symbol("+") {
+                Occurrence::AtLeastOne
+            } else if self.eat_question_mark() {
+                Occurrence::Optional
+            } else {
+                Occurrence::One
+            };
+            return Ok(Expr::InstanceOf(Box::new(expr), sequence_type, occurrence));
+        }
+        Ok(expr)
+    }
+
+    fn castable(&mut self) -> Result<Expr, String> {
+        let expr = self.cast()?;
+        if self.is_keyword("castable")
+            && matches!(self.peek_at(1), Some(Token::Name(name)) if name == "as")
+        {
+            self.index += 2;
+            let (target, optional) = self.single_type()?;
+            return Ok(Expr::Castable(Box::new(expr), target, optional));
+        }
+        Ok(expr)
+    }
+
+    fn cast(&mut self) -> Result<Expr, String> {
+        let expr = self.unary()?;
+        if self.is_keyword("cast")
+            && matches!(self.peek_at(1), Some(Token::Name(name)) if name == "as")
+        {
+            self.index += 2;
+            let (target, optional) = self.single_type()?;
+            return Ok(Expr::Cast(Box::new(expr), target, optional));
+        }
+        Ok(expr)
+    }
+
+    /// Parses the atomic type of a `cast` or `castable` expression, and
+    /// whether it is followed by `?`.
+    fn single_type(&mut self) -> Result<(SequenceType, bool), String> {
+        let target = match self.sequence_type()? {
+            target @ (SequenceType::Atomic(_) | SequenceType::Untyped) => target,
+            _ => return Err("the target of a cast must be an atomic type".to_string()),
+        };
+        Ok((target, self.eat_question_mark()))
+    }
+
+    fn eat_question_mark(&mut self) -> bool {
+        self.eat_symbol("?")
+    }
+
+    fn sequence_type(&mut self) -> Result<SequenceType, String> {
+        let name = match self.peek() {
+            Some(Token::Name(name)) => name.clone(),
+            _ => return Err(self.expected("a type")),
+        };
+        self.index += 1;
+        if self.eat_symbol("(") {
+            let kind = match name.as_str() {
+                "item" => SequenceType::Item,
+                "node" => SequenceType::Node(NodeKind::Any),
+                "element" => SequenceType::Node(NodeKind::Element),
+                "attribute" => SequenceType::Node(NodeKind::Attribute),
+                "text" => SequenceType::Node(NodeKind::Text),
+                "empty-sequence" => SequenceType::Empty,
+                _ => return Err(format!("unknown sequence type '{}()'", name)),
+            };
+            self.expect_symbol(")")?;
+            return Ok(kind);
+        }
+        let qname = self.qname(&name, None)?;
+        match (qname.namespace(), qname.local()) {
+            (Some(XSD_NAMESPACE), "untypedAtomic") => Ok(SequenceType::Untyped),
+            (Some(XSD_NAMESPACE), local) => match SimpleDatatype::from_name(local) {
+                Some(datatype)
+                    if datatype != SimpleDatatype::AnySimpleType
+                        && datatype.item_type().is_none() =>
+                {
+                    Ok(SequenceType::Atomic(datatype))
+                }
+                _ => Err(format!("unknown type '{}'", name)),
+            },
+            _ => Err(format!("unknown type '{}'", name)),
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat_symbol("-") {
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        if self.eat_symbol("+") {
+            return self.unary();
+        }
+        self.path()
+    }
+
+    fn path(&mut self) -> Result<Expr, String> {
+        if self.eat_symbol("/") {
+            if !self.starts_step() {
+                return Ok(Expr::Root);
+            }
+            let step = self.step()?;
+            return self.relative(Expr::Path(Box::new(Expr::Root), Box::new(step)));
+        }
+        if self.eat_symbol("//") {
+            let descendants = Expr::Path(Box::new(Expr::Root), Box::new(descendant_or_self()));
+            let step = self.step()?;
+            return self.relative(Expr::Path(Box::new(descendants), Box::new(step)));
+        }
+        let step = self.step()?;
+        self.relative(step)
+    }
+
+    fn relative(&mut self, mut expr: Expr) -> Result<Expr, String> {
+        loop {
+            if self.eat_symbol("/") {
+                let step = self.step()?;
+                expr = Expr::Path(Box::new(expr), Box::new(step));
+            } else if self.eat_symbol("//") {
+                expr = Expr::Path(Box::new(expr), Box::new(descendant_or_self()));
+                let step = self.step()?;
+                expr = Expr::Path(Box::new(expr), Box::new(step));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    /// Returns `true` if the next token can start a step.
+    fn starts_step(&self) -> bool {
+        match self.peek() {
+            Some(Token::Name(_) | Token::Variable(_) | Token::String(_) | Token::Number(_)) => true,
+            Some(Token::Symbol(symbol)) => matches!(*symbol, "@" | "." | ".." | "*" | "("),
+            None => false,
+        }
+    }
+
+    fn step(&mut self) -> Result<Expr, String> {
+        if self.eat_symbol("..") {
+            return Ok(Expr::Step(
+                Axis::Parent,
+                NodeTest::Kind(NodeKind::Any, None),
+                Vec::new(),
+            ));
+        }
+        let axis = if self.eat_symbol("@") {
+            Some(Axis::Attribute)
+        } else if let (Some(Token::Name(name)), Some(Token::Symbol("::"))) =
+            (self.peek(), self.peek_at(1))
+        {
+            let axis = match name.as_str() {
+                "child" => Axis::Child,
+                "attribute" => Axis::Attribute,
+                "self" => Axis::Context,
+                "descendant" => Axis::Descendant,
+                "descendant-or-self" => Axis::DescendantOrSelf,
+                "parent" => Axis::Parent,
+                "ancestor" => Axis::Ancestor,
+                "ancestor-or-self" => Axis::AncestorOrSelf,
+                "following-sibling" => Axis::FollowingSibling,
+                "preceding-sibling" => Axis::PrecedingSibling,
+                _ => return Err(format!("unsupported axis '{}'", name)),
+            };
+            self.index += 2;
+            Some(axis)
+        } else {
+            None
+        };
+        let is_call = matches!(
+            (self.peek(), self.peek_at(1)),
+            (Some(Token::Name(_)), Some(Token::Symbol("(")))
+        );
+        let is_test = match self.peek() {
+            Some(Token::Name(name)) => !is_call || kind_test(name).is_some(),
+            Some(Token::Symbol("*")) => true,
+            _ => false,
+        };
+        if axis.is_some() || is_test {
+            let test = self.node_test(axis == Some(Axis::Attribute))?;
+            let axis = axis.unwrap_or(match test {
+                NodeTest::Kind(NodeKind::Attribute, _) => Axis::Attribute,
+                _ => Axis::Child,
+            });
+            let predicates = self.predicates()?;
+            return Ok(Expr::Step(axis, test, predicates));
+        }
+        let primary = self.primary()?;
+        let predicates = self.predicates()?;
+        Ok(match predicates.is_empty() {
+            true => primary,
+            false => Expr::Filter(Box::new(primary), predicates),
+        })
+    }
+
+    fn predicates(&mut self) -> Result<Vec<Expr>, String> {
+        let mut predicates = Vec::new();
+        while self.eat_symbol("[") {
+            predicates.push(self.expr()?);
+            self.expect_symbol("]")?;
+        }
+        Ok(predicates)
+    }
+
+    fn node_test(&mut self, attribute: bool) -> Result<NodeTest, String> {
+        if self.eat_symbol("*") {
+            return Ok(NodeTest::Any);
+        }
+        let name = match self.peek() {
+            Some(Token::Name(name)) => name.clone(),
+            _ => return Err(self.expected("a node test")),
+        };
+        self.index += 1;
+        if let Some(kind) = kind_test(&name) {
+            if self.eat_symbol("(") {
+                let mut test = None;
+                if !self.is_symbol(")") && matches!(kind, NodeKind::Element | NodeKind::Attribute) {
+                    test = Some(Box::new(self.node_test(kind == NodeKind::Attribute)?));
+                }
+                self.expect_symbol(")")?;
+                return Ok(NodeTest::Kind(kind, test));
+            }
+        }
+        self.name_test(&name, attribute)
+    }
+
+    fn name_test(&self, name: &str, attribute: bool) -> Result<NodeTest, String> {
+        if let Some(local) = name.strip_prefix("*:") {
+            return Ok(NodeTest::Local(local.to_string()));
+        }
+        if let Some(prefix) = name.strip_suffix(":*") {
+            return Ok(NodeTest::Namespace(Some(self.prefix(prefix)?)));
+        }
+        let default = match attribute {
+            true => None,
+            false => self.default_namespace,
+        };
+        Ok(NodeTest::Name(self.qname(name, default)?))
+    }
+
+    /// Returns the namespace bound to `prefix`. The `xs` and `fn`
+    /// prefixes are bound to the namespaces of XML Schema and of the
+    /// functions unless the schema document binds them otherwise.
+    fn prefix(&self, prefix: &str) -> Result<String, String> {
+        (self.namespace)(prefix)
+            .or_else(|| match prefix {
+                "xs" => Some(XSD_NAMESPACE.to_string()),
+                "fn" => Some(FN_NAMESPACE.to_string()),
+                _ => None,
+            })
+            .ok_or_else(|| format!("the prefix '{}' is not bound", prefix))
+    }
+
+    /// Expands the name `name`, whose unprefixed form is in the namespace
+    /// `default`.
+    fn qname(&self, name: &str, default: Option<&str>) -> Result<QName, String> {
+        match name.split_once(':') {
+            Some((prefix, local)) if is_ncname(prefix) && is_ncname(local) => {
+                Ok(QName::new(Some(&self.prefix(prefix)?), local))
+            }
+            None if is_ncname(name) => Ok(QName::new(default, name)),
+            _ => Err(format!("'{}' is not a valid name", name)),
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = match self.peek() {
+            Some(token) => token.clone(),
+            None => return Err(self.expected("an expression")),
+        };
+        self.index += 1;
+        match token {
+            Token::String(string) => Ok(Expr::Literal(Atomic::String(string))),
+            Token::Number(number) => parse_number(&number).map(Expr::Literal),
+            Token::Variable(name) => Ok(Expr::Variable(name)),
+            Token::Symbol(".") => Ok(Expr::ContextItem),
+            Token::Symbol("(") => {
+                if self.eat_symbol(")") {
+                    return Ok(Expr::Sequence(Vec::new()));
+                }
+                let expr = self.expr()?;
+                self.expect_symbol(")")?;
+                Ok(match expr {
+                    Expr::Sequence(items) => Expr::Sequence(items),
+                    expr => Expr::Sequence(vec![expr]),
+                })
+            }
+            Token::Name(name) if self.eat_symbol("(") => {
+                let mut arguments = Vec::new();
+                if !self.eat_symbol(")") {
+                    loop {
+                        arguments.push(self.expr_single()?);
+                        if self.eat_symbol(")") {
+                            break;
+                        }
+                        self.expect_symbol(",")?;
+                    }
+                }
+                let function = self.function(&name, arguments.len())?;
+                Ok(Expr::Call(function, arguments))
+            }
+            token => {
+                self.index -= 1;
+                Err(format!("unexpected {}", token))
+            }
+        }
+    }
+
+    /// Returns the function called `name` taking `arity` arguments.
+    fn function(&self, name: &str, arity: usize) -> Result<Function, String> {
+        let qname = self.qname(name, Some(FN_NAMESPACE))?;
+        let function = match (qname.namespace(), qname.local()) {
+            (Some(FN_NAMESPACE), local) => FUNCTIONS
+                .iter()
+                .find(|(name, _, _)| *name == local)
+                .map(|(name, min, max)| (Function::Builtin(name), *min, *max)),
+            (Some(XSD_NAMESPACE), "untypedAtomic") => Some((Function::Untyped, 1, 1)),
+            (Some(XSD_NAMESPACE), local) => SimpleDatatype::from_name(local)
+                .filter(|datatype| {
+                    datatype.item_type().is_none()
+                        && !matches!(
+                            datatype,
+                            SimpleDatatype::AnySimpleType
+                                | SimpleDatatype::AnyAtomicType
+                                | SimpleDatatype::Notation
+                        )
+                })
+                .map(|datatype| (Function::Constructor(datatype), 1, 1)),
+            _ => None,
+        };
+        match function {
+            Some((function, min, max)) if (min..=max).contains(&arity) => Ok(function),
+            Some(_) => Err(format!(
+                "function '{}' cannot take {} arguments",
+                name, arity
+            )),
+            None => Err(format!("unknown function '{}'", name)),
+        }
+    }
+}
+
+/// Returns the kind of the kind test called `name`, if it is one.
+fn kind_test(name: &str) -> Option<NodeKind> {
+    match name {
+        "node" => Some(NodeKind::Any),
+        "element" => Some(NodeKind::Element),
+        "attribute" => Some(NodeKind::Attribute),
+        "text" => Some(NodeKind::Text),
+        "comment" | "processing-instruction" | "document-node" => Some(NodeKind::Other),
+        _ => None,
+    }
+}
+
+/// Returns the `descendant-or-self::node()` step `//` stands for.
+fn descendant_or_self() -> Expr {
+    Expr::Step(
+        Axis::DescendantOrSelf,
+        NodeTest::Kind(NodeKind::Any, None),
+        Vec::new(),
+    )
+}
+
+fn parse_number(number: &str) -> Result<Atomic, String> {
+    let invalid = || format!("'{}' is not a valid number", number);
+    if number.contains(['e', 'E']) {
+        return number.parse().map(Atomic::Double).map_err(|_| invalid());
+    }
+    if number.contains('.') {
+        return Decimal::parse(number, true)
+            .map(Atomic::Decimal)
+            .map_err(|_| invalid());
+    }
+    Decimal::parse(number, false)
+        .map(Atomic::Integer)
+        .map_err(|_| invalid())
+}
+
+/// The focus of an expression: the context item, its position and the
+/// size of the sequence it is taken from.
+struct Focus<'n> {
+    item: Option<Item<'n>>,
+    position: usize,
+    size: usize,
+}
+
+/// The variables in scope, the latest binding of a name hiding the
+/// earlier ones.
+type Variables<'n> = Vec<(String, Vec<Item<'n>>)>;
+
+/// Evaluates expressions on the tree of an element.
+struct Evaluator<'t, 'n> {
+    /// Returns the typed value of an element or attribute
+    typed: &'t dyn Fn(&XmlElement, Option<&QName>) -> Option<Value>,
+    /// The root of the tree
+    root: Option<&'n XmlElement>,
+}
+
+impl<'t, 'n> Evaluator<'t, 'n> {
+    fn eval(
+        &self,
+        expr: &Expr,
+        focus: &Focus<'n>,
+        variables: &mut Variables<'n>,
+    ) -> Result<Vec<Item<'n>>, String> {
+        match expr {
+            Expr::Sequence(items) => {
+                let mut result = Vec::new();
+                for item in items {
+                    result.extend(self.eval(item, focus, variables)?);
+                }
+                Ok(result)
+            }
+            Expr::Literal(atomic) => Ok(vec![Item::Atomic(atomic.clone())]),
+            Expr::Variable(name) => variables
+                .iter()
+                .rev()
+                .find(|(bound, _)| bound == name)
+                .map(|(_, value)| value.clone())
+                .ok_or_else(|| format!("XPST0008: the variable '${}' is not defined", name)),
+            Expr::ContextItem => focus
+                .item
+                .clone()
+                .map(|item| vec![item])
+                .ok_or_else(no_context),
+            Expr::Root => Err(
+                "XPDY0050: the root of the tree of the context node is not a document node"
+                    .to_string(),
+            ),
+            Expr::Path(left, right) => {
+                let left = self.eval(left, focus, variables)?;
+                let size = left.len();
+                let mut nodes = Vec::new();
+                let mut atomics = Vec::new();
+                for (index, item) in left.into_iter().enumerate() {
+                    if let Item::Atomic(_) = item {
+                        return Err(
+                            "XPTY0019: the left operand of '/' must be a sequence of nodes"
+                                .to_string(),
+                        );
+                    }
+                    let focus = Focus {
+                        item: Some(item),
+                        position: index + 1,
+                        size,
+                    };
+                    for item in self.eval(right, &focus, variables)? {
+                        match item {
+                            Item::Node(node) => nodes.push(node),
+                            Item::Atomic(atomic) => atomics.push(atomic),
+                        }
+                    }
+                }
+                match (nodes.is_empty(), atomics.is_empty()) {
+                    (_, true) => Ok(document_order(nodes).into_iter().map(Item::Node).collect()),
+                    (true, false) => Ok(atomics.into_iter().map(Item::Atomic).collect()),
+                    (false, false) => Err(
+                        "XPTY0018: a path cannot return both nodes and atomic values".to_string(),
+                    ),
+                }
+            }
+            Expr::Step(axis, test, predicates) => {
+                let node = match &focus.item {
+                    Some(Item::Node(node)) => *node,
+                    Some(Item::Atomic(_)) => {
+                        return Err("XPTY0020: the context item of a step is not a node".to_string())
+                    }
+                    None => return Err(no_context()),
+                };
+                let mut nodes: Vec<Node> = self
+                    .axis_nodes(node, *axis)
+                    .into_iter()
+                    .filter(|node| test.matches(node, *axis))
+                    .collect();
+                let reverse = matches!(
+                    axis,
+                    Axis::Parent | Axis::Ancestor | Axis::AncestorOrSelf | Axis::PrecedingSibling
+                );
+                if reverse {
+                    nodes.reverse();
+                }
+                let items = nodes.into_iter().map(Item::Node).collect();
+                let mut items = self.filter(items, predicates, variables)?;
+                if reverse {
+                    items.reverse();
+                }
+                Ok(items)
+            }
+            Expr::Filter(primary, predicates) => {
+                let items = self.eval(primary, focus, variables)?;
+                self.filter(items, predicates, variables)
+            }
+            Expr::Or(left, right) => {
+                let result = effective_boolean_value(&self.eval(left, focus, variables)?)?
+                    || effective_boolean_value(&self.eval(right, focus, variables)?)?;
+                Ok(boolean(result))
+            }
+            Expr::And(left, right) => {
+                let result = effective_boolean_value(&self.eval(left, focus, variables)?)?
+                    && effective_boolean_value(&self.eval(right, focus, variables)?)?;
+                Ok(boolean(result))
+            }
+            Expr::General(comparison, left, right) => {
+                let left = self.atomize(self.eval(left, focus, variables)?);
+                let right = self.atomize(self.eval(right, focus, variables)?);
+                for left in &left {
+                    for right in &right {
+                        let (left, right) = general_operands(left, right)?;
+                        if comparison.holds(compare(&left, &right)?) {
+                            return Ok(boolean(true));
+                        }
+                    }
+                }
+                Ok(boolean(false))
+            }
+            Expr::Value(comparison, left, right) => {
+                let left = self.atomize(self.eval(left, focus, variables)?);
+                let right = self.atomize(self.eval(right, focus, variables)?);
+                let (left, right) = match (optional(left)?, optional(right)?) {
+                    (Some(left), Some(right)) => (as_string(left), as_string(right)),
+                    _ => return Ok(Vec::new()),
+                };
+                Ok(boolean(comparison.holds(compare(&left, &right)?)))
+            }
+            Expr::Node(ordering, identity, left, right) => {
+                let left = self.eval(left, focus, variables)?;
+                let right = self.eval(right, focus, variables)?;
+                let (left, right) = match (optional(left)?, optional(right)?) {
+                    (Some(Item::Node(left)), Some(Item::Node(right))) => (left, right),
+                    (None, _) | (_, None) => return Ok(Vec::new()),
+                    _ => {
+                        return Err(
+                            "XPTY0004: the operands of a node comparison must be nodes".to_string()
+                        )
+                    }
+                };
+                Ok(boolean(match identity {
+                    true => left.is(&right),
+                    false => left.order().cmp(&right.order()) == *ordering && !left.is(&right),
+                }))
+            }
+            Expr::Arithmetic(operator, left, right) => {
+                let left = self.atomize(self.eval(left, focus, variables)?);
+                let right = self.atomize(self.eval(right, focus, variables)?);
+                match (optional(left)?, optional(right)?) {
+                    (Some(left), Some(right)) => {
+                        Ok(vec![Item::Atomic(arithmetic(*operator, left, right)?)])
+                    }
+                    _ => Ok(Vec::new()),
+                }
+            }
+            Expr::Negate(operand) => {
+                let operand = self.atomize(self.eval(operand, focus, variables)?);
+                match optional(operand)?.map(numeric) {
+                    Some(Ok(Atomic::Integer(integer))) => {
+                        Ok(vec![Item::Atomic(Atomic::Integer(integer.negate()))])
+                    }
+                    Some(Ok(Atomic::Decimal(decimal))) => {
+                        Ok(vec![Item::Atomic(Atomic::Decimal(decimal.negate()))])
+                    }
+                    Some(Ok(Atomic::Double(number))) => {
+                        Ok(vec![Item::Atomic(Atomic::Double(-number))])
+                    }
+                    Some(Ok(_)) => unreachable!("numeric() returns numbers"),
+                    Some(Err(error)) => Err(error),
+                    None => Ok(Vec::new()),
+                }
+            }
+            Expr::Range(low, high) => {
+                let low = self.atomize(self.eval(low, focus, variables)?);
+                let high = self.atomize(self.eval(high, focus, variables)?);
+                match (optional(low)?, optional(high)?) {
+                    (Some(low), Some(high)) => {
+                        let (low, high) = (integer(low)?, integer(high)?);
+                        if high.saturating_sub(low) > 10_000_000 {
+                            return Err("FOAR0002: the range is too large".to_string());
+                        }
+                        Ok((low..=high)
+                            .map(|integer| Item::Atomic(Atomic::Integer(integer.into())))
+                            .collect())
+                    }
+                    _ => Ok(Vec::new()),
+                }
+            }
+            Expr::Union(left, right) => {
+                let mut nodes = nodes(self.eval(left, focus, variables)?)?;
+                nodes.extend(self::nodes(self.eval(right, focus, variables)?)?);
+                Ok(document_order(nodes).into_iter().map(Item::Node).collect())
+            }
+            Expr::Intersect(intersect, left, right) => {
+                let left = nodes(self.eval(left, focus, variables)?)?;
+                let right = nodes(self.eval(right, focus, variables)?)?;
+                let kept = left
+                    .into_iter()
+                    .filter(|node| right.iter().any(|other| node.is(other)) == *intersect)
+                    .collect();
+                Ok(document_order(kept).into_iter().map(Item::Node).collect())
+            }
+            Expr::If(condition, then, otherwise) => {
+                match effective_boolean_value(&self.eval(condition, focus, variables)?)? {
+                    true => self.eval(then, focus, variables),
+                    false => self.eval(otherwise, focus, variables),
+                }
+            }
+            Expr::Quantified(every, bindings, condition) => {
+                // `some` stops at the first binding satisfying the
+                // condition, and `every` at the first one that does not.
+                let mut result = *every;
+                self.bind(bindings, focus, variables, &mut |evaluator, variables| {
+                    let holds =
+                        effective_boolean_value(&evaluator.eval(condition, focus, variables)?)?;
+                    if holds != *every {
+                        result = holds;
+                        return Ok(false);
+                    }
+                    Ok(true)
+                })?;
+                Ok(boolean(result))
+            }
+            Expr::For(bindings, result) => {
+                let mut items = Vec::new();
+                self.bind(bindings, focus, variables, &mut |evaluator, variables| {
+                    items.extend(evaluator.eval(result, focus, variables)?);
+                    Ok(true)
+                })?;
+                Ok(items)
+            }
+            Expr::Call(function, arguments) => self.call(*function, arguments, focus, variables),
+            Expr::Cast(operand, target, allow_empty) => {
+                let operand = self.atomize(self.eval(operand, focus, variables)?);
+                match optional(operand)? {
+                    Some(atomic) => Ok(vec![Item::Atomic(cast(atomic, *target)?)]),
+                    None if *allow_empty => Ok(Vec::new()),
+                    None => Err("XPTY0004: an empty sequence cannot be cast".to_string()),
+                }
+            }
+            Expr::Castable(operand, target, allow_empty) => {
+                let operand = self.atomize(self.eval(operand, focus, variables)?);
+                let castable = match operand.len() {
+                    0 => *allow_empty,
+                    1 => cast(
+                        operand.into_iter().next().unwrap_or(Atomic::Boolean(false)),
+                        *target,
+                    )
+                    .is_ok(),
+                    _ => false,
+                };
+                Ok(boolean(castable))
+            }
+            Expr::InstanceOf(operand, sequence_type, occurrence) => {
+                let items = self.eval(operand, focus, variables)?;
+                let count_matches = match occurrence {
+                    Occurrence::One => items.len() == 1,
+                    Occurrence::Optional => items.len() <= 1,
+                    Occurrence::Many => true,
+                    Occurrence::AtLeastOne => !items.is_empty(),
+                };
+                let matches = match sequence_type {
+                    SequenceType::Empty => items.is_empty(),
+                    _ => count_matches && items.iter().all(|item| sequence_type.matches(item)),
+                };
+                Ok(boolean(matches))
+            }
+        }
+    }
+
+    /// Calls `each` with the variables of `bindings` bound to every
+    /// combination of their values, until it returns `false`.
+    fn bind(
+        &self,
+        bindings: &[(String, Expr)],
+        focus: &Focus<'n>,
+        variables: &mut Variables<'n>,
+        each: &mut dyn FnMut(&Self, &mut Variables<'n>) -> Result<bool, String>,
+    ) -> Result<bool, String> {
+        let ((name, expr), rest) = match bindings.split_first() {
+            Some(first) => first,
+            None => return each(self, variables),
+        };
+        for item in self.eval(expr, focus, variables)? {
+            variables.push((name.clone(), vec![item]));
+            let go_on = self.bind(rest, focus, variables, each);
+            variables.pop();
+            if !go_on? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Keeps the items of `items` that satisfy every predicate of
+    /// `predicates`.
+    fn filter(
+        &self,
+        mut items: Vec<Item<'n>>,
+        predicates: &[Expr],
+        variables: &mut Variables<'n>,
+    ) -> Result<Vec<Item<'n>>, String> {
+        for predicate in predicates {
+            let size = items.len();
+            let mut kept = Vec::new();
+            for (index, item) in items.into_iter().enumerate() {
+                let focus = Focus {
+                    item: Some(item.clone()),
+                    position: index + 1,
+                    size,
+                };
+                let result = self.eval(predicate, &focus, variables)?;
+                let keep = match result.as_slice() {
+                    [Item::Atomic(atomic)] if atomic.is_numeric() => {
+                        atomic.to_f64() == Some((index + 1) as f64)
+                    }
+                    _ => effective_boolean_value(&result)?,
+                };
+                if keep {
+                    kept.push(item);
+                }
+            }
+            items = kept;
+        }
+        Ok(items)
+    }
+
+    /// Returns the atomic values of `items`, the typed values of their
+    /// nodes.
+    fn atomize(&self, items: Vec<Item>) -> Vec<Atomic> {
+        let mut atomics = Vec::new();
+        for item in items {
+            match item {
+                Item::Atomic(atomic) => atomics.push(atomic),
+                Item::Node(node) => atomics.extend(self.typed_value(&node)),
+            }
+        }
+        atomics
+    }
+
+    fn typed_value(&self, node: &Node) -> Vec<Atomic> {
+        let typed = match node {
+            Node::Element(element) => (self.typed)(element, None),
+            Node::Attribute(element, _) => node
+                .name()
+                .and_then(|name| (self.typed)(element, Some(&name))),
+            Node::Text(_, _) => None,
+        };
+        let string = node.string_value();
+        match typed {
+            Some(value) => Atomic::from_value(value, &string),
+            None => vec![Atomic::Untyped(string)],
+        }
+    }
+
+    fn call(
+        &self,
+        function: Function,
+        arguments: &[Expr],
+        focus: &Focus<'n>,
+        variables: &mut Variables<'n>,
+    ) -> Result<Vec<Item<'n>>, String> {
+        let name = match function {
+            Function::Constructor(datatype) => {
+                let operand = self.atomize(self.eval(&arguments[0], focus, variables)?);
+                return match optional(operand)? {
+                    Some(atomic) => Ok(vec![Item::Atomic(cast(
+                        atomic,
+                        SequenceType::Atomic(datatype),
+                    )?)]),
+                    None => Ok(Vec::new()),
+                };
+            }
+            Function::Untyped => {
+                let operand = self.atomize(self.eval(&arguments[0], focus, variables)?);
+                return Ok(optional(operand)?
+                    .map(|atomic| Item::Atomic(Atomic::Untyped(atomic.string())))
+                    .into_iter()
+                    .collect());
+            }
+            Function::Builtin(name) => name,
+        };
+        let mut values = Vec::new();
+        for argument in arguments {
+            values.push(self.eval(argument, focus, variables)?);
+        }
+        // The context item stands for the missing argument of the
+        // functions taking one optionally.
+        if values.is_empty()
+            && matches!(
+                name,
+                "string"
+                    | "number"
+                    | "string-length"
+                    | "normalize-space"
+                    | "name"
+                    | "local-name"
+                    | "namespace-uri"
+            )
+        {
+            values.push(vec![focus.item.clone().ok_or_else(no_context)?]);
+        }
+        let string_argument = |index: usize, values: &[Vec<Item<'n>>]| -> Result<String, String> {
+            Ok(optional(self.atomize(values[index].clone()))?
+                .map(|atomic| atomic.string())
+                .unwrap_or_default())
+        };
+        let atomic = |value: Atomic| Ok(vec![Item::Atomic(value)]);
+        match name {
+            "true" => Ok(boolean(true)),
+            "false" => Ok(boolean(false)),
+            "boolean" => Ok(boolean(effective_boolean_value(&values[0])?)),
+            "not" => Ok(boolean(!effective_boolean_value(&values[0])?)),
+            "count" => atomic(Atomic::Integer((values[0].len() as i64).into())),
+            "empty" => Ok(boolean(values[0].is_empty())),
+            "exists" => Ok(boolean(!values[0].is_empty())),
+            "position" => atomic(Atomic::Integer((focus.position as i64).into())),
+            "last" => atomic(Atomic::Integer((focus.size as i64).into())),
+            "data" => Ok(self
+                .atomize(values.remove(0))
+                .into_iter()
+                .map(Item::Atomic)
+                .collect()),
+            "string" => atomic(Atomic::String(match optional(values.remove(0))? {
+                Some(Item::Node(node)) => node.string_value(),
+                Some(Item::Atomic(value)) => value.string(),
+                None => String::new(),
+            })),
+            "number" => {
+                let value = optional(self.atomize(values.remove(0)))?;
+                atomic(Atomic::Double(
+                    value
+                        .and_then(|value| {
+                            cast(value, SequenceType::Atomic(SimpleDatatype::Double)).ok()
+                        })
+                        .and_then(|value| value.to_f64())
+                        .unwrap_or(f64::NAN),
+                ))
+            }
+            "string-length" => {
+                let string = string_argument(0, &values)?;
+                atomic(Atomic::Integer((string.chars().count() as i64).into()))
+            }
+            "normalize-space" => {
+                let string = string_argument(0, &values)?;
+                atomic(Atomic::String(
+                    string.split_whitespace().collect::<Vec<_>>().join(" "),
+                ))
+            }
+            "concat" => {
+                let mut result = String::new();
+                for index in 0..values.len() {
+                    result.push_str(&string_argument(index, &values)?);
+                }
+                atomic(Atomic::String(result))
+            }
+            "contains" | "starts-with" | "ends-with" | "substring-before" | "substring-after" => {
+                let (string, search) = (string_argument(0, &values)?, string_argument(1, &values)?);
+                match name {
+                    "contains" => Ok(boolean(string.contains(&search))),
+                    "starts-with" => Ok(boolean(string.starts_with(&search))),
+                    "ends-with" => Ok(boolean(string.ends_with(&search))),
+                    "substring-before" => atomic(Atomic::String(
+                        string
+                            .find(&search)
+                            .map(|index| string[..index].to_string())
+                            .unwrap_or_default(),
+                    )),
+                    _ => atomic(Atomic::String(
+                        string
+                            .find(&search)
+                            .map(|index| string[index + search.len()..].to_string())
+                            .unwrap_or_default(),
+                    )),
+                }
+            }
+            "substring" => {
+                let string = string_argument(0, &values)?;
+                let start = round_half_up(double_argument(self.atomize(values[1].clone()))?);
+                let end = match values.get(2) {
+                    Some(length) => {
+                        start + round_half_up(double_argument(self.atomize(length.clone()))?)
+                    }
+                    None => f64::INFINITY,
+                };
+                let result = string
+                    .chars()
+                    .enumerate()
+                    .filter(|(index, _)| {
+                        let position = (*index + 1) as f64;
+                        position >= start && position < end
+                    })
+                    .map(|(_, ch)| ch)
+                    .collect();
+                atomic(Atomic::String(result))
+            }
+            "upper-case" => atomic(Atomic::String(string_argument(0, &values)?.to_uppercase())),
+            "lower-case" => atomic(Atomic::String(string_argument(0, &values)?.to_lowercase())),
+            "translate" => {
+                let string = string_argument(0, &values)?;
+                let from: Vec<char> = string_argument(1, &values)?.chars().collect();
+                let to: Vec<char> = string_argument(2, &values)?.chars().collect();
+                let result = string
+                    .chars()
+                    .filter_map(|ch| match from.iter().position(|from| *from == ch) {
+                        Some(index) => to.get(index).copied(),
+                        None => Some(ch),
+                    })
+                    .collect();
+                atomic(Atomic::String(result))
+            }
+            "string-join" => {
+                let separator = string_argument(1, &values)?;
+                let strings: Vec<String> = self
+                    .atomize(values.remove(0))
+                    .iter()
+                    .map(Atomic::string)
+                    .collect();
+                atomic(Atomic::String(strings.join(&separator)))
+            }
+            "matches" => {
+                let string = string_argument(0, &values)?;
+                let pattern = string_argument(1, &values)?;
+                Ok(boolean(regex_search(&pattern)?.is_match(&string)))
+            }
+            "compare" => {
+                let left = optional(self.atomize(values[0].clone()))?;
+                let right = optional(self.atomize(values[1].clone()))?;
+                match (left, right) {
+                    (Some(left), Some(right)) => {
+                        let ordering = left.string().cmp(&right.string());
+                        atomic(Atomic::Integer((ordering as i64).into()))
+                    }
+                    _ => Ok(Vec::new()),
+                }
+            }
+            "abs" | "ceiling" | "floor" | "round" => {
+                let value = match optional(self.atomize(values.remove(0)))? {
+                    Some(value) => numeric(value)?,
+                    None => return Ok(Vec::new()),
+                };
+                let apply = |number: f64| match name {
+                    "abs" => number.abs(),
+                    "ceiling" => number.ceil(),
+                    "floor" => number.floor(),
+                    _ => round_half_up(number),
+                };
+                let exact = |decimal: Decimal| match name {
+                    "abs" => decimal.abs(),
+                    "ceiling" => decimal.ceiling(),
+                    "floor" => decimal.floor(),
+                    _ => decimal.round(),
+                };
+                atomic(match value {
+                    Atomic::Integer(integer) => Atomic::Integer(exact(integer)),
+                    Atomic::Decimal(decimal) => Atomic::Decimal(exact(decimal)),
+                    value => Atomic::Double(apply(value.to_f64().unwrap_or(f64::NAN))),
+                })
+            }
+            "sum" | "avg" => {
+                let items = self.atomize(values.remove(0));
+                if items.is_empty() {
+                    return match (name, values.pop()) {
+                        ("sum", Some(zero)) => Ok(zero),
+                        ("sum", None) => atomic(Atomic::Integer(0.into())),
+                        _ => Ok(Vec::new()),
+                    };
+                }
+                let count = items.len();
+                let mut total = Atomic::Integer(0.into());
+                for item in items {
+                    total = arithmetic(Operator::Add, total, item)?;
+                }
+                match name {
+                    "sum" => atomic(total),
+                    _ => atomic(arithmetic(
+                        Operator::Divide,
+                        total,
+                        Atomic::Integer((count as i64).into()),
+                    )?),
+                }
+            }
+            "min" | "max" => {
+                let mut best: Option<Atomic> = None;
+                for item in self.atomize(values.remove(0)) {
+                    let item = match item {
+                        Atomic::Untyped(_) => numeric(item)?,
+                        item => item,
+                    };
+                    best = Some(match best {
+                        None => item,
+                        Some(current) => {
+                            let ordering = compare(&item, &current)?;
+                            let better = match name {
+                                "min" => ordering == Some(Ordering::Less),
+                                _ => ordering == Some(Ordering::Greater),
+                            };
+                            if better || item.to_f64().map_or(false, f64::is_nan) {
+                                item
+                            } else {
+                                current
+                            }
+                        }
+                    });
+                }
+                Ok(best.map(Item::Atomic).into_iter().collect())
+            }
+            "distinct-values" => {
+                let mut distinct: Vec<Atomic> = Vec::new();
+                for item in self.atomize(values.remove(0)) {
+                    let item = as_string(item);
+                    let seen = distinct
+                        .iter()
+                        .any(|other| compare(other, &item).ok().flatten() == Some(Ordering::Equal));
+                    if !seen {
+                        distinct.push(item);
+                    }
+                }
+                Ok(distinct.into_iter().map(Item::Atomic).collect())
+            }
+            "index-of" => {
+                let search = match optional(self.atomize(values[1].clone()))? {
+                    Some(search) => as_string(search),
+                    None => {
+                        return Err("XPTY0004: the search value of index-of() is empty".to_string())
+                    }
+                };
+                Ok(self
+                    .atomize(values.remove(0))
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(_, item)| {
+                        compare(&as_string(item.clone()), &search).ok().flatten()
+                            == Some(Ordering::Equal)
+                    })
+                    .map(|(index, _)| Item::Atomic(Atomic::Integer((index as i64 + 1).into())))
+                    .collect())
+            }
+            "reverse" => {
+                let mut items = values.remove(0);
+                items.reverse();
+                Ok(items)
+            }
+            "subsequence" => {
+                let items = values.remove(0);
+                let start = round_half_up(double_argument(self.atomize(values.remove(0)))?);
+                let end = match values.pop() {
+                    Some(length) => start + round_half_up(double_argument(self.atomize(length))?),
+                    None => f64::INFINITY,
+                };
+                Ok(items
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(index, _)| {
+                        let position = (*index + 1) as f64;
+                        position >= start && position < end
+                    })
+                    .map(|(_, item)| item)
+                    .collect())
+            }
+            "exactly-one" | "zero-or-one" | "one-or-more" => {
+                let items = values.remove(0);
+                let valid = match name {
+                    "exactly-one" => items.len() == 1,
+                    "zero-or-one" => items.len() <= 1,
+                    _ => !items.is_empty(),
+                };
+                match valid {
+                    true => Ok(items),
+                    false => Err(format!(
+                        "FORG0003: {}() was called with a sequence of {} items",
+                        name,
+                        items.len()
+                    )),
+                }
+            }
+            "name" | "local-name" | "namespace-uri" => {
+                let node = match optional(values.remove(0))? {
+                    Some(Item::Node(node)) => node,
+                    Some(Item::Atomic(_)) => {
+                        return Err(format!(
+                            "XPTY0004: the argument of {}() is not a node",
+                            name
+                        ))
+                    }
+                    None => return atomic(Atomic::String(String::new())),
+                };
+                let qname = node.name();
+                atomic(Atomic::String(match (name, qname) {
+                    (_, None) => String::new(),
+                    ("name", Some(_)) => node.lexical_name(),
+                    ("local-name", Some(qname)) => qname.local().to_string(),
+                    (_, Some(qname)) => qname.namespace().unwrap_or_default().to_string(),
+                }))
+            }
+            _ => Err(format!("XPST0017: unknown function '{}'", name)),
+        }
+    }
+}
+
+impl<'t, 'n> Evaluator<'t, 'n> {
+    /// Returns the nodes along `axis` from `node`, in document order.
+    fn axis_nodes(&self, node: Node<'n>, axis: Axis) -> Vec<Node<'n>> {
+        match axis {
+            Axis::Child => node.children(),
+            Axis::Attribute => match node {
+                Node::Element(element) => (0..attributes(element).count())
+                    .map(|index| Node::Attribute(element, index))
+                    .collect(),
+                _ => Vec::new(),
+            },
+            Axis::Context => vec![node],
+            Axis::Descendant | Axis::DescendantOrSelf => {
+                let mut nodes = Vec::new();
+                if axis == Axis::DescendantOrSelf {
+                    nodes.push(node);
+                }
+                let mut stack = node.children();
+                stack.reverse();
+                while let Some(next) = stack.pop() {
+                    nodes.push(next);
+                    stack.extend(next.children().into_iter().rev());
+                }
+                nodes
+            }
+            Axis::Parent => self.parent(node).into_iter().collect(),
+            Axis::Ancestor | Axis::AncestorOrSelf => {
+                let mut nodes = Vec::new();
+                if axis == Axis::AncestorOrSelf {
+                    nodes.push(node);
+                }
+                let mut current = self.parent(node);
+                while let Some(ancestor) = current {
+                    nodes.push(ancestor);
+                    current = self.parent(ancestor);
+                }
+                nodes.reverse();
+                nodes
+            }
+            Axis::FollowingSibling | Axis::PrecedingSibling => {
+                let siblings = match self.parent(node) {
+                    Some(parent) if !matches!(node, Node::Attribute(_, _)) => parent.children(),
+                    _ => return Vec::new(),
+                };
+                let index = match siblings.iter().position(|sibling| sibling.is(&node)) {
+                    Some(index) => index,
+                    None => return Vec::new(),
+                };
+                match axis {
+                    Axis::FollowingSibling => siblings[index + 1..].to_vec(),
+                    _ => siblings[..index].to_vec(),
+                }
+            }
+        }
+    }
+
+    /// Returns the parent of `node`. The tree keeps no links to parents,
+    /// so that of an element is looked up from the root, which has none.
+    fn parent(&self, node: Node<'n>) -> Option<Node<'n>> {
+        let element = match node {
+            Node::Attribute(element, _) | Node::Text(element, _) => {
+                return Some(Node::Element(element))
+            }
+            Node::Element(element) => element,
+        };
+        let mut stack: Vec<&XmlElement> = self.root.into_iter().collect();
+        while let Some(current) = stack.pop() {
+            for child in current.child_elements() {
+                if ptr::eq(child, element) {
+                    return Some(Node::Element(current));
+                }
+                stack.push(child);
+            }
+        }
+        None
+    }
+}
+
+impl NodeTest {
+    fn matches(&self, node: &Node, axis: Axis) -> bool {
+        let principal = match axis {
+            Axis::Attribute => matches!(node, Node::Attribute(_, _)),
+            _ => matches!(node, Node::Element(_)),
+        };
+        match self {
+            NodeTest::Kind(kind, name) => {
+                kind.matches(node)
+                    && name.as_ref().map_or(true, |name| match name.as_ref() {
+                        NodeTest::Any => true,
+                        test => test.matches_name(node),
+                    })
+            }
+            NodeTest::Any => principal,
+            test => principal && test.matches_name(node),
+        }
+    }
+
+    fn matches_name(&self, node: &Node) -> bool {
+        let name = match node.name() {
+            Some(name) => name,
+            None => return false,
+        };
+        match self {
+            NodeTest::Any => true,
+            NodeTest::Namespace(namespace) => name.namespace() == namespace.as_deref(),
+            NodeTest::Local(local) => name.local() == local,
+            NodeTest::Name(test) => *test == name,
+            NodeTest::Kind(_, _) => false,
+        }
+    }
+}
+
+impl NodeKind {
+    fn matches(&self, node: &Node) -> bool {
+        match self {
+            NodeKind::Any => true,
+            NodeKind::Element => matches!(node, Node::Element(_)),
+            NodeKind::Attribute => matches!(node, Node::Attribute(_, _)),
+            NodeKind::Text => matches!(node, Node::Text(_, _)),
+            NodeKind::Other => false,
+        }
+    }
+}
+
+impl SequenceType {
+    fn matches(&self, item: &Item) -> bool {
+        let datatype = match (self, item) {
+            (SequenceType::Item, _) => return true,
+            (SequenceType::Node(kind), Item::Node(node)) => return kind.matches(node),
+            (SequenceType::Untyped, Item::Atomic(atomic)) => {
+                return matches!(atomic, Atomic::Untyped(_))
+            }
+            (SequenceType::Atomic(datatype), Item::Atomic(atomic)) => (datatype, atomic),
+            _ => return false,
+        };
+        let actual = match datatype.1 {
+            Atomic::String(_) => SimpleDatatype::String,
+            Atomic::Boolean(_) => SimpleDatatype::Boolean,
+            Atomic::Integer(_) => SimpleDatatype::Integer,
+            Atomic::Decimal(_) => SimpleDatatype::Decimal,
+            Atomic::Double(_) => SimpleDatatype::Double,
+            Atomic::Untyped(_) | Atomic::Typed(_, _) => SimpleDatatype::AnyAtomicType,
+        };
+        actual.is_derived_from(*datatype.0)
+    }
+}
+
+impl Comparison {
+    /// Returns `true` if two values ordered by `ordering` satisfy the
+    /// comparison. Values without an order, such as `NaN`, are only
+    /// different.
+    fn holds(&self, ordering: Option<Ordering>) -> bool {
+        match (self, ordering) {
+            (Comparison::NotEqual, ordering) => ordering != Some(Ordering::Equal),
+            (_, None) => false,
+            (Comparison::Equal, Some(ordering)) => ordering == Ordering::Equal,
+            (Comparison::Less, Some(ordering)) => ordering == Ordering::Less,
+            (Comparison::LessOrEqual, Some(ordering)) => ordering != Ordering::Greater,
+            (Comparison::Greater, Some(ordering)) => ordering == Ordering::Greater,
+            (Comparison::GreaterOrEqual, Some(ordering)) => ordering != Ordering::Less,
+        }
+    }
+}
+
+impl<'n> Node<'n> {
+    /// Returns `true` if this is the same node as `other`.
+    fn is(&self, other: &Node) -> bool {
+        match (self, other) {
+            (Node::Element(a), Node::Element(b)) => ptr::eq(*a, *b),
+            (Node::Attribute(a, i), Node::Attribute(b, j))
+            | (Node::Text(a, i), Node::Text(b, j)) => ptr::eq(*a, *b) && i == j,
+            _ => false,
+        }
+    }
+
+    /// Returns a key ordering the nodes of a tree in document order.
+    ///
+    /// Elements are ordered by the offset of their start tag, followed by
+    /// their attributes. A text node comes after the last descendant of
+    /// the element before it, and after the text nodes nested deeper
+    /// than itself there.
+    fn order(&self) -> (usize, u8, usize, usize) {
+        match self {
+            Node::Element(element) => (element.position, 0, 0, 0),
+            Node::Attribute(element, index) => (element.position, 1, 0, *index),
+            Node::Text(element, index) => {
+                let before =
+                    element.children[..*index]
+                        .iter()
+                        .rev()
+                        .find_map(|child| match child {
+                            XmlNode::Element(child) => Some(child),
+                            XmlNode::Text(_) => None,
+                        });
+                match before {
+                    Some(child) => {
+                        let (last, depth) = last_descendant(child);
+                        (last.position, 2, depth + 1, *index)
+                    }
+                    None => (element.position, 2, 0, *index),
+                }
+            }
+        }
+    }
+
+    /// Returns the expanded name of an element or attribute.
+    fn name(&self) -> Option<QName> {
+        match self {
+            Node::Element(element) => Some(element.qname.clone()),
+            Node::Attribute(element, index) => attributes(element)
+                .nth(*index)
+                .and_then(|(name, _)| element.attribute_qname(name).ok()),
+            Node::Text(_, _) => None,
+        }
+    }
+
+    /// Returns the name of an element or attribute, as written.
+    fn lexical_name(&self) -> String {
+        match self {
+            Node::Element(element) => element.name.clone(),
+            Node::Attribute(element, index) => attributes(element)
+                .nth(*index)
+                .map(|(name, _)| name.to_string())
+                .unwrap_or_default(),
+            Node::Text(_, _) => String::new(),
+        }
+    }
+
+    fn string_value(&self) -> String {
+        match self {
+            Node::Element(element) => {
+                let mut text = String::new();
+                collect_text(element, &mut text);
+                text
+            }
+            Node::Attribute(element, index) => attributes(element)
+                .nth(*index)
+                .map(|(_, value)| value.to_string())
+                .unwrap_or_default(),
+            Node::Text(element, index) => match &element.children[*index] {
+                XmlNode::Text(text) => text.clone(),
+                XmlNode::Element(_) => String::new(),
+            },
+        }
+    }
+
+    /// Returns the child elements and text nodes of an element.
+    fn children(&self) -> Vec<Node<'n>> {
+        match self {
+            Node::Element(element) => element
+                .children
+                .iter()
+                .enumerate()
+                .map(|(index, child)| match child {
+                    XmlNode::Element(child) => Node::Element(child),
+                    XmlNode::Text(_) => Node::Text(element, index),
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Returns the attributes of `element`, without the namespace
+/// declarations.
+fn attributes(element: &XmlElement) -> impl Iterator<Item = (&str, &str)> {
+    element
+        .attributes
+        .iter()
+        .filter(|(name, _)| *name != "xmlns" && !name.starts_with("xmlns:"))
+}
+
+/// Returns the last element of the subtree of `element` in document
+/// order, and how deep it is below `element`.
+fn last_descendant(element: &XmlElement) -> (&XmlElement, usize) {
+    let mut last = (element, 0);
+    while let Some(child) = last.0.child_elements().last() {
+        last = (child, last.1 + 1);
+    }
+    last
+}
+
+fn collect_text(element: &XmlElement, text: &mut String) {
+    for child in &element.children {
+        match child {
+            XmlNode::Element(child) => collect_text(child, text),
+            XmlNode::Text(content) => text.push_str(content),
+        }
+    }
+}
+
+/// Sorts `nodes` in document order and removes the duplicates.
+fn document_order(mut nodes: Vec<Node>) -> Vec<Node> {
+    nodes.sort_by_key(Node::order);
+    nodes.dedup_by(|a, b| a.is(b));
+    nodes
+}
+
+fn nodes(items: Vec<Item>) -> Result<Vec<Node>, String> {
+    items
+        .into_iter()
+        .map(|item| match item {
+            Item::Node(node) => Ok(node),
+            Item::Atomic(_) => Err(
+                "XPTY0004: the operands of union, intersect and except must be nodes".to_string(),
+            ),
+        })
+        .collect()
+}
+
+fn boolean<'n>(value: bool) -> Vec<Item<'n>> {
+    vec![Item::Atomic(Atomic::Boolean(value))]
+}
+
+fn no_context() -> String {
+    "XPDY0002: the context item is not defined".to_string()
+}
+
+fn overflow() -> String {
+    "FOAR0002: integer overflow".to_string()
+}
+
+/// Returns the only item of `items`, if any, or an error if there are
+/// several.
+fn optional<T>(mut items: Vec<T>) -> Result<Option<T>, String> {
+    match items.len() {
+        0 | 1 => Ok(items.pop()),
+        count => Err(format!(
+            "XPTY0004: a sequence of {} items is not allowed where at most one is expected",
+            count
+        )),
+    }
+}
+
+/// Returns the effective boolean value of `items`.
+fn effective_boolean_value(items: &[Item]) -> Result<bool, String> {
+    match items {
+        [] => Ok(false),
+        [Item::Node(_), ..] => Ok(true),
+        [Item::Atomic(atomic)] => match atomic {
+            Atomic::Boolean(boolean) => Ok(*boolean),
+            Atomic::String(string) | Atomic::Untyped(string) => Ok(!string.is_empty()),
+            Atomic::Integer(decimal) | Atomic::Decimal(decimal) => Ok(!decimal.is_zero()),
+            Atomic::Double(number) => Ok(*number != 0.0 && !number.is_nan()),
+            Atomic::Typed(_, _) => Err(format!(
+                "FORG0006: a value of type {} has no effective boolean value",
+                atomic.type_name()
+            )),
+        },
+        _ => Err(
+            "FORG0006: a sequence of several atomic values has no effective boolean value"
+                .to_string(),
+        ),
+    }
+}
+
+/// Turns an untyped value into a string, as value comparisons do.
+fn as_string(atomic: Atomic) -> Atomic {
+    match atomic {
+        Atomic::Untyped(string) => Atomic::String(string),
+        atomic => atomic,
+    }
+}
+
+/// Converts the untyped operands of a general comparison to the type of
+/// the other operand: numbers if it is a number, strings if it is
+/// untyped or a string.
+fn general_operands(left: &Atomic, right: &Atomic) -> Result<(Atomic, Atomic), String> {
+    let convert = |untyped: &Atomic, other: &Atomic| -> Result<Atomic, String> {
+        match other {
+            Atomic::Integer(_) | Atomic::Decimal(_) | Atomic::Double(_) => cast(
+                untyped.clone(),
+                SequenceType::Atomic(SimpleDatatype::Double),
+            ),
+            Atomic::Boolean(_) => cast(
+                untyped.clone(),
+                SequenceType::Atomic(SimpleDatatype::Boolean),
+            ),
+            Atomic::Typed(value, _) => retype(&untyped.string(), value),
+            Atomic::Untyped(string) | Atomic::String(string) => Ok(Atomic::String(string.clone())),
+        }
+    };
+    match (left, right) {
+        (Atomic::Untyped(_), _) => Ok((convert(left, right)?, as_string(right.clone()))),
+        (_, Atomic::Untyped(_)) => Ok((left.clone(), convert(right, left)?)),
+        _ => Ok((left.clone(), right.clone())),
+    }
+}
+
+/// Parses `lexical` into a value of the same kind as `value`.
+fn retype(lexical: &str, value: &Value) -> Result<Atomic, String> {
+    use SimpleDatatype::*;
+    let candidates: &[SimpleDatatype] = match value {
+        Value::Duration(_) => &[Duration],
+        Value::DateTime(_) => &[
+            DateTime, Date, Time, GYearMonth, GYear, GMonthDay, GDay, GMonth,
+        ],
+        Value::Binary(_) => &[HexBinary, Base64Binary],
+        _ => &[String],
+    };
+    candidates
+        .iter()
+        .find_map(|datatype| {
+            cast(
+                Atomic::String(lexical.to_string()),
+                SequenceType::Atomic(*datatype),
+            )
+            .ok()
+        })
+        .ok_or_else(|| format!("FORG0001: cannot convert '{}' for the comparison", lexical))
+}
+
+/// Compares two atomic values of comparable types. Values without an
+/// order, such as `NaN` or some durations, return `None`.
+fn compare(left: &Atomic, right: &Atomic) -> Result<Option<Ordering>, String> {
+    let ordering = match (left, right) {
+        (
+            Atomic::Integer(left) | Atomic::Decimal(left),
+            Atomic::Integer(right) | Atomic::Decimal(right),
+        ) => Some(left.cmp(right)),
+        (left, right) if left.is_numeric() && right.is_numeric() => {
+            left.to_f64().partial_cmp(&right.to_f64()).filter(|_| {
+                !left.to_f64().map_or(true, f64::is_nan)
+                    && !right.to_f64().map_or(true, f64::is_nan)
+            })
+        }
+        (
+            Atomic::String(left) | Atomic::Untyped(left),
+            Atomic::String(right) | Atomic::Untyped(right),
+        ) => Some(left.cmp(right)),
+        (Atomic::Boolean(left), Atomic::Boolean(right)) => Some(left.cmp(right)),
+        (Atomic::Typed(left, _), Atomic::Typed(right, _))
+            if std::mem::discriminant(left) == std::mem::discriminant(right) =>
+        {
+            left.partial_cmp(right)
+        }
+        _ => {
+            return Err(format!(
+                "XPTY0004: cannot compare {} with {}",
+                left.type_name(),
+                right.type_name()
+            ))
+        }
+    };
+    Ok(ordering)
+}
+
+/// Converts an operand of an arithmetic operator to a number.
+fn numeric(atomic: Atomic) -> Result<Atomic, String> {
+    match atomic {
+        Atomic::Untyped(_) => cast(atomic, SequenceType::Atomic(SimpleDatatype::Double)),
+        atomic if atomic.is_numeric() => Ok(atomic),
+        atomic => Err(format!(
+            "XPTY0004: a value of type {} is not a number",
+            atomic.type_name()
+        )),
+    }
+}
+
+fn integer(atomic: Atomic) -> Result<i64, String> {
+    match atomic {
+        Atomic::Integer(integer) => integer.to_i64().ok_or_else(overflow),
+        Atomic::Untyped(_) => match cast(atomic, SequenceType::Atomic(SimpleDatatype::Integer))? {
+            Atomic::Integer(integer) => integer.to_i64().ok_or_else(overflow),
+            _ => Err(overflow()),
+        },
+        atomic => Err(format!(
+            "XPTY0004: a value of type {} is not an integer",
+            atomic.type_name()
+        )),
+    }
+}
+
+fn double_argument(atomics: Vec<Atomic>) -> Result<f64, String> {
+    match optional(atomics)? {
+        Some(atomic) => Ok(numeric(atomic)?.to_f64().unwrap_or(f64::NAN)),
+        None => Err("XPTY0004: a number is expected, not an empty sequence".to_string()),
+    }
+}
+
+/// Rounds `number` to the nearest integer, halves upwards.
+fn round_half_up(number: f64) -> f64 {
+    match number.is_finite() {
+        true => (number + 0.5).floor(),
+        false => number,
+    }
+}
+
+/// Applies an arithmetic operator, promoting integers to decimals and
+/// decimals to doubles as needed. Integers and decimals are computed
+/// exactly.
+fn arithmetic(operator: Operator, left: Atomic, right: Atomic) -> Result<Atomic, String> {
+    let (left, right) = (numeric(left)?, numeric(right)?);
+    let division_by_zero = || "FOAR0001: division by zero".to_string();
+    if let (Atomic::Integer(a) | Atomic::Decimal(a), Atomic::Integer(b) | Atomic::Decimal(b)) =
+        (&left, &right)
+    {
+        let integers = matches!((&left, &right), (Atomic::Integer(_), Atomic::Integer(_)));
+        let result = match operator {
+            Operator::Add => a.add(b),
+            Operator::Subtract => a.subtract(b),
+            Operator::Multiply => a.multiply(b),
+            Operator::Divide => {
+                return Ok(Atomic::Decimal(a.divide(b).ok_or_else(division_by_zero)?))
+            }
+            Operator::IntegerDivide => {
+                return Ok(Atomic::Integer(
+                    a.integer_divide(b).ok_or_else(division_by_zero)?,
+                ))
+            }
+            Operator::Modulo => a.remainder(b).ok_or_else(division_by_zero)?,
+        };
+        return Ok(match integers {
+            true => Atomic::Integer(result),
+            false => Atomic::Decimal(result),
+        });
+    }
+    let (a, b) = (
+        left.to_f64().unwrap_or(f64::NAN),
+        right.to_f64().unwrap_or(f64::NAN),
+    );
+    let result = match operator {
+        Operator::Add => a + b,
+        Operator::Subtract => a - b,
+        Operator::Multiply => a * b,
+        Operator::Divide => a / b,
+        Operator::Modulo => a % b,
+        Operator::IntegerDivide => {
+            return Decimal::from_f64((a / b).trunc())
+                .map(Atomic::Integer)
+                .ok_or_else(division_by_zero)
+        }
+    };
+    Ok(Atomic::Double(result))
+}
+
+/// Casts `atomic` to the atomic type `target`.
+fn cast(atomic: Atomic, target: SequenceType) -> Result<Atomic, String> {
+    let datatype = match target {
+        SequenceType::Atomic(datatype) => datatype,
+        SequenceType::Untyped => return Ok(Atomic::Untyped(atomic.string())),
+        _ => return Err("XPST0080: the target of a cast must be an atomic type".to_string()),
+    };
+    let lexical = atomic.string();
+    let invalid = |reason: String| {
+        format!(
+            "FORG0001: cannot cast '{}' to xs:{}: {}",
+            lexical, datatype, reason
+        )
+    };
+    if datatype == SimpleDatatype::AnyAtomicType {
+        return Ok(atomic);
+    }
+    let number = match &atomic {
+        Atomic::Integer(_) | Atomic::Decimal(_) | Atomic::Double(_) => atomic.to_f64(),
+        Atomic::Boolean(boolean) => Some(if *boolean { 1.0 } else { 0.0 }),
+        _ => None,
+    };
+    let primitive = datatype.primitive();
+    match (primitive, number) {
+        (SimpleDatatype::Boolean, Some(number)) => {
+            return Ok(Atomic::Boolean(match &atomic {
+                Atomic::Integer(decimal) | Atomic::Decimal(decimal) => !decimal.is_zero(),
+                _ => number != 0.0 && !number.is_nan(),
+            }))
+        }
+        (SimpleDatatype::Double | SimpleDatatype::Float, Some(number)) => {
+            return Ok(Atomic::Double(number))
+        }
+        (SimpleDatatype::Decimal, Some(number)) => {
+            let decimal = match atomic {
+                Atomic::Integer(integer) if !datatype.is_derived_from(SimpleDatatype::Integer) => {
+                    return Ok(Atomic::Integer(integer))
+                }
+                Atomic::Integer(decimal) | Atomic::Decimal(decimal) => decimal,
+                _ => Decimal::from_f64(number)
+                    .ok_or_else(|| invalid("not a finite number".to_string()))?,
+            };
+            if !datatype.is_derived_from(SimpleDatatype::Integer) {
+                return Ok(Atomic::Decimal(decimal));
+            }
+            let integer = decimal.truncate();
+            datatype
+                .parse(&integer.to_string(), XsdVersion::V1_1)
+                .map_err(invalid)?;
+            return Ok(Atomic::Integer(integer));
+        }
+        (SimpleDatatype::String | SimpleDatatype::AnyUri, _) => {}
+        (_, Some(_)) => {
+            return Err(format!(
+                "XPTY0004: a value of type {} cannot be cast to xs:{}",
+                atomic.type_name(),
+                datatype
+            ))
+        }
+        _ => {}
+    }
+    let value = datatype
+        .parse(&lexical, XsdVersion::V1_1)
+        .map_err(invalid)?;
+    let normalized = datatype.whitespace().normalize(&lexical);
+    Ok(match value {
+        Value::String(string) => Atomic::String(string),
+        Value::Boolean(boolean) => Atomic::Boolean(boolean),
+        Value::Double(double) => Atomic::Double(double),
+        Value::Decimal(decimal) if datatype.is_derived_from(SimpleDatatype::Integer) => {
+            Atomic::Integer(decimal)
+        }
+        Value::Decimal(decimal) => Atomic::Decimal(decimal),
+        value => Atomic::Typed(value, normalized),
+    })
+}
+
+/// Compiles the pattern of `fn:matches`, which matches anywhere in the
+/// string unless it is anchored with `^` or `$`.
+fn regex_search(pattern: &str) -> Result<Regex, String> {
+    let (start, rest) = match pattern.strip_prefix('^') {
+        Some(rest) => ("", rest),
+        None => ("[\\s\\S]*", pattern),
+    };
+    let escaped = rest.ends_with("\\$") && !rest.ends_with("\\\\$");
+    let (end, rest) = match rest.strip_suffix('$') {
+        Some(rest) if !escaped => ("", rest),
+        _ => ("[\\s\\S]*", rest),
+    };
+    Regex::new(&format!("{}({}){}", start, rest, end), XsdVersion::V1_1).map_err(|reason| {
+        format!(
+            "FORX0002: invalid regular expression '{}': {}",
+            pattern, reason
+        )
+    })
+}
@@ -0,0 +1,241 @@
+//! Checks made on schema documents while they are loaded.
+
+use std::str::FromStr;
+
+use xmlschema::{Schema, SchemaError};
+
+/// Loads the schema made of the top-level components `body`.
+fn load(body: &str) -> Result<Schema, SchemaError> {
+    Schema::from_str(&schema(body))
+}
+
+fn schema(body: &str) -> String {
+    format!(
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">{}</xs:schema>"#,
+        body
+    )
+}
+
+/// Returns the code of the error `result` fails with.
+fn code(result: Result<Schema, SchemaError>) -> String {
+    match result {
+        Ok(_) => panic!("the schema was loaded"),
+        Err(error) => error.code().unwrap_or_default().to_string(),
+    }
+}
+
+#[test]
+fn substitution_group_cycle_not_through_the_member() {
+    let result = load(
+        r#"<xs:element name="a" substitutionGroup="b"/>
+        <xs:element name="b" substitutionGroup="c"/>
+        <xs:element name="c" substitutionGroup="b"/>"#,
+    );
+    assert_eq!(code(result), "e-props-correct.6");
+}
+
+#[test]
+fn list_of_built_in_lists() {
+    for item_type in ["xs:IDREFS", "xs:NMTOKENS", "xs:ENTITIES"] {
+        let result = load(&format!(
+            r#"<xs:simpleType name="l"><xs:list itemType="{}"/></xs:simpleType>"#,
+            item_type
+        ));
+        assert_eq!(code(result), "cos-st-restricts.2.1");
+    }
+    let result = load(
+        r#"<xs:simpleType name="u"><xs:union memberTypes="xs:int xs:NMTOKENS"/></xs:simpleType>
+        <xs:simpleType name="l"><xs:list itemType="u"/></xs:simpleType>"#,
+    );
+    assert_eq!(code(result), "cos-st-restricts.2.1");
+    assert!(
+        load(r#"<xs:simpleType name="l"><xs:list itemType="xs:NMTOKEN"/></xs:simpleType>"#).is_ok()
+    );
+}
+
+#[test]
+fn duplicate_local_attributes() {
+    let result = load(
+        r#"<xs:complexType name="t">
+            <xs:attribute name="a" type="xs:int"/>
+            <xs:attribute name="a" type="xs:string"/>
+        </xs:complexType>"#,
+    );
+    assert_eq!(code(result), "ct-props-correct.4");
+    let result = load(
+        r#"<xs:complexType name="b"/>
+        <xs:complexType name="t">
+            <xs:complexContent>
+                <xs:extension base="b">
+                    <xs:attribute name="a"/>
+                    <xs:attribute name="a"/>
+                </xs:extension>
+            </xs:complexContent>
+        </xs:complexType>"#,
+    );
+    assert_eq!(code(result), "ct-props-correct.4");
+}
+
+#[test]
+fn extension_of_an_all_group() {
+    let body = r#"<xs:complexType name="b">
+            <xs:all><xs:element name="a"/></xs:all>
+        </xs:complexType>
+        <xs:complexType name="t">
+            <xs:complexContent>
+                <xs:extension base="b">
+                    <xs:sequence><xs:element name="c"/></xs:sequence>
+                </xs:extension>
+            </xs:complexContent>
+        </xs:complexType>"#;
+    assert_eq!(code(load(body)), "cos-all-limited.1.2");
+    let attributes_only = r#"<xs:complexType name="b">
+            <xs:all><xs:element name="a"/></xs:all>
+        </xs:complexType>
+        <xs:complexType name="t">
+            <xs:complexContent>
+                <xs:extension base="b"><xs:attribute name="z"/></xs:extension>
+            </xs:complexContent>
+        </xs:complexType>"#;
+    assert!(load(attributes_only).is_ok());
+}
+
+#[test]
+fn unique_particle_attribution_message() {
+    let error = load(
+        r#"<xs:complexType name="t">
+            <xs:choice>
+                <xs:element name="a"/>
+                <xs:sequence><xs:element name="a"/><xs:element name="b"/></xs:sequence>
+            </xs:choice>
+        </xs:complexType>"#,
+    )
+    .unwrap_err();
+    assert_eq!(error.code(), Some("cos-nonambig"));
+    assert!(error
+        .to_string()
+        .contains("the last element of the sequence 'a' can be matched by either particle at"));
+}
+
+#[test]
+fn facets_applicable_to_the_primitive_datatype() {
+    let restriction = |base: &str, facet: &str| {
+        load(&format!(
+            r#"<xs:simpleType name="t">
+                <xs:restriction base="{}"><xs:{} value="3"/></xs:restriction>
+            </xs:simpleType>"#,
+            base, facet
+        ))
+    };
+    assert!(restriction("xs:integer", "totalDigits").is_ok());
+    assert!(restriction("xs:NMTOKENS", "maxLength").is_ok());
+    assert!(restriction("xs:hexBinary", "length").is_ok());
+    assert_eq!(
+        code(restriction("xs:string", "totalDigits")),
+        "cos-applicable-facets"
+    );
+    assert_eq!(
+        code(restriction("xs:boolean", "length")),
+        "cos-applicable-facets"
+    );
+    assert_eq!(
+        code(restriction("xs:date", "maxLength")),
+        "cos-applicable-facets"
+    );
+}
+
+#[test]
+fn default_and_fixed_values_of_the_declared_type() {
+    assert_eq!(
+        code(load(
+            r#"<xs:element name="e" type="xs:int" default="abc"/>"#
+        )),
+        "e-props-correct.2"
+    );
+    assert_eq!(
+        code(load(
+            r#"<xs:complexType name="t">
+                <xs:sequence><xs:element name="e" fixed="x">
+                    <xs:simpleType>
+                        <xs:restriction base="xs:string"><xs:length value="2"/></xs:restriction>
+                    </xs:simpleType>
+                </xs:element></xs:sequence>
+            </xs:complexType>"#
+        )),
+        "e-props-correct.2"
+    );
+    assert_eq!(
+        code(load(
+            r#"<xs:element name="e" default="x">
+                <xs:complexType><xs:sequence><xs:element name="a"/></xs:sequence></xs:complexType>
+            </xs:element>"#
+        )),
+        "e-props-correct.2"
+    );
+    assert_eq!(
+        code(load(
+            r#"<xs:attribute name="a" type="xs:boolean" fixed="yes"/>"#
+        )),
+        "a-props-correct.2"
+    );
+    assert_eq!(
+        code(load(
+            r#"<xs:attribute name="a" type="xs:int"/>
+            <xs:attributeGroup name="g"><xs:attribute ref="a" default="x"/></xs:attributeGroup>"#
+        )),
+        "a-props-correct.2"
+    );
+    assert!(load(
+        r#"<xs:element name="e" type="xs:int" default=" 12 "/>
+        <xs:element name="m" default="text">
+            <xs:complexType mixed="true">
+                <xs:sequence><xs:element name="a" minOccurs="0"/></xs:sequence>
+            </xs:complexType>
+        </xs:element>
+        <xs:attribute name="a" type="xs:boolean" fixed="true"/>"#
+    )
+    .is_ok());
+}
+
+#[test]
+fn order_and_number_of_schema_elements() {
+    let two_sequences = r#"<xs:complexType name="t">
+            <xs:sequence><xs:element name="a"/></xs:sequence>
+            <xs:sequence><xs:element name="b"/></xs:sequence>
+        </xs:complexType>"#;
+    let attribute_first = r#"<xs:complexType name="t">
+            <xs:attribute name="z"/>
+            <xs:sequence><xs:element name="a"/></xs:sequence>
+        </xs:complexType>"#;
+    let two_types = r#"<xs:element name="e">
+            <xs:complexType/>
+            <xs:simpleType><xs:restriction base="xs:string"/></xs:simpleType>
+        </xs:element>"#;
+    for body in [two_sequences, attribute_first, two_types] {
+        assert_eq!(code(load(body)), "s4s-elt-invalid-content.1");
+    }
+    let missing_field = r#"<xs:element name="e">
+            <xs:key name="k"><xs:selector xpath="a"/></xs:key>
+        </xs:element>"#;
+    let error = load(missing_field).unwrap_err();
+    assert_eq!(error.code(), Some("s4s-elt-must-match.1"));
+    assert!(error
+        .to_string()
+        .contains("must match (annotation?, selector, field+)"));
+    assert_eq!(code(load(r#"<xs:attribute name="xmlns"/>"#)), "no-xmlns");
+}
+
+#[test]
+fn substitution_group_member_of_an_unrelated_type() {
+    let result = load(
+        r#"<xs:element name="head" type="xs:int"/>
+        <xs:element name="member" type="xs:date" substitutionGroup="head"/>"#,
+    );
+    assert_eq!(code(result), "e-props-correct.4");
+    assert!(load(
+        r#"<xs:element name="head" type="xs:decimal"/>
+        <xs:element name="member" type="xs:int" substitutionGroup="head"/>
+        <xs:element name="inherited" substitutionGroup="member"/>"#
+    )
+    .is_ok());
+}
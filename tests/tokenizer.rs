@@ -0,0 +1,43 @@
+//! Reading documents with the tokenizer re-exported at the crate root.
+
+use xmlschema::{SchemaError, Token, Tokenizer};
+
+/// Returns the first error found in `input`, if any.
+fn first_error(input: &str) -> Option<SchemaError> {
+    Tokenizer::new(input).find_map(Result::err)
+}
+
+/// Returns a document whose internal subset declares `e0` as empty, and
+/// each `eN` as ten references to `eN-1`, and whose root references
+/// `e{depth}`.
+fn nested_entities(depth: usize) -> String {
+    let mut input = String::from(r#"<!DOCTYPE r [<!ENTITY e0 "">"#);
+    for level in 1..=depth {
+        let value = format!("&e{};", level - 1).repeat(10);
+        input.push_str(&format!(r#"<!ENTITY e{} "{}">"#, level, value));
+    }
+    input.push_str(&format!("]><r>&e{};</r>", depth));
+    input
+}
+
+#[test]
+fn entities_expanding_to_nothing() {
+    // Ten thousand references, under the limit
+    assert!(first_error(&nested_entities(4)).is_none());
+}
+
+#[test]
+fn too_many_entity_references() {
+    let error = first_error(&nested_entities(9)).unwrap();
+    assert!(error
+        .to_string()
+        .contains("Entity reference limit exceeded"));
+}
+
+#[test]
+fn tokens_from_the_crate_root() {
+    let tokens: Vec<Token> = Tokenizer::new("<a>x</a>")
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(tokens[1], Token::Text("x".to_string()));
+}
@@ -0,0 +1,243 @@
+//! Validation of instance documents.
+
+use std::str::FromStr;
+
+use xmlschema::{Schema, SimpleDatatype, XsdVersion};
+
+/// Loads the schema made of the top-level components `body`.
+fn load(body: &str) -> Schema {
+    let input = format!(
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">{}</xs:schema>"#,
+        body
+    );
+    Schema::from_str(&input).unwrap()
+}
+
+/// Returns the codes of the errors found in `instance`, sorted.
+fn codes(schema: &Schema, instance: &str) -> Vec<String> {
+    let mut codes: Vec<String> = match schema.validate(instance) {
+        Ok(_) => Vec::new(),
+        Err(error) => error
+            .errors()
+            .map(|error| error.code().unwrap_or_default().to_string())
+            .collect(),
+    };
+    codes.sort();
+    codes
+}
+
+#[test]
+fn children_before_a_content_model_error() {
+    let schema = load(
+        r#"<xs:element name="r">
+            <xs:complexType>
+                <xs:sequence>
+                    <xs:element name="a" type="xs:int"/>
+                    <xs:element name="b"/>
+                </xs:sequence>
+            </xs:complexType>
+        </xs:element>"#,
+    );
+    assert_eq!(
+        codes(&schema, "<r><a>x</a><c/></r>"),
+        ["cvc-complex-type.2.4.a", "cvc-datatype-valid.1.2.1"]
+    );
+    assert_eq!(
+        codes(&schema, "<r><a>x</a></r>"),
+        ["cvc-complex-type.2.4.b", "cvc-datatype-valid.1.2.1"]
+    );
+}
+
+#[test]
+fn empty_element_with_a_default_or_fixed_value() {
+    for constraint in ["default", "fixed"] {
+        let schema = load(&format!(
+            r#"<xs:element name="q" type="xs:integer" {}="5"/>"#,
+            constraint
+        ));
+        assert!(codes(&schema, "<q/>").is_empty());
+        assert!(codes(&schema, "<q>x</q>").contains(&"cvc-datatype-valid.1.2.1".to_string()));
+    }
+}
+
+#[test]
+fn content_of_any_type_assessed_laxly() {
+    let schema = load(
+        r#"<xs:element name="z" type="xs:int"/>
+        <xs:attribute name="g" type="xs:int"/>
+        <xs:element name="r">
+            <xs:complexType>
+                <xs:sequence>
+                    <xs:element name="e"/>
+                    <xs:element name="f" type="xs:anyType"/>
+                </xs:sequence>
+            </xs:complexType>
+        </xs:element>"#,
+    );
+    assert!(codes(&schema, "<r><e><y><z>1</z></y>text</e><f/></r>").is_empty());
+    assert_eq!(
+        codes(&schema, "<r><e><z>notint</z></e><f g='x'/></r>"),
+        ["cvc-attribute.3", "cvc-datatype-valid.1.2.1"]
+    );
+}
+
+#[test]
+fn year_zero_only_in_version_1_1() {
+    let body = r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">
+            <xs:element name="d" type="xs:date"/>
+        </xs:schema>"#;
+    let schema = Schema::from_str_with_version(body, XsdVersion::V1_0).unwrap();
+    assert!(schema.validate("<d>0000-01-01</d>").is_err());
+    assert!(schema.validate("<d>-0001-01-01</d>").is_ok());
+    let schema = Schema::from_str_with_version(body, XsdVersion::V1_1).unwrap();
+    assert!(schema.validate("<d>0000-01-01</d>").is_ok());
+    assert!(!SimpleDatatype::Date.is_valid("0000-01-01"));
+}
+
+#[test]
+fn path_of_a_nested_element() {
+    let schema = load(
+        r#"<xs:element name="r">
+            <xs:complexType>
+                <xs:sequence>
+                    <xs:element name="a" maxOccurs="2">
+                        <xs:complexType>
+                            <xs:sequence><xs:element name="b" type="xs:int"/></xs:sequence>
+                        </xs:complexType>
+                    </xs:element>
+                </xs:sequence>
+            </xs:complexType>
+        </xs:element>"#,
+    );
+    let error = schema
+        .validate("<r><a><b>1</b></a><a><b>x</b></a></r>")
+        .unwrap_err();
+    assert_eq!(error.path(), Some("/r/a[2]/b[1]"));
+}
+
+#[test]
+fn locations_of_several_errors() {
+    let schema = load(
+        r#"<xs:element name="r">
+            <xs:complexType>
+                <xs:sequence>
+                    <xs:element name="a" type="xs:int" maxOccurs="3"/>
+                </xs:sequence>
+            </xs:complexType>
+        </xs:element>"#,
+    );
+    let error = schema
+        .validate("<r>\n<a>1</a>\n  <a>x</a>\n<a>é</a><a>y</a>\n</r>")
+        .unwrap_err();
+    let mut locations: Vec<(usize, usize)> = error
+        .errors()
+        .map(|error| error.location().unwrap())
+        .map(|location| (location.line(), location.column()))
+        .collect();
+    locations.sort();
+    assert_eq!(locations, [(3, 3), (4, 1), (4, 9)]);
+}
+
+#[test]
+fn identity_constraints_on_many_rows() {
+    let schema = load(
+        r#"<xs:element name="r">
+            <xs:complexType>
+                <xs:sequence>
+                    <xs:element name="k" maxOccurs="unbounded">
+                        <xs:complexType>
+                            <xs:attribute name="id" type="xs:decimal"/>
+                            <xs:attribute name="w" type="xs:double"/>
+                        </xs:complexType>
+                    </xs:element>
+                    <xs:element name="ref" minOccurs="0" maxOccurs="unbounded">
+                        <xs:complexType><xs:attribute name="to" type="xs:decimal"/></xs:complexType>
+                    </xs:element>
+                </xs:sequence>
+            </xs:complexType>
+            <xs:key name="ids">
+                <xs:selector xpath="k | ./k"/>
+                <xs:field xpath="@id"/>
+            </xs:key>
+            <xs:unique name="weights">
+                <xs:selector xpath="k"/>
+                <xs:field xpath="@w"/>
+            </xs:unique>
+            <xs:keyref name="refs" refer="ids">
+                <xs:selector xpath="ref"/>
+                <xs:field xpath="@to"/>
+            </xs:keyref>
+        </xs:element>"#,
+    );
+    let mut instance = String::from("<r>");
+    for index in 0..20000 {
+        instance.push_str(&format!("<k id='{}' w='{}'/>", index, index));
+    }
+    for index in 0..20000 {
+        instance.push_str(&format!("<ref to='{}.0'/>", index));
+    }
+    instance.push_str("</r>");
+    assert!(codes(&schema, &instance).is_empty());
+    assert_eq!(
+        codes(
+            &schema,
+            "<r><k id='1' w='0'/><k id='1.00' w='-0'/><ref to='2'/></r>"
+        ),
+        [
+            "cvc-identity-constraint.4.1",
+            "cvc-identity-constraint.4.2.2",
+            "cvc-identity-constraint.4.3"
+        ]
+    );
+}
+
+#[test]
+fn references_followed_through_their_handles() {
+    let schema = load(
+        r#"<xs:simpleType name="small">
+            <xs:restriction base="xs:int"><xs:maxInclusive value="9"/></xs:restriction>
+        </xs:simpleType>
+        <xs:complexType name="base">
+            <xs:sequence><xs:element ref="item" maxOccurs="unbounded"/></xs:sequence>
+            <xs:attribute ref="size"/>
+        </xs:complexType>
+        <xs:complexType name="derived">
+            <xs:complexContent>
+                <xs:extension base="base"><xs:attribute name="extra" type="small"/></xs:extension>
+            </xs:complexContent>
+        </xs:complexType>
+        <xs:element name="item" type="small"/>
+        <xs:element name="part" substitutionGroup="item"/>
+        <xs:attribute name="size" type="small"/>
+        <xs:element name="r" type="base"/>
+        <xs:element name="s" type="base" block="extension"/>"#,
+    );
+    assert!(codes(&schema, "<r size='3'><item>1</item><part>2</part></r>").is_empty());
+    assert_eq!(
+        codes(&schema, "<r size='10'><part>12</part></r>"),
+        ["cvc-attribute.3", "cvc-maxInclusive-valid"]
+    );
+    let xsi = "xmlns:xsi='http://www.w3.org/2001/XMLSchema-instance'";
+    assert!(codes(
+        &schema,
+        &format!("<r {} xsi:type='derived' extra='4'><item>1</item></r>", xsi)
+    )
+    .is_empty());
+    assert_eq!(
+        codes(
+            &schema,
+            &format!(
+                "<r {} xsi:type='derived' extra='40'><item>1</item></r>",
+                xsi
+            )
+        ),
+        ["cvc-attribute.3"]
+    );
+    assert_eq!(
+        codes(
+            &schema,
+            &format!("<s {} xsi:type='derived'><item>1</item></s>", xsi)
+        ),
+        ["cvc-elt.4.3"]
+    );
+}
@@ -0,0 +1,134 @@
+//! Schemas read as XSD 1.1 documents.
+
+use xmlschema::{Schema, SchemaError, XsdVersion};
+
+fn schema(body: &str) -> String {
+    format!(
+        r#"<xs:schema xmlns:xs="http://www.w3.org/2001/XMLSchema">{}</xs:schema>"#,
+        body
+    )
+}
+
+/// Loads the schema made of the top-level components `body` as a
+/// document of `version`.
+fn load(body: &str, version: XsdVersion) -> Result<Schema, SchemaError> {
+    Schema::from_str_with_version(&schema(body), version)
+}
+
+/// Returns `true` if `instance` is valid against `schema`.
+fn is_valid(schema: &Schema, instance: &str) -> bool {
+    schema.validate(instance).is_ok()
+}
+
+#[test]
+fn all_group_with_repeated_elements_and_wildcards() {
+    let body = r###"<xs:element name="r">
+            <xs:complexType>
+                <xs:all>
+                    <xs:element name="a" maxOccurs="3"/>
+                    <xs:any namespace="##other" processContents="skip"/>
+                </xs:all>
+            </xs:complexType>
+        </xs:element>"###;
+    let error = load(body, XsdVersion::V1_0).unwrap_err();
+    assert_eq!(error.errors().count(), 2);
+    let schema = load(body, XsdVersion::V1_1).unwrap();
+    assert!(is_valid(&schema, "<r><a/><x:o xmlns:x='urn:x'/><a/></r>"));
+    assert!(!is_valid(
+        &schema,
+        "<r><a/><a/><a/><a/><x:o xmlns:x='urn:x'/></r>"
+    ));
+    assert!(!is_valid(&schema, "<r><a/></r>"));
+}
+
+#[test]
+fn all_group_referencing_an_all_group() {
+    let body = r#"<xs:group name="g">
+            <xs:all>
+                <xs:element name="b"/>
+                <xs:element name="c" minOccurs="0"/>
+            </xs:all>
+        </xs:group>
+        <xs:element name="r">
+            <xs:complexType>
+                <xs:all>
+                    <xs:element name="a"/>
+                    <xs:group ref="g"/>
+                </xs:all>
+            </xs:complexType>
+        </xs:element>"#;
+    let error = load(body, XsdVersion::V1_0).unwrap_err();
+    assert_eq!(error.code(), Some("s4s-elt-invalid-content.1"));
+    let schema = load(body, XsdVersion::V1_1).unwrap();
+    assert!(is_valid(&schema, "<r><c/><a/><b/></r>"));
+    assert!(!is_valid(&schema, "<r><a/></r>"));
+}
+
+#[test]
+fn group_reference_in_an_all_group_occurs_once() {
+    let body = r#"<xs:group name="g">
+            <xs:all><xs:element name="b"/></xs:all>
+        </xs:group>
+        <xs:element name="r">
+            <xs:complexType>
+                <xs:all><xs:group ref="g" maxOccurs="2"/></xs:all>
+            </xs:complexType>
+        </xs:element>"#;
+    let error = load(body, XsdVersion::V1_1).unwrap_err();
+    assert_eq!(error.code(), Some("s4s-att-invalid-value"));
+}
+
+#[test]
+fn extension_of_an_all_group_by_an_all_group() {
+    let body = r#"<xs:complexType name="b">
+            <xs:all><xs:element name="a"/><xs:element name="b"/></xs:all>
+        </xs:complexType>
+        <xs:complexType name="t">
+            <xs:complexContent>
+                <xs:extension base="b">
+                    <xs:all><xs:element name="c"/></xs:all>
+                </xs:extension>
+            </xs:complexContent>
+        </xs:complexType>
+        <xs:element name="r" type="t"/>"#;
+    let error = load(body, XsdVersion::V1_0).unwrap_err();
+    assert_eq!(error.code(), Some("cos-all-limited.1.2"));
+    let schema = load(body, XsdVersion::V1_1).unwrap();
+    assert!(is_valid(&schema, "<r><c/><a/><b/></r>"));
+    assert!(is_valid(&schema, "<r><b/><c/><a/></r>"));
+    assert!(!is_valid(&schema, "<r><a/><b/></r>"));
+    let sequence = body.replace(
+        "<xs:all><xs:element name=\"c\"/></xs:all>",
+        "<xs:sequence><xs:element name=\"c\"/></xs:sequence>",
+    );
+    let error = load(&sequence, XsdVersion::V1_1).unwrap_err();
+    assert_eq!(error.code(), Some("cos-all-limited.1.2"));
+}
+
+#[test]
+fn decimal_arithmetic_in_assertions() {
+    let assertion = |test: &str| {
+        let body = format!(
+            r#"<xs:element name="r"><xs:complexType><xs:assert test="{}"/></xs:complexType></xs:element>"#,
+            test
+        );
+        is_valid(&load(&body, XsdVersion::V1_1).unwrap(), "<r/>")
+    };
+    assert!(assertion("0.1 + 0.2 eq 0.3"));
+    assert!(assertion("1 div 3 * 3 lt 1"));
+    assert!(assertion("-7.5 mod 2 = -1.5 and -7 idiv 2 = -3"));
+    assert!(assertion(
+        "99999999999999999999 * 10 = 999999999999999999990"
+    ));
+    assert!(assertion(
+        "round(-2.5) = -2 and floor(-2.1) = -3 and ceiling(2.1) = 3"
+    ));
+    assert!(assertion(
+        "string(1.50) = '1.5' and string(10 div 4) = '2.5'"
+    ));
+    assert!(assertion(
+        "xs:integer(12345678901234567890.9) = 12345678901234567890"
+    ));
+    assert!(!assertion("12345678901234567.1 = 12345678901234567.2"));
+    assert!(!assertion("1.0 div 0 = 0"));
+}